@@ -0,0 +1,70 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Times parsing one generated track with each source of events.
+//!
+//! `cargo bench --features quick-xml -- <points>`
+
+extern crate gpx_rust;
+
+use std::env;
+use std::time::{ Duration, Instant };
+
+use gpx_rust::gpx;
+use gpx_rust::par::ParserOptions;
+use gpx_rust::xml::quick::QuickReader;
+
+
+const RUNS: u32 = 5;
+
+fn document(points: usize) -> String {
+    let mut document = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <gpx version=\"1.1\" creator=\"bench\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n\
+         <trk><name>Bench</name><trkseg>\n");
+    for i in 0..points {
+        document.push_str(&format!(
+            "<trkpt lat=\"{}\" lon=\"{}\"><ele>{}</ele><time>2017-01-01T00:00:00Z</time></trkpt>\n",
+            (i % 90) as f64 + 0.5, (i % 180) as f64 - 0.25, i % 1000));
+    }
+    document.push_str("</trkseg></trk>\n</gpx>\n");
+    document
+}
+
+/// Returns the shortest time of `RUNS` runs of `parse`
+fn time<F: Fn()>(parse: F) -> Duration {
+    (0..RUNS).map(|_| {
+        let start = Instant::now();
+        parse();
+        start.elapsed()
+    }).min().unwrap()
+}
+
+fn main() {
+    let points = env::args().skip(1).filter_map(|arg| arg.parse().ok()).next().unwrap_or(20000);
+    let document = document(points);
+    let options = ParserOptions::default();
+    println!("{} points, {} bytes, best of {} runs", points, document.len(), RUNS);
+    let xml_rs = time(|| {
+        gpx::par::parse_with(document.as_bytes(), &options).unwrap();
+    });
+    println!("xml-rs:    {:?}", xml_rs);
+    let quick = time(|| {
+        gpx::par::parse_from(QuickReader::new(document.as_bytes(), &options), &options).unwrap();
+    });
+    println!("quick-xml: {:?}", quick);
+    let borrowed = time(|| {
+        gpx::borrowed::parse(&document).unwrap();
+    });
+    println!("borrowed:  {:?}", borrowed);
+}
@@ -16,7 +16,7 @@ extern crate xml_parsergen;
 
 use std::collections::HashMap;
 use std::env;
-use std::path::PathBuf;
+use std::path::{ Path, PathBuf };
 use std::fs::File;
 use std::io;
 use std::io::{ Write, BufWriter };
@@ -29,7 +29,6 @@ use xml_parsergen::gpx::DEFAULT_GENERATOR;
 struct SimpleImplInfo<'a> {
     type_name: String,
     type_: &'a SimpleType,
-    elem: bool,
 }
 
 macro_rules! map(
@@ -45,43 +44,46 @@ macro_rules! map(
 );
 
 fn get_complex<'a>(types: &'a TypeMap, name: &str) -> &'a ComplexType {
-    match *types.get(name).expect(&format!("Type {} undefined", name)) {
+    match *types.get(name).unwrap_or_else(|| panic!("Type {} undefined", name)) {
          Type::Complex(ref type_) => type_,
          _ => panic!("Type of {} is not Type::Complex", name),
     }
 }
 
 fn get_simple<'a>(types: &'a TypeMap, name: &str) -> &'a SimpleType {
-    match *types.get(name).expect(&format!("Type {} undefined", name)) {
+    match *types.get(name).unwrap_or_else(|| panic!("Type {} undefined", name)) {
          Type::Simple(ref type_) => type_,
          _ => panic!("Type of {} is not Type::Simple", name),
     }
 }
 
 #[derive(Debug)]
+#[allow(dead_code)] // Only read through Debug
 enum Error {
     Var(env::VarError),
     Io(io::Error),
 }
 
-fn write_file<F: FnOnce(&mut BufWriter<File>) -> Result<(), Error>>(filename: &PathBuf, inner: F)
+fn write_file<F: FnOnce(&mut BufWriter<File>) -> Result<(), Error>>(filename: &Path, inner: F)
         -> Result<(), Error> {
     { // to drop and flush & close f before prettifying
-        let f = try!(File::create(filename.clone()).map_err(Error::Io));
+        let f = File::create(filename).map_err(Error::Io)?;
         let mut f = BufWriter::new(f);
         
-        try!(inner(&mut f));
+        inner(&mut f)?;
     }
-    Ok(match prettify(filename) {
-        Ok(_) => {},
-        Err(e) => println!("warning=prettifying failed with {:?}", e),
-    })
+    if let Err(e) = prettify(filename) {
+        println!("warning=prettifying failed with {:?}", e);
+    }
+    Ok(())
 }
 
 fn process() -> Result<(), Error> {
-    let out_dir = PathBuf::from(try!(env::var("OUT_DIR").map_err(Error::Var)));
+    let out_dir = PathBuf::from(env::var("OUT_DIR").map_err(Error::Var)?);
 
     let types = gpx::get_types();
+    // parsers accept both GPX 1.0 and GPX 1.1
+    let parser_types = gpx::get_types_with_v1_0();
     let attr_convs: ConvMap = map!{
         "gpxType".into() => ("Gpx".into(), "::gpx::conv::Gpx".into()),
         "boundsType".into() => ("Bounds".into(), "::gpx::conv::Bounds".into()),
@@ -91,14 +93,15 @@ fn process() -> Result<(), Error> {
         "linkType".into() => ("Link".into(), "::gpx::conv::Link".into()),
         "fixType".into() => ("Fix".into(), "::gpx::conv::Fix".into()),
         "dgpsStationType".into() => ("u16".into(), "::gpx::conv::DgpsStation".into()),
-        "extensionsType".into() => ("xml::Element".into(), "::gpx::conv::Extensions".into()), // FIXME: dedicated type?
+        "extensionsType".into() => ("xml::Element".into(), "::gpx::conv::Extensions".into()),
         "personType".into() => ("Person".into(), "::gpx::conv::Person".into()),
         "wptType".into() => ("Waypoint".into(), "::gpx::conv::Wpt".into()),
         "metadataType".into() => ("Metadata".into(), "::gpx::conv::Metadata".into()),
         "trkType".into() => ("Track".into(), "::gpx::conv::Trk".into()),
         "rteType".into() => ("Route".into(), "::gpx::conv::Rte".into()),
         "trksegType".into() => ("TrackSegment".into(), "::gpx::conv::Trkseg".into()),
-        "emailType".into() => ("String".into(), "::gpx::conv::Email".into()),
+        "emailType".into() => ("Email".into(), "::gpx::conv::Email".into()),
+        "_gpx:email".into() => ("Option<Email>".into(), "::gpx::conv::v1_0::Email".into()),
         "_gpx:version".into() => ("Version".into(), "::gpx::conv::Version".into()),
         "xsd:decimal".into() => ("xsd::Decimal".into(), "::xsd::conv::Decimal".into()),
         "xsd:dateTime".into() => ("xsd::DateTime".into(), "::xsd::conv::DateTime".into()),
@@ -107,27 +110,25 @@ fn process() -> Result<(), Error> {
         "degreesType".into() => ("f32".into(), "::gpx::conv::Degrees".into()),
         "xsd:anyURI".into() => ("xsd::Uri".into(), "::xsd::conv::Uri".into()),
         "xsd:integer".into() => ("i64".into(), "::xsd::conv::Integer".into()),
-        "xsd:gYear".into() => ("i16".into(), "::xsd::conv::GYear".into()),
+        "xsd:gYear".into() => ("xsd::GYear".into(), "::xsd::conv::GYear".into()),
     };
     let parsers = vec![
-        ParserInfo { name: "TrackSegmentParser".into(), type_: get_complex(&types, "trksegType") },
-        ParserInfo { name: "MetadataParser".into(), type_: get_complex(&types, "metadataType") },
-        ParserInfo { name: "WaypointParser".into(), type_: get_complex(&types, "wptType") },
-        ParserInfo { name: "BoundsParser".into(), type_: get_complex(&types, "boundsType") },
-        ParserInfo { name: "GpxElemParser".into(), type_: get_complex(&types, "gpxType") },
-        ParserInfo { name: "RteParser".into(), type_: get_complex(&types, "rteType") },
-        ParserInfo { name: "TrkParser".into(), type_: get_complex(&types, "trkType") },
-        ParserInfo { name: "LinkParser".into(), type_: get_complex(&types, "linkType") },
-        ParserInfo { name: "CopyrightParser".into(), type_: get_complex(&types, "copyrightType") },
-        ParserInfo { name: "PersonParser".into(), type_: get_complex(&types, "personType") },
-        ParserInfo { name: "EmailParser".into(), type_: get_complex(&types, "emailType") },
+        ParserInfo { name: "TrackSegmentParser".into(), type_: get_complex(&parser_types, "trksegType") },
+        ParserInfo { name: "MetadataParser".into(), type_: get_complex(&parser_types, "metadataType") },
+        ParserInfo { name: "WaypointParser".into(), type_: get_complex(&parser_types, "wptType") },
+        ParserInfo { name: "BoundsParser".into(), type_: get_complex(&parser_types, "boundsType") },
+        ParserInfo { name: "GpxElemParser".into(), type_: get_complex(&parser_types, "gpxType") },
+        ParserInfo { name: "RteParser".into(), type_: get_complex(&parser_types, "rteType") },
+        ParserInfo { name: "TrkParser".into(), type_: get_complex(&parser_types, "trkType") },
+        ParserInfo { name: "LinkParser".into(), type_: get_complex(&parser_types, "linkType") },
+        ParserInfo { name: "CopyrightParser".into(), type_: get_complex(&parser_types, "copyrightType") },
+        ParserInfo { name: "PersonParser".into(), type_: get_complex(&parser_types, "personType") },
+        ParserInfo { name: "EmailParser".into(), type_: get_complex(&parser_types, "emailType") },
     ];
 
     let simple_impls = vec![
-        SimpleImplInfo { type_name: "degreesType".into(), type_: get_simple(&types, "degreesType"),
-                         elem: true },
-        SimpleImplInfo { type_name: "dgpsStationType".into(), type_: get_simple(&types, "dgpsStationType"),
-                         elem: true },
+        SimpleImplInfo { type_name: "degreesType".into(), type_: get_simple(&types, "degreesType") },
+        SimpleImplInfo { type_name: "dgpsStationType".into(), type_: get_simple(&types, "dgpsStationType") },
     ];
     let structs = vec![
         StructInfo { name: "Metadata".into(),
@@ -167,15 +168,15 @@ fn process() -> Result<(), Error> {
                      type_name: "personType".into(),
                      tags: HashMap::new() },
     ];
-    let builder_impls = ["RteParser", "TrkParser", "LinkParser", "GpxElemParser", "TrackSegmentParser", "CopyrightParser", "PersonParser", "MetadataParser"]
+    let builder_impls = ["LinkParser", "TrackSegmentParser", "CopyrightParser", "PersonParser", "MetadataParser"]
                         .iter().map(|name: &&'static str| {
         let type_ = parsers.iter()
                                 .find(|pinfo| pinfo.name.as_str() == *name)
-                                .expect(&format!("{} not in parser impls", *name))
+                                .unwrap_or_else(|| panic!("{} not in parser impls", *name))
                                 .type_;
         let sinfo = structs.iter()
-                           .find(|sinfo| get_complex(&types, sinfo.type_name.as_str()) as *const _ == type_ as *const _)
-                           .expect(&format!("type of {} not in structs", *name));
+                           .find(|sinfo| std::ptr::eq(get_complex(&parser_types, sinfo.type_name.as_str()), type_))
+                           .unwrap_or_else(|| panic!("type of {} not in structs", *name));
         (*name,
          type_,
          sinfo)
@@ -184,63 +185,204 @@ fn process() -> Result<(), Error> {
                       .iter().map(|name: &&'static str| {
         structs.iter()
                .find(|sinfo| sinfo.name == *name)
-               .expect(&format!("Structure {} not defined", *name))
+               .unwrap_or_else(|| panic!("Structure {} not defined", *name))
     }).collect::<Vec<_>>();
     let parser_impls_via = vec![
         ("CopyrightParser", attr_convs.get("copyrightType").expect("copyrighterr")),
         ("PersonParser", attr_convs.get("personType").expect("personerr")),
         ("EmailParser", attr_convs.get("emailType").expect("emailerr")),
-        ("WaypointParser", attr_convs.get("wptType").expect("wpterr")),
         ("MetadataParser", attr_convs.get("metadataType").expect("metaerr"))
     ];
-    try!(write_file(&out_dir.join("gpx_auto.rs"), |f| {
+    write_file(&out_dir.join("gpx_auto.rs"), |f| {
         for item in &structs {
-            try!(f.write(
+            f.write(
                 DEFAULT_GENERATOR.data_struct_type(&item.name, &item.tags,
                                                    get_complex(&types, item.type_name.as_str()),
                                                    &attr_convs).as_bytes()
-            ).map_err(Error::Io));
+            ).map_err(Error::Io)?;
         }
         Ok(())
-    }));
-    try!(write_file(&out_dir.join("gpx_par_auto.rs"), |f| {
+    })?;
+    write_file(&out_dir.join("gpx_par_auto.rs"), |f| {
         for item in &parsers {
-            try!(f.write(
+            f.write(
                 DEFAULT_GENERATOR.parser_type(&item.name, item.type_, &attr_convs).as_bytes()
-            ).map_err(Error::Io));
-            try!(f.write(
+            ).map_err(Error::Io)?;
+            f.write(
                 DEFAULT_GENERATOR.parser_impl(&item.name, item.type_, &attr_convs).as_bytes()
-            ).map_err(Error::Io));
+            ).map_err(Error::Io)?;
         }
-        for &(ref name, ref conv_) in &parser_impls_via {
-            try!(f.write(
+        for &(name, conv_) in &parser_impls_via {
+            f.write(
                 DEFAULT_GENERATOR.parse_impl_complex(name, conv_).as_bytes()
-            ).map_err(Error::Io));
+            ).map_err(Error::Io)?;
         }
         for item in &builder_impls {
-            try!(f.write(
-                gpx::Generator::build_impl(&item.0, item.1, item.2, &attr_convs).as_bytes()
-            ).map_err(Error::Io));
+            f.write(
+                gpx::Generator::build_impl(item.0, item.1, item.2, &attr_convs).as_bytes()
+            ).map_err(Error::Io)?;
         }
         for item in &simple_impls {
-            try!(f.write(
+            f.write(
                 DEFAULT_GENERATOR.parse_impl(&item.type_name, item.type_,
                                              &attr_convs, &types).as_bytes()
-            ).map_err(Error::Io));
+            ).map_err(Error::Io)?;
         }
         Ok(())
-    }));
-    try!(write_file(&out_dir.join("gpx_ser_auto.rs"), |f| {
+    })?;
+    write_file(&out_dir.join("gpx_ser_auto.rs"), |f| {
         for item in &serializers {
-            try!(f.write(
+            f.write(
                 gpx::Generator::serializer_impl(&item.name, &item.tags,
                                                 &item.type_name,
                                                 get_complex(&types, item.type_name.as_str()),
                                                 &attr_convs).as_bytes()
-            ).map_err(Error::Io));
+            ).map_err(Error::Io)?;
+        }
+        Ok(())
+    })?;
+    process_garmin(&out_dir)?;
+    Ok(())
+}
+
+/// Garmin TrackPointExtension, which both versions read into one type, and GpxExtensions v3
+fn process_garmin(out_dir: &Path) -> Result<(), Error> {
+    static TPX_V1_NAMESPACES: [&str; 1] = ["http://www.garmin.com/xmlschemas/TrackPointExtension/v1"];
+    static TPX_V2_NAMESPACES: [&str; 1] = ["http://www.garmin.com/xmlschemas/TrackPointExtension/v2"];
+    static GPXX_NAMESPACES: [&str; 1] = ["http://www.garmin.com/xmlschemas/GpxExtensions/v3"];
+    let types = gpx::get_garmin_types();
+    let convs: ConvMap = map!{
+        "TrackPointExtensionV1_t".into() => ("TrackPointExtension".into(), "::gpx::garmin::conv::TrackPointExtensionV1".into()),
+        "TrackPointExtensionV2_t".into() => ("TrackPointExtension".into(), "::gpx::garmin::conv::TrackPointExtension".into()),
+        "BeatsPerMinute_t".into() => ("u8".into(), "::gpx::garmin::conv::BeatsPerMinute".into()),
+        "RevolutionsPerMinute_t".into() => ("u8".into(), "::gpx::garmin::conv::RevolutionsPerMinute".into()),
+        "DegreesTrue_t".into() => ("f64".into(), "::gpx::garmin::conv::DegreesTrue".into()),
+        "WaypointExtension_t".into() => ("WaypointExtension".into(), "::gpx::garmin::conv::WaypointExtension".into()),
+        "Categories_t".into() => ("Vec<String>".into(), "::gpx::garmin::conv::Categories".into()),
+        "Address_t".into() => ("Address".into(), "::gpx::garmin::conv::Address".into()),
+        "PhoneNumber_t".into() => ("PhoneNumber".into(), "::gpx::garmin::conv::PhoneNumber".into()),
+        "DisplayMode_t".into() => ("DisplayMode".into(), "::gpx::garmin::conv::DisplayMode".into()),
+        "DisplayColor_t".into() => ("DisplayColor".into(), "::gpx::garmin::conv::DisplayColor".into()),
+        "RouteExtension_t".into() => ("RouteExtension".into(), "::gpx::garmin::conv::RouteExtension".into()),
+        "RoutePointExtension_t".into() => ("RoutePointExtension".into(), "::gpx::garmin::conv::RoutePointExtension".into()),
+        "AutoroutePoint_t".into() => ("AutoroutePoint".into(), "::gpx::garmin::conv::AutoroutePoint".into()),
+        "TrackExtension_t".into() => ("TrackExtension".into(), "::gpx::garmin::conv::TrackExtension".into()),
+        "latitudeType".into() => ("f64".into(), "::gpx::conv::Latitude".into()),
+        "longitudeType".into() => ("f64".into(), "::gpx::conv::Longitude".into()),
+        "extensionsType".into() => ("xml::Element".into(), "::gpx::conv::Extensions".into()),
+        "xsd:double".into() => ("f64".into(), "::xsd::conv::Double".into()),
+        "xsd:unsignedByte".into() => ("u8".into(), "::xsd::conv::UnsignedByte".into()),
+        "xsd:string".into() => ("String".into(), "::xsd::conv::String".into()),
+        "xsd:boolean".into() => ("bool".into(), "::xsd::conv::Boolean".into()),
+        "xsd:hexBinary".into() => ("Vec<u8>".into(), "::xsd::conv::HexBinary".into()),
+    };
+    let tpx_v1 = DEFAULT_GENERATOR.with_namespaces(&TPX_V1_NAMESPACES);
+    let tpx_v2 = DEFAULT_GENERATOR.with_namespaces(&TPX_V2_NAMESPACES);
+    let gpxx = DEFAULT_GENERATOR.with_namespaces(&GPXX_NAMESPACES);
+    let parsers = vec![
+        ("TrackPointExtensionV1Parser", "TrackPointExtensionV1_t", &tpx_v1),
+        ("TrackPointExtensionParser", "TrackPointExtensionV2_t", &tpx_v2),
+        ("WaypointExtensionParser", "WaypointExtension_t", &gpxx),
+        ("CategoriesParser", "Categories_t", &gpxx),
+        ("AddressParser", "Address_t", &gpxx),
+        ("RouteExtensionParser", "RouteExtension_t", &gpxx),
+        ("RoutePointExtensionParser", "RoutePointExtension_t", &gpxx),
+        ("AutoroutePointParser", "AutoroutePoint_t", &gpxx),
+        ("TrackExtensionParser", "TrackExtension_t", &gpxx),
+    ];
+    let structs = vec![
+        StructInfo { name: "TrackPointExtension".into(),
+                     type_name: "TrackPointExtensionV2_t".into(),
+                     tags: map! {
+                         "atemp" => "air_temperature",
+                         "wtemp" => "water_temperature",
+                         "hr" => "heart_rate",
+                         "cad" => "cadence",
+                         "Extensions" => "extensions" } },
+        StructInfo { name: "WaypointExtension".into(),
+                     type_name: "WaypointExtension_t".into(),
+                     tags: map! {
+                         "Proximity" => "proximity",
+                         "Temperature" => "temperature",
+                         "Depth" => "depth",
+                         "DisplayMode" => "display_mode",
+                         "Categories" => "categories",
+                         "Address" => "address",
+                         "PhoneNumber" => "phone_numbers",
+                         "Extensions" => "extensions" } },
+        StructInfo { name: "Address".into(),
+                     type_name: "Address_t".into(),
+                     tags: map! {
+                         "StreetAddress" => "street_addresses",
+                         "City" => "city",
+                         "State" => "state",
+                         "Country" => "country",
+                         "PostalCode" => "postal_code",
+                         "Extensions" => "extensions" } },
+        StructInfo { name: "RouteExtension".into(),
+                     type_name: "RouteExtension_t".into(),
+                     tags: map! {
+                         "IsAutoNamed" => "is_auto_named",
+                         "DisplayColor" => "display_color",
+                         "Extensions" => "extensions" } },
+        StructInfo { name: "RoutePointExtension".into(),
+                     type_name: "RoutePointExtension_t".into(),
+                     tags: map! {
+                         "Subclass" => "subclass",
+                         "rpt" => "points",
+                         "Extensions" => "extensions" } },
+        StructInfo { name: "AutoroutePoint".into(),
+                     type_name: "AutoroutePoint_t".into(),
+                     tags: map! {
+                         "lat" => "latitude",
+                         "lon" => "longitude",
+                         "Subclass" => "subclass" } },
+        StructInfo { name: "TrackExtension".into(),
+                     type_name: "TrackExtension_t".into(),
+                     tags: map! {
+                         "DisplayColor" => "display_color",
+                         "Extensions" => "extensions" } },
+    ];
+    let simple_impls = ["BeatsPerMinute_t", "RevolutionsPerMinute_t", "DegreesTrue_t"];
+
+    write_file(&out_dir.join("garmin_par_auto.rs"), |f| {
+        for &(name, type_name, generator) in &parsers {
+            let type_ = get_complex(&types, type_name);
+            f.write(generator.parser_type(name, type_, &convs).as_bytes()).map_err(Error::Io)?;
+            f.write(generator.parser_impl(name, type_, &convs).as_bytes()).map_err(Error::Io)?;
+            f.write(
+                DEFAULT_GENERATOR.parse_impl_complex(name, convs.get(type_name).expect("garmin conv")).as_bytes()
+            ).map_err(Error::Io)?;
+            // the rest build types other than their own and are written by hand
+            if let Some(sinfo) = structs.iter().find(|sinfo| sinfo.type_name == type_name) {
+                f.write(
+                    gpx::Generator::build_impl(name, type_, sinfo, &convs).as_bytes()
+                ).map_err(Error::Io)?;
+            }
+        }
+        for type_name in &simple_impls {
+            f.write(
+                DEFAULT_GENERATOR.parse_impl(type_name, get_simple(&types, type_name),
+                                             &convs, &types).as_bytes()
+            ).map_err(Error::Io)?;
+        }
+        Ok(())
+    })?;
+    write_file(&out_dir.join("garmin_ser_auto.rs"), |f| {
+        for sinfo in &structs {
+            f.write(
+                gpx::Generator::serializer_impl(&sinfo.name, &sinfo.tags, &sinfo.type_name,
+                                                get_complex(&types, &sinfo.type_name), &convs).as_bytes()
+            ).map_err(Error::Io)?;
         }
+        // version 1 writes a part of the same type
+        let tpx = &structs[0];
+        f.write(
+            gpx::Generator::serializer_impl(&tpx.name, &tpx.tags, "TrackPointExtensionV1_t",
+                                            get_complex(&types, "TrackPointExtensionV1_t"), &convs).as_bytes()
+        ).map_err(Error::Io)?;
         Ok(())
-    }));
+    })?;
     Ok(())
 }
 
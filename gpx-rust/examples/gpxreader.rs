@@ -17,14 +17,16 @@ extern crate clap;
 use std::io::BufReader;
 use std::fs::File;
 use clap::{App, Arg};
-use gpx_rust::xml::ParseXml;
-use gpx_rust::gpx::{ Gpx, Parser, Error };
+use gpx_rust::gpx;
+use gpx_rust::gpx::Gpx;
 
 
-fn parse(filename: &str) -> Result<Gpx, Error> {
-    let f = try!(File::open(filename).map_err(Error::Io));
+/// Returns the message of any failure
+fn parse(filename: &str) -> Result<Gpx, String> {
+    let f = File::open(filename).map_err(|e| e.to_string())?;
     let f = BufReader::new(f);
-    Parser::new(f).parse()
+    let (document, _warnings) = gpx::parse(f).map_err(|e| e.to_string())?;
+    Ok(document.data)
 }
  
 fn main() {
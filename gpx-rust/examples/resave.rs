@@ -12,6 +12,10 @@
  */
 
 //! Reads a file and saves somewhere else
+
+// The errors of the library are large, see the same allowance there
+#![allow(clippy::result_large_err, clippy::large_enum_variant)]
+
 extern crate gpx_rust;
 extern crate clap;
 
@@ -23,29 +27,39 @@ use clap::{ App, Arg };
 use gpx_rust::ser;
 use gpx_rust::ser::SerializeDocument;
 use gpx_rust::gpx;
-use gpx_rust::gpx::{ Gpx, Document };
+use gpx_rust::gpx::Document;
+use gpx_rust::gpx::par::ParserOptions;
 
 
 #[derive(Debug)]
+#[allow(dead_code)] // Only read through Debug
 enum ResaveError {
     Io(std::io::Error),
     Serialize(ser::Error),
 }
 
 #[derive(Debug)]
+#[allow(dead_code)] // Only read through Debug
 enum ParseError {
     Io(std::io::Error),
     Parse(gpx::par::DocumentError),
 }
 
-fn parse(filename: &str) -> Result<Document, ParseError> {
-    let f = try!(File::open(filename).map_err(ParseError::Io));
+fn parse(filename: &str, options: &ParserOptions) -> Result<Document, ParseError> {
+    let f = File::open(filename).map_err(ParseError::Io)?;
     let f = BufReader::new(f);
-    gpx::parse(f).map_err(ParseError::Parse)
+    let (document, warnings) = gpx::par::parse_with(f, options).map_err(ParseError::Parse)?;
+    for warning in warnings {
+        println!("Warning: {}", warning);
+    }
+    Ok(document)
 }
 
-fn save(filename: &str, data: Gpx) -> Result<(), ResaveError> {
-    let f = try!(File::create(filename).map_err(ResaveError::Io));
+fn save(filename: &str, data: Document, valid: bool) -> Result<(), ResaveError> {
+    if valid {
+        data.check_valid().map_err(ResaveError::Serialize)?;
+    }
+    let f = File::create(filename).map_err(ResaveError::Io)?;
     let f = BufWriter::new(f);
     data.serialize(f).map_err(ResaveError::Serialize)//, WspMode::IndentLevel(0)).map_err(ResaveError::Io));
 }
@@ -56,13 +70,25 @@ fn main() {
                               .required(true))
                       .arg(Arg::with_name("destination")
                               .required(true))
+                      .arg(Arg::with_name("keep-markup")
+                              .long("keep-markup")
+                              .help("Keep comments, processing instructions and namespace declarations"))
+                      .arg(Arg::with_name("strict")
+                              .long("strict")
+                              .help("Reject elements out of order, repeated too often or missing"))
+                      .arg(Arg::with_name("valid")
+                              .long("valid")
+                              .help("Refuse to save data breaking the constraints of the schema"))
                       .get_matches();
-    let data = match parse(matches.value_of("source").unwrap()) {
+    let options = ParserOptions { keep_markup: matches.is_present("keep-markup"),
+                                  strict: matches.is_present("strict"),
+                                  ..ParserOptions::default() };
+    let data = match parse(matches.value_of("source").unwrap(), &options) {
         Err(e) => {
             println!("Failed to load\n{:?}", e);
             exit(1);
         }
-        Ok(doc) => doc.data
+        Ok(doc) => doc
     };
-    save(matches.value_of("destination").unwrap(), data).expect("Failed to save");
+    save(matches.value_of("destination").unwrap(), data, matches.is_present("valid")).expect("Failed to save");
 }
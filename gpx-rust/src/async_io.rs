@@ -0,0 +1,221 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Parsing from `futures` streams and writing to `futures` sinks, enabled by the `async` feature.
+//!
+//! ```ignore
+//! let (document, warnings) = gpx::par::parse_async(upload).await.unwrap();
+//! document.serialize_async(&mut response).await.unwrap();
+//! ```
+//!
+//! The element parsers read synchronously, so a parse runs on a thread of its own.
+//! The input is handed to it in chunks as it arrives,
+//! and only a few chunks are buffered when the parser falls behind.
+//! Every parse started spawns a thread, without any bound on how many run at once,
+//! so servers should limit the number of concurrent parses themselves.
+//! If the thread can't be started, or ends without a result, the future resolves to an error.
+//! A panic of the parser resumes in the task awaiting the result.
+//!
+//! Serialization is not streamed. The whole document is serialized to memory first,
+//! then written to the sink, so the output stays in memory until the sink accepts all of it.
+
+extern crate futures;
+
+use std::cmp;
+use std::io;
+use std::io::Read;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::thread;
+use std::future::Future;
+use std::task::{ Context, Poll };
+
+use self::futures::channel::{ mpsc, oneshot };
+use self::futures::executor;
+
+pub use self::futures::io::{ AsyncRead, AsyncWrite };
+
+use ser;
+
+
+/// Size of chunks read from the source
+const CHUNK_SIZE: usize = 8192;
+/// Chunks waiting for the parser before reading stops
+const QUEUED_CHUNKS: usize = 4;
+
+type Chunk = io::Result<Vec<u8>>;
+
+/// Input of the parser thread, blocks until the next chunk arrives
+pub struct ChunkReader {
+    chunks: executor::BlockingStream<mpsc::Receiver<Chunk>>,
+    current: Vec<u8>,
+    offset: usize,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset == self.current.len() {
+            match self.chunks.next() {
+                Some(chunk) => {
+                    self.current = chunk?;
+                    self.offset = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let count = cmp::min(buf.len(), self.current.len() - self.offset);
+        buf[..count].copy_from_slice(&self.current[self.offset..self.offset + count]);
+        self.offset += count;
+        Ok(count)
+    }
+}
+
+/// Result of `parse` running on `source`, resolved when the parser thread finishes
+pub struct ParseFuture<R, T, E> {
+    source: R,
+    buffer: Vec<u8>,
+    /// Dropped when the source ends or the parser doesn't need more
+    chunks: Option<mpsc::Sender<Chunk>>,
+    result: oneshot::Receiver<thread::Result<Result<T, E>>>,
+    /// Failure to start the parser thread, reported instead of its result
+    spawn_error: Option<io::Error>,
+}
+
+/// Starts `parse` on a new thread, with the contents of `source` as its input.
+///
+/// The thread ends with the parse, see the module documentation about bounding them.
+pub fn parse<R, T, E, F>(source: R, parse: F) -> ParseFuture<R, T, E>
+        where R: AsyncRead + Unpin, T: Send + 'static, E: From<io::Error> + Send + 'static,
+              F: FnOnce(ChunkReader) -> Result<T, E> + Send + 'static {
+    let (chunks, receiver) = mpsc::channel(QUEUED_CHUNKS);
+    let (done, result) = oneshot::channel();
+    let spawned = thread::Builder::new().name("gpx parser".into()).spawn(move || {
+        let input = ChunkReader { chunks: executor::block_on_stream(receiver),
+                                  current: Vec::new(),
+                                  offset: 0 };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| parse(input)));
+        // the future may be gone already
+        let _ = done.send(result);
+    });
+    let (chunks, spawn_error) = match spawned {
+        Ok(_) => (Some(chunks), None),
+        Err(e) => (None, Some(e)),
+    };
+    ParseFuture { source,
+                  buffer: vec![0; CHUNK_SIZE],
+                  chunks,
+                  result,
+                  spawn_error }
+}
+
+impl<R: AsyncRead + Unpin, T, E> ParseFuture<R, T, E> {
+    /// Passes chunks of the source to the parser until either of them makes it wait
+    fn feed(&mut self, cx: &mut Context) -> Poll<()> {
+        loop {
+            let chunks = match self.chunks {
+                Some(ref mut chunks) => chunks,
+                None => return Poll::Ready(()),
+            };
+            match chunks.poll_ready(cx) {
+                Poll::Ready(Ok(())) => {}
+                // the parser stopped reading
+                Poll::Ready(Err(_)) => {
+                    self.chunks = None;
+                    continue;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+            let chunk = match Pin::new(&mut self.source).poll_read(cx, &mut self.buffer) {
+                Poll::Ready(Ok(0)) => {
+                    self.chunks = None;
+                    continue;
+                }
+                Poll::Ready(Ok(count)) => Ok(self.buffer[..count].to_vec()),
+                Poll::Ready(Err(e)) => Err(e),
+                Poll::Pending => return Poll::Pending,
+            };
+            let failed = chunk.is_err();
+            if chunks.start_send(chunk).is_err() || failed {
+                self.chunks = None;
+            }
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin, T, E: From<io::Error>> Future for ParseFuture<R, T, E> {
+    type Output = Result<T, E>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Some(e) = this.spawn_error.take() {
+            return Poll::Ready(Err(e.into()));
+        }
+        // the parser may finish without reading everything
+        let _ = this.feed(cx);
+        match Pin::new(&mut this.result).poll(cx) {
+            Poll::Ready(Ok(Ok(result))) => Poll::Ready(result),
+            Poll::Ready(Ok(Err(payload))) => panic::resume_unwind(payload),
+            Poll::Ready(Err(oneshot::Canceled)) => {
+                let e = io::Error::other("Parser thread ended without a result");
+                Poll::Ready(Err(e.into()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+fn writer_error(e: io::Error) -> ser::Error {
+    ser::Error::Writer(e.into())
+}
+
+/// Writes a document serialized to memory to `sink` and flushes it
+pub struct SerializeFuture<'a, W: 'a> {
+    sink: &'a mut W,
+    data: Vec<u8>,
+    written: usize,
+    /// Failure of serialization, reported before writing anything
+    error: Option<ser::Error>,
+}
+
+impl<'a, W: AsyncWrite + Unpin> SerializeFuture<'a, W> {
+    /// Prepares to write `data`, the result of a serializer
+    pub fn new(sink: &'a mut W, data: Result<Vec<u8>, ser::Error>) -> Self {
+        let (data, error) = match data {
+            Ok(data) => (data, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        SerializeFuture { sink, data, written: 0, error }
+    }
+}
+
+impl<'a, W: AsyncWrite + Unpin> Future for SerializeFuture<'a, W> {
+    type Output = Result<(), ser::Error>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Some(e) = this.error.take() {
+            return Poll::Ready(Err(e));
+        }
+        while this.written < this.data.len() {
+            match Pin::new(&mut *this.sink).poll_write(cx, &this.data[this.written..]) {
+                Poll::Ready(Ok(0)) => {
+                    let e = io::Error::new(io::ErrorKind::WriteZero, "Sink accepts no more data");
+                    return Poll::Ready(Err(writer_error(e)));
+                }
+                Poll::Ready(Ok(count)) => this.written += count,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(writer_error(e))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Pin::new(&mut *this.sink).poll_flush(cx).map(|result| result.map_err(writer_error))
+    }
+}
@@ -0,0 +1,282 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! GPX types borrowing their text from the document, for reading data without changing it.
+//!
+//! ```ignore
+//! let data = fs::read_to_string("foo.gpx").unwrap();
+//! let (gpx, warnings) = gpx::borrowed::parse(&data).unwrap();
+//! for segment in &gpx.tracks[0].segments {
+//!     println!("{} points", segment.waypoints.len());
+//! }
+//! let owned: gpx::Gpx = gpx.into_owned();
+//! ```
+//!
+//! Strings stay in the document unless entity references, CDATA sections or comments
+//! make them differ from it. Other values and extensions are the same as in the owned types,
+//! which are described in the `gpx` module.
+
+use std::borrow::Cow;
+
+use xml;
+use xsd;
+use gpx;
+use gpx::{ Version, Point, Fix, Bounds, Degrees, Email };
+
+pub use gpx::par::borrowed::{ parse, parse_with, parse_bytes };
+
+
+/// `gpxType` contents
+#[derive(Debug)]
+pub struct Gpx<'a> {
+    pub version: Version,
+    pub creator: Cow<'a, str>,
+    pub metadata: Option<Metadata<'a>>,
+    pub waypoints: Vec<Waypoint<'a>>,
+    pub routes: Vec<Route<'a>>,
+    pub tracks: Vec<Track<'a>>,
+    pub extensions: Option<xml::Element>,
+}
+
+/// `metadataType` contents
+#[derive(Debug)]
+pub struct Metadata<'a> {
+    pub name: Option<Cow<'a, str>>,
+    pub description: Option<Cow<'a, str>>,
+    pub author: Option<Person<'a>>,
+    pub copyright: Option<Copyright<'a>>,
+    pub links: Vec<Link<'a>>,
+    pub time: Option<xsd::Time>,
+    pub keywords: Option<Cow<'a, str>>,
+    pub bounds: Option<Bounds>,
+    pub extensions: Option<xml::Element>,
+}
+
+/// `personType` contents
+#[derive(Debug)]
+pub struct Person<'a> {
+    pub name: Option<Cow<'a, str>>,
+    pub email: Option<Email>,
+    pub link: Option<Link<'a>>,
+}
+
+/// `copyrightType` contents
+#[derive(Debug)]
+pub struct Copyright<'a> {
+    pub author: Cow<'a, str>,
+    pub year: Option<xsd::GYear>,
+    /// Checked when parsed, so never borrowed
+    pub license: Option<xsd::Uri>,
+}
+
+/// `linkType` contents
+#[derive(Debug)]
+pub struct Link<'a> {
+    /// Checked when parsed, so never borrowed
+    pub href: xsd::Uri,
+    pub text: Option<Cow<'a, str>>,
+    pub type_: Option<Cow<'a, str>>,
+}
+
+/// `<wpt>`, `<rtept>`, `<trkpt>` elements and `wptType`
+#[derive(Debug)]
+pub struct Waypoint<'a> {
+    pub location: Point,
+    pub time: Option<xsd::DateTime>,
+    /// Instantaneous course at the point, GPX 1.0 `<course>`
+    pub course: Option<Degrees>,
+    /// Speed at the point in meters per second, GPX 1.0 `<speed>`
+    pub speed: Option<xsd::Decimal>,
+    pub mag_variation: Option<Degrees>,
+    pub geoid_height: Option<xsd::Decimal>,
+    pub name: Option<Cow<'a, str>>,
+    pub comment: Option<Cow<'a, str>>,
+    pub description: Option<Cow<'a, str>>,
+    pub source: Option<Cow<'a, str>>,
+    pub links: Vec<Link<'a>>,
+    pub symbol: Option<Cow<'a, str>>,
+    pub type_: Option<Cow<'a, str>>,
+    pub fix: Option<Fix>,
+    pub satellites: Option<xsd::NonNegativeInteger>,
+    pub hdop: Option<xsd::Decimal>,
+    pub pdop: Option<xsd::Decimal>,
+    pub vdop: Option<xsd::Decimal>,
+    pub dgps_age: Option<xsd::Decimal>,
+    pub dgps_id: Option<u16>,
+    pub extensions: Option<xml::Element>,
+}
+
+/// `<trk>` and `trkType`
+#[derive(Debug)]
+pub struct Track<'a> {
+    pub name: Option<Cow<'a, str>>,
+    pub comment: Option<Cow<'a, str>>,
+    pub description: Option<Cow<'a, str>>,
+    pub source: Option<Cow<'a, str>>,
+    pub links: Vec<Link<'a>>,
+    pub number: Option<xsd::NonNegativeInteger>,
+    pub type_: Option<Cow<'a, str>>,
+    pub extensions: Option<xml::Element>,
+    pub segments: Vec<TrackSegment<'a>>,
+}
+
+/// `<trkseg>` and `trksegType`
+#[derive(Debug)]
+pub struct TrackSegment<'a> {
+    pub waypoints: Vec<Waypoint<'a>>,
+    pub extensions: Option<xml::Element>,
+}
+
+/// `<rte>` and `rteType`
+#[derive(Debug)]
+pub struct Route<'a> {
+    pub name: Option<Cow<'a, str>>,
+    pub comment: Option<Cow<'a, str>>,
+    pub description: Option<Cow<'a, str>>,
+    pub source: Option<Cow<'a, str>>,
+    pub links: Vec<Link<'a>>,
+    pub number: Option<xsd::NonNegativeInteger>,
+    pub type_: Option<Cow<'a, str>>,
+    pub extensions: Option<xml::Element>,
+    pub waypoints: Vec<Waypoint<'a>>,
+}
+
+fn owned(text: Option<Cow<str>>) -> Option<String> {
+    text.map(Cow::into_owned)
+}
+
+fn owned_links(links: Vec<Link>) -> Vec<gpx::Link> {
+    links.into_iter().map(Link::into_owned).collect()
+}
+
+fn owned_points(points: Vec<Waypoint>) -> Vec<gpx::Waypoint> {
+    points.into_iter().map(Waypoint::into_owned).collect()
+}
+
+impl<'a> Gpx<'a> {
+    /// Copies the borrowed strings, leaving other values in place
+    pub fn into_owned(self) -> gpx::Gpx {
+        gpx::Gpx { version: self.version,
+                   creator: self.creator.into_owned(),
+                   metadata: self.metadata.map(Metadata::into_owned),
+                   waypoints: owned_points(self.waypoints),
+                   routes: self.routes.into_iter().map(Route::into_owned).collect(),
+                   tracks: self.tracks.into_iter().map(Track::into_owned).collect(),
+                   extensions: self.extensions }
+    }
+}
+
+impl<'a> Metadata<'a> {
+    pub fn into_owned(self) -> gpx::Metadata {
+        gpx::Metadata { name: owned(self.name),
+                        description: owned(self.description),
+                        author: self.author.map(Person::into_owned),
+                        copyright: self.copyright.map(Copyright::into_owned),
+                        links: owned_links(self.links),
+                        time: self.time,
+                        keywords: owned(self.keywords),
+                        bounds: self.bounds,
+                        extensions: self.extensions }
+    }
+}
+
+impl<'a> Person<'a> {
+    pub fn into_owned(self) -> gpx::Person {
+        gpx::Person { name: owned(self.name),
+                      email: self.email,
+                      link: self.link.map(Link::into_owned) }
+    }
+}
+
+impl<'a> Copyright<'a> {
+    pub fn into_owned(self) -> gpx::Copyright {
+        gpx::Copyright { author: self.author.into_owned(),
+                         year: self.year,
+                         license: self.license }
+    }
+}
+
+impl<'a> Link<'a> {
+    pub fn into_owned(self) -> gpx::Link {
+        gpx::Link { href: self.href,
+                    text: owned(self.text),
+                    type_: owned(self.type_) }
+    }
+}
+
+impl<'a> Waypoint<'a> {
+    pub fn into_owned(self) -> gpx::Waypoint {
+        gpx::Waypoint { location: self.location,
+                        time: self.time,
+                        course: self.course,
+                        speed: self.speed,
+                        mag_variation: self.mag_variation,
+                        geoid_height: self.geoid_height,
+                        name: owned(self.name),
+                        comment: owned(self.comment),
+                        description: owned(self.description),
+                        source: owned(self.source),
+                        links: owned_links(self.links),
+                        symbol: owned(self.symbol),
+                        type_: owned(self.type_),
+                        fix: self.fix,
+                        satellites: self.satellites,
+                        hdop: self.hdop,
+                        pdop: self.pdop,
+                        vdop: self.vdop,
+                        dgps_age: self.dgps_age,
+                        dgps_id: self.dgps_id,
+                        extensions: self.extensions }
+    }
+}
+
+impl<'a> Track<'a> {
+    pub fn into_owned(self) -> gpx::Track {
+        gpx::Track { name: owned(self.name),
+                     comment: owned(self.comment),
+                     description: owned(self.description),
+                     source: owned(self.source),
+                     links: owned_links(self.links),
+                     number: self.number,
+                     type_: owned(self.type_),
+                     extensions: self.extensions,
+                     segments: self.segments.into_iter().map(TrackSegment::into_owned).collect() }
+    }
+}
+
+impl<'a> TrackSegment<'a> {
+    pub fn into_owned(self) -> gpx::TrackSegment {
+        gpx::TrackSegment { waypoints: owned_points(self.waypoints),
+                            extensions: self.extensions }
+    }
+}
+
+impl<'a> Route<'a> {
+    pub fn into_owned(self) -> gpx::Route {
+        gpx::Route { name: owned(self.name),
+                     comment: owned(self.comment),
+                     description: owned(self.description),
+                     source: owned(self.source),
+                     links: owned_links(self.links),
+                     number: self.number,
+                     type_: owned(self.type_),
+                     extensions: self.extensions,
+                     waypoints: owned_points(self.waypoints) }
+    }
+}
+
+impl<'a> From<Gpx<'a>> for gpx::Gpx {
+    fn from(gpx: Gpx<'a>) -> gpx::Gpx {
+        gpx.into_owned()
+    }
+}
@@ -0,0 +1,202 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Builders for GPX data.
+//!
+//! ```ignore
+//! let point = Waypoint::at(52.52, 13.40).ele(34.0).time(time).name("Start").build().unwrap();
+//! let track = Track::builder().name("Walk").segment(vec![point]).build().unwrap();
+//! let mut gpx = Gpx::new("exporter");
+//! gpx.tracks.push(track);
+//! ```
+//!
+//! `build` checks the values against the constraints of the schema like `Gpx::validate`,
+//! so that latitudes, longitudes, degrees and DGPS station numbers out of range
+//! are refused before the data gets written. Points added to routes and tracks are checked again,
+//! with paths leading to them from the route or track.
+//!
+//! All types also implement `Default`, to be filled in with the struct update syntax.
+
+use xml;
+use xsd;
+use gpx::{ Gpx, Version, Waypoint, Point, Link, Fix, Degrees, Track, TrackSegment, Route };
+use gpx::validate::{ Invalid, Violation };
+
+
+impl Gpx {
+    /// Empty GPX 1.1 document data, written by `creator`
+    pub fn new<S: Into<String>>(creator: S) -> Gpx {
+        Gpx { version: Version::V1_1,
+              creator: creator.into(),
+              ..Gpx::default() }
+    }
+}
+
+impl Waypoint {
+    /// Starts building the point at `latitude` and `longitude`
+    pub fn at(latitude: f64, longitude: f64) -> WaypointBuilder {
+        WaypointBuilder {
+            point: Waypoint { location: Point { latitude,
+                                                longitude,
+                                                elevation: None },
+                              ..Waypoint::default() }
+        }
+    }
+}
+
+impl Track {
+    pub fn builder() -> TrackBuilder {
+        TrackBuilder { track: Track::default() }
+    }
+}
+
+impl Route {
+    pub fn builder() -> RouteBuilder {
+        RouteBuilder { route: Route::default() }
+    }
+}
+
+impl From<Vec<Waypoint>> for TrackSegment {
+    fn from(waypoints: Vec<Waypoint>) -> TrackSegment {
+        TrackSegment { waypoints, extensions: None }
+    }
+}
+
+/// Returns `value` unless `validate` found violations in it
+fn checked<T>(value: T, validate: fn(&T) -> Vec<Violation>) -> Result<T, Invalid> {
+    let violations = validate(&value);
+    if violations.is_empty() {
+        Ok(value)
+    } else {
+        Err(Invalid(violations))
+    }
+}
+
+/// Sets optional fields of the value being built, named like the fields
+macro_rules! setters(
+    ($target:ident { $($name:ident: $type_:ty),* }) => { $(
+        pub fn $name(mut self, value: $type_) -> Self {
+            self.$target.$name = Some(value);
+            self
+        }
+    )* }
+);
+
+/// Sets optional text fields of the value being built, named like the fields
+macro_rules! text_setters(
+    ($target:ident { $($name:ident),* }) => { $(
+        pub fn $name<S: Into<String>>(mut self, value: S) -> Self {
+            self.$target.$name = Some(value.into());
+            self
+        }
+    )* }
+);
+
+/// Builds `Waypoint`, see `Waypoint::at`
+#[derive(Debug)]
+pub struct WaypointBuilder {
+    point: Waypoint,
+}
+
+impl WaypointBuilder {
+    /// Elevation in meters, `<ele>`
+    pub fn ele(mut self, elevation: f64) -> Self {
+        self.point.location.elevation = Some(elevation);
+        self
+    }
+
+    setters!(point { time: xsd::DateTime,
+                     course: Degrees,
+                     speed: xsd::Decimal,
+                     mag_variation: Degrees,
+                     geoid_height: xsd::Decimal,
+                     fix: Fix,
+                     satellites: xsd::NonNegativeInteger,
+                     hdop: xsd::Decimal,
+                     pdop: xsd::Decimal,
+                     vdop: xsd::Decimal,
+                     dgps_age: xsd::Decimal,
+                     dgps_id: u16,
+                     extensions: xml::Element });
+
+    text_setters!(point { name, comment, description, source, symbol, type_ });
+
+    /// Adds a link after the ones added before
+    pub fn link(mut self, link: Link) -> Self {
+        self.point.links.push(link);
+        self
+    }
+
+    /// Returns the point, or the values out of range
+    pub fn build(self) -> Result<Waypoint, Invalid> {
+        checked(self.point, Waypoint::validate)
+    }
+}
+
+/// Builds `Track`, see `Track::builder`
+#[derive(Debug)]
+pub struct TrackBuilder {
+    track: Track,
+}
+
+impl TrackBuilder {
+    setters!(track { number: xsd::NonNegativeInteger, extensions: xml::Element });
+
+    text_setters!(track { name, comment, description, source, type_ });
+
+    /// Adds a link after the ones added before
+    pub fn link(mut self, link: Link) -> Self {
+        self.track.links.push(link);
+        self
+    }
+
+    /// Adds a segment after the ones added before, for example from a `Vec<Waypoint>`
+    pub fn segment<S: Into<TrackSegment>>(mut self, segment: S) -> Self {
+        self.track.segments.push(segment.into());
+        self
+    }
+
+    /// Returns the track, or the values out of range in its points
+    pub fn build(self) -> Result<Track, Invalid> {
+        checked(self.track, Track::validate)
+    }
+}
+
+/// Builds `Route`, see `Route::builder`
+#[derive(Debug)]
+pub struct RouteBuilder {
+    route: Route,
+}
+
+impl RouteBuilder {
+    setters!(route { number: xsd::NonNegativeInteger, extensions: xml::Element });
+
+    text_setters!(route { name, comment, description, source, type_ });
+
+    /// Adds a link after the ones added before
+    pub fn link(mut self, link: Link) -> Self {
+        self.route.links.push(link);
+        self
+    }
+
+    /// Adds a point after the ones added before
+    pub fn point(mut self, point: Waypoint) -> Self {
+        self.route.waypoints.push(point);
+        self
+    }
+
+    /// Returns the route, or the values out of range in its points
+    pub fn build(self) -> Result<Route, Invalid> {
+        checked(self.route, Route::validate)
+    }
+}
@@ -0,0 +1,397 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+//! Compact storage for long tracks.
+//!
+//! `ColumnarSegment` keeps the points of a track segment in columns: coordinates, elevations
+//! and times in parallel arrays, and each remaining field of `Waypoint` in a column of its own,
+//! holding values only for the points which carry that field.
+//! A point costs about 50 bytes, plus the fields it carries, instead of several hundred.
+//!
+//! Segments convert to and from `TrackSegment` without losing data.
+//! To avoid holding the whole track as `Waypoint`s, fill them from the streaming parser:
+//!
+//! ```ignore
+//! let mut segments = Vec::new();
+//! for event in gpx::par::stream(f) {
+//!     match event.unwrap() {
+//!         Event::SegmentStart => segments.push(ColumnarSegment::new()),
+//!         Event::TrackPoint(point) => segments.last_mut().unwrap().push(point),
+//!         Event::SegmentEnd(extensions) => segments.last_mut().unwrap().extensions = extensions,
+//!         _ => {}
+//!     }
+//! }
+//! for point in &segments[0] {
+//!     println!("{} {} {:?}", point.latitude(), point.longitude(), point.name());
+//! }
+//! ```
+
+use std::iter::Peekable;
+use std::vec;
+
+use xml;
+use xsd;
+use gpx::{ Waypoint, TrackSegment, Point, Link, Fix, Degrees };
+use gpx::ext;
+
+
+/// Appends the value of the point at `index`, if it has one
+fn put<T>(column: &mut Vec<(usize, T)>, index: usize, value: Option<T>) {
+    if let Some(value) = value {
+        column.push((index, value));
+    }
+}
+
+/// Returns the value of the point at `index`
+fn find<T>(column: &[(usize, T)], index: usize) -> Option<&T> {
+    column.binary_search_by_key(&index, |&(i, _)| i).ok().map(|found| &column[found].1)
+}
+
+/// Takes the value of the point at `index` from a column read in order
+fn take<T>(column: &mut Peekable<vec::IntoIter<(usize, T)>>, index: usize) -> Option<T> {
+    if column.peek().map(|&(i, _)| i) == Some(index) {
+        column.next().map(|(_, value)| value)
+    } else {
+        None
+    }
+}
+
+/// `<trkseg>` with its points stored in columns.
+///
+/// Optional fields are kept as `(point index, value)` for the points which have them,
+/// sorted by index.
+#[derive(XmlDebug, Default)]
+pub struct ColumnarSegment {
+    latitudes: Vec<f64>,
+    longitudes: Vec<f64>,
+    elevations: Vec<Option<f64>>,
+    times: Vec<Option<xsd::DateTime>>,
+    courses: Vec<(usize, Degrees)>,
+    speeds: Vec<(usize, xsd::Decimal)>,
+    mag_variations: Vec<(usize, Degrees)>,
+    geoid_heights: Vec<(usize, xsd::Decimal)>,
+    names: Vec<(usize, String)>,
+    comments: Vec<(usize, String)>,
+    descriptions: Vec<(usize, String)>,
+    sources: Vec<(usize, String)>,
+    /// Links of the points which have any
+    links: Vec<(usize, Vec<Link>)>,
+    symbols: Vec<(usize, String)>,
+    types: Vec<(usize, String)>,
+    fixes: Vec<(usize, Fix)>,
+    satellites: Vec<(usize, xsd::NonNegativeInteger)>,
+    hdops: Vec<(usize, xsd::Decimal)>,
+    pdops: Vec<(usize, xsd::Decimal)>,
+    vdops: Vec<(usize, xsd::Decimal)>,
+    dgps_ages: Vec<(usize, xsd::Decimal)>,
+    dgps_ids: Vec<(usize, u16)>,
+    /// Extensions of points
+    point_extensions: Vec<(usize, xml::Element)>,
+    /// Extensions of the segment itself
+    pub extensions: Option<xml::Element>,
+}
+
+impl ColumnarSegment {
+    pub fn new() -> Self {
+        ColumnarSegment::default()
+    }
+
+    /// Number of points
+    pub fn len(&self) -> usize {
+        self.latitudes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latitudes.is_empty()
+    }
+
+    /// Appends a point
+    pub fn push(&mut self, point: Waypoint) {
+        let index = self.len();
+        let Waypoint { location, time, course, speed, mag_variation, geoid_height, name, comment,
+                       description, source, links, symbol, type_, fix, satellites, hdop, pdop, vdop,
+                       dgps_age, dgps_id, extensions } = point;
+        self.latitudes.push(location.latitude);
+        self.longitudes.push(location.longitude);
+        self.elevations.push(location.elevation);
+        self.times.push(time);
+        put(&mut self.courses, index, course);
+        put(&mut self.speeds, index, speed);
+        put(&mut self.mag_variations, index, mag_variation);
+        put(&mut self.geoid_heights, index, geoid_height);
+        put(&mut self.names, index, name);
+        put(&mut self.comments, index, comment);
+        put(&mut self.descriptions, index, description);
+        put(&mut self.sources, index, source);
+        put(&mut self.links, index, if links.is_empty() { None } else { Some(links) });
+        put(&mut self.symbols, index, symbol);
+        put(&mut self.types, index, type_);
+        put(&mut self.fixes, index, fix);
+        put(&mut self.satellites, index, satellites);
+        put(&mut self.hdops, index, hdop);
+        put(&mut self.pdops, index, pdop);
+        put(&mut self.vdops, index, vdop);
+        put(&mut self.dgps_ages, index, dgps_age);
+        put(&mut self.dgps_ids, index, dgps_id);
+        put(&mut self.point_extensions, index, extensions);
+    }
+
+    /// Returns the point at `index`
+    pub fn get(&self, index: usize) -> Option<WaypointView<'_>> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(WaypointView { segment: self, index })
+    }
+
+    pub fn iter(&self) -> Points<'_> {
+        Points { segment: self, index: 0 }
+    }
+
+    pub fn latitudes(&self) -> &[f64] {
+        &self.latitudes
+    }
+
+    pub fn longitudes(&self) -> &[f64] {
+        &self.longitudes
+    }
+
+    pub fn elevations(&self) -> &[Option<f64>] {
+        &self.elevations
+    }
+
+    pub fn times(&self) -> &[Option<xsd::DateTime>] {
+        &self.times
+    }
+}
+
+impl From<TrackSegment> for ColumnarSegment {
+    fn from(segment: TrackSegment) -> ColumnarSegment {
+        let mut columnar = ColumnarSegment { latitudes: Vec::with_capacity(segment.waypoints.len()),
+                                             longitudes: Vec::with_capacity(segment.waypoints.len()),
+                                             elevations: Vec::with_capacity(segment.waypoints.len()),
+                                             times: Vec::with_capacity(segment.waypoints.len()),
+                                             extensions: segment.extensions,
+                                             ..ColumnarSegment::default() };
+        for point in segment.waypoints {
+            columnar.push(point);
+        }
+        columnar
+    }
+}
+
+impl From<ColumnarSegment> for TrackSegment {
+    fn from(segment: ColumnarSegment) -> TrackSegment {
+        let mut courses = segment.courses.into_iter().peekable();
+        let mut speeds = segment.speeds.into_iter().peekable();
+        let mut mag_variations = segment.mag_variations.into_iter().peekable();
+        let mut geoid_heights = segment.geoid_heights.into_iter().peekable();
+        let mut names = segment.names.into_iter().peekable();
+        let mut comments = segment.comments.into_iter().peekable();
+        let mut descriptions = segment.descriptions.into_iter().peekable();
+        let mut sources = segment.sources.into_iter().peekable();
+        let mut links = segment.links.into_iter().peekable();
+        let mut symbols = segment.symbols.into_iter().peekable();
+        let mut types = segment.types.into_iter().peekable();
+        let mut fixes = segment.fixes.into_iter().peekable();
+        let mut satellites = segment.satellites.into_iter().peekable();
+        let mut hdops = segment.hdops.into_iter().peekable();
+        let mut pdops = segment.pdops.into_iter().peekable();
+        let mut vdops = segment.vdops.into_iter().peekable();
+        let mut dgps_ages = segment.dgps_ages.into_iter().peekable();
+        let mut dgps_ids = segment.dgps_ids.into_iter().peekable();
+        let mut extensions = segment.point_extensions.into_iter().peekable();
+        let columns = segment.latitudes.into_iter()
+                                       .zip(segment.longitudes)
+                                       .zip(segment.elevations)
+                                       .zip(segment.times);
+        let waypoints = columns.enumerate().map(|(index, (((latitude, longitude), elevation), time))| {
+            Waypoint { location: Point { latitude,
+                                         longitude,
+                                         elevation },
+                       time,
+                       course: take(&mut courses, index),
+                       speed: take(&mut speeds, index),
+                       mag_variation: take(&mut mag_variations, index),
+                       geoid_height: take(&mut geoid_heights, index),
+                       name: take(&mut names, index),
+                       comment: take(&mut comments, index),
+                       description: take(&mut descriptions, index),
+                       source: take(&mut sources, index),
+                       links: take(&mut links, index).unwrap_or_default(),
+                       symbol: take(&mut symbols, index),
+                       type_: take(&mut types, index),
+                       fix: take(&mut fixes, index),
+                       satellites: take(&mut satellites, index),
+                       hdop: take(&mut hdops, index),
+                       pdop: take(&mut pdops, index),
+                       vdop: take(&mut vdops, index),
+                       dgps_age: take(&mut dgps_ages, index),
+                       dgps_id: take(&mut dgps_ids, index),
+                       extensions: take(&mut extensions, index) }
+        }).collect();
+        TrackSegment { waypoints, extensions: segment.extensions }
+    }
+}
+
+/// Point of a `ColumnarSegment`, read in place.
+///
+/// Fields other than the location and time are looked up in their columns on each call.
+#[derive(Clone, Copy)]
+pub struct WaypointView<'a> {
+    segment: &'a ColumnarSegment,
+    index: usize,
+}
+
+impl<'a> WaypointView<'a> {
+    /// Position of the point in the segment
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn location(&self) -> Point {
+        Point { latitude: self.latitude(),
+                longitude: self.longitude(),
+                elevation: self.elevation() }
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.segment.latitudes[self.index]
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.segment.longitudes[self.index]
+    }
+
+    pub fn elevation(&self) -> Option<f64> {
+        self.segment.elevations[self.index]
+    }
+
+    pub fn time(&self) -> Option<&'a xsd::DateTime> {
+        self.segment.times[self.index].as_ref()
+    }
+
+    pub fn course(&self) -> Option<Degrees> {
+        find(&self.segment.courses, self.index).cloned()
+    }
+
+    pub fn speed(&self) -> Option<xsd::Decimal> {
+        find(&self.segment.speeds, self.index).cloned()
+    }
+
+    pub fn mag_variation(&self) -> Option<Degrees> {
+        find(&self.segment.mag_variations, self.index).cloned()
+    }
+
+    pub fn geoid_height(&self) -> Option<xsd::Decimal> {
+        find(&self.segment.geoid_heights, self.index).cloned()
+    }
+
+    pub fn name(&self) -> Option<&'a str> {
+        find(&self.segment.names, self.index).map(String::as_str)
+    }
+
+    pub fn comment(&self) -> Option<&'a str> {
+        find(&self.segment.comments, self.index).map(String::as_str)
+    }
+
+    pub fn description(&self) -> Option<&'a str> {
+        find(&self.segment.descriptions, self.index).map(String::as_str)
+    }
+
+    pub fn source(&self) -> Option<&'a str> {
+        find(&self.segment.sources, self.index).map(String::as_str)
+    }
+
+    pub fn links(&self) -> &'a [Link] {
+        find(&self.segment.links, self.index).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn symbol(&self) -> Option<&'a str> {
+        find(&self.segment.symbols, self.index).map(String::as_str)
+    }
+
+    pub fn type_(&self) -> Option<&'a str> {
+        find(&self.segment.types, self.index).map(String::as_str)
+    }
+
+    pub fn fix(&self) -> Option<&'a Fix> {
+        find(&self.segment.fixes, self.index)
+    }
+
+    pub fn satellites(&self) -> Option<xsd::NonNegativeInteger> {
+        find(&self.segment.satellites, self.index).cloned()
+    }
+
+    pub fn hdop(&self) -> Option<xsd::Decimal> {
+        find(&self.segment.hdops, self.index).cloned()
+    }
+
+    pub fn pdop(&self) -> Option<xsd::Decimal> {
+        find(&self.segment.pdops, self.index).cloned()
+    }
+
+    pub fn vdop(&self) -> Option<xsd::Decimal> {
+        find(&self.segment.vdops, self.index).cloned()
+    }
+
+    pub fn dgps_age(&self) -> Option<xsd::Decimal> {
+        find(&self.segment.dgps_ages, self.index).cloned()
+    }
+
+    pub fn dgps_id(&self) -> Option<u16> {
+        find(&self.segment.dgps_ids, self.index).cloned()
+    }
+
+    pub fn extensions(&self) -> Option<&'a xml::Element> {
+        find(&self.segment.point_extensions, self.index)
+    }
+
+    /// Returns the first extension of type `T`, see the `ext` module
+    pub fn extension<T: 'static>(&self) -> Option<&'a T> {
+        ext::find_in(self.extensions())
+    }
+}
+
+/// Iterator over the points of a `ColumnarSegment`
+pub struct Points<'a> {
+    segment: &'a ColumnarSegment,
+    index: usize,
+}
+
+impl<'a> Iterator for Points<'a> {
+    type Item = WaypointView<'a>;
+
+    fn next(&mut self) -> Option<WaypointView<'a>> {
+        let point = self.segment.get(self.index);
+        if point.is_some() {
+            self.index += 1;
+        }
+        point
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.segment.len() - self.index;
+        (left, Some(left))
+    }
+}
+
+impl<'a> ExactSizeIterator for Points<'a> {}
+
+impl<'a> IntoIterator for &'a ColumnarSegment {
+    type Item = WaypointView<'a>;
+    type IntoIter = Points<'a>;
+
+    fn into_iter(self) -> Points<'a> {
+        self.iter()
+    }
+}
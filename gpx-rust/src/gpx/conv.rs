@@ -49,8 +49,8 @@ pub struct Fix {}
 /// Defines conversions for GPX degreesType
 pub struct Degrees {}
 
-/// Defines conversions for GPX extensionsType
-pub type Extensions = ::xml::conv::Element;
+/// Defines conversions for GPX extensionsType, typing registered extensions
+pub struct Extensions {}
 
 /// Defines conversion for GPX dgpsStationType
 pub struct DgpsStation {}
@@ -61,8 +61,26 @@ pub struct Copyright {}
 /// Defines conversion for GPX personType
 pub struct Person {}
 
-/// Defines conversion between String and GPX emailType
+/// Defines conversion between gpx::Email and GPX emailType
 pub struct Email {}
 
 /// Defines conversion for GPX wptType
 pub struct Wpt {}
+
+/// Converters for GPX 1.0
+pub mod v1_0 {
+    /// Defines conversions between gpx::Email and the GPX 1.0 email element
+    pub struct Email {}
+
+    /// Defines conversions for GPX 1.0 wpt, rtept and trkpt elements
+    pub struct Wpt {}
+
+    /// Defines conversions for GPX 1.0 rte element
+    pub struct Rte {}
+
+    /// Defines conversions for GPX 1.0 trk element
+    pub struct Trk {}
+
+    /// Defines conversions for GPX 1.0 trkseg element
+    pub struct Trkseg {}
+}
@@ -0,0 +1,265 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Typed extensions.
+//!
+//! Elements inside `<extensions>` are kept as `xml::Element` unless their namespace
+//! is registered in `ParserOptions::extensions`. Those are parsed into values of the registered type,
+//! stored as `xml::Node::Typed` in place of the element, and written back by the registered converter.
+//!
+//! ```ignore
+//! let mut options = ParserOptions::default();
+//! options.extensions.register::<HeartRate, conv::HeartRate>("urn:example:heart");
+//! let (document, _) = gpx::par::parse_with(f, &options).unwrap();
+//! let hr = document.data.waypoints[0].extension::<HeartRate>();
+//! ```
+//!
+//! Nothing is registered by default, see `gpx::garmin::register` for the Garmin extensions.
+//!
+//! Converters are the usual `ParseVia` and `SerializeVia` implementations.
+//! They read the element straight from the document. Since registered types are only known
+//! at run time, writing goes through memory instead: the converter's output is read back
+//! into an `xml::Element`, which costs an extra pass over its contents.
+
+extern crate xml as _xml;
+
+use std::any::Any;
+use std::fmt;
+use std::borrow::Cow;
+use std::error::Error as ErrorTrait;
+use std::collections::HashMap;
+
+use self::_xml::attribute::OwnedAttribute;
+use self::_xml::common::Position;
+use self::_xml::name::{ Name, OwnedName };
+use self::_xml::reader;
+use self::_xml::reader::EventReader;
+use self::_xml::writer::{ EmitterConfig, XmlEvent };
+
+use xml;
+use xml::{ ElementParser, EventSource };
+use par::{ ParseVia, ElementParse, Context, ParserOptions, Positioned };
+use ser;
+use ser::{ SerializeVia, SerializerOptions };
+use gpx::par::Error;
+
+
+/// Value of an extension element, produced by a registered converter
+pub trait Extension: fmt::Debug + Send {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn box_clone(&self) -> Box<dyn Extension>;
+    /// Turns the value into the element called `name`
+    fn to_element(&self, name: &OwnedName, options: &SerializerOptions)
+        -> Result<xml::Element, ser::Error>;
+}
+
+impl Clone for Box<dyn Extension> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Extension value together with the way to write it
+struct Value<T> {
+    value: T,
+    write: fn(&T, &OwnedName, &SerializerOptions) -> Result<xml::Element, ser::Error>,
+}
+
+impl<T: fmt::Debug> fmt::Debug for Value<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.value.fmt(fmt)
+    }
+}
+
+impl<T: Clone + fmt::Debug + Send + 'static> Extension for Value<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn box_clone(&self) -> Box<dyn Extension> {
+        Box::new(Value { value: self.value.clone(), write: self.write })
+    }
+    fn to_element(&self, name: &OwnedName, options: &SerializerOptions)
+            -> Result<xml::Element, ser::Error> {
+        (self.write)(&self.value, name, options)
+    }
+}
+
+/// Serializer of an extension wrote XML which could not be read back
+#[derive(Debug)]
+pub struct BadOutput(pub Positioned<Error>);
+
+impl ser::FormatError for BadOutput {}
+
+impl fmt::Display for BadOutput {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "Extension serializer wrote unreadable XML: {}", self.0)
+    }
+}
+
+impl ErrorTrait for BadOutput {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// Creates the node of an extension element called `name`, written by the converter `C`
+pub fn node<T, C>(name: OwnedName, value: T) -> xml::Node
+        where T: Clone + fmt::Debug + Send + 'static, C: SerializeVia<T> {
+    xml::Node::Typed(name, Box::new(Value { value, write: write_value::<T, C> }))
+}
+
+/// Returns the first extension of type `T`
+pub fn find<T: 'static>(extensions: &Option<xml::Element>) -> Option<&T> {
+    find_in(extensions.as_ref())
+}
+
+/// Like `find`, for extensions held outside of an `Option`
+pub fn find_in<T: 'static>(extensions: Option<&xml::Element>) -> Option<&T> {
+    extensions.into_iter().flat_map(|ext| ext.nodes.iter()).filter_map(|node| match *node {
+        xml::Node::Typed(_, ref value) => value.as_any().downcast_ref::<Value<T>>(),
+        _ => None,
+    }).map(|value| &value.value).next()
+}
+
+/// Returns the first extension of type `T` for modification
+pub fn find_mut<T: 'static>(extensions: &mut Option<xml::Element>) -> Option<&mut T> {
+    extensions.iter_mut().flat_map(|ext| ext.nodes.iter_mut()).filter_map(|node| match *node {
+        xml::Node::Typed(_, ref mut value) => value.as_any_mut().downcast_mut::<Value<T>>(),
+        _ => None,
+    }).map(|value| &mut value.value).next()
+}
+
+/// Replaces the first extension of type `T`, or adds one called `name` if there's none
+pub fn set<T, C>(extensions: &mut Option<xml::Element>, name: OwnedName, value: T)
+        where T: Clone + fmt::Debug + Send + 'static, C: SerializeVia<T> {
+    if let Some(old) = find_mut::<T>(extensions) {
+        *old = value;
+        return;
+    }
+    extensions.get_or_insert_with(xml::Element::default).nodes.push(node::<T, C>(name, value));
+}
+
+/// Writes `value` with `C` and reads it back as a plain element
+fn write_value<T, C: SerializeVia<T>>(value: &T, name: &OwnedName, options: &SerializerOptions)
+        -> Result<xml::Element, ser::Error> {
+    let mut sink = EmitterConfig::new().write_document_declaration(false)
+                                       .create_writer(Vec::new());
+    // declares the namespace of `name` in case the converter doesn't
+    sink.write(XmlEvent::StartElement { name: Name::local("wrapper"),
+                                             attributes: Cow::Owned(Vec::new()),
+                                             namespace: Cow::Owned(ser::element_namespace(name)) })?;
+    C::serialize_via(value, &mut sink, options, name)?;
+    sink.write(XmlEvent::EndElement { name: None })?;
+    let output = sink.into_inner();
+
+    let options = ParserOptions { keep_markup: true, ..ParserOptions::default() };
+    let mut warnings = Vec::new();
+    let mut ctx = Context::new(&options, &mut warnings);
+    let mut reader = EventReader::new(&output[..]);
+    let mut depth = 0;
+    loop {
+        let event = reader.next().map_err(|e| {
+            let position = e.position();
+            BadOutput(Positioned::with_position(Error::Xml(e), position))
+        })?;
+        match event {
+            reader::XmlEvent::StartElement { name, attributes, .. } => {
+                if depth == 1 {
+                    return ElementParser::new().parse(&name, &attributes, &mut reader, &mut ctx)
+                                               .map_err(|e| BadOutput(e).into());
+                }
+                depth += 1;
+            }
+            reader::XmlEvent::EndDocument => {
+                let error = Error::MissingElement { element: OwnedName::local("wrapper"),
+                                                    child: name.clone() };
+                return Err(BadOutput(Positioned::with_position(error, Position::position(&reader))).into());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reads the element called `name`, whose start was just read from `reader`, with `C`
+fn parse_value<T, C>(mut reader: &mut dyn EventSource, ctx: &mut Context,
+                     name: &OwnedName, attributes: &[OwnedAttribute])
+        -> Result<xml::Node, Positioned<Error>>
+        where T: Clone + fmt::Debug + Send + 'static, C: ParseVia<T> + SerializeVia<T> {
+    let value = C::parse_via(&mut reader, ctx, name, attributes)?;
+    Ok(node::<T, C>(name.clone(), value))
+}
+
+type ParseFn = fn(&mut dyn EventSource, &mut Context, &OwnedName, &[OwnedAttribute])
+        -> Result<xml::Node, Positioned<Error>>;
+
+/// Maps namespaces of extension elements to the types they are parsed into
+#[derive(Clone, Default)]
+pub struct Registry {
+    handlers: HashMap<String, ParseFn>,
+    elements: HashMap<(String, String), ParseFn>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Makes elements of `namespace` inside `<extensions>` parse into `T` with the converter `C`.
+    ///
+    /// Replaces any converter registered earlier for the namespace.
+    pub fn register<T, C>(&mut self, namespace: &str)
+            where T: Clone + fmt::Debug + Send + 'static, C: ParseVia<T> + SerializeVia<T> {
+        self.handlers.insert(namespace.into(), parse_value::<T, C>);
+    }
+
+    /// Makes elements called `local_name` in `namespace` parse into `T` with the converter `C`,
+    /// for namespaces holding elements of different types.
+    ///
+    /// Takes precedence over the converter registered for the whole namespace.
+    pub fn register_element<T, C>(&mut self, namespace: &str, local_name: &str)
+            where T: Clone + fmt::Debug + Send + 'static, C: ParseVia<T> + SerializeVia<T> {
+        self.elements.insert((namespace.into(), local_name.into()), parse_value::<T, C>);
+    }
+
+    /// Reads the element called `name`, whose start was just read from `reader`,
+    /// into a typed node if it or its namespace is registered, and into a plain element otherwise.
+    ///
+    /// `ctx` must be at the element.
+    pub fn parse<R: EventSource>(&self, reader: &mut R, ctx: &mut Context,
+                                     name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<xml::Node, Positioned<Error>> {
+        let handler = name.namespace.as_ref().and_then(|ns| {
+            self.elements.get(&(ns.clone(), name.local_name.clone()))
+                         .or_else(|| self.handlers.get(ns))
+        }).cloned();
+        match handler {
+            Some(parse) => parse(reader, ctx, name, attributes),
+            None => {
+                let element = ElementParser::new().parse(name, attributes, reader, ctx)?;
+                Ok(xml::Node::Element(name.clone(), element))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_set().entries(self.handlers.keys())
+                       .entries(self.elements.keys().map(|(ns, name)| format!("{{{}}}{}", ns, name)))
+                       .finish()
+    }
+}
@@ -0,0 +1,356 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Garmin extensions: TrackPointExtension versions 1 and 2, and GpxExtensions version 3.
+//!
+//! Once added to `ParserOptions::extensions` with `register`, they are parsed into the types defined here.
+//! TrackPointExtension is written back in the version it was read in.
+//!
+//! ```ignore
+//! let heart_rate = waypoint.track_point_extension().and_then(|ext| ext.heart_rate);
+//! let shaping_points = route.waypoints[0].route_point_extension().map(|ext| &ext.points);
+//! ```
+
+extern crate xml as _xml;
+
+use self::_xml::name::OwnedName;
+
+use xml;
+use gpx::{ Waypoint, Route, Track };
+use gpx::columnar::WaypointView;
+use gpx::ext;
+use gpx::ext::Registry;
+
+mod par;
+mod ser;
+
+
+/// Namespace of TrackPointExtension version 1
+pub const TRACK_POINT_EXTENSION_V1: &str = "http://www.garmin.com/xmlschemas/TrackPointExtension/v1";
+/// Namespace of TrackPointExtension version 2
+pub const TRACK_POINT_EXTENSION_V2: &str = "http://www.garmin.com/xmlschemas/TrackPointExtension/v2";
+/// Namespace of GpxExtensions version 3
+pub const GPX_EXTENSIONS_V3: &str = "http://www.garmin.com/xmlschemas/GpxExtensions/v3";
+
+/// Defines an enumeration of strings
+macro_rules! string_enum(
+    ($(#[$meta:meta])* pub enum $name:ident { $($variant:ident),* $(,)* }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub enum $name {
+            $($variant),*
+        }
+
+        impl $name {
+            /// Returns the value as written in XML
+            pub fn as_str(&self) -> &'static str {
+                match *self {
+                    $($name::$variant => stringify!($variant)),*
+                }
+            }
+
+            fn from_str(s: &str) -> Option<$name> {
+                match s {
+                    $(stringify!($variant) => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    }
+);
+
+/// `TrackPointExtension_t` contents
+///
+/// `speed`, `course` and `bearing` exist only in version 2
+/// and are not written when the extension is in version 1.
+#[derive(XmlDebug, Clone, Default)]
+pub struct TrackPointExtension {
+    /// Air temperature in degrees Celsius
+    pub air_temperature: Option<f64>,
+    /// Water temperature in degrees Celsius
+    pub water_temperature: Option<f64>,
+    /// Depth in meters
+    pub depth: Option<f64>,
+    /// Heart rate in beats per minute
+    pub heart_rate: Option<u8>,
+    /// Cadence in revolutions per minute
+    pub cadence: Option<u8>,
+    /// Speed in meters per second
+    pub speed: Option<f64>,
+    /// Course in degrees from true north
+    pub course: Option<f64>,
+    /// Bearing in degrees from true north
+    pub bearing: Option<f64>,
+    pub extensions: Option<xml::Element>,
+}
+
+/// `WaypointExtension_t` contents
+#[derive(XmlDebug, Clone, Default)]
+pub struct WaypointExtension {
+    /// Distance in meters at which the device alarms
+    pub proximity: Option<f64>,
+    /// Temperature in degrees Celsius
+    pub temperature: Option<f64>,
+    /// Depth in meters
+    pub depth: Option<f64>,
+    pub display_mode: Option<DisplayMode>,
+    pub categories: Option<Vec<String>>,
+    pub address: Option<Address>,
+    pub phone_numbers: Vec<PhoneNumber>,
+    pub extensions: Option<xml::Element>,
+}
+
+/// `Address_t` contents
+#[derive(XmlDebug, Clone, Default)]
+pub struct Address {
+    /// Up to 2 lines
+    pub street_addresses: Vec<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub country: Option<String>,
+    pub postal_code: Option<String>,
+    pub extensions: Option<xml::Element>,
+}
+
+/// `PhoneNumber_t` contents
+#[derive(XmlDebug, Clone, Default)]
+pub struct PhoneNumber {
+    pub number: String,
+    pub category: Option<String>,
+}
+
+string_enum!(
+    /// `DisplayMode_t` values
+    pub enum DisplayMode {
+        SymbolOnly,
+        SymbolAndName,
+        SymbolAndDescription,
+    }
+);
+
+string_enum!(
+    /// `DisplayColor_t` values
+    pub enum DisplayColor {
+        Black,
+        DarkRed,
+        DarkGreen,
+        DarkYellow,
+        DarkBlue,
+        DarkMagenta,
+        DarkCyan,
+        LightGray,
+        DarkGray,
+        Red,
+        Green,
+        Yellow,
+        Blue,
+        Magenta,
+        Cyan,
+        White,
+        Transparent,
+    }
+);
+
+/// `RouteExtension_t` contents
+#[derive(XmlDebug, Clone, Default)]
+pub struct RouteExtension {
+    /// Required by the schema
+    pub is_auto_named: Option<bool>,
+    pub display_color: Option<DisplayColor>,
+    pub extensions: Option<xml::Element>,
+}
+
+/// `RoutePointExtension_t` contents, describing the way to the next route point
+#[derive(XmlDebug, Clone, Default)]
+pub struct RoutePointExtension {
+    /// Road class of the route point
+    pub subclass: Option<Vec<u8>>,
+    /// Shaping points calculated by the device, `<gpxx:rpt>`
+    pub points: Vec<AutoroutePoint>,
+    pub extensions: Option<xml::Element>,
+}
+
+/// `AutoroutePoint_t` contents
+#[derive(XmlDebug, Clone)]
+pub struct AutoroutePoint {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Road class of the point
+    pub subclass: Option<Vec<u8>>,
+}
+
+/// `TrackExtension_t` contents
+#[derive(XmlDebug, Clone, Default)]
+pub struct TrackExtension {
+    pub display_color: Option<DisplayColor>,
+    pub extensions: Option<xml::Element>,
+}
+
+pub mod conv {
+    //! Converters of Garmin types
+
+    /// Defines conversions for TrackPointExtension version 2
+    pub struct TrackPointExtension {}
+
+    /// Defines conversions for TrackPointExtension version 1
+    pub struct TrackPointExtensionV1 {}
+
+    /// Defines conversions for BeatsPerMinute_t
+    pub struct BeatsPerMinute {}
+
+    /// Defines conversions for RevolutionsPerMinute_t
+    pub struct RevolutionsPerMinute {}
+
+    /// Defines conversions for DegreesTrue_t
+    pub struct DegreesTrue {}
+
+    /// Defines conversions for WaypointExtension_t
+    pub struct WaypointExtension {}
+
+    /// Defines conversions between a list of strings and Categories_t
+    pub struct Categories {}
+
+    /// Defines conversions for Address_t
+    pub struct Address {}
+
+    /// Defines conversions for PhoneNumber_t
+    pub struct PhoneNumber {}
+
+    /// Defines conversions for DisplayMode_t
+    pub struct DisplayMode {}
+
+    /// Defines conversions for DisplayColor_t
+    pub struct DisplayColor {}
+
+    /// Defines conversions for RouteExtension_t
+    pub struct RouteExtension {}
+
+    /// Defines conversions for RoutePointExtension_t
+    pub struct RoutePointExtension {}
+
+    /// Defines conversions for AutoroutePoint_t
+    pub struct AutoroutePoint {}
+
+    /// Defines conversions for TrackExtension_t
+    pub struct TrackExtension {}
+}
+
+/// Makes `registry` parse all the extensions of this module
+pub fn register(registry: &mut Registry) {
+    registry.register::<TrackPointExtension, conv::TrackPointExtensionV1>(TRACK_POINT_EXTENSION_V1);
+    registry.register::<TrackPointExtension, conv::TrackPointExtension>(TRACK_POINT_EXTENSION_V2);
+    registry.register_element::<WaypointExtension, conv::WaypointExtension>(GPX_EXTENSIONS_V3,
+                                                                            "WaypointExtension");
+    registry.register_element::<RoutePointExtension, conv::RoutePointExtension>(GPX_EXTENSIONS_V3,
+                                                                                "RoutePointExtension");
+    registry.register_element::<RouteExtension, conv::RouteExtension>(GPX_EXTENSIONS_V3,
+                                                                      "RouteExtension");
+    registry.register_element::<TrackExtension, conv::TrackExtension>(GPX_EXTENSIONS_V3,
+                                                                      "TrackExtension");
+}
+
+fn gpxx_name(local_name: &str) -> OwnedName {
+    OwnedName::qualified(local_name, GPX_EXTENSIONS_V3, Some("gpxx"))
+}
+
+impl Waypoint {
+    /// Returns the Garmin TrackPointExtension of the point
+    pub fn track_point_extension(&self) -> Option<&TrackPointExtension> {
+        self.extension()
+    }
+
+    /// Returns the Garmin TrackPointExtension of the point for modification
+    pub fn track_point_extension_mut(&mut self) -> Option<&mut TrackPointExtension> {
+        self.extension_mut()
+    }
+
+    /// Replaces the Garmin TrackPointExtension of the point.
+    ///
+    /// A point without one gets the extension in version 2.
+    pub fn set_track_point_extension(&mut self, data: TrackPointExtension) {
+        let name = OwnedName::qualified("TrackPointExtension", TRACK_POINT_EXTENSION_V2, Some("gpxtpx"));
+        ext::set::<_, conv::TrackPointExtension>(&mut self.extensions, name, data);
+    }
+
+    /// Returns the Garmin WaypointExtension of the point
+    pub fn waypoint_extension(&self) -> Option<&WaypointExtension> {
+        self.extension()
+    }
+
+    /// Returns the Garmin WaypointExtension of the point for modification
+    pub fn waypoint_extension_mut(&mut self) -> Option<&mut WaypointExtension> {
+        self.extension_mut()
+    }
+
+    /// Replaces the Garmin WaypointExtension of the point
+    pub fn set_waypoint_extension(&mut self, data: WaypointExtension) {
+        ext::set::<_, conv::WaypointExtension>(&mut self.extensions, gpxx_name("WaypointExtension"), data);
+    }
+
+    /// Returns the Garmin RoutePointExtension of the route point
+    pub fn route_point_extension(&self) -> Option<&RoutePointExtension> {
+        self.extension()
+    }
+
+    /// Returns the Garmin RoutePointExtension of the route point for modification
+    pub fn route_point_extension_mut(&mut self) -> Option<&mut RoutePointExtension> {
+        self.extension_mut()
+    }
+
+    /// Replaces the Garmin RoutePointExtension of the route point
+    pub fn set_route_point_extension(&mut self, data: RoutePointExtension) {
+        ext::set::<_, conv::RoutePointExtension>(&mut self.extensions, gpxx_name("RoutePointExtension"),
+                                                 data);
+    }
+}
+
+impl<'a> WaypointView<'a> {
+    /// Returns the Garmin TrackPointExtension of the point
+    pub fn track_point_extension(&self) -> Option<&'a TrackPointExtension> {
+        self.extension()
+    }
+}
+
+impl Route {
+    /// Returns the Garmin RouteExtension of the route
+    pub fn route_extension(&self) -> Option<&RouteExtension> {
+        self.extension()
+    }
+
+    /// Returns the Garmin RouteExtension of the route for modification
+    pub fn route_extension_mut(&mut self) -> Option<&mut RouteExtension> {
+        self.extension_mut()
+    }
+
+    /// Replaces the Garmin RouteExtension of the route
+    pub fn set_route_extension(&mut self, data: RouteExtension) {
+        ext::set::<_, conv::RouteExtension>(&mut self.extensions, gpxx_name("RouteExtension"), data);
+    }
+}
+
+impl Track {
+    /// Returns the Garmin TrackExtension of the track
+    pub fn track_extension(&self) -> Option<&TrackExtension> {
+        self.extension()
+    }
+
+    /// Returns the Garmin TrackExtension of the track for modification
+    pub fn track_extension_mut(&mut self) -> Option<&mut TrackExtension> {
+        self.extension_mut()
+    }
+
+    /// Replaces the Garmin TrackExtension of the track
+    pub fn set_track_extension(&mut self, data: TrackExtension) {
+        ext::set::<_, conv::TrackExtension>(&mut self.extensions, gpxx_name("TrackExtension"), data);
+    }
+}
@@ -0,0 +1,129 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Parsing of Garmin extensions
+
+extern crate xml as _xml;
+
+use self::_xml::common::TextPosition;
+use self::_xml::name::OwnedName;
+use self::_xml::attribute::OwnedAttribute;
+
+use xml;
+use xml::{ OptionalElement, EventSource };
+use gpx::garmin::{ TrackPointExtension, WaypointExtension, Address, PhoneNumber, DisplayMode,
+                   DisplayColor, RouteExtension, RoutePointExtension, AutoroutePoint, TrackExtension };
+use gpx::garmin::conv;
+use gpx::par::Error;
+use par::{ ParseVia, ParseViaChar, FromAttributeVia, ElementParse, ElementBuild, Positioned };
+use par::{ Context, AttributeError };
+
+
+include!(concat!(env!("OUT_DIR"), "/garmin_par_auto.rs"));
+
+
+impl ElementBuild for TrackPointExtensionV1Parser {
+    type Element = TrackPointExtension;
+    type BuildError = xml::BuildError;
+    fn build(self, _name: &OwnedName) -> Result<Self::Element, Self::BuildError> {
+        Ok(TrackPointExtension {
+            air_temperature: self.atemp,
+            water_temperature: self.wtemp,
+            depth: self.depth,
+            heart_rate: self.hr,
+            cadence: self.cad,
+            speed: None,
+            course: None,
+            bearing: None,
+            extensions: self.extensions,
+        })
+    }
+}
+
+impl ElementBuild for CategoriesParser {
+    type Element = Vec<String>;
+    type BuildError = xml::BuildError;
+    fn build(self, _name: &OwnedName) -> Result<Self::Element, Self::BuildError> {
+        Ok(self.category)
+    }
+}
+
+/// Phone numbers are character data with an attribute
+#[derive(Default)]
+struct PhoneNumberParser {
+    number: String,
+    category: Option<String>,
+}
+
+impl ElementParse<Error> for PhoneNumberParser {
+    fn parse_start(&mut self, ctx: &mut Context, name: &OwnedName,
+                   attributes: &[OwnedAttribute], position: TextPosition)
+            -> Result<(), AttributeError<Error>> {
+        for attr in attributes {
+            match (&attr.name.namespace, &attr.name.local_name as &str) {
+                (&None, "Category") => {
+                    self.category = Some(::xsd::conv::String::from_attribute(&attr.value)?);
+                }
+                _ => {
+                    let policy = ctx.options.unknown_attributes;
+                    self.unexpected_attribute(ctx, policy, name, attr, position)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn parse_element<R: EventSource>(&mut self, reader: &mut R, ctx: &mut Context,
+                                  name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<(), Positioned<Error>> {
+        let policy = ctx.options.unknown_elements;
+        self.unexpected_element(reader, ctx, policy, name, attributes)
+    }
+    fn parse_characters(&mut self, data: String) -> Result<(), Error> {
+        self.number.push_str(&data);
+        Ok(())
+    }
+    fn parse_whitespace(&mut self, space: String) -> Result<(), Error> {
+        self.parse_characters(space)
+    }
+    fn parse_cdata(&mut self, _ctx: &mut Context, data: String) -> Result<(), Error> {
+        self.parse_characters(data)
+    }
+}
+
+impl ElementBuild for PhoneNumberParser {
+    type Element = PhoneNumber;
+    type BuildError = xml::BuildError;
+    fn build(self, _name: &OwnedName) -> Result<Self::Element, Self::BuildError> {
+        Ok(PhoneNumber { number: self.number, category: self.category })
+    }
+}
+
+impl ParseVia<PhoneNumber> for conv::PhoneNumber {
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
+                              name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<PhoneNumber, Positioned<Error>> {
+        PhoneNumberParser::new().parse(name, attributes, parser, ctx)
+    }
+}
+
+impl ParseViaChar<DisplayMode> for conv::DisplayMode {
+    fn from_char(s: &str) -> Result<DisplayMode, Error> {
+        DisplayMode::from_str(s).ok_or_else(|| Error::UnknownValue(s.into()))
+    }
+}
+
+impl ParseViaChar<DisplayColor> for conv::DisplayColor {
+    fn from_char(s: &str) -> Result<DisplayColor, Error> {
+        DisplayColor::from_str(s).ok_or_else(|| Error::UnknownValue(s.into()))
+    }
+}
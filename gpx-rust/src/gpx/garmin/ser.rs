@@ -0,0 +1,118 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Serialization of Garmin extensions
+
+extern crate xml as _xml;
+
+use std::io;
+use std::borrow::Cow;
+
+use self::_xml::attribute::{ Attribute, OwnedAttribute };
+use self::_xml::name::{ Name, OwnedName };
+use self::_xml::writer::{ XmlEvent, EventWriter };
+
+use xsd;
+use gpx::garmin::{ TrackPointExtension, WaypointExtension, Address, PhoneNumber, DisplayMode,
+                   DisplayColor, RouteExtension, RoutePointExtension, AutoroutePoint, TrackExtension };
+use gpx::garmin::conv;
+use gpx::ser::BoundCondition;
+use gpx::ser::Error as GpxError;
+use ser;
+use ser::{ Error, SerializeVia, SerializerOptions, ToCharsVia };
+
+
+include!(concat!(env!("OUT_DIR"), "/garmin_ser_auto.rs"));
+
+
+impl ToCharsVia<u8> for conv::BeatsPerMinute {
+    type Error = GpxError;
+    fn to_characters(data: &u8) -> Result<String, Self::Error> {
+        if *data < 1 {
+            Err(GpxError::out_of_bounds(BoundCondition::EqualGreater, 1, *data))
+        } else {
+            <xsd::conv::UnsignedByte as ToCharsVia<u8>>::to_characters(data).map_err(GpxError::from)
+        }
+    }
+}
+
+impl ToCharsVia<u8> for conv::RevolutionsPerMinute {
+    type Error = GpxError;
+    fn to_characters(data: &u8) -> Result<String, Self::Error> {
+        if *data > 254 {
+            Err(GpxError::out_of_bounds(BoundCondition::EqualLesser, 254, *data))
+        } else {
+            <xsd::conv::UnsignedByte as ToCharsVia<u8>>::to_characters(data).map_err(GpxError::from)
+        }
+    }
+}
+
+impl ToCharsVia<f64> for conv::DegreesTrue {
+    type Error = GpxError;
+    fn to_characters(data: &f64) -> Result<String, Self::Error> {
+        // NaN is in no range
+        if data.is_nan() || *data < 0. {
+            Err(GpxError::out_of_bounds(BoundCondition::EqualGreater, 0., *data))
+        } else if *data >= 360. {
+            Err(GpxError::out_of_bounds(BoundCondition::Lesser, 360., *data))
+        } else {
+            <xsd::conv::Double as ToCharsVia<f64>>::to_characters(data).map_err(GpxError::from)
+        }
+    }
+}
+
+impl SerializeVia<Vec<String>> for conv::Categories {
+    fn serialize_via<W: io::Write>(data: &Vec<String>, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
+            -> Result<(), Error> {
+        sink.write(XmlEvent::StartElement { name: name.borrow(),
+                                                 attributes: Cow::Owned(Vec::new()),
+                                                 namespace: Cow::Owned(ser::element_namespace(name)) })?;
+        for category in data {
+            xsd::conv::String::serialize_via(category, sink, options, &ser::child_name(name, "Category"))?;
+        }
+        sink.write(XmlEvent::EndElement { name: Some(name.borrow()) })?;
+        Ok(())
+    }
+}
+
+impl SerializeVia<PhoneNumber> for conv::PhoneNumber {
+    fn serialize_via<W: io::Write>(data: &PhoneNumber, sink: &mut EventWriter<W>,
+                                   _options: &SerializerOptions, name: &OwnedName)
+            -> Result<(), Error> {
+        let attributes = match data.category {
+            Some(ref category) => vec![Attribute::new(Name::local("Category"), category)],
+            None => Vec::new(),
+        };
+        sink.write(XmlEvent::StartElement { name: name.borrow(),
+                                                 attributes: Cow::Owned(attributes),
+                                                 namespace: Cow::Owned(ser::element_namespace(name)) })?;
+        sink.write(XmlEvent::Characters(&data.number))?;
+        sink.write(XmlEvent::EndElement { name: Some(name.borrow()) })?;
+        Ok(())
+    }
+}
+
+impl ToCharsVia<DisplayMode> for conv::DisplayMode {
+    type Error = xsd::ser::Error;
+    fn to_characters(data: &DisplayMode) -> Result<String, Self::Error> {
+        Ok(data.as_str().into())
+    }
+}
+
+impl ToCharsVia<DisplayColor> for conv::DisplayColor {
+    type Error = xsd::ser::Error;
+    fn to_characters(data: &DisplayColor) -> Result<String, Self::Error> {
+        Ok(data.as_str().into())
+    }
+}
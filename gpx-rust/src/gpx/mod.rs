@@ -12,35 +12,73 @@
  */
 
 //! GPX types
+//!
+//! The types describe GPX 1.1 and GPX 1.0 documents alike.
+//! `Gpx::version` decides which version gets written, so changing it converts the document.
+//!
+//! GPX 1.0 data is stored as follows:
+//!
+//! * `<name>`, `<desc>`, `<time>`, `<keywords>` and `<bounds>` of `<gpx>` go to `Metadata`,
+//! * `<author>` and `<email>` of `<gpx>` go to `Metadata::author`,
+//! * `<url>` and `<urlname>` become the first of the `links` of their element,
+//! * `<course>` and `<speed>` of points go to `Waypoint::course` and `Waypoint::speed`,
+//! * elements from other namespaces go to `extensions` of their parent.
+//!
+//! When writing GPX 1.1, `course` and `speed` are stored in the point's `<extensions>`
+//! as elements of the GPX 1.0 namespace, where the parser finds them again.
+//!
+//! When writing GPX 1.0, data which has no place in the format is written
+//! as GPX 1.1 elements in the spots reserved for extensions:
+//!
+//! * `<metadata>` when it holds a copyright, extensions, an author's link
+//!   or more links than `<url>` can take,
+//! * `<link>` for every link except the first one, or every link if the first one has a type,
+//! * `<type>` of routes and tracks.
+//!
+//! The parser reads them back, so no data is lost in either direction,
+//! except for extensions of track segments, which GPX 1.0 has no place for.
 
 extern crate xml as _xml;
 extern crate chrono;
 extern crate geo;
 
+use std::fmt;
 use std::io;
+use std::str::FromStr;
 use self::geo::Bbox;
 
 use xml;
 use xsd;
 use xsd::*;
 
+pub mod build;
 mod conv;
+pub mod columnar;
+pub mod borrowed;
+pub mod ext;
+pub mod garmin;
 mod ser_auto;
+#[macro_use]
 pub mod ser;
+mod ser_v1_0;
 pub mod par;
+pub mod validate;
+pub mod visit;
 
 /// Parses XML stream containing GPX data
 pub use self::par::parse;
 
 /// Xml document containing parsed GPX data
 ///
-/// ```
+/// ```no_run
+/// # let f = std::fs::File::open("foo").unwrap();
+/// # let (document, _) = gpx_rust::gpx::parse(f).unwrap();
 /// let gpx = document.data;
 /// ```
 pub type Document = xml::Document<Gpx>;
 
 /// `gpxType` contents
-#[derive(XmlDebug)]
+#[derive(XmlDebug, Default)]
 pub struct Gpx {
     pub version: Version,
     pub creator: String,
@@ -52,15 +90,39 @@ pub struct Gpx {
 }
 
 /// `<gpx version=...>` attribute values
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum Version {
     V1_0,
     V1_1,
 }
 
+impl Default for Version {
+    /// The latest version
+    fn default() -> Self {
+        Version::V1_1
+    }
+}
+
+impl Version {
+    /// Namespace of the GPX schema in this version
+    pub fn namespace(&self) -> &'static str {
+        match *self {
+            Version::V1_0 => "http://www.topografix.com/GPX/1/0",
+            Version::V1_1 => "http://www.topografix.com/GPX/1/1",
+        }
+    }
+
+    fn to_attribute(self) -> &'static str {
+        match self {
+            Version::V1_0 => "1.0",
+            Version::V1_1 => "1.1",
+        }
+    }
+}
+
 /// `metadataType` contents
-#[derive(XmlDebug)]
+#[derive(XmlDebug, Default)]
 pub struct Metadata {
     pub name: Option<String>,
     pub description: Option<String>,
@@ -74,18 +136,73 @@ pub struct Metadata {
 }
 
 /// `personType` contents
-#[derive(XmlDebug)]
+#[derive(XmlDebug, Default)]
 pub struct Person {
     pub name: Option<String>,
-    pub email: Option<String>,
+    pub email: Option<Email>,
     pub link: Option<Link>,
 }
 
+/// `emailType` contents, an address split at the `@`.
+///
+/// The schema leaves both parts free, apart from neither being empty nor holding the `@`.
+/// Addresses read from GPX 1.0 follow its stricter pattern instead.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Email {
+    id: String,
+    domain: String,
+}
+
+impl Email {
+    pub fn new<I: Into<String>, D: Into<String>>(id: I, domain: D) -> Result<Email, par::Error> {
+        let id = id.into();
+        let domain = domain.into();
+        if id.is_empty() || id.contains('@') {
+            return Err(par::Error::BadEmailId(id));
+        }
+        if domain.is_empty() || domain.contains('@') {
+            return Err(par::Error::InvalidEmailDomain(domain));
+        }
+        Ok(Email { id, domain })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+}
+
+/// Splits an address like `jane@example.com`
+impl FromStr for Email {
+    type Err = par::Error;
+    fn from_str(s: &str) -> Result<Email, par::Error> {
+        match s.find('@') {
+            Some(at) => Email::new(&s[..at], &s[at + 1..]),
+            None => Email::new(s, ""),
+        }
+    }
+}
+
+impl fmt::Display for Email {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}@{}", self.id, self.domain)
+    }
+}
+
+impl fmt::Debug for Email {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_string(), fmt)
+    }
+}
+
 /// `copyrightType` contents
 #[derive(XmlDebug)]
 pub struct Copyright {
     pub author: String,
-    pub year: Option<i16>,
+    pub year: Option<xsd::GYear>,
     pub license: Option<xsd::Uri>,
 }
 
@@ -101,10 +218,14 @@ pub struct Link {
 pub type Bounds = Bbox<f64>;
 
 /// `<wpt>`, `<rtept>`, `<trkpt>` elements and `wptType`
-#[derive(XmlDebug)]
+#[derive(XmlDebug, Default)]
 pub struct Waypoint {
     pub location: Point,
     pub time: Option<xsd::DateTime>,
+    /// Instantaneous course at the point, GPX 1.0 `<course>`
+    pub course: Option<Degrees>,
+    /// Speed at the point in meters per second, GPX 1.0 `<speed>`
+    pub speed: Option<xsd::Decimal>,
     pub mag_variation: Option<Degrees>,
     pub geoid_height: Option<xsd::Decimal>,
     pub name: Option<String>,
@@ -125,7 +246,7 @@ pub struct Waypoint {
 }
 
 /// WGS84 geographical coordinates
-#[derive(XmlDebug)]
+#[derive(XmlDebug, Default)]
 pub struct Point {
     pub latitude: f64,
     pub longitude: f64,
@@ -143,7 +264,7 @@ pub enum Fix {
 }
 
 /// `<trk>` and `trkType`
-#[derive(XmlDebug)]
+#[derive(XmlDebug, Default)]
 pub struct Track {
     pub name: Option<String>,
     pub comment: Option<String>,
@@ -157,14 +278,14 @@ pub struct Track {
 }
 
 /// `<trkseg>` and `trksegType`
-#[derive(XmlDebug)]
+#[derive(XmlDebug, Default)]
 pub struct TrackSegment {
     pub waypoints: Vec<Waypoint>,
     pub extensions: Option<xml::Element>,
 }
 
 /// `<rte>` and `rteType`
-#[derive(XmlDebug)]
+#[derive(XmlDebug, Default)]
 pub struct Route {
     pub name: Option<String>,
     pub comment: Option<String>,
@@ -179,3 +300,20 @@ pub struct Route {
 
 /// direction on the circle
 pub type Degrees = f32;
+
+macro_rules! impl_extension_access(
+    ($($type_:ty),*) => { $(
+        impl $type_ {
+            /// Returns the first extension of type `T`, see the `ext` module
+            pub fn extension<T: 'static>(&self) -> Option<&T> {
+                ext::find(&self.extensions)
+            }
+            /// Returns the first extension of type `T` for modification
+            pub fn extension_mut<T: 'static>(&mut self) -> Option<&mut T> {
+                ext::find_mut(&mut self.extensions)
+            }
+        }
+    )* }
+);
+
+impl_extension_access!(Gpx, Metadata, Waypoint, Route, Track, TrackSegment);
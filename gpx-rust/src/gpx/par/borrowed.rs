@@ -0,0 +1,1009 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Parsing of GPX documents in memory into the types of `gpx::borrowed`.
+//!
+//! Follows `ParserOptions` like the parsers of the owned types and gives the same results,
+//! except that markup around the root element is not kept with `ParserOptions::keep_markup`.
+//! Extensions and data preserved in them are handed over to those parsers.
+
+extern crate xml as _xml;
+
+use std::borrow::Cow;
+use std::str;
+
+use self::_xml::common::TextPosition;
+use self::_xml::namespace::Namespace;
+use self::_xml::reader::Error as XmlError;
+
+use xml;
+use xml::OptionalElement;
+use xml::slice::{ SliceReader, Token, Start, Name, Attribute };
+use xsd;
+use gpx::{ Version, Point, Fix, Bounds, Degrees, Email };
+use gpx::borrowed::{ Gpx, Metadata, Person, Copyright, Link, Waypoint, Track, TrackSegment, Route };
+use gpx::conv;
+use gpx::conv::{ Latitude, Longitude };
+use gpx::par::{ Error, DocumentError, detect_version, take_v1_0_extension, invalid_v1_0_email };
+use par::{ FromAttributeVia, ParseVia, ParseViaChar, ElementParse, AttributeError };
+use par::{ Positioned, Context, ParserOptions, Policy, Preserved, SequencePosition, Skip };
+use par::{ Warning, WarningKind, skip_element };
+
+
+/// Error located at the last token read
+fn at<E: Into<Error>>(reader: &SliceReader, ctx: &Context, error: E) -> Positioned<Error> {
+    ctx.locate(Positioned::with_position(error.into(), reader.token_position()))
+}
+
+/// Failure to parse the value of `attribute`, reported like in `ElementParse::parse`
+fn bad_attribute<E>(reader: &SliceReader, ctx: &Context, error: E, attribute: &Attribute)
+        -> Positioned<Error> where AttributeError<Error>: From<E> {
+    let error = AttributeError::bad_value(error, &attribute.owned());
+    let text = error.text();
+    let mut error = at(reader, ctx, error);
+    error.text = text;
+    error
+}
+
+fn missing(name: &Name, attribute: &str) -> xml::BuildError {
+    xml::BuildError::MissingAttribute { element: name.owned(),
+                                        attribute: _xml::name::OwnedName::local(attribute) }
+}
+
+/// Returns the local name if `name` belongs to GPX, so that the schema describes it
+fn gpx_local<'a>(name: &Name<'a>) -> Option<&'a str> {
+    match name.namespace {
+        None => Some(name.local_name),
+        Some(ref ns) if ns == Version::V1_1.namespace() || ns == Version::V1_0.namespace() => {
+            Some(name.local_name)
+        }
+        Some(_) => None,
+    }
+}
+
+/// Stores data not described by the schema in `extensions`,
+/// or leaves it for the closest ancestor able to store it
+fn preserve(ctx: &mut Context, extensions: Option<&mut Option<xml::Element>>, data: Preserved) {
+    match extensions {
+        Some(extensions) => data.store(extensions.get_or_insert_with(xml::Element::default)),
+        None => ctx.preserved.push(data),
+    }
+}
+
+/// Takes over data which children of an element left in `ctx` since `mark`.
+///
+/// Called after each child, so that the data stays in document order.
+fn adopt(ctx: &mut Context, mark: usize, extensions: &mut Option<xml::Element>) {
+    for data in ctx.preserved.split_off(mark) {
+        data.store(extensions.get_or_insert_with(xml::Element::default));
+    }
+}
+
+/// Deals with an attribute of the element `name` which the schema doesn't describe
+fn other_attribute(reader: &SliceReader, ctx: &mut Context, name: &Name, attribute: &Attribute)
+        -> Result<(), Positioned<Error>> {
+    let policy = match gpx_local(&attribute.name) {
+        Some(_) => ctx.options.unknown_attributes,
+        None => ctx.options.foreign_namespaces,
+    };
+    match policy {
+        Policy::Error => Err(at(reader, ctx, AttributeError::Unexpected(attribute.name.owned()))),
+        Policy::Skip | Policy::Preserve => {
+            ctx.warn(WarningKind::IgnoredAttribute(attribute.name.owned()), &name.owned(),
+                     reader.token_position());
+            Ok(())
+        }
+    }
+}
+
+/// Deals with a child element which the schema doesn't describe, reading all of it.
+///
+/// Foreign elements are preserved in GPX 1.0 where there is a place for extensions.
+fn other_element(reader: &mut SliceReader, ctx: &mut Context, start: Start,
+                 extensions: Option<&mut Option<xml::Element>>)
+        -> Result<(), Positioned<Error>> {
+    let policy = match gpx_local(&start.name) {
+        Some(_) => ctx.options.unknown_elements,
+        None if ctx.flat_extensions && extensions.is_some() => Policy::Preserve,
+        None => ctx.options.foreign_namespaces,
+    };
+    let name = start.name.owned();
+    if policy == Policy::Error {
+        return Err(at(reader, ctx, AttributeError::Unexpected(name)));
+    }
+    if policy == Policy::Skip {
+        ctx.warn(WarningKind::IgnoredElement, &name, reader.token_position());
+    }
+    let attributes = start.owned_attributes();
+    if policy == Policy::Preserve {
+        let options = ctx.options;
+        let node = options.extensions.parse(reader, ctx, &name, &attributes)?;
+        preserve(ctx, extensions, Preserved(node));
+    } else {
+        xml::ElementParser::new().parse(&name, &attributes, reader, ctx)?;
+    }
+    Ok(())
+}
+
+/// Checks the place of the child `start` in the sequence of its parent,
+/// where it's at `index` and may occur up to `max_occurs` times
+fn place(reader: &SliceReader, ctx: &mut Context, sequence: &mut SequencePosition, start: &Start,
+         index: usize, max_occurs: Option<u64>)
+        -> Result<(), Positioned<Error>> {
+    ctx.path.set_max_occurs(max_occurs);
+    if ctx.options.strict {
+        let name = start.name.owned();
+        if ctx.checks_order(&name) {
+            sequence.advance(index, max_occurs, &name).map_err(|e| at(reader, ctx, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the contents of the element just started, up to its end, passing child elements to `child`.
+///
+/// Text is checked against the limits and dropped, like in the generated parsers.
+fn children<'a, F>(reader: &mut SliceReader<'a>, ctx: &mut Context, mut child: F)
+        -> Result<(), Positioned<Error>>
+        where F: FnMut(&mut SliceReader<'a>, &mut Context, Start<'a>) -> Result<(), Positioned<Error>> {
+    let mut text_length = 0;
+    loop {
+        match reader.next_token().map_err(|e| at(reader, ctx, e))? {
+            Token::Start(start) => {
+                let outer = if ctx.options.keep_markup {
+                    ctx.enter_scope(reader.scope())
+                } else {
+                    Namespace::empty()
+                };
+                ctx.path.enter_qualified(start.name.qualified);
+                ctx.count_element(start.attributes.len()).map_err(|e| at(reader, ctx, e))?;
+                if ctx.options.skip != Skip::default() {
+                    let name = start.name.owned();
+                    if ctx.options.skip.stops_at(&name, ctx.path.depth()) {
+                        ctx.stopped = true;
+                        ctx.path.leave();
+                        ctx.leave_scope(outer);
+                        return Ok(());
+                    }
+                    if ctx.skips(&name) {
+                        skip_element(reader, ctx)?;
+                        ctx.path.leave();
+                        ctx.leave_scope(outer);
+                        continue;
+                    }
+                }
+                child(reader, ctx, start).map_err(|e| ctx.locate(e))?;
+                ctx.path.leave();
+                ctx.leave_scope(outer);
+                if ctx.stopped {
+                    return Ok(());
+                }
+            }
+            Token::End(_) => return Ok(()),
+            Token::Text(text) => {
+                text_length += text.len();
+                ctx.check_text(text_length).map_err(|e| at(reader, ctx, e))?;
+            }
+            Token::CData(data) => {
+                text_length += data.len();
+                ctx.check_text(text_length).map_err(|e| at(reader, ctx, e))?;
+                let event = _xml::reader::XmlEvent::CData(data.into_owned());
+                return Err(at(reader, ctx, xml::ElementError::UnexpectedEvent(event)));
+            }
+            Token::Comment(comment) => ctx.keep_markup(xml::Node::Comment(comment.into_owned())),
+            Token::ProcessingInstruction { name, data } => {
+                ctx.keep_markup(xml::Node::ProcessingInstruction(name.into(), data.map(Cow::into_owned)));
+            }
+            Token::Declaration { .. } | Token::EndDocument => {
+                return Err(at(reader, ctx, xml::ElementError::UnexpectedEnd));
+            }
+        }
+    }
+}
+
+/// Reads the text of a simple element up to its end, borrowing it if it's in one piece.
+///
+/// Returns the text with the position where it starts.
+fn text<'a>(reader: &mut SliceReader<'a>, ctx: &mut Context)
+        -> Result<(Cow<'a, str>, TextPosition), Positioned<Error>> {
+    let mut text: Option<Cow<'a, str>> = None;
+    let mut start = None;
+    loop {
+        let piece = match reader.next_token().map_err(|e| at(reader, ctx, e))? {
+            Token::Text(piece) => piece,
+            Token::CData(piece) => piece,
+            Token::End(_) => {
+                let position = start.unwrap_or_else(|| reader.token_position());
+                return Ok((text.unwrap_or(Cow::Borrowed("")), position));
+            }
+            Token::Comment(comment) => {
+                ctx.keep_markup(xml::Node::Comment(comment.into_owned()));
+                continue;
+            }
+            Token::ProcessingInstruction { name, data } => {
+                ctx.keep_markup(xml::Node::ProcessingInstruction(name.into(), data.map(Cow::into_owned)));
+                continue;
+            }
+            Token::Start(child) => {
+                let event = reader.start_event(&child);
+                return Err(Positioned::with_position(xml::ElementError::UnexpectedEvent(event).into(),
+                                                     reader.token_position()));
+            }
+            Token::Declaration { .. } | Token::EndDocument => {
+                return Err(Positioned::with_position(xml::ElementError::UnexpectedEnd.into(),
+                                                     reader.token_position()));
+            }
+        };
+        start = start.or_else(|| Some(reader.token_position()));
+        text = Some(match text {
+            None => piece,
+            Some(mut text) => {
+                text.to_mut().push_str(&piece);
+                text
+            }
+        });
+        let length = text.as_ref().map_or(0, |text| text.len());
+        ctx.check_text(length).map_err(|e| at(reader, ctx, e))?;
+    }
+}
+
+fn string<'a>(reader: &mut SliceReader<'a>, ctx: &mut Context) -> Result<Cow<'a, str>, Positioned<Error>> {
+    text(reader, ctx).map(|(text, _)| text)
+}
+
+/// Reads a simple element and converts its text with `C`
+fn value<T, C: ParseViaChar<T>>(reader: &mut SliceReader, ctx: &mut Context)
+        -> Result<T, Positioned<Error>> {
+    let (text, position) = text(reader, ctx)?;
+    C::from_char(&text).map_err(|e| {
+        // points to the text rather than to the end of the element
+        let mut error = ctx.locate(Positioned::with_position(e, position));
+        error.text = Some(text.into_owned());
+        error
+    })
+}
+
+/// Reads `<extensions>`, typing them according to `ParserOptions::extensions`
+fn extensions(reader: &mut SliceReader, ctx: &mut Context, start: &Start)
+        -> Result<xml::Element, Positioned<Error>> {
+    conv::Extensions::parse_via(reader, ctx, &start.name.owned(), &start.owned_attributes())
+}
+
+/// Joins GPX 1.0 `<url>` and `<urlname>` with GPX 1.1 links, the former going first
+fn join_links<'a>(url: Option<xsd::Uri>, urlname: Option<Cow<'a, str>>, links: Vec<Link<'a>>)
+        -> Vec<Link<'a>> {
+    match url {
+        Some(href) => {
+            let mut joined = vec![Link { href, text: urlname, type_: None }];
+            joined.extend(links);
+            joined
+        }
+        None => links,
+    }
+}
+
+fn parse_gpx<'a>(reader: &mut SliceReader<'a>, ctx: &mut Context, start: Start<'a>)
+        -> Result<Gpx<'a>, Positioned<Error>> {
+    let preserved_mark = ctx.preserved.len();
+    let mut version = None;
+    let mut creator = None;
+    let mut metadata = None;
+    let mut name = None;
+    let mut desc = None;
+    let mut author = None;
+    let mut email = None;
+    let mut url = None;
+    let mut urlname = None;
+    let mut time = None;
+    let mut keywords = None;
+    let mut bounds = None;
+    let mut waypoints = Vec::new();
+    let mut routes = Vec::new();
+    let mut tracks = Vec::new();
+    let mut ext = None;
+    let Start { name: element, attributes } = start;
+    for attribute in attributes {
+        match gpx_local(&attribute.name) {
+            Some("version") => {
+                version = Some(conv::Version::from_attribute(&attribute.value)
+                                   .map_err(|e| bad_attribute(reader, ctx, e, &attribute))?);
+            }
+            Some("creator") => creator = Some(attribute.value),
+            _ => other_attribute(reader, ctx, &element, &attribute)?,
+        }
+    }
+    let mut sequence = SequencePosition::default();
+    children(reader, ctx, |reader, ctx, start| {
+        match gpx_local(&start.name) {
+            Some("metadata") => {
+                place(reader, ctx, &mut sequence, &start, 0, Some(1))?;
+                metadata = Some(parse_metadata(reader, ctx)?);
+            }
+            Some("name") => {
+                place(reader, ctx, &mut sequence, &start, 1, Some(1))?;
+                name = Some(string(reader, ctx)?);
+            }
+            Some("desc") => {
+                place(reader, ctx, &mut sequence, &start, 2, Some(1))?;
+                desc = Some(string(reader, ctx)?);
+            }
+            Some("author") => {
+                place(reader, ctx, &mut sequence, &start, 3, Some(1))?;
+                author = Some(string(reader, ctx)?);
+            }
+            Some("email") => {
+                place(reader, ctx, &mut sequence, &start, 4, Some(1))?;
+                email = match value::<Email, conv::v1_0::Email>(reader, ctx) {
+                    Ok(email) => Some(email),
+                    Err(error) => invalid_v1_0_email(ctx, error, &start.name.owned(),
+                                                     &start.owned_attributes())?,
+                };
+            }
+            Some("url") => {
+                place(reader, ctx, &mut sequence, &start, 5, Some(1))?;
+                url = Some(value::<xsd::Uri, xsd::conv::Uri>(reader, ctx)?);
+            }
+            Some("urlname") => {
+                place(reader, ctx, &mut sequence, &start, 6, Some(1))?;
+                urlname = Some(string(reader, ctx)?);
+            }
+            Some("time") => {
+                place(reader, ctx, &mut sequence, &start, 7, Some(1))?;
+                time = Some(value::<xsd::DateTime, xsd::conv::DateTime>(reader, ctx)?);
+            }
+            Some("keywords") => {
+                place(reader, ctx, &mut sequence, &start, 8, Some(1))?;
+                keywords = Some(string(reader, ctx)?);
+            }
+            Some("bounds") => {
+                place(reader, ctx, &mut sequence, &start, 9, Some(1))?;
+                bounds = Some(parse_bounds(reader, ctx, start)?);
+            }
+            Some("wpt") => {
+                place(reader, ctx, &mut sequence, &start, 10, None)?;
+                waypoints.push(parse_waypoint(reader, ctx, start)?);
+            }
+            Some("rte") => {
+                place(reader, ctx, &mut sequence, &start, 11, None)?;
+                routes.push(parse_route(reader, ctx, start)?);
+            }
+            Some("trk") => {
+                place(reader, ctx, &mut sequence, &start, 12, None)?;
+                tracks.push(parse_track(reader, ctx, start)?);
+            }
+            Some("extensions") => {
+                place(reader, ctx, &mut sequence, &start, 13, Some(1))?;
+                ext.merge(extensions(reader, ctx, &start)?);
+            }
+            _ => other_element(reader, ctx, start, Some(&mut ext))?,
+        }
+        adopt(ctx, preserved_mark, &mut ext);
+        Ok(())
+    })?;
+    // GPX 1.0 describes the file with elements of its own
+    let metadata = metadata.or_else(|| {
+        let author = match (author, email) {
+            (None, None) => None,
+            (name, email) => Some(Person { name, email, link: None }),
+        };
+        let metadata = Metadata { name,
+                                  description: desc,
+                                  author,
+                                  copyright: None,
+                                  links: join_links(url, urlname, Vec::new()),
+                                  time,
+                                  keywords,
+                                  bounds,
+                                  extensions: None };
+        match metadata {
+            Metadata { name: None, description: None, author: None, time: None,
+                       keywords: None, bounds: None, ref links, .. } if links.is_empty() => None,
+            metadata => Some(metadata),
+        }
+    });
+    Ok(Gpx { version: version.ok_or_else(|| at(reader, ctx, missing(&element, "version")))?,
+             creator: creator.ok_or_else(|| at(reader, ctx, missing(&element, "creator")))?,
+             metadata,
+             waypoints,
+             routes,
+             tracks,
+             extensions: ext })
+}
+
+fn parse_metadata<'a>(reader: &mut SliceReader<'a>, ctx: &mut Context)
+        -> Result<Metadata<'a>, Positioned<Error>> {
+    let preserved_mark = ctx.preserved.len();
+    let mut metadata = Metadata { name: None,
+                                  description: None,
+                                  author: None,
+                                  copyright: None,
+                                  links: Vec::new(),
+                                  time: None,
+                                  keywords: None,
+                                  bounds: None,
+                                  extensions: None };
+    let mut sequence = SequencePosition::default();
+    children(reader, ctx, |reader, ctx, start| {
+        match gpx_local(&start.name) {
+            Some("name") => {
+                place(reader, ctx, &mut sequence, &start, 0, Some(1))?;
+                metadata.name = Some(string(reader, ctx)?);
+            }
+            Some("desc") => {
+                place(reader, ctx, &mut sequence, &start, 1, Some(1))?;
+                metadata.description = Some(string(reader, ctx)?);
+            }
+            Some("author") => {
+                place(reader, ctx, &mut sequence, &start, 2, Some(1))?;
+                metadata.author = Some(parse_person(reader, ctx)?);
+            }
+            Some("copyright") => {
+                place(reader, ctx, &mut sequence, &start, 3, Some(1))?;
+                metadata.copyright = Some(parse_copyright(reader, ctx, start)?);
+            }
+            Some("link") => {
+                place(reader, ctx, &mut sequence, &start, 4, None)?;
+                metadata.links.push(parse_link(reader, ctx, start)?);
+            }
+            Some("time") => {
+                place(reader, ctx, &mut sequence, &start, 5, Some(1))?;
+                metadata.time = Some(value::<xsd::DateTime, xsd::conv::DateTime>(reader, ctx)?);
+            }
+            Some("keywords") => {
+                place(reader, ctx, &mut sequence, &start, 6, Some(1))?;
+                metadata.keywords = Some(string(reader, ctx)?);
+            }
+            Some("bounds") => {
+                place(reader, ctx, &mut sequence, &start, 7, Some(1))?;
+                metadata.bounds = Some(parse_bounds(reader, ctx, start)?);
+            }
+            Some("extensions") => {
+                place(reader, ctx, &mut sequence, &start, 8, Some(1))?;
+                metadata.extensions.merge(extensions(reader, ctx, &start)?);
+            }
+            _ => other_element(reader, ctx, start, Some(&mut metadata.extensions))?,
+        }
+        adopt(ctx, preserved_mark, &mut metadata.extensions);
+        Ok(())
+    })?;
+    Ok(metadata)
+}
+
+fn parse_person<'a>(reader: &mut SliceReader<'a>, ctx: &mut Context)
+        -> Result<Person<'a>, Positioned<Error>> {
+    let mut person = Person { name: None, email: None, link: None };
+    let mut sequence = SequencePosition::default();
+    children(reader, ctx, |reader, ctx, start| {
+        match gpx_local(&start.name) {
+            Some("name") => {
+                place(reader, ctx, &mut sequence, &start, 0, Some(1))?;
+                person.name = Some(string(reader, ctx)?);
+            }
+            Some("email") => {
+                place(reader, ctx, &mut sequence, &start, 1, Some(1))?;
+                person.email = Some(parse_email(reader, ctx, start)?);
+            }
+            Some("link") => {
+                place(reader, ctx, &mut sequence, &start, 2, Some(1))?;
+                person.link = Some(parse_link(reader, ctx, start)?);
+            }
+            _ => other_element(reader, ctx, start, None)?,
+        }
+        Ok(())
+    })?;
+    Ok(person)
+}
+
+fn parse_email(reader: &mut SliceReader, ctx: &mut Context, start: Start)
+        -> Result<Email, Positioned<Error>> {
+    let mut id = None;
+    let mut domain = None;
+    for attribute in &start.attributes {
+        match gpx_local(&attribute.name) {
+            Some("id") => id = Some(attribute.value.clone()),
+            Some("domain") => domain = Some(attribute.value.clone()),
+            _ => other_attribute(reader, ctx, &start.name, attribute)?,
+        }
+    }
+    children(reader, ctx, |reader, ctx, start| other_element(reader, ctx, start, None))?;
+    let id = id.ok_or_else(|| at(reader, ctx, missing(&start.name, "id")))?;
+    let domain = domain.ok_or_else(|| at(reader, ctx, missing(&start.name, "domain")))?;
+    Email::new(id.into_owned(), domain.into_owned())
+         .map_err(|e| at(reader, ctx, xml::BuildError::Custom(Box::new(e))))
+}
+
+fn parse_copyright<'a>(reader: &mut SliceReader<'a>, ctx: &mut Context, start: Start<'a>)
+        -> Result<Copyright<'a>, Positioned<Error>> {
+    let mut author = None;
+    let mut year = None;
+    let mut license = None;
+    let Start { name: element, attributes } = start;
+    for attribute in attributes {
+        match gpx_local(&attribute.name) {
+            Some("author") => author = Some(attribute.value),
+            _ => other_attribute(reader, ctx, &element, &attribute)?,
+        }
+    }
+    let mut sequence = SequencePosition::default();
+    children(reader, ctx, |reader, ctx, start| {
+        match gpx_local(&start.name) {
+            Some("year") => {
+                place(reader, ctx, &mut sequence, &start, 0, Some(1))?;
+                year = Some(value::<xsd::GYear, xsd::conv::GYear>(reader, ctx)?);
+            }
+            Some("license") => {
+                place(reader, ctx, &mut sequence, &start, 1, Some(1))?;
+                license = Some(value::<xsd::Uri, xsd::conv::Uri>(reader, ctx)?);
+            }
+            _ => other_element(reader, ctx, start, None)?,
+        }
+        Ok(())
+    })?;
+    Ok(Copyright { author: author.ok_or_else(|| at(reader, ctx, missing(&element, "author")))?,
+                   year,
+                   license })
+}
+
+fn parse_link<'a>(reader: &mut SliceReader<'a>, ctx: &mut Context, start: Start<'a>)
+        -> Result<Link<'a>, Positioned<Error>> {
+    let mut href = None;
+    let mut text = None;
+    let mut type_ = None;
+    let Start { name: element, attributes } = start;
+    for attribute in attributes {
+        match gpx_local(&attribute.name) {
+            Some("href") => {
+                href = Some(xsd::conv::Uri::from_attribute(&attribute.value)
+                                .map_err(|e| bad_attribute(reader, ctx, e, &attribute))?);
+            }
+            _ => other_attribute(reader, ctx, &element, &attribute)?,
+        }
+    }
+    let mut sequence = SequencePosition::default();
+    children(reader, ctx, |reader, ctx, start| {
+        match gpx_local(&start.name) {
+            Some("text") => {
+                place(reader, ctx, &mut sequence, &start, 0, Some(1))?;
+                text = Some(string(reader, ctx)?);
+            }
+            Some("type") => {
+                place(reader, ctx, &mut sequence, &start, 1, Some(1))?;
+                type_ = Some(string(reader, ctx)?);
+            }
+            _ => other_element(reader, ctx, start, None)?,
+        }
+        Ok(())
+    })?;
+    Ok(Link { href: href.ok_or_else(|| at(reader, ctx, missing(&element, "href")))?,
+              text,
+              type_ })
+}
+
+fn parse_bounds(reader: &mut SliceReader, ctx: &mut Context, start: Start)
+        -> Result<Bounds, Positioned<Error>> {
+    let mut minlat = None;
+    let mut minlon = None;
+    let mut maxlat = None;
+    let mut maxlon = None;
+    for attribute in &start.attributes {
+        let (target, result) = match gpx_local(&attribute.name) {
+            Some("minlat") => (&mut minlat, Latitude::from_attribute(&attribute.value)),
+            Some("minlon") => (&mut minlon, Longitude::from_attribute(&attribute.value)),
+            Some("maxlat") => (&mut maxlat, Latitude::from_attribute(&attribute.value)),
+            Some("maxlon") => (&mut maxlon, Longitude::from_attribute(&attribute.value)),
+            _ => {
+                other_attribute(reader, ctx, &start.name, attribute)?;
+                continue;
+            }
+        };
+        *target = Some(result.map_err(|e| bad_attribute(reader, ctx, e, attribute))?);
+    }
+    children(reader, ctx, |reader, ctx, start| other_element(reader, ctx, start, None))?;
+    Ok(Bounds { xmin: minlat.ok_or_else(|| at(reader, ctx, missing(&start.name, "minlat")))?,
+                ymin: minlon.ok_or_else(|| at(reader, ctx, missing(&start.name, "minlon")))?,
+                xmax: maxlat.ok_or_else(|| at(reader, ctx, missing(&start.name, "maxlat")))?,
+                ymax: maxlon.ok_or_else(|| at(reader, ctx, missing(&start.name, "maxlon")))? })
+}
+
+fn parse_waypoint<'a>(reader: &mut SliceReader<'a>, ctx: &mut Context, start: Start<'a>)
+        -> Result<Waypoint<'a>, Positioned<Error>> {
+    ctx.count_waypoint().map_err(|e| at(reader, ctx, e))?;
+    let preserved_mark = ctx.preserved.len();
+    let mut lat = None;
+    let mut lon = None;
+    let mut url = None;
+    let mut urlname = None;
+    let mut links = Vec::new();
+    let mut point = Waypoint { location: Point { latitude: 0.0, longitude: 0.0, elevation: None },
+                               time: None,
+                               course: None,
+                               speed: None,
+                               mag_variation: None,
+                               geoid_height: None,
+                               name: None,
+                               comment: None,
+                               description: None,
+                               source: None,
+                               links: Vec::new(),
+                               symbol: None,
+                               type_: None,
+                               fix: None,
+                               satellites: None,
+                               hdop: None,
+                               pdop: None,
+                               vdop: None,
+                               dgps_age: None,
+                               dgps_id: None,
+                               extensions: None };
+    for attribute in &start.attributes {
+        let (target, result) = match gpx_local(&attribute.name) {
+            Some("lat") => (&mut lat, Latitude::from_attribute(&attribute.value)),
+            Some("lon") => (&mut lon, Longitude::from_attribute(&attribute.value)),
+            _ => {
+                other_attribute(reader, ctx, &start.name, attribute)?;
+                continue;
+            }
+        };
+        *target = Some(result.map_err(|e| bad_attribute(reader, ctx, e, attribute))?);
+    }
+    let mut sequence = SequencePosition::default();
+    children(reader, ctx, |reader, ctx, start| {
+        match gpx_local(&start.name) {
+            Some("ele") => {
+                place(reader, ctx, &mut sequence, &start, 0, Some(1))?;
+                point.location.elevation = Some(value::<f64, xsd::conv::Decimal>(reader, ctx)?);
+            }
+            Some("time") => {
+                place(reader, ctx, &mut sequence, &start, 1, Some(1))?;
+                point.time = Some(value::<xsd::DateTime, xsd::conv::DateTime>(reader, ctx)?);
+            }
+            Some("course") => {
+                place(reader, ctx, &mut sequence, &start, 2, Some(1))?;
+                point.course = Some(value::<Degrees, conv::Degrees>(reader, ctx)?);
+            }
+            Some("speed") => {
+                place(reader, ctx, &mut sequence, &start, 3, Some(1))?;
+                point.speed = Some(value::<f64, xsd::conv::Decimal>(reader, ctx)?);
+            }
+            Some("magvar") => {
+                place(reader, ctx, &mut sequence, &start, 4, Some(1))?;
+                point.mag_variation = Some(value::<Degrees, conv::Degrees>(reader, ctx)?);
+            }
+            Some("geoidheight") => {
+                place(reader, ctx, &mut sequence, &start, 5, Some(1))?;
+                point.geoid_height = Some(value::<f64, xsd::conv::Decimal>(reader, ctx)?);
+            }
+            Some("name") => {
+                place(reader, ctx, &mut sequence, &start, 6, Some(1))?;
+                point.name = Some(string(reader, ctx)?);
+            }
+            Some("cmt") => {
+                place(reader, ctx, &mut sequence, &start, 7, Some(1))?;
+                point.comment = Some(string(reader, ctx)?);
+            }
+            Some("desc") => {
+                place(reader, ctx, &mut sequence, &start, 8, Some(1))?;
+                point.description = Some(string(reader, ctx)?);
+            }
+            Some("src") => {
+                place(reader, ctx, &mut sequence, &start, 9, Some(1))?;
+                point.source = Some(string(reader, ctx)?);
+            }
+            Some("url") => {
+                place(reader, ctx, &mut sequence, &start, 10, Some(1))?;
+                url = Some(value::<xsd::Uri, xsd::conv::Uri>(reader, ctx)?);
+            }
+            Some("urlname") => {
+                place(reader, ctx, &mut sequence, &start, 11, Some(1))?;
+                urlname = Some(string(reader, ctx)?);
+            }
+            Some("link") => {
+                place(reader, ctx, &mut sequence, &start, 12, None)?;
+                links.push(parse_link(reader, ctx, start)?);
+            }
+            Some("sym") => {
+                place(reader, ctx, &mut sequence, &start, 13, Some(1))?;
+                point.symbol = Some(string(reader, ctx)?);
+            }
+            Some("type") => {
+                place(reader, ctx, &mut sequence, &start, 14, Some(1))?;
+                point.type_ = Some(string(reader, ctx)?);
+            }
+            Some("fix") => {
+                place(reader, ctx, &mut sequence, &start, 15, Some(1))?;
+                point.fix = Some(value::<Fix, conv::Fix>(reader, ctx)?);
+            }
+            Some("sat") => {
+                place(reader, ctx, &mut sequence, &start, 16, Some(1))?;
+                point.satellites = Some(value::<u64, xsd::conv::NonNegativeInteger>(reader, ctx)?);
+            }
+            Some("hdop") => {
+                place(reader, ctx, &mut sequence, &start, 17, Some(1))?;
+                point.hdop = Some(value::<f64, xsd::conv::Decimal>(reader, ctx)?);
+            }
+            Some("pdop") => {
+                place(reader, ctx, &mut sequence, &start, 18, Some(1))?;
+                point.pdop = Some(value::<f64, xsd::conv::Decimal>(reader, ctx)?);
+            }
+            Some("vdop") => {
+                place(reader, ctx, &mut sequence, &start, 19, Some(1))?;
+                point.vdop = Some(value::<f64, xsd::conv::Decimal>(reader, ctx)?);
+            }
+            Some("ageofdgpsdata") => {
+                place(reader, ctx, &mut sequence, &start, 20, Some(1))?;
+                point.dgps_age = Some(value::<f64, xsd::conv::Decimal>(reader, ctx)?);
+            }
+            Some("dgpsid") => {
+                place(reader, ctx, &mut sequence, &start, 21, Some(1))?;
+                point.dgps_id = Some(value::<u16, conv::DgpsStation>(reader, ctx)?);
+            }
+            Some("extensions") => {
+                place(reader, ctx, &mut sequence, &start, 22, Some(1))?;
+                point.extensions.merge(extensions(reader, ctx, &start)?);
+            }
+            _ => other_element(reader, ctx, start, Some(&mut point.extensions))?,
+        }
+        adopt(ctx, preserved_mark, &mut point.extensions);
+        Ok(())
+    })?;
+    // GPX 1.0 elements which GPX 1.1 writers placed in extensions
+    if point.course.is_none() {
+        point.course = take_v1_0_extension::<Degrees, conv::Degrees>(&mut point.extensions, "course");
+    }
+    if point.speed.is_none() {
+        point.speed = take_v1_0_extension::<xsd::Decimal, xsd::conv::Decimal>(&mut point.extensions,
+                                                                             "speed");
+    }
+    point.location.latitude = lat.ok_or_else(|| at(reader, ctx, missing(&start.name, "lat")))?;
+    point.location.longitude = lon.ok_or_else(|| at(reader, ctx, missing(&start.name, "lon")))?;
+    point.links = join_links(url, urlname, links);
+    Ok(point)
+}
+
+fn parse_route<'a>(reader: &mut SliceReader<'a>, ctx: &mut Context, start: Start<'a>)
+        -> Result<Route<'a>, Positioned<Error>> {
+    let preserved_mark = ctx.preserved.len();
+    let mut url = None;
+    let mut urlname = None;
+    let mut links = Vec::new();
+    let mut route = Route { name: None,
+                            comment: None,
+                            description: None,
+                            source: None,
+                            links: Vec::new(),
+                            number: None,
+                            type_: None,
+                            extensions: None,
+                            waypoints: Vec::new() };
+    for attribute in &start.attributes {
+        other_attribute(reader, ctx, &start.name, attribute)?;
+    }
+    let mut sequence = SequencePosition::default();
+    children(reader, ctx, |reader, ctx, start| {
+        match gpx_local(&start.name) {
+            Some("name") => {
+                place(reader, ctx, &mut sequence, &start, 0, Some(1))?;
+                route.name = Some(string(reader, ctx)?);
+            }
+            Some("cmt") => {
+                place(reader, ctx, &mut sequence, &start, 1, Some(1))?;
+                route.comment = Some(string(reader, ctx)?);
+            }
+            Some("desc") => {
+                place(reader, ctx, &mut sequence, &start, 2, Some(1))?;
+                route.description = Some(string(reader, ctx)?);
+            }
+            Some("src") => {
+                place(reader, ctx, &mut sequence, &start, 3, Some(1))?;
+                route.source = Some(string(reader, ctx)?);
+            }
+            Some("url") => {
+                place(reader, ctx, &mut sequence, &start, 4, Some(1))?;
+                url = Some(value::<xsd::Uri, xsd::conv::Uri>(reader, ctx)?);
+            }
+            Some("urlname") => {
+                place(reader, ctx, &mut sequence, &start, 5, Some(1))?;
+                urlname = Some(string(reader, ctx)?);
+            }
+            Some("link") => {
+                place(reader, ctx, &mut sequence, &start, 6, None)?;
+                links.push(parse_link(reader, ctx, start)?);
+            }
+            Some("number") => {
+                place(reader, ctx, &mut sequence, &start, 7, Some(1))?;
+                route.number = Some(value::<u64, xsd::conv::NonNegativeInteger>(reader, ctx)?);
+            }
+            Some("type") => {
+                place(reader, ctx, &mut sequence, &start, 8, Some(1))?;
+                route.type_ = Some(string(reader, ctx)?);
+            }
+            Some("extensions") => {
+                place(reader, ctx, &mut sequence, &start, 9, Some(1))?;
+                route.extensions.merge(extensions(reader, ctx, &start)?);
+            }
+            Some("rtept") => {
+                place(reader, ctx, &mut sequence, &start, 10, None)?;
+                route.waypoints.push(parse_waypoint(reader, ctx, start)?);
+            }
+            _ => other_element(reader, ctx, start, Some(&mut route.extensions))?,
+        }
+        adopt(ctx, preserved_mark, &mut route.extensions);
+        Ok(())
+    })?;
+    route.links = join_links(url, urlname, links);
+    Ok(route)
+}
+
+fn parse_track<'a>(reader: &mut SliceReader<'a>, ctx: &mut Context, start: Start<'a>)
+        -> Result<Track<'a>, Positioned<Error>> {
+    let preserved_mark = ctx.preserved.len();
+    let mut url = None;
+    let mut urlname = None;
+    let mut links = Vec::new();
+    let mut track = Track { name: None,
+                            comment: None,
+                            description: None,
+                            source: None,
+                            links: Vec::new(),
+                            number: None,
+                            type_: None,
+                            extensions: None,
+                            segments: Vec::new() };
+    for attribute in &start.attributes {
+        other_attribute(reader, ctx, &start.name, attribute)?;
+    }
+    let mut sequence = SequencePosition::default();
+    children(reader, ctx, |reader, ctx, start| {
+        match gpx_local(&start.name) {
+            Some("name") => {
+                place(reader, ctx, &mut sequence, &start, 0, Some(1))?;
+                track.name = Some(string(reader, ctx)?);
+            }
+            Some("cmt") => {
+                place(reader, ctx, &mut sequence, &start, 1, Some(1))?;
+                track.comment = Some(string(reader, ctx)?);
+            }
+            Some("desc") => {
+                place(reader, ctx, &mut sequence, &start, 2, Some(1))?;
+                track.description = Some(string(reader, ctx)?);
+            }
+            Some("src") => {
+                place(reader, ctx, &mut sequence, &start, 3, Some(1))?;
+                track.source = Some(string(reader, ctx)?);
+            }
+            Some("url") => {
+                place(reader, ctx, &mut sequence, &start, 4, Some(1))?;
+                url = Some(value::<xsd::Uri, xsd::conv::Uri>(reader, ctx)?);
+            }
+            Some("urlname") => {
+                place(reader, ctx, &mut sequence, &start, 5, Some(1))?;
+                urlname = Some(string(reader, ctx)?);
+            }
+            Some("link") => {
+                place(reader, ctx, &mut sequence, &start, 6, None)?;
+                links.push(parse_link(reader, ctx, start)?);
+            }
+            Some("number") => {
+                place(reader, ctx, &mut sequence, &start, 7, Some(1))?;
+                track.number = Some(value::<u64, xsd::conv::NonNegativeInteger>(reader, ctx)?);
+            }
+            Some("type") => {
+                place(reader, ctx, &mut sequence, &start, 8, Some(1))?;
+                track.type_ = Some(string(reader, ctx)?);
+            }
+            Some("extensions") => {
+                place(reader, ctx, &mut sequence, &start, 9, Some(1))?;
+                track.extensions.merge(extensions(reader, ctx, &start)?);
+            }
+            Some("trkseg") => {
+                place(reader, ctx, &mut sequence, &start, 10, None)?;
+                track.segments.push(parse_segment(reader, ctx, start)?);
+            }
+            _ => other_element(reader, ctx, start, Some(&mut track.extensions))?,
+        }
+        adopt(ctx, preserved_mark, &mut track.extensions);
+        Ok(())
+    })?;
+    track.links = join_links(url, urlname, links);
+    Ok(track)
+}
+
+fn parse_segment<'a>(reader: &mut SliceReader<'a>, ctx: &mut Context, start: Start<'a>)
+        -> Result<TrackSegment<'a>, Positioned<Error>> {
+    let preserved_mark = ctx.preserved.len();
+    let mut segment = TrackSegment { waypoints: Vec::new(), extensions: None };
+    for attribute in &start.attributes {
+        other_attribute(reader, ctx, &start.name, attribute)?;
+    }
+    let mut sequence = SequencePosition::default();
+    children(reader, ctx, |reader, ctx, start| {
+        match gpx_local(&start.name) {
+            Some("trkpt") => {
+                place(reader, ctx, &mut sequence, &start, 0, None)?;
+                segment.waypoints.push(parse_waypoint(reader, ctx, start)?);
+            }
+            Some("extensions") => {
+                place(reader, ctx, &mut sequence, &start, 1, Some(1))?;
+                segment.extensions.merge(extensions(reader, ctx, &start)?);
+            }
+            _ => other_element(reader, ctx, start, Some(&mut segment.extensions))?,
+        }
+        adopt(ctx, preserved_mark, &mut segment.extensions);
+        Ok(())
+    })?;
+    Ok(segment)
+}
+
+/// Finds the root element and parses it
+fn parse_document<'a>(mut reader: SliceReader<'a>, ctx: &mut Context)
+        -> Result<Gpx<'a>, DocumentError> {
+    let mut gpx = None;
+    loop {
+        match reader.next_token()? {
+            Token::Start(start) => {
+                let name = start.name.owned();
+                let outer = ctx.enter_scope(reader.scope());
+                ctx.path.enter(&name);
+                ctx.count_element(start.attributes.len()).map_err(|e| at(&reader, ctx, e))?;
+                if gpx.is_some() {
+                    return Err(at(&reader, ctx, Error::DuplicateGpx).into());
+                }
+                detect_version(ctx, &name, reader.token_position());
+                gpx = Some(parse_gpx(&mut reader, ctx, start).map_err(|e| ctx.locate(e))?);
+                ctx.path.leave();
+                ctx.leave_scope(outer);
+                if ctx.stopped {
+                    break;
+                }
+            }
+            Token::EndDocument => break,
+            _ => {}
+        }
+    }
+    gpx.ok_or(DocumentError::MissingGpx)
+}
+
+/// Parses the GPX document in `source`, borrowing text from it. Uses default `ParserOptions`.
+///
+/// ```ignore
+/// let (gpx, warnings) = gpx::borrowed::parse(&data).unwrap();
+/// ```
+pub fn parse<'a>(source: &'a str) -> Result<(Gpx<'a>, Vec<Positioned<Warning>>), DocumentError> {
+    parse_with(source, &ParserOptions::default())
+}
+
+/// Like `parse`, treating data not described by the GPX schema according to `options`
+pub fn parse_with<'a>(source: &'a str, options: &ParserOptions)
+        -> Result<(Gpx<'a>, Vec<Positioned<Warning>>), DocumentError> {
+    let mut warnings = Vec::new();
+    let gpx = parse_document(SliceReader::new(source, options),
+                             &mut Context::new(options, &mut warnings))?;
+    Ok((gpx, warnings))
+}
+
+/// Like `parse`, for UTF-8 bytes such as a memory mapped file
+pub fn parse_bytes<'a>(source: &'a [u8]) -> Result<(Gpx<'a>, Vec<Positioned<Warning>>), DocumentError> {
+    match str::from_utf8(source) {
+        Ok(source) => parse(source),
+        Err(e) => {
+            let valid = String::from_utf8_lossy(&source[..e.valid_up_to()]);
+            let mut position = TextPosition::new();
+            for line in valid.split('\n') {
+                position.row += 1;
+                position.column = line.chars().count() as u64;
+            }
+            position.row -= 1;
+            Err(XmlError::from((&position, "Invalid UTF-8")).into())
+        }
+    }
+}
@@ -0,0 +1,718 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Parsing of GPX files.
+//!
+//! Parses elements of XML files into Rust types
+
+extern crate xml as _xml;
+extern crate chrono;
+
+use std::fmt;
+use std::io;
+use std::io::Read;
+use std::str::FromStr;
+use std::error::Error as ErrorTrait;
+use self::_xml::common::TextPosition;
+use self::_xml::name::OwnedName;
+use self::_xml::attribute::OwnedAttribute;
+
+use xml;
+use xml::{ DocumentParserData, OptionalElement, EventSource };
+use xml::par::required_attribute;
+use xsd;
+use gpx;
+use gpx::{ Document, Gpx, Bounds, Version, Waypoint, Fix, Metadata, Point, TrackSegment, Track, Route, Link, Copyright, Person, Email, Degrees };
+use gpx::conv;
+use gpx::conv::{ Latitude, Longitude };
+use ::par::{ FromAttributeVia, ParseVia, ParseViaChar, ElementParse, ElementBuild };
+use ::par::{ Positioned, FormatError, AttributeError };
+use ::par::{ Context, Diagnostics, Warning, WarningKind, Limit, Preserved };
+#[cfg(feature = "async")]
+use async_io;
+#[cfg(feature = "async")]
+use async_io::{ AsyncRead, ParseFuture };
+
+pub mod stream;
+pub mod push;
+pub mod recover;
+pub mod borrowed;
+
+pub use self::stream::{ stream, stream_with, stream_from };
+pub use self::push::PushParser;
+pub use self::recover::{ recover, recover_with };
+pub use ::par::{ ParserOptions, Policy, ParseLimits, Skip };
+
+
+include!(concat!(env!("OUT_DIR"), "/gpx_par_auto.rs"));
+
+
+/// Describes a failure while parsing data
+#[derive(Debug)]
+pub enum Error {
+    /// IO and XML problems
+    Xml(_xml::reader::Error),
+    DuplicateGpx,
+    UnknownFix(String),
+    /// Value is none of the ones listed in its enumeration type
+    UnknownValue(String),
+    /// Errors from XSD types
+    Xsd(xsd::par::Error),
+    //BadAttribute(AttributeError),
+    BadElement(xml::ElementError),
+    BadShape(xml::BuildError),
+    TooSmall { limit: f64,
+               value: f64 },
+    TooLarge { limit: f64,
+               value: f64 },
+    BadEmailId(String),
+    InvalidEmailDomain(String),
+    UnknownElement(OwnedName), // also attribute
+    /// Element appears after elements which must follow it
+    OutOfOrder(OwnedName),
+    /// Element appears more times than allowed
+    TooMany(OwnedName),
+    /// Required attribute is not present on the element
+    MissingAttribute { element: OwnedName, attribute: OwnedName },
+    /// Required sub-element is not present in the element
+    MissingElement { element: OwnedName, child: OwnedName },
+    InvalidVersion(String),
+    /// Document is bigger than allowed by `ParserOptions::limits`
+    LimitExceeded { limit: Limit, max: u64 },
+}
+
+impl From<xsd::par::Error> for Error {
+    fn from(err: xsd::par::Error) -> Error {
+        Error::Xsd(err)
+    }
+}
+
+impl From<AttributeError<Error>> for Error {
+    fn from(err: AttributeError<Error>) -> Error {
+        match err {
+            AttributeError::Unexpected(name) => Error::UnknownElement(name),
+            AttributeError::InvalidValue(e) => e,
+            AttributeError::BadValue { error, .. } => error,
+        }
+    }
+}
+
+impl From<xml::ElementError> for Error {
+    fn from(err: xml::ElementError) -> Error {
+        Error::BadElement(err)
+    }
+}
+
+impl From<xml::BuildError> for Error {
+    fn from(err: xml::BuildError) -> Error {
+        match err {
+            xml::BuildError::MissingAttribute { element, attribute } => {
+                Error::MissingAttribute { element, attribute }
+            }
+            xml::BuildError::MissingElement { element, child } => {
+                Error::MissingElement { element, child }
+            }
+            err => Error::BadShape(err),
+        }
+    }
+}
+
+impl From<_xml::reader::Error> for Error {
+    fn from(err: _xml::reader::Error) -> Error {
+        Error::Xml(err)
+    }
+}
+
+impl From<xsd::par::Error> for AttributeError<Error> {
+    fn from(err: xsd::par::Error) -> AttributeError<Error> {
+        AttributeError::InvalidValue(Error::from(err))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let description = self.summary();
+        match *self {
+            Error::Xml(ref e) => write!(fmt, "{}: {}", description, e),
+            Error::UnknownFix(ref value) | Error::UnknownValue(ref value)
+                    | Error::BadEmailId(ref value) | Error::InvalidEmailDomain(ref value)
+                    | Error::InvalidVersion(ref value) => {
+                write!(fmt, "{} {:?}", description, value)
+            }
+            Error::Xsd(ref e) => write!(fmt, "{}", e),
+            Error::BadShape(ref e) => write!(fmt, "{}", e),
+            Error::BadElement(ref e) => write!(fmt, "{}", e),
+            Error::TooSmall { limit, value } => {
+                write!(fmt, "{}: {} is less than {}", description, value, limit)
+            }
+            Error::TooLarge { limit, value } => {
+                write!(fmt, "{}: {} is more than {}", description, value, limit)
+            }
+            Error::UnknownElement(ref name) | Error::OutOfOrder(ref name)
+                    | Error::TooMany(ref name) => {
+                write!(fmt, "{} {}", description, name)
+            }
+            Error::MissingAttribute { ref element, ref attribute } => {
+                write!(fmt, "{} {} on {}", description, attribute, element)
+            }
+            Error::MissingElement { ref element, ref child } => {
+                write!(fmt, "{} {} in {}", description, child, element)
+            }
+            Error::DuplicateGpx => write!(fmt, "{}", description),
+            Error::LimitExceeded { limit, max } => {
+                write!(fmt, "{}: {} is over {}", description, limit, max)
+            }
+        }
+    }
+}
+
+impl Error {
+    /// Kind of the error, which its message starts with
+    fn summary(&self) -> &'static str {
+        match *self {
+            Error::DuplicateGpx => "Repeated gpx root",
+            Error::UnknownFix(_) => "Unknown fix value",
+            Error::UnknownValue(_) => "Unknown enumeration value",
+            Error::Xml(_) => "XML parser error",
+            Error::Xsd(_) => "XSD type parsing error",
+            Error::BadShape(_) => "Wrong elements number",
+            Error::BadElement(_) => "Bad element",
+            Error::TooSmall { limit: _, value: _ } => "Too small",
+            Error::TooLarge { limit: _, value: _ } => "Too large",
+            Error::BadEmailId(_) => "Bad email ID",
+            Error::InvalidEmailDomain(_) => "Invalid email domain",
+            Error::InvalidVersion(_) => "Invalid GPX version",
+            Error::UnknownElement(_) => "Unknown element",
+            Error::OutOfOrder(_) => "Element out of order",
+            Error::TooMany(_) => "Element repeated too many times",
+            Error::MissingAttribute { .. } => "Missing required attribute",
+            Error::MissingElement { .. } => "Missing required element",
+            Error::LimitExceeded { .. } => "Limit exceeded",
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        match *self {
+            Error::Xml(ref e) => Some(e),
+            // displayed the same as the wrapped errors
+            Error::Xsd(ref e) => e.source(),
+            Error::BadElement(ref e) => e.source(),
+            Error::BadShape(ref e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+impl FormatError for Error {}
+
+impl FromAttributeVia<f64> for Latitude {
+    type Error = Error;
+    fn from_attribute(attr: &str) -> Result<f64, Error> {
+        f64::from_str(attr).map_err(|e| Error::from(xsd::par::Error::from(e)))
+    }
+}
+
+impl FromAttributeVia<f64> for Longitude {
+    type Error = Error;
+    fn from_attribute(attr: &str) -> Result<f64, Error> {
+        f64::from_str(attr).map_err(|e| Error::from(xsd::par::Error::from(e)))
+    }
+}
+
+impl FromAttributeVia<Version> for conv::Version {
+    type Error = Error;
+    fn from_attribute(attr: &str) -> Result<Version, Error> {
+        match attr {
+            "1.0" => Ok(Version::V1_0),
+            "1.1" => Ok(Version::V1_1),
+            v => Err(Error::InvalidVersion(v.into())),
+        }
+    }
+}
+
+/// Joins GPX 1.0 `<url>` and `<urlname>` with GPX 1.1 links, the former going first
+fn join_links(url: Option<xsd::Uri>, urlname: Option<String>, links: Vec<Link>) -> Vec<Link> {
+    match url {
+        Some(href) => {
+            let mut joined = vec![Link { href, text: urlname, type_: None }];
+            joined.extend(links);
+            joined
+        }
+        None => links,
+    }
+}
+
+/// Takes out an element which GPX 1.1 writers placed in extensions because it only exists in GPX 1.0.
+///
+/// Elements with invalid contents stay in place.
+fn take_v1_0_extension<T, C: ParseViaChar<T>>(extensions: &mut Option<xml::Element>,
+                                               local_name: &str) -> Option<T> {
+    let (value, now_empty) = {
+        let ext = match *extensions {
+            Some(ref mut ext) => ext,
+            None => return None,
+        };
+        let found = ext.nodes.iter().enumerate().filter_map(|(index, node)| {
+            let elem = match *node {
+                xml::Node::Element(ref name, ref elem) if name.local_name == local_name
+                        && name.namespace.as_ref().map(|ns| ns as &str)
+                            == Some(Version::V1_0.namespace()) => elem,
+                _ => return None,
+            };
+            if elem.nodes.len() != 1 {
+                return None;
+            }
+            match elem.nodes[0] {
+                xml::Node::Text(ref text) => C::from_char(text.trim()).ok()
+                                                                  .map(|value| (index, value)),
+                _ => None,
+            }
+        }).next();
+        let (index, value) = found?;
+        ext.nodes.remove(index);
+        (value, ext.attributes.is_empty() && ext.nodes.is_empty())
+    };
+    if now_empty {
+        *extensions = None;
+    }
+    Some(value)
+}
+
+impl GpxElemParser {
+    /// Takes `<metadata>`, or builds metadata from GPX 1.0 elements if there was none
+    fn build_metadata(&mut self) -> Option<Metadata> {
+        if let Some(metadata) = self.metadata.take() {
+            return Some(metadata);
+        }
+        let author = match (self.author.take(), self.email.take().and_then(|email| email)) {
+            (None, None) => None,
+            (name, email) => Some(Person { name, email, link: None }),
+        };
+        let metadata = Metadata { name: self.name.take(),
+                                  description: self.desc.take(),
+                                  author,
+                                  copyright: None,
+                                  links: join_links(self.url.take(), self.urlname.take(), Vec::new()),
+                                  time: self.time.take(),
+                                  keywords: self.keywords.take(),
+                                  bounds: self.bounds.take(),
+                                  extensions: None };
+        match metadata {
+            Metadata { name: None, description: None, author: None, time: None,
+                       keywords: None, bounds: None, ref links, .. } if links.is_empty() => None,
+            metadata => Some(metadata),
+        }
+    }
+}
+
+/// Gpx needs custom building because of GPX 1.0 elements describing the file
+impl ElementBuild for GpxElemParser {
+    type Element = Gpx;
+    type BuildError = xml::BuildError;
+    fn build(mut self, name: &OwnedName) -> Result<Self::Element, Self::BuildError> {
+        let metadata = self.build_metadata();
+        Ok(Gpx { version: required_attribute(self.version, name, "version")?,
+                 creator: required_attribute(self.creator, name, "creator")?,
+                 metadata,
+                 waypoints: self.wpt,
+                 routes: self.rte,
+                 tracks: self.trk,
+                 extensions: self.extensions })
+    }
+}
+
+/// Routes need custom building because of GPX 1.0 `<url>`
+impl ElementBuild for RteParser {
+    type Element = Route;
+    type BuildError = xml::BuildError;
+    fn build(self, name: &OwnedName) -> Result<Self::Element, Self::BuildError> {
+        let _ = name;
+        Ok(Route { name: self.name,
+                   comment: self.cmt,
+                   description: self.desc,
+                   source: self.src,
+                   links: join_links(self.url, self.urlname, self.link),
+                   number: self.number,
+                   type_: self.type_,
+                   extensions: self.extensions,
+                   waypoints: self.rtept })
+    }
+}
+
+/// Tracks need custom building because of GPX 1.0 `<url>`
+impl ElementBuild for TrkParser {
+    type Element = Track;
+    type BuildError = xml::BuildError;
+    fn build(self, name: &OwnedName) -> Result<Self::Element, Self::BuildError> {
+        let _ = name;
+        Ok(Track { name: self.name,
+                   comment: self.cmt,
+                   description: self.desc,
+                   source: self.src,
+                   links: join_links(self.url, self.urlname, self.link),
+                   number: self.number,
+                   type_: self.type_,
+                   extensions: self.extensions,
+                   segments: self.trkseg })
+    }
+}
+
+impl ElementBuild for BoundsParser {
+    type Element = Bounds;
+    type BuildError = xml::BuildError;
+    fn build(self, name: &OwnedName) -> Result<Self::Element, Self::BuildError> {
+        Ok(Bounds { xmin: required_attribute(self.minlat, name, "minlat")?,
+                    ymin: required_attribute(self.minlon, name, "minlon")?,
+                    xmax: required_attribute(self.maxlat, name, "maxlat")?,
+                    ymax: required_attribute(self.maxlon, name, "maxlon")? })
+    }
+}
+
+/// Extensions are typed according to `ParserOptions::extensions`
+impl ParseVia<xml::Element> for conv::Extensions {
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
+                              name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<xml::Element, Positioned<Error>> {
+        xml::ElementParser::extensions().parse(name, attributes, parser, ctx)
+    }
+}
+
+impl ParseVia<Bounds> for conv::Bounds {
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
+                              name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<Bounds, Positioned<Error>> {
+        BoundsParser::new().parse(name, attributes, parser, ctx)
+    }
+}
+
+impl ParseVia<Route> for conv::Rte {
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
+                              name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<Route, Positioned<Error>> {
+        RteParser::new().parse(name, attributes, parser, ctx)
+    }
+}
+
+impl ParseVia<Track> for conv::Trk {
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
+                              name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<Track, Positioned<Error>> {
+        TrkParser::new().parse(name, attributes, parser, ctx)
+    }
+}
+
+impl ParseVia<TrackSegment> for conv::Trkseg {
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
+                              name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<TrackSegment, Positioned<Error>> {
+        TrackSegmentParser::new().parse(name, attributes, parser, ctx)
+    }
+}
+
+impl ParseVia<Link> for conv::Link {
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
+                              name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<Link, Positioned<Error>> {
+        LinkParser::new().parse(name, attributes, parser, ctx)
+    }
+}
+
+impl ParseViaChar<Fix> for conv::Fix {
+    fn from_char(s: &str) -> Result<Fix, Error> {
+        Ok(match s {
+            "none" => Fix::None,
+            "2d" => Fix::_2D,
+            "3d" => Fix::_3D,
+            "dgps" => Fix::DGPS,
+            "pps" => Fix::PPS,
+            _ => return Err(Error::UnknownFix(s.into())),
+        })
+    }
+}
+
+/// Waypoints need custom building because of the "location" field being composed of attributes and an element.
+///
+/// GPX 1.0 `<course>` and `<speed>` are also found in extensions of GPX 1.1 points.
+impl ElementBuild for WaypointParser {
+    type Element = Waypoint;
+    type BuildError = xml::BuildError;
+    fn build(mut self, name: &OwnedName) -> Result<Self::Element, Self::BuildError> {
+        if self.course.is_none() {
+            self.course = take_v1_0_extension::<Degrees, conv::Degrees>(&mut self.extensions,
+                                                                       "course");
+        }
+        if self.speed.is_none() {
+            self.speed = take_v1_0_extension::<xsd::Decimal, xsd::conv::Decimal>(&mut self.extensions,
+                                                                                 "speed");
+        }
+        Ok(Waypoint { location: Point { latitude: required_attribute(self.lat, name, "lat")?,
+                                        longitude: required_attribute(self.lon, name, "lon")?,
+                                        elevation: self.ele },
+                      time: self.time,
+                      course: self.course,
+                      speed: self.speed,
+                      mag_variation: self.magvar,
+                      geoid_height: self.geoidheight,
+                      name: self.name,
+                      comment: self.cmt,
+                      description: self.desc,
+                      source: self.src,
+                      links: join_links(self.url, self.urlname, self.link),
+                      symbol: self.sym,
+                      type_: self.type_,
+                      fix: self.fix,
+                      satellites: self.sat,
+                      hdop: self.hdop,
+                      pdop: self.pdop,
+                      vdop: self.vdop,
+                      dgps_age: self.ageofdgpsdata,
+                      dgps_id: self.dgpsid,
+                      extensions: self.extensions })
+    }
+}
+
+/// Counts waypoints against `ParseLimits::max_waypoints`
+impl ParseVia<Waypoint> for conv::Wpt {
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
+                              name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<Waypoint, Positioned<Error>> {
+        ctx.count_waypoint().map_err(|e| ctx.locate(Positioned::with_position(e, parser.position())))?;
+        WaypointParser::new().parse(name, attributes, parser, ctx)
+    }
+}
+
+impl ElementBuild for EmailParser {
+    type Element = Email;
+    type BuildError = xml::BuildError;
+    fn build(self, name: &OwnedName) -> Result<Self::Element, Self::BuildError> {
+        let id = required_attribute(self.id, name, "id")?;
+        let domain = required_attribute(self.domain, name, "domain")?;
+        Email::new(id, domain).map_err(|e| xml::par::BuildError::Custom(Box::new(e)))
+    }
+}
+
+/// GPX 1.0 keeps the address in one piece, following the pattern of its `<email>`.
+///
+/// Both parts are labels separated by dots, and the domain has at least two of them.
+/// Labels are made of letters and `_`, and also digits and `-` which the pattern leaves out
+/// but addresses use.
+impl ParseViaChar<Email> for conv::v1_0::Email {
+    fn from_char(s: &str) -> Result<Email, Error> {
+        let email = Email::from_str(s.trim())?;
+        let invalid_label = |label: &str| {
+            label.is_empty() || !label.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        };
+        if email.id().split('.').any(invalid_label) {
+            return Err(Error::BadEmailId(email.id().into()));
+        }
+        if !email.domain().contains('.') || email.domain().split('.').any(invalid_label) {
+            return Err(Error::InvalidEmailDomain(email.domain().into()));
+        }
+        Ok(email)
+    }
+}
+
+/// Addresses not fitting `emailType` are left out according to `ParserOptions::unknown_elements`
+impl ParseVia<Option<Email>> for conv::v1_0::Email {
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
+                                 name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<Option<Email>, Positioned<Error>> {
+        match <Self as ParseVia<Email>>::parse_via(parser, ctx, name, attributes) {
+            Ok(email) => Ok(Some(email)),
+            Err(error) => invalid_v1_0_email(ctx, error, name, attributes),
+        }
+    }
+}
+
+/// Deals with the GPX 1.0 `<email>` which failed with `error`.
+///
+/// It used to be free text, so addresses which don't fit are handled like unknown elements.
+pub fn invalid_v1_0_email(ctx: &mut Context, error: Positioned<Error>,
+                          name: &OwnedName, attributes: &[OwnedAttribute])
+        -> Result<Option<Email>, Positioned<Error>> {
+    match (&error.data, ctx.options.unknown_elements) {
+        (&Error::BadEmailId(_), Policy::Skip) | (&Error::InvalidEmailDomain(_), Policy::Skip) => {
+            ctx.warn(WarningKind::IgnoredElement, name, error.position);
+            Ok(None)
+        }
+        (&Error::BadEmailId(_), Policy::Preserve)
+                | (&Error::InvalidEmailDomain(_), Policy::Preserve) => {
+            let text = error.text.unwrap_or_default();
+            let element = xml::Element { attributes: attributes.to_vec(),
+                                         nodes: vec![xml::Node::Text(text)],
+                                         ..xml::Element::default() };
+            ctx.preserved.push(Preserved(xml::Node::Element(name.clone(), element)));
+            Ok(None)
+        }
+        _ => Err(error),
+    }
+}
+
+
+/// Error describing a failure while parsing an XML stream
+#[derive(Debug)]
+pub enum DocumentError {
+    /// IO, XML errors
+    ParserError(_xml::reader::Error),
+    /// XML parser errors arising from broken parser
+    DocumentParserError(xml::DocumentParserError), // TODO: turn into panics?
+    /// Problems parsing contents
+    BadData(Positioned<Error>),
+    MissingGpx, // try to make this positioned and put inside BadData?
+}
+
+impl From<_xml::reader::Error> for DocumentError {
+    fn from(err: _xml::reader::Error) -> DocumentError {
+        DocumentError::ParserError(err)
+    }
+}
+
+/// Failure to read the source
+impl From<io::Error> for DocumentError {
+    fn from(err: io::Error) -> DocumentError {
+        DocumentError::ParserError(err.into())
+    }
+}
+
+impl From<Positioned<Error>> for DocumentError {
+    fn from(err: Positioned<Error>) -> DocumentError {
+        DocumentError::BadData(err)
+    }
+}
+
+impl From<xml::DocumentParserError> for DocumentError {
+    fn from(err: xml::DocumentParserError) -> DocumentError {
+        DocumentError::DocumentParserError(err)
+    }
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            DocumentError::ParserError(ref e) => write!(fmt, "XML parser error: {}", e),
+            DocumentError::DocumentParserError(ref e) => write!(fmt, "{}", e),
+            DocumentError::BadData(ref e) => write!(fmt, "{}", e),
+            DocumentError::MissingGpx => write!(fmt, "Document has no gpx element"),
+        }
+    }
+}
+
+impl ErrorTrait for DocumentError {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        match *self {
+            DocumentError::ParserError(ref e) => Some(e),
+            DocumentError::DocumentParserError(ref e) => e.source(),
+            DocumentError::BadData(ref e) => e.source(),
+            DocumentError::MissingGpx => None,
+        }
+    }
+}
+
+/// Prepares `ctx` for the GPX version of the document, judging by its `root` element.
+///
+/// Reports documents using an unknown namespace.
+fn detect_version(ctx: &mut Context, root: &OwnedName, position: TextPosition) {
+    match root.namespace.as_ref().map(|ns| ns as &str) {
+        None => {}
+        Some(ns) if ns == Version::V1_1.namespace() => {}
+        Some(ns) if ns == Version::V1_0.namespace() => ctx.flat_extensions = true,
+        Some(_) => ctx.warn(WarningKind::UnsupportedVersion, root, position),
+    }
+}
+
+#[derive(Default)]
+struct ParserData(Option<Gpx>);
+
+impl DocumentParserData for ParserData {
+    type Contents = Gpx;
+    type Error = DocumentError;
+    fn parse_element<R: EventSource>(&mut self, reader: &mut R, ctx: &mut Context,
+                              name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<(), Positioned<Error>> {
+        if let &mut ParserData(Some(_)) = self {
+            return Err(Positioned::with_position(Error::DuplicateGpx,
+                                                 reader.position()));
+        }
+        detect_version(ctx, name, reader.position());
+        self.0 = Some(GpxElemParser::new().parse(name, attributes, reader, ctx)?);
+        Ok(())
+    }
+    fn build(self) -> Result<Gpx, Self::Error> {
+        match self.0 {
+            Some(gpx) => Ok(gpx),
+            None => Err(DocumentError::MissingGpx)
+        }
+    }
+}
+
+/// Takes in GPX stream and returns an instance of `gpx::Document` along with warnings.
+///
+/// Uses default `ParserOptions`.
+///
+/// ```no_run
+/// # use std::fs::File;
+/// # use gpx_rust::gpx;
+/// let f = File::open("foo").unwrap();
+/// let (xml_gpx, warnings) = gpx::par::parse(f).unwrap();
+/// ```
+pub fn parse<R: Read>(source: R)
+        -> Result<(Document, Vec<Positioned<Warning>>), DocumentError> {
+    parse_with(source, &ParserOptions::default())
+}
+
+/// Like `parse`, treating data not described by the GPX schema according to `options`.
+///
+/// ```ignore
+/// let options = ParserOptions { unknown_elements: Policy::Preserve,
+///                               ..ParserOptions::default() };
+/// let (xml_gpx, warnings) = gpx::par::parse_with(f, &options).unwrap();
+/// ```
+pub fn parse_with<R: Read>(source: R, options: &ParserOptions)
+        -> Result<(Document, Vec<Positioned<Warning>>), DocumentError> {
+    let mut warnings = Vec::new();
+    let document = parse_with_diagnostics(source, options, &mut warnings)?;
+    Ok((document, warnings))
+}
+
+/// Takes in GPX stream and returns an instance of `gpx::Document`, passing warnings to `diagnostics`.
+pub fn parse_with_diagnostics<R: Read>(source: R, options: &ParserOptions,
+                                       diagnostics: &mut dyn Diagnostics)
+        -> Result<Document, DocumentError> {
+    xml::parse_document::<R, ParserData>(source, &mut Context::new(options, diagnostics))
+}
+
+/// Like `parse_with`, reading events from any source, for example another XML parser
+pub fn parse_from<S: EventSource>(source: S, options: &ParserOptions)
+        -> Result<(Document, Vec<Positioned<Warning>>), DocumentError> {
+    let mut warnings = Vec::new();
+    let document = xml::parse_events::<S, ParserData>(source,
+                                                      &mut Context::new(options, &mut warnings))?;
+    Ok((document, warnings))
+}
+
+/// Like `parse`, reading from an asynchronous source. The parser runs on its own thread.
+#[cfg(feature = "async")]
+pub fn parse_async<R: AsyncRead + Unpin>(source: R)
+        -> ParseFuture<R, (Document, Vec<Positioned<Warning>>), DocumentError> {
+    parse_async_with(source, &ParserOptions::default())
+}
+
+/// Like `parse_with`, reading from an asynchronous source
+#[cfg(feature = "async")]
+pub fn parse_async_with<R: AsyncRead + Unpin>(source: R, options: &ParserOptions)
+        -> ParseFuture<R, (Document, Vec<Positioned<Warning>>), DocumentError> {
+    let options = options.clone();
+    async_io::parse(source, move |input| parse_with(input, &options))
+}
@@ -0,0 +1,369 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Push parser for GPX documents arriving in chunks.
+//!
+//! ```ignore
+//! let mut parser = PushParser::new();
+//! for chunk in chunks {
+//!     parser.feed(&chunk);
+//!     for event in &mut parser {
+//!         if let Event::TrackPoint(point) = event.unwrap() {
+//!             println!("{:?}", point.location);
+//!         }
+//!     }
+//! }
+//! parser.finish();
+//! let rest = parser.collect::<Result<Vec<_>, _>>().unwrap();
+//! ```
+//!
+//! Reports the same events as `Stream`. A waypoint or segment is reported
+//! as soon as the chunk holding its closing tag is fed.
+
+extern crate xml as _xml;
+
+use std::cmp;
+use std::io;
+use std::io::Read;
+use std::collections::VecDeque;
+
+use self::_xml::common::TextPosition;
+use self::_xml::name::OwnedName;
+use self::_xml::reader::{ ParserConfig, EventReader, XmlEvent };
+
+use xml::EventSource;
+use gpx::par::DocumentError;
+use gpx::par::stream::{ Event, Stream, PartialSource, stream_from };
+use par::{ Positioned, Warning, ParserOptions };
+
+
+/// Bytes fed so far, handed to the XML parser only as far as it can't run out of them
+struct Input {
+    data: Vec<u8>,
+    /// Bytes taken by the XML parser
+    read: usize,
+    /// End of the last complete markup after which the parser stops, except for comments
+    safe: usize,
+    /// End of the data searched for markup, up to the markup not complete yet
+    scanned: usize,
+    /// Search for the end of the markup starting at `scanned`, kept between pieces
+    pending: Option<Scan>,
+    closed: bool,
+}
+
+/// Kind of markup, known from its beginning
+#[derive(Clone, Copy)]
+enum Markup {
+    /// Ends with a fixed delimiter, like comments, CDATA sections and processing instructions
+    Delimited { start: usize, end: &'static [u8], comment: bool },
+    /// Ends with the first `>` not quoted, like tags and declarations
+    Tag,
+}
+
+/// Unfinished search for the end of markup
+struct Scan {
+    markup: Markup,
+    /// Bytes of the markup searched already
+    searched: usize,
+    /// Quote open at `searched` in a tag
+    quote: Option<u8>,
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Tells the kind of the markup at the beginning of `data`, if enough of it is there
+fn markup_kind(data: &[u8]) -> Option<Markup> {
+    let delimited = [(&b"<!--"[..], &b"-->"[..], true),
+                     (&b"<![CDATA["[..], &b"]]>"[..], false),
+                     (&b"<?"[..], &b"?>"[..], false)];
+    for &(start, end, comment) in &delimited {
+        if data.starts_with(start) {
+            return Some(Markup::Delimited { start: start.len(), end, comment });
+        }
+        if start.starts_with(data) {
+            // not known yet
+            return None;
+        }
+    }
+    Some(Markup::Tag)
+}
+
+impl Scan {
+    fn new(markup: Markup) -> Scan {
+        Scan { markup, searched: 0, quote: None }
+    }
+
+    /// Returns the length of the markup at the beginning of `data` if it's complete,
+    /// continuing from where the last call stopped
+    fn length(&mut self, data: &[u8]) -> Option<usize> {
+        match self.markup {
+            Markup::Delimited { start, end, .. } => {
+                // the delimiter may have been cut
+                let from = cmp::max(start, self.searched.saturating_sub(end.len() - 1));
+                self.searched = data.len();
+                find(&data[from..], end).map(|i| from + i + end.len())
+            }
+            Markup::Tag => {
+                for (i, &byte) in data.iter().enumerate().skip(self.searched) {
+                    match (self.quote, byte) {
+                        (None, b'"') | (None, b'\'') => self.quote = Some(byte),
+                        (Some(q), byte) if q == byte => self.quote = None,
+                        (None, b'>') => return Some(i + 1),
+                        _ => {}
+                    }
+                }
+                self.searched = data.len();
+                None
+            }
+        }
+    }
+
+    fn is_comment(&self) -> bool {
+        match self.markup {
+            Markup::Delimited { comment, .. } => comment,
+            Markup::Tag => false,
+        }
+    }
+}
+
+impl Input {
+    fn push(&mut self, bytes: &[u8]) {
+        // drops what the XML parser doesn't need anymore
+        self.data.drain(..self.read);
+        self.safe -= self.read;
+        self.scanned -= self.read;
+        self.read = 0;
+
+        self.data.extend_from_slice(bytes);
+        loop {
+            let mut scan = match self.pending.take() {
+                Some(scan) => scan,
+                None => {
+                    let rest = &self.data[self.scanned..];
+                    let start = match rest.iter().position(|&byte| byte == b'<') {
+                        Some(start) => start,
+                        None => {
+                            // plain text needs no more scanning
+                            self.scanned = self.data.len();
+                            return;
+                        }
+                    };
+                    self.scanned += start;
+                    match markup_kind(&rest[start..]) {
+                        Some(markup) => Scan::new(markup),
+                        None => return,
+                    }
+                }
+            };
+            match scan.length(&self.data[self.scanned..]) {
+                Some(length) => {
+                    self.scanned += length;
+                    // text following a comment is joined with the text before it
+                    if !scan.is_comment() {
+                        self.safe = self.scanned;
+                    }
+                }
+                None => {
+                    self.pending = Some(scan);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        self.safe = self.data.len();
+    }
+
+    /// Tells if the XML parser can continue without reaching the end of the data fed
+    fn has_markup(&self) -> bool {
+        self.read < self.safe
+    }
+
+    /// Tells if the element started last was an empty one, like `<a/>`
+    fn ends_empty_element(&self) -> bool {
+        self.data[..self.read].ends_with(b"/>")
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.read == self.safe && !self.closed {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Markup incomplete"));
+        }
+        let count = cmp::min(buf.len(), self.safe - self.read);
+        buf[..count].copy_from_slice(&self.data[self.read..self.read + count]);
+        self.read += count;
+        Ok(count)
+    }
+}
+
+/// XML events waiting for the stream
+struct Events {
+    events: VecDeque<(XmlEvent, TextPosition)>,
+    position: TextPosition,
+}
+
+impl EventSource for Events {
+    fn next(&mut self) -> Result<XmlEvent, _xml::reader::Error> {
+        match self.events.pop_front() {
+            Some((event, position)) => {
+                self.position = position;
+                Ok(event)
+            }
+            None => Err(_xml::reader::Error::from(
+                (&self.position, "Internal error: stream read an event which wasn't available"))),
+        }
+    }
+    fn position(&self) -> TextPosition {
+        self.position
+    }
+}
+
+impl PartialSource for Events {
+    fn available<F: Fn(&OwnedName) -> bool>(&self, whole: F) -> bool {
+        match self.events.front() {
+            None => false,
+            Some(&(XmlEvent::StartElement { ref name, .. }, _)) if whole(name) => {
+                let mut depth = 0;
+                for (event, _) in &self.events {
+                    match *event {
+                        XmlEvent::StartElement { .. } => depth += 1,
+                        XmlEvent::EndElement { .. } => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return true;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                false
+            }
+            Some(_) => true,
+        }
+    }
+}
+
+/// Parses a GPX document fed piece by piece.
+///
+/// Iterating returns the `Event`s which the data fed so far completes,
+/// and ends when it needs more data or the document is over.
+pub struct PushParser {
+    reader: EventReader<Input>,
+    stream: Stream<Events>,
+    /// The element started last was empty, its end comes without reading
+    empty_element: bool,
+    /// All events of the document were read
+    ended: bool,
+    failed: bool,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        PushParser::new()
+    }
+}
+
+impl PushParser {
+    /// Creates a parser with default `ParserOptions`
+    pub fn new() -> Self {
+        PushParser::with_options(ParserOptions::default())
+    }
+
+    /// Creates a parser treating data not described by the GPX schema according to `options`
+    pub fn with_options(options: ParserOptions) -> Self {
+        let input = Input { data: Vec::new(), read: 0, safe: 0, scanned: 0, pending: None,
+                            closed: false };
+        let reader = ParserConfig::new().ignore_comments(!options.keep_markup)
+                                        .create_reader(input);
+        let events = Events { events: VecDeque::new(), position: TextPosition::new() };
+        PushParser { reader,
+                     stream: stream_from(events, options),
+                     empty_element: false,
+                     ended: false,
+                     failed: false }
+    }
+
+    /// Adds the next piece of the document
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.reader.source_mut().push(bytes);
+    }
+
+    /// Declares that the whole document was fed, so that the remaining events can be returned
+    pub fn finish(&mut self) {
+        self.reader.source_mut().close();
+    }
+
+    /// Removes and returns warnings collected so far, see `Stream::take_warnings`
+    pub fn take_warnings(&mut self) -> Vec<Positioned<Warning>> {
+        self.stream.take_warnings()
+    }
+
+    /// Moves the XML events which can be read from the data fed into the stream.
+    ///
+    /// Returns the number of events.
+    fn read_events(&mut self) -> Result<usize, DocumentError> {
+        let mut count = 0;
+        loop {
+            let more = {
+                let input = self.reader.source();
+                input.has_markup() || self.empty_element || (input.closed && !self.ended)
+            };
+            if !more {
+                return Ok(count);
+            }
+            let event = EventSource::next(&mut self.reader)?;
+            let position = self.reader.position();
+            self.empty_element = match event {
+                XmlEvent::StartElement { .. } => self.reader.source().ends_empty_element(),
+                _ => false,
+            };
+            if let XmlEvent::EndDocument = event {
+                self.ended = true;
+            }
+            self.stream.source_mut().events.push_back((event, position));
+            count += 1;
+        }
+    }
+}
+
+impl Iterator for PushParser {
+    type Item = Result<Event, DocumentError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        loop {
+            match self.stream.next_available() {
+                Some(Ok(event)) => return Some(Ok(event)),
+                Some(Err(e)) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+                None => {}
+            }
+            match self.read_events() {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
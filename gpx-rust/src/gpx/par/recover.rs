@@ -0,0 +1,191 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Recovery of data from damaged GPX files.
+//!
+//! Meant for files cut off by loggers which lost power:
+//! everything parsed completely before the damage is kept.
+
+extern crate xml as _xml;
+
+use std::io::Read;
+
+use self::_xml::common::{ Position, TextPosition };
+
+use xml::OptionalElement;
+use gpx::{ Gpx, TrackSegment };
+use gpx::par::DocumentError;
+use gpx::par::stream::{ Event, stream_with };
+use par::{ Positioned, Warning, ParserOptions };
+
+
+/// Part of the document which was open when parsing stopped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Section {
+    /// Before the `<gpx>` element
+    Prolog,
+    /// Directly inside `<gpx>`, or inside a route or track which had no points yet
+    Gpx,
+    /// Inside the last recovered route
+    Route,
+    /// Inside the last recovered track, between segments
+    Track,
+    /// Inside the last segment of the last recovered track
+    Segment,
+    /// After the `<gpx>` element
+    Epilog,
+}
+
+/// Describes where and why parsing stopped
+#[derive(Debug)]
+pub struct Failure {
+    pub error: DocumentError,
+    /// Position of the error, or of the last XML event read if the error has none
+    pub position: TextPosition,
+    pub section: Section,
+}
+
+/// Data recovered from a possibly damaged document
+#[derive(Debug)]
+pub struct Recovered {
+    /// Contents parsed completely. `None` if the `<gpx>` element was never reached.
+    ///
+    /// The last route, track and segment may be unfinished, see `Failure::section`.
+    pub data: Option<Gpx>,
+    pub warnings: Vec<Positioned<Warning>>,
+    /// `None` if the whole document was parsed
+    pub failure: Option<Failure>,
+}
+
+/// Assembles `Gpx` from stream events
+struct Builder {
+    gpx: Option<Gpx>,
+    section: Section,
+}
+
+impl Builder {
+    fn add(&mut self, event: Event) {
+        let gpx = match self.gpx {
+            Some(ref mut gpx) => gpx,
+            None => {
+                // the stream always starts with this
+                if let Event::Start { version, creator } = event {
+                    self.gpx = Some(Gpx { version,
+                                          creator,
+                                          metadata: None,
+                                          waypoints: Vec::new(),
+                                          routes: Vec::new(),
+                                          tracks: Vec::new(),
+                                          extensions: None });
+                    self.section = Section::Gpx;
+                }
+                return;
+            }
+        };
+        self.section = match event {
+            Event::Start { .. } => Section::Gpx,
+            Event::Metadata(metadata) => {
+                gpx.metadata = Some(metadata);
+                Section::Gpx
+            }
+            Event::Waypoint(point) => {
+                gpx.waypoints.push(point);
+                Section::Gpx
+            }
+            Event::RouteStart(route) => {
+                gpx.routes.push(route);
+                Section::Route
+            }
+            Event::RoutePoint(point) => {
+                if let Some(route) = gpx.routes.last_mut() {
+                    route.waypoints.push(point);
+                }
+                Section::Route
+            }
+            Event::RouteEnd => Section::Gpx,
+            Event::TrackStart(track) => {
+                gpx.tracks.push(track);
+                Section::Track
+            }
+            Event::SegmentStart => {
+                if let Some(track) = gpx.tracks.last_mut() {
+                    track.segments.push(TrackSegment { waypoints: Vec::new(),
+                                                       extensions: None });
+                }
+                Section::Segment
+            }
+            Event::TrackPoint(point) => {
+                if let Some(segment) = gpx.tracks.last_mut().and_then(|t| t.segments.last_mut()) {
+                    segment.waypoints.push(point);
+                }
+                Section::Segment
+            }
+            Event::SegmentEnd(extensions) => {
+                if let Some(segment) = gpx.tracks.last_mut().and_then(|t| t.segments.last_mut()) {
+                    segment.extensions = extensions;
+                }
+                Section::Track
+            }
+            Event::TrackEnd => Section::Gpx,
+            Event::Extensions(extensions) => {
+                gpx.extensions.merge(extensions);
+                Section::Gpx
+            }
+            Event::End => Section::Epilog,
+        };
+    }
+}
+
+fn error_position(error: &DocumentError) -> Option<TextPosition> {
+    match *error {
+        DocumentError::ParserError(ref e) => Some(e.position()),
+        DocumentError::BadData(ref e) => Some(e.position),
+        _ => None,
+    }
+}
+
+/// Parses GPX stream, keeping everything read before the first error.
+///
+/// Uses default `ParserOptions`.
+///
+/// ```ignore
+/// let recovered = gpx::par::recover(File::open("crashed.gpx").unwrap());
+/// if let Some(failure) = recovered.failure {
+///     println!("Stopped at {} in {:?}: {:?}", failure.position, failure.section, failure.error);
+/// }
+/// ```
+pub fn recover<R: Read>(source: R) -> Recovered {
+    recover_with(source, ParserOptions::default())
+}
+
+/// Like `recover`, treating data not described by the GPX schema according to `options`.
+pub fn recover_with<R: Read>(source: R, options: ParserOptions) -> Recovered {
+    let mut events = stream_with(source, options);
+    let mut builder = Builder { gpx: None, section: Section::Prolog };
+    let mut failure = None;
+    while let Some(event) = events.next() {
+        match event {
+            Ok(event) => builder.add(event),
+            Err(error) => {
+                let position = error_position(&error).unwrap_or_else(|| events.position());
+                failure = Some(Failure { error,
+                                         position,
+                                         section: builder.section });
+                break;
+            }
+        }
+    }
+    Recovered { data: builder.gpx,
+                warnings: events.take_warnings(),
+                failure }
+}
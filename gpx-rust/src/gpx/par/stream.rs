@@ -0,0 +1,629 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Streaming parser for GPX files.
+//!
+//! Reports the contents of the document piece by piece instead of building `gpx::Gpx`,
+//! so that memory use does not grow with the number of points.
+
+extern crate xml as _xml;
+
+use std::io::Read;
+use std::mem;
+use std::collections::VecDeque;
+
+use self::_xml::common::TextPosition;
+use self::_xml::name::OwnedName;
+use self::_xml::attribute::OwnedAttribute;
+use self::_xml::namespace::Namespace;
+use self::_xml::reader::{ ParserConfig, EventReader, XmlEvent };
+
+use xml;
+use xml::{ ElementParser, OptionalElement, EventSource };
+use xml::par::required_attribute;
+use gpx::{ Version, Metadata, Waypoint, Route, Track };
+use gpx::conv;
+use gpx::par::{ Error, DocumentError, GpxElemParser, RteParser, TrkParser, TrackSegmentParser };
+use gpx::par::detect_version;
+use par::{ ParseVia, ElementParse, ElementBuild, Positioned };
+use par::{ Context, Counts, ElementPath, Warning, WarningKind, ParserOptions, Policy };
+use par::SequencePosition;
+
+
+/// A piece of GPX document
+#[derive(Debug)]
+pub enum Event {
+    /// Attributes of the `<gpx>` element
+    Start { version: Version, creator: String },
+    /// `<metadata>`, or the GPX 1.0 elements describing the file.
+    ///
+    /// GPX 1.0 documents written by this library may carry `<metadata>` also at their end,
+    /// which then arrives as another `Metadata` event replacing the first one.
+    Metadata(Metadata),
+    /// `<wpt>` element
+    Waypoint(Waypoint),
+    /// Route properties. `waypoints` is always empty, points follow as `RoutePoint`
+    RouteStart(Route),
+    RoutePoint(Waypoint),
+    RouteEnd,
+    /// Track properties. `segments` is always empty, segments follow as `SegmentStart`
+    TrackStart(Track),
+    SegmentStart,
+    TrackPoint(Waypoint),
+    /// Closes the segment, carries its extensions
+    SegmentEnd(Option<xml::Element>),
+    TrackEnd,
+    /// Extensions of the `<gpx>` element.
+    ///
+    /// Data preserved because of `ParserOptions` arrives in additional `Extensions` events.
+    Extensions(xml::Element),
+    /// Closes the `<gpx>` element
+    End,
+}
+
+/// Position in the document tree.
+///
+/// Route and track properties are collected in their parsers until the first point arrives.
+enum State {
+    PreStart,
+    PreRoot,
+    Gpx,
+    Route(Option<Header<RteParser>>),
+    Track(Option<Header<TrkParser>>),
+    Segment(Option<xml::Element>),
+    /// Inside an element left out because of `ParserOptions::skip`,
+    /// with the length of text read so far in each element open since
+    Skipping(Box<State>, Vec<usize>),
+    PostRoot,
+    Finished,
+}
+
+impl State {
+    /// Returns the number of elements open in this state
+    fn depth(&self) -> usize {
+        match *self {
+            State::PreStart | State::PreRoot | State::PostRoot | State::Finished => 0,
+            State::Gpx => 1,
+            State::Route(_) | State::Track(_) => 2,
+            State::Segment(_) => 3,
+            State::Skipping(ref outer, ref text_lengths) => outer.depth() + text_lengths.len(),
+        }
+    }
+
+    /// Tells if the element starting in this state is reported piece by piece, instead of being read whole
+    fn enters(&self, name: &OwnedName) -> bool {
+        match *self {
+            State::PreStart | State::PreRoot | State::PostRoot | State::Finished => true,
+            State::Skipping(..) => true,
+            State::Gpx => is_gpx(name) && (name.local_name == "rte" || name.local_name == "trk"),
+            State::Track(_) => is_gpx(name) && name.local_name == "trkseg",
+            State::Route(_) | State::Segment(_) => false,
+        }
+    }
+}
+
+/// Source of XML events which may not hold the whole document yet, see `Stream::next_available`
+pub trait PartialSource: EventSource {
+    /// Tells if the next event is there.
+    ///
+    /// If it starts an element for which `whole` is true, the element must be there up to its end.
+    fn available<F: Fn(&OwnedName) -> bool>(&self, whole: F) -> bool;
+}
+
+/// Route or track properties collected so far
+struct Header<P> {
+    name: OwnedName,
+    parser: P,
+}
+
+impl<P: ElementBuild> Header<P> {
+    fn build(self) -> Result<P::Element, P::BuildError> {
+        self.parser.build(&self.name)
+    }
+}
+
+/// Iterator over `Event`s of a GPX document.
+///
+/// Keeps at most one point in memory at a time.
+/// Elements of `<gpx>`, routes and tracks must come in the order defined in the GPX schema
+/// as far as metadata and properties go before points.
+/// With `ParserOptions::strict`, the rest of the order is checked too, as when parsing whole.
+/// Unknown data found after the first point of a route or track can't be preserved anymore,
+/// and is skipped instead.
+///
+/// Warnings are kept until `take_warnings` is called, so they grow with the skipped data.
+/// Take them as you go when skipping data in long documents.
+pub struct Stream<S: EventSource> {
+    reader: S,
+    options: ParserOptions,
+    /// Set for GPX 1.0 documents, see `Context::flat_extensions`
+    flat_extensions: bool,
+    /// Elements open at the current event, see `Context::path`
+    path: ElementPath,
+    /// Data parsed so far, see `Context::counts`
+    counts: Counts,
+    state: State,
+    /// Place among the children of `<gpx>` after the metadata, checked in strict mode
+    sequence: SequencePosition,
+    /// Collects metadata from the beginning of `<gpx>`, until the rest of the contents starts
+    root: Option<GpxElemParser>,
+    pending: VecDeque<Event>,
+    /// Not bounded, emptied only by `take_warnings`
+    warnings: Vec<Positioned<Warning>>,
+}
+
+fn is_gpx(name: &OwnedName) -> bool {
+    match name.namespace {
+        None => true,
+        Some(ref ns) => matches!(ns as &str, "http://www.topografix.com/GPX/1/1" |
+                                             "http://www.topografix.com/GPX/1/0"),
+    }
+}
+
+/// Positions `err` at the last event read, in the element being parsed
+fn with_pos<R: EventSource, E: Into<Error>>(reader: &R, ctx: &Context, err: E)
+        -> Positioned<Error> {
+    ctx.locate(Positioned::with_position(err.into(), reader.position()))
+}
+
+/// Chooses the policy for an element from a foreign namespace
+fn foreign_policy(ctx: &Context) -> Policy {
+    if ctx.flat_extensions {
+        Policy::Preserve
+    } else {
+        ctx.options.foreign_namespaces
+    }
+}
+
+/// Chooses the policy for an element not described by the schema
+fn policy_for(ctx: &Context, name: &OwnedName) -> Policy {
+    if is_gpx(name) {
+        ctx.options.unknown_elements
+    } else {
+        foreign_policy(ctx)
+    }
+}
+
+/// Tells if the element belongs to the description of the file placed at the beginning of `<gpx>`
+pub fn is_metadata(name: &OwnedName) -> bool {
+    is_gpx(name) && match &name.local_name as &str {
+        "metadata" |
+        // GPX 1.0
+        "name" | "desc" | "author" | "email" | "url" | "urlname" | "time" | "keywords" | "bounds" => true,
+        _ => false,
+    }
+}
+
+/// Turns data collected at the beginning of `<gpx>` into events
+fn root_events(root: Option<GpxElemParser>) -> Vec<Event> {
+    let mut events = Vec::new();
+    if let Some(mut parser) = root {
+        if let Some(metadata) = parser.build_metadata() {
+            events.push(Event::Metadata(metadata));
+        }
+        if let Some(ext) = parser.extensions {
+            events.push(Event::Extensions(ext));
+        }
+    }
+    events
+}
+
+/// Applies `policy` to an element not described by the schema.
+///
+/// Returns the element if it should be preserved.
+fn unexpected<R: EventSource>(reader: &mut R, ctx: &mut Context, policy: Policy,
+                       name: &OwnedName, attributes: &[OwnedAttribute])
+        -> Result<Option<xml::Element>, Positioned<Error>> {
+    match policy {
+        Policy::Error => Err(with_pos(reader, ctx, Error::UnknownElement(name.clone()))),
+        Policy::Skip => {
+            ctx.warn(WarningKind::IgnoredElement, name, reader.position());
+            ElementParser::new().parse(name, attributes, reader, ctx)?;
+            Ok(None)
+        }
+        Policy::Preserve => {
+            let options = ctx.options;
+            let node = options.extensions.parse(reader, ctx, name, attributes)?;
+            Ok(Some(xml::Element { attributes: Vec::new(),
+                                   nodes: vec![node],
+                                   namespace: Namespace::empty() }))
+        }
+    }
+}
+
+/// Checks the place of the child `name` in the sequence of its parent,
+/// where it's at `index` and may occur up to `max_occurs` times, like the generated parsers do
+fn place<R: EventSource>(reader: &R, ctx: &mut Context, sequence: &mut SequencePosition,
+                         name: &OwnedName, index: usize, max_occurs: Option<u64>)
+        -> Result<(), Positioned<Error>> {
+    ctx.path.set_max_occurs(max_occurs);
+    if ctx.checks_order(name) {
+        sequence.advance(index, max_occurs, name).map_err(|e| with_pos(reader, ctx, e))?;
+    }
+    Ok(())
+}
+
+/// Handles a direct child of `<gpx>` following the metadata
+fn gpx_child<R: EventSource>(reader: &mut R, ctx: &mut Context, sequence: &mut SequencePosition,
+                      name: &OwnedName, attributes: &[OwnedAttribute])
+        -> Result<(State, Option<Event>), Positioned<Error>> {
+    if !is_gpx(name) {
+        let policy = foreign_policy(ctx);
+        let preserved = unexpected(reader, ctx, policy, name, attributes)?;
+        return Ok((State::Gpx, preserved.map(Event::Extensions)));
+    }
+    let position = reader.position();
+    Ok(match &name.local_name as &str {
+        "wpt" => {
+            place(reader, ctx, sequence, name, 10, None)?;
+            let point = conv::Wpt::parse_via(reader, ctx, name, attributes)?;
+            (State::Gpx, Some(Event::Waypoint(point)))
+        }
+        "rte" => {
+            place(reader, ctx, sequence, name, 11, None)?;
+            let mut parser = RteParser::new();
+            parser.parse_start(ctx, name, attributes, position)
+                  .map_err(|e| with_pos(reader, ctx, e))?;
+            (State::Route(Some(Header { name: name.clone(), parser })), None)
+        }
+        "trk" => {
+            place(reader, ctx, sequence, name, 12, None)?;
+            let mut parser = TrkParser::new();
+            parser.parse_start(ctx, name, attributes, position)
+                  .map_err(|e| with_pos(reader, ctx, e))?;
+            (State::Track(Some(Header { name: name.clone(), parser })), None)
+        }
+        "extensions" => {
+            place(reader, ctx, sequence, name, 13, Some(1))?;
+            let ext = conv::Extensions::parse_via(reader, ctx, name, attributes)?;
+            (State::Gpx, Some(Event::Extensions(ext)))
+        }
+        _ => {
+            let policy = ctx.options.unknown_elements;
+            let preserved = unexpected(reader, ctx, policy, name, attributes)?;
+            (State::Gpx, preserved.map(Event::Extensions))
+        }
+    })
+}
+
+/// Passes a route or track property to its parser.
+///
+/// Properties are not accepted once points started arriving, because the header was already emitted.
+/// The same goes for preserving unknown data.
+fn header_child<R: EventSource, P>(reader: &mut R, ctx: &mut Context,
+                            header: Option<Header<P>>,
+                            name: &OwnedName, attributes: &[OwnedAttribute])
+        -> Result<Option<Header<P>>, Positioned<Error>>
+        where P: ElementParse<Error> + ElementBuild<BuildError=xml::BuildError> {
+    match header {
+        Some(mut header) => {
+            header.parser.parse_element(reader, ctx, name, attributes)?;
+            header.parser.adopt_preserved(ctx, 0);
+            Ok(Some(header))
+        }
+        None => {
+            if is_gpx(name) {
+                Err(with_pos(reader, ctx, Error::OutOfOrder(name.clone())))
+            } else {
+                let policy = match foreign_policy(ctx) {
+                    Policy::Preserve => Policy::Skip,
+                    other => other,
+                };
+                unexpected(reader, ctx, policy, name, attributes)?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<S: EventSource> Stream<S> {
+    /// Removes and returns warnings collected so far.
+    ///
+    /// Nothing else frees them, see `Stream`.
+    pub fn take_warnings(&mut self) -> Vec<Positioned<Warning>> {
+        mem::take(&mut self.warnings)
+    }
+
+    /// Returns the position of the last XML event read
+    pub fn position(&self) -> TextPosition {
+        self.reader.position()
+    }
+
+    /// Returns the source of XML events
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.reader
+    }
+
+    /// Consumes a single XML event
+    fn step(&mut self) -> Result<Option<Event>, DocumentError> {
+        let next = self.reader.next()?;
+        let reader = &mut self.reader;
+        let mut ctx = Context::new(&self.options, &mut self.warnings);
+        ctx.flat_extensions = self.flat_extensions;
+        ctx.path = mem::take(&mut self.path);
+        ctx.counts = self.counts;
+        if let XmlEvent::StartElement { ref name, ref attributes, .. } = next {
+            ctx.path.enter(name);
+            ctx.count_element(attributes.len()).map_err(|e| with_pos(reader, &ctx, e))?;
+        }
+        let ctx = &mut ctx;
+        let (state, event) = match (mem::replace(&mut self.state, State::Finished), next) {
+            (State::PreStart, XmlEvent::StartDocument { .. }) => (State::PreRoot, None),
+            (State::PreStart, ev) => {
+                return Err(xml::DocumentParserError::UnexpectedEventPreStart(ev).into());
+            }
+            (State::Skipping(outer, mut text_lengths), XmlEvent::StartElement { .. }) => {
+                text_lengths.push(0);
+                (State::Skipping(outer, text_lengths), None)
+            }
+            (State::Skipping(outer, mut text_lengths), XmlEvent::EndElement { .. }) => {
+                text_lengths.pop();
+                (if text_lengths.is_empty() { *outer } else { State::Skipping(outer, text_lengths) }, None)
+            }
+            (State::Skipping(outer, mut text_lengths), XmlEvent::Characters(data)) |
+            (State::Skipping(outer, mut text_lengths), XmlEvent::Whitespace(data)) |
+            (State::Skipping(outer, mut text_lengths), XmlEvent::CData(data)) => {
+                // text may come in many pieces
+                let length = text_lengths.last_mut().expect("the skipped element is open");
+                *length += data.len();
+                ctx.check_text(*length).map_err(|e| with_pos(reader, ctx, e))?;
+                (State::Skipping(outer, text_lengths), None)
+            }
+            (state, XmlEvent::Whitespace(_)) => (state, None),
+            // there's no place for markup between the reported pieces
+            (state, XmlEvent::Comment(_)) => (state, None),
+            (state, XmlEvent::ProcessingInstruction { .. }) => (state, None),
+            (State::Gpx, XmlEvent::StartElement { ref name, .. })
+                    if ctx.options.skip.stops_at(name, ctx.path.depth()) => {
+                self.pending.extend(root_events(self.root.take()));
+                self.pending.push_back(Event::End);
+                (State::Finished, self.pending.pop_front())
+            }
+            // read event by event, so that the element doesn't need to be available whole
+            (state, XmlEvent::StartElement { ref name, .. }) if state.depth() > 0 && ctx.skips(name) => {
+                (State::Skipping(Box::new(state), vec![0]), None)
+            }
+            (State::PreRoot, XmlEvent::StartElement { name, attributes, .. }) => {
+                let position = reader.position();
+                detect_version(ctx, &name, position);
+                self.flat_extensions = ctx.flat_extensions;
+                let mut parser = GpxElemParser::new();
+                parser.parse_start(ctx, &name, &attributes, position)
+                      .map_err(|e| with_pos(reader, ctx, e))?;
+                let version = required_attribute(parser.version, &name, "version")
+                                  .map_err(|e| with_pos(reader, ctx, e))?;
+                let creator = required_attribute(parser.creator.take(), &name, "creator")
+                                  .map_err(|e| with_pos(reader, ctx, e))?;
+                // preserved attributes
+                self.pending.extend(parser.extensions.take().map(Event::Extensions));
+                self.root = Some(parser);
+                (State::Gpx, Some(Event::Start { version, creator }))
+            }
+            (State::PreRoot, XmlEvent::EndDocument) => return Err(DocumentError::MissingGpx),
+            (State::Gpx, XmlEvent::StartElement { name, attributes, .. }) => {
+                if is_metadata(&name) {
+                    match self.root {
+                        Some(ref mut parser) => {
+                            parser.parse_element(reader, ctx, &name, &attributes)?;
+                            parser.adopt_preserved(ctx, 0);
+                            (State::Gpx, None)
+                        }
+                        // complete metadata written at the end of a GPX 1.0 document
+                        None if name.local_name == "metadata" => {
+                            let meta = conv::Metadata::parse_via(reader, ctx,
+                                                                 &name, &attributes)?;
+                            (State::Gpx, Some(Event::Metadata(meta)))
+                        }
+                        None => return Err(with_pos(reader, ctx, Error::OutOfOrder(name)).into()),
+                    }
+                } else {
+                    let (state, event) = gpx_child(reader, ctx, &mut self.sequence,
+                                                   &name, &attributes)?;
+                    self.pending.extend(root_events(self.root.take()));
+                    self.pending.extend(event);
+                    (state, self.pending.pop_front())
+                }
+            }
+            (State::Gpx, XmlEvent::EndElement { .. }) => {
+                self.pending.extend(root_events(self.root.take()));
+                self.pending.push_back(Event::End);
+                (State::PostRoot, self.pending.pop_front())
+            }
+            (State::Route(mut header), XmlEvent::StartElement { name, attributes, .. }) => {
+                if is_gpx(&name) && name.local_name == "rtept" {
+                    // points come last, so only the first one needs checking
+                    if let Some(ref mut header) = header {
+                        place(reader, ctx, &mut header.parser.sequence, &name, 10, None)?;
+                    }
+                    let point = conv::Wpt::parse_via(reader, ctx, &name, &attributes)?;
+                    match header {
+                        Some(header) => {
+                            self.pending.push_back(Event::RoutePoint(point));
+                            let route = header.build().map_err(|e| with_pos(reader, ctx, e))?;
+                            (State::Route(None), Some(Event::RouteStart(route)))
+                        }
+                        None => (State::Route(None), Some(Event::RoutePoint(point))),
+                    }
+                } else {
+                    (State::Route(header_child(reader, ctx, header, &name, &attributes)?),
+                     None)
+                }
+            }
+            (State::Route(header), XmlEvent::EndElement { .. }) => {
+                match header {
+                    Some(header) => {
+                        self.pending.push_back(Event::RouteEnd);
+                        let route = header.build().map_err(|e| with_pos(reader, ctx, e))?;
+                        (State::Gpx, Some(Event::RouteStart(route)))
+                    }
+                    None => (State::Gpx, Some(Event::RouteEnd)),
+                }
+            }
+            (State::Track(mut header), XmlEvent::StartElement { name, attributes, .. }) => {
+                if is_gpx(&name) && name.local_name == "trkseg" {
+                    // segments come last, so only the first one needs checking
+                    if let Some(ref mut header) = header {
+                        place(reader, ctx, &mut header.parser.sequence, &name, 10, None)?;
+                    }
+                    // segments carry no attributes, but the parser reports unexpected ones
+                    let position = reader.position();
+                    let mut segment = TrackSegmentParser::new();
+                    segment.parse_start(ctx, &name, &attributes, position)
+                           .map_err(|e| with_pos(reader, ctx, e))?;
+                    match header {
+                        Some(header) => {
+                            self.pending.push_back(Event::SegmentStart);
+                            let track = header.build().map_err(|e| with_pos(reader, ctx, e))?;
+                            (State::Segment(segment.extensions), Some(Event::TrackStart(track)))
+                        }
+                        None => (State::Segment(segment.extensions), Some(Event::SegmentStart)),
+                    }
+                } else {
+                    (State::Track(header_child(reader, ctx, header, &name, &attributes)?),
+                     None)
+                }
+            }
+            (State::Track(header), XmlEvent::EndElement { .. }) => {
+                match header {
+                    Some(header) => {
+                        self.pending.push_back(Event::TrackEnd);
+                        let track = header.build().map_err(|e| with_pos(reader, ctx, e))?;
+                        (State::Gpx, Some(Event::TrackStart(track)))
+                    }
+                    None => (State::Gpx, Some(Event::TrackEnd)),
+                }
+            }
+            (State::Segment(mut extensions), XmlEvent::StartElement { name, attributes, .. }) => {
+                match (is_gpx(&name), &name.local_name as &str) {
+                    (true, "trkpt") => {
+                        let point = conv::Wpt::parse_via(reader, ctx, &name, &attributes)?;
+                        (State::Segment(extensions), Some(Event::TrackPoint(point)))
+                    }
+                    (true, "extensions") => {
+                        let ext = conv::Extensions::parse_via(reader, ctx,
+                                                              &name, &attributes)?;
+                        extensions.merge(ext);
+                        (State::Segment(extensions), None)
+                    }
+                    _ => {
+                        let policy = policy_for(ctx, &name);
+                        if let Some(ext) = unexpected(reader, ctx, policy,
+                                                      &name, &attributes)? {
+                            extensions.merge(ext);
+                        }
+                        (State::Segment(extensions), None)
+                    }
+                }
+            }
+            (State::Segment(extensions), XmlEvent::EndElement { .. }) => {
+                (State::Track(None), Some(Event::SegmentEnd(extensions)))
+            }
+            (State::PostRoot, XmlEvent::StartElement { .. }) => {
+                return Err(with_pos(reader, ctx, Error::DuplicateGpx).into());
+            }
+            (State::PostRoot, XmlEvent::EndDocument) => (State::Finished, None),
+            (state, XmlEvent::Characters(_)) => (state, None),
+            (_, ev) => {
+                return Err(with_pos(reader, ctx, xml::ElementError::UnexpectedEvent(ev)).into());
+            }
+        };
+        // elements handled whole are closed already
+        while ctx.path.depth() > state.depth() {
+            ctx.path.leave();
+        }
+        self.path = mem::take(&mut ctx.path);
+        self.counts = ctx.counts;
+        self.state = state;
+        Ok(event)
+    }
+}
+
+impl<S: EventSource> Stream<S> {
+    /// Returns the next piece of the document, reading XML events only while `available` allows it
+    fn advance<F>(&mut self, available: F) -> Option<Result<Event, DocumentError>>
+            where F: Fn(&Self) -> bool {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(Ok(event));
+        }
+        loop {
+            if let State::Finished = self.state {
+                return None;
+            }
+            if !available(self) {
+                return None;
+            }
+            match self.step() {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => {}
+                Err(e) => {
+                    self.state = State::Finished;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl<S: PartialSource> Stream<S> {
+    /// Like `next`, but returns `None` also when the source doesn't hold the events for the next piece yet
+    pub fn next_available(&mut self) -> Option<Result<Event, DocumentError>> {
+        self.advance(|stream| stream.reader.available(|name| !stream.state.enters(name)))
+    }
+}
+
+impl<S: EventSource> Iterator for Stream<S> {
+    type Item = Result<Event, DocumentError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.advance(|_| true)
+    }
+}
+
+/// Takes in GPX stream and returns an iterator over its contents.
+///
+/// Uses default `ParserOptions`.
+/// Warnings are collected inside the iterator, see `Stream::take_warnings`.
+///
+/// ```ignore
+/// let f = File::open("foo").unwrap();
+/// for event in gpx::par::stream(f) {
+///     if let gpx::par::stream::Event::TrackPoint(point) = event.unwrap() {
+///         println!("{:?}", point.location);
+///     }
+/// }
+/// ```
+pub fn stream<R: Read>(source: R) -> Stream<EventReader<R>> {
+    stream_with(source, ParserOptions::default())
+}
+
+/// Like `stream`, treating data not described by the GPX schema according to `options`.
+///
+/// With `ParserOptions::keep_markup`, markup is kept only inside the pieces reported whole, like waypoints.
+pub fn stream_with<R: Read>(source: R, options: ParserOptions) -> Stream<EventReader<R>> {
+    let reader = ParserConfig::new().ignore_comments(!options.keep_markup)
+                                    .create_reader(source);
+    stream_from(reader, options)
+}
+
+/// Like `stream_with`, reading events from any source
+pub fn stream_from<S: EventSource>(source: S, options: ParserOptions) -> Stream<S> {
+    Stream { reader: source,
+             options,
+             flat_extensions: false,
+             path: ElementPath::default(),
+             counts: Counts::default(),
+             state: State::PreStart,
+             sequence: SequencePosition::default(),
+             root: None,
+             pending: VecDeque::new(),
+             warnings: Vec::new() }
+}
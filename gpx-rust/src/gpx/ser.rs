@@ -24,21 +24,21 @@ use self::_xml::namespace::{ Namespace, NS_NO_PREFIX };
 use self::_xml::attribute::Attribute;
 use self::_xml::writer::{ XmlEvent, EventWriter };
 
+use xml;
 use xsd;
-use gpx::{ Gpx, Version, Waypoint, Fix, Bounds };
+use gpx::{ Gpx, Version, Waypoint, Fix, Bounds, Email };
 use gpx::conv::{ Latitude, Longitude };
 use gpx::conv;
+use gpx::validate::Invalid;
 use ser;
 use ser::FormatError;
-use ser::{ SerializeDocument, SerializeVia, ToCharsVia };
-
-const GPX_NS: &'static str = "http://www.topografix.com/GPX/1/1";
+use ser::{ SerializeDocument, SerializeVia, SerializerOptions, ToCharsVia };
 
 
 macro_rules! set_optional(
-    ($sink:ident, $name:expr, $tag:expr, $type_:path) => {
+    ($sink:ident, $options:ident, $name:expr, $tag:expr, $type_:path) => {
         if let Some(ref item) = $name {
-            try!(<$type_>::serialize_via(item, $sink, &OwnedName::local($tag)));
+            <$type_>::serialize_via(item, $sink, $options, &OwnedName::local($tag))?;
         }
     }
 );
@@ -46,8 +46,7 @@ macro_rules! set_optional(
 /// Value cannot be serialized
 #[derive(Debug)]
 pub enum Error {
-    InvalidEmail,
-    OutOfBounds(BoundCondition, Box<OutOfBoundsTrait>),
+    OutOfBounds(BoundCondition, Box<dyn OutOfBoundsTrait>),
     DecimalOutOfBounds(f64),
     Xsd(xsd::ser::Error),
 }
@@ -58,14 +57,41 @@ pub struct OutOfBoundsValue<T> {
     value: T,
 }
 
-pub trait OutOfBoundsTrait where Self: fmt::Debug {}
+/// Value and limit of any type, for `Error::OutOfBounds`
+pub trait OutOfBoundsTrait: fmt::Debug + Send + Sync {
+    fn value(&self) -> String;
+    fn limit(&self) -> String;
+}
 
-impl<T: fmt::Debug> OutOfBoundsTrait for OutOfBoundsValue<T> {}
+impl<T: fmt::Debug + fmt::Display + Send + Sync> OutOfBoundsTrait for OutOfBoundsValue<T> {
+    fn value(&self) -> String { self.value.to_string() }
+    fn limit(&self) -> String { self.limit.to_string() }
+}
 
+/// Condition which the value must meet against the limit
 #[derive(Debug)]
 pub enum BoundCondition {
     EqualGreater,
     EqualLesser,
+    Lesser,
+}
+
+impl fmt::Display for BoundCondition {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.write_str(match *self {
+            BoundCondition::EqualGreater => "at least",
+            BoundCondition::EqualLesser => "at most",
+            BoundCondition::Lesser => "less than",
+        })
+    }
+}
+
+impl Error {
+    /// Creates the error of `value` not meeting `condition` against `limit`
+    pub fn out_of_bounds<T>(condition: BoundCondition, limit: T, value: T) -> Error
+            where T: fmt::Debug + fmt::Display + Send + Sync + 'static {
+        Error::OutOfBounds(condition, Box::new(OutOfBoundsValue { limit, value }))
+    }
 }
 
 impl FormatError for Error {}
@@ -76,17 +102,25 @@ impl From<xsd::ser::Error> for Error {
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        fmt::Debug::fmt(self, fmt)
+        match *self {
+            Error::OutOfBounds(ref condition, ref bounds) => {
+                // the value may equal an exclusive limit, so it's not the subject
+                write!(fmt, "Value outside of allowed range: must be {} {}, got {}",
+                       condition, bounds.limit(), bounds.value())
+            }
+            Error::DecimalOutOfBounds(value) => {
+                write!(fmt, "Decimal value is outside of allowed range: {}", value)
+            }
+            Error::Xsd(ref e) => write!(fmt, "{}", e),
+        }
     }
 }
 
 impl ErrorTrait for Error {
-    fn description(&self) -> &str {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
         match *self {
-            Error::InvalidEmail => "Email must contain exactly one @ sign",
-            Error::DecimalOutOfBounds(_) => "Decimal value is outside of allowed range",
-            Error::OutOfBounds(_, _) => "Value outside of allowed range",
-            Error::Xsd(_) => "XSD",
+            Error::Xsd(ref e) => Some(e),
+            _ => None,
         }
     }
 }
@@ -94,7 +128,9 @@ impl ErrorTrait for Error {
 impl ToCharsVia<f64> for Latitude {
     type Error = Error;
     fn to_characters(data: &f64) -> Result<String, Error> {
-        if *data >= 90.0 || *data < -90.0 {
+        if !data.is_finite() {
+            Err(xsd::ser::Error::NotFinite(*data).into())
+        } else if *data > 90.0 || *data < -90.0 {
             Err(Error::DecimalOutOfBounds(*data))
         } else {
             Ok(data.to_string())
@@ -105,7 +141,9 @@ impl ToCharsVia<f64> for Latitude {
 impl ToCharsVia<f64> for Longitude {
     type Error = Error;
     fn to_characters(data: &f64) -> Result<String, Error> {
-        if *data >= 180.0 || *data < -180.0 {
+        if !data.is_finite() {
+            Err(xsd::ser::Error::NotFinite(*data).into())
+        } else if *data >= 180.0 || *data < -180.0 {
             Err(Error::DecimalOutOfBounds(*data))
         } else {
             Ok(data.to_string())
@@ -113,151 +151,224 @@ impl ToCharsVia<f64> for Longitude {
     }
 }
 
+impl ToCharsVia<f32> for conv::Degrees {
+    type Error = Error;
+    fn to_characters(data: &f32) -> Result<String, Error> {
+        if !data.is_finite() {
+            Err(xsd::ser::Error::NotFinite(*data as f64).into())
+        } else if *data < 0. {
+            Err(Error::out_of_bounds(BoundCondition::EqualGreater, 0., *data))
+        } else if *data >= 360. {
+            Err(Error::out_of_bounds(BoundCondition::Lesser, 360., *data))
+        } else {
+            xsd::conv::Decimal::to_characters(data).map_err(Error::from)
+        }
+    }
+}
+
 impl SerializeVia<Bounds> for conv::Bounds {
-    fn serialize_via<W: io::Write>(data: &Bounds, sink: &mut EventWriter<W>, name: &OwnedName)
+    fn serialize_via<W: io::Write>(data: &Bounds, sink: &mut EventWriter<W>,
+                                   _options: &SerializerOptions, name: &OwnedName)
             -> Result<(), ser::Error> {
         let name = name.borrow();
-        try!(sink.write(
+        let minlat = Latitude::to_characters(&data.xmin)?;
+        let minlon = Longitude::to_characters(&data.ymin)?;
+        let maxlat = Latitude::to_characters(&data.xmax)?;
+        let maxlon = Longitude::to_characters(&data.ymax)?;
+        sink.write(
             XmlEvent::StartElement {
-                name: name,
+                name,
                 attributes: Cow::Owned(
-                // FIXME: turn to_string() into Latitude/Longitude conv
                     vec![Attribute { name: Name::local("minlat"),
-                                     value: &data.xmin.to_string() },
+                                     value: &minlat },
                          Attribute { name: Name::local("minlon"),
-                                     value: &data.ymin.to_string() },
+                                     value: &minlon },
                          Attribute { name: Name::local("maxlat"),
-                                     value: &data.xmax.to_string() },
+                                     value: &maxlat },
                          Attribute { name: Name::local("maxlon"),
-                                     value: &data.ymax.to_string() }]
+                                     value: &maxlon }]
                 ),
                 namespace: Cow::Owned(Namespace::empty())
             }
-        ));
-        try!(sink.write(XmlEvent::EndElement { name: Some(name) }));    
+        )?;
+        sink.write(XmlEvent::EndElement { name: Some(name) })?;    
         Ok(())
     }
 }
 
+impl SerializeVia<xml::Element> for conv::Extensions {
+    fn serialize_via<W: io::Write>(data: &xml::Element, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
+            -> Result<(), ser::Error> {
+        xml::conv::Element::serialize_via(data, sink, options, name)
+    }
+}
+
 impl SerializeDocument for Gpx {
-    fn serialize_root<W: io::Write>(&self, sink: &mut EventWriter<W>)
+    /// Fails with `validate::Invalid` holding the violations found by `Gpx::validate`
+    fn check_valid(&self) -> Result<(), ser::Error> {
+        let violations = self.validate();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ser::Error::from(Invalid(violations)))
+        }
+    }
+    fn serialize_root<W: io::Write>(&self, sink: &mut EventWriter<W>,
+                                    options: &SerializerOptions)
+            -> Result<(), ser::Error> {
+        conv::Gpx::serialize_via(self, sink, options, &OwnedName::local("gpx"))
+    }
+    fn serialize_root_declaring<W: io::Write>(&self, sink: &mut EventWriter<W>,
+                                              options: &SerializerOptions, namespace: &Namespace)
             -> Result<(), ser::Error> {
-        conv::Gpx::serialize_via(self, sink, &OwnedName::local("gpx"))
+        write_gpx(self, sink, options, &OwnedName::local("gpx"), namespace)
     }
 }
 
-/// Gpx needs custom serialization because it needs to carry the GPX namespace and version number
+/// Gpx needs custom serialization because it needs to carry the GPX namespace and version number.
+///
+/// Documents with `Version::V1_0` are written in GPX 1.0.
 impl SerializeVia<Gpx> for conv::Gpx {
-    fn serialize_via<W: io::Write>(data: &Gpx, sink: &mut EventWriter<W>, name: &OwnedName)
+    fn serialize_via<W: io::Write>(data: &Gpx, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
             -> Result<(), ser:: Error> {
-        let elemname = name.borrow();
-        let mut ns = Namespace::empty();
-        ns.put(NS_NO_PREFIX, GPX_NS);
-        let ns = ns;
-        try!(sink.write(
-            XmlEvent::StartElement {
-                name: elemname.clone(),
-                attributes: Cow::Owned(
-                    vec![Attribute { name: Name::local("version"),
-                                     value: Version::V1_1.to_attribute() },
-                         Attribute { name: Name::local("creator"),
-                                     value: &data.creator }]
-                ),
-                namespace: Cow::Owned(ns)
-            }
-        ));
-        if let Some(ref meta) = data.metadata {
-            try!(::gpx::conv::Metadata::serialize_via(meta, sink, &OwnedName::local("metadata")));
-        }
-        for item in &data.waypoints {
-            try!(::gpx::conv::Wpt::serialize_via(item, sink, &OwnedName::local("wpt")));
-        }
-        for item in &data.routes {
-            try!(::gpx::conv::Rte::serialize_via(item, sink, &OwnedName::local("rte")));
-        }
-        for item in &data.tracks {
-            try!(::gpx::conv::Trk::serialize_via(item, sink, &OwnedName::local("trk")));
-        }
-        if let Some(ref ext) = data.extensions {
-            try!(::gpx::conv::Extensions::serialize_via(ext, sink, &OwnedName::local("extensions")));
+        write_gpx(data, sink, options, name, &Namespace::empty())
+    }
+}
+
+/// Writes the `<gpx>` element in its version, with `namespace` declared besides the GPX one
+fn write_gpx<W: io::Write>(data: &Gpx, sink: &mut EventWriter<W>, options: &SerializerOptions,
+                           name: &OwnedName, namespace: &Namespace)
+        -> Result<(), ser::Error> {
+    if data.version == Version::V1_0 {
+        return ::gpx::ser_v1_0::write_gpx(data, sink, options, name, namespace);
+    }
+    let elemname = name.borrow();
+    let mut ns = namespace.clone();
+    ns.put(NS_NO_PREFIX, Version::V1_1.namespace());
+    let ns = ns;
+    sink.write(
+        XmlEvent::StartElement {
+            name: elemname,
+            attributes: Cow::Owned(
+                vec![Attribute { name: Name::local("version"),
+                                 value: Version::V1_1.to_attribute() },
+                     Attribute { name: Name::local("creator"),
+                                 value: &data.creator }]
+            ),
+            namespace: Cow::Owned(ns)
         }
-        try!(sink.write(XmlEvent::EndElement { name: Some(elemname) }));
-        Ok(())
+    )?;
+    if let Some(ref meta) = data.metadata {
+        ::gpx::conv::Metadata::serialize_via(meta, sink, options, &OwnedName::local("metadata"))?;
     }
+    for item in &data.waypoints {
+        ::gpx::conv::Wpt::serialize_via(item, sink, options, &OwnedName::local("wpt"))?;
+    }
+    for item in &data.routes {
+        ::gpx::conv::Rte::serialize_via(item, sink, options, &OwnedName::local("rte"))?;
+    }
+    for item in &data.tracks {
+        ::gpx::conv::Trk::serialize_via(item, sink, options, &OwnedName::local("trk"))?;
+    }
+    if let Some(ref ext) = data.extensions {
+        ::gpx::conv::Extensions::serialize_via(ext, sink, options, &OwnedName::local("extensions"))?;
+    }
+    sink.write(XmlEvent::EndElement { name: Some(elemname) })?;
+    Ok(())
 }
 
-impl SerializeVia<String> for conv::Email {
-    fn serialize_via<W: io::Write>(data: &String, sink: &mut EventWriter<W>, name: &OwnedName)
+impl SerializeVia<Email> for conv::Email {
+    fn serialize_via<W: io::Write>(data: &Email, sink: &mut EventWriter<W>,
+                                   _options: &SerializerOptions, name: &OwnedName)
            -> Result<(), ser::Error> {
-        let split = data.split("@").collect::<Vec<_>>();
-        if split.len() != 2 {
-            return Err(ser::Error::Value(Box::new(Error::InvalidEmail)));
-        }
-        let (id, domain) = (split[0], split[1]);
-        
         let elemname = name.borrow();
-        try!(sink.write(XmlEvent::StartElement {
-            name: elemname.clone(),
-            attributes: Cow::Owned(Vec::new()),
+        sink.write(XmlEvent::StartElement {
+            name: elemname,
+            attributes: Cow::Owned(
+                    vec![Attribute { name: Name::local("id"),
+                                     value: data.id() },
+                         Attribute { name: Name::local("domain"),
+                                     value: data.domain() }]),
             namespace: Cow::Owned(Namespace::empty()),
-        }));
-
-        try!(::xsd::conv::String::serialize_via(id, sink, &OwnedName::local("id")));
-        try!(::xsd::conv::String::serialize_via(domain, sink, &OwnedName::local("domain")));
-
-        try!(sink.write(XmlEvent::EndElement { name: Some(elemname) }));
+        })?;
+        sink.write(XmlEvent::EndElement { name: Some(elemname) })?;
         Ok(())
     }
 }
 
 
-impl Version {
-    fn to_attribute(&self) -> &'static str {
-        match self {
-            &Version::V1_0 => "1.0",
-            &Version::V1_1 => "1.1",
-        }
+/// Element of the GPX 1.0 namespace holding `value`
+fn v1_0_node(local_name: &str, value: String) -> xml::Node {
+    let name = OwnedName { local_name: local_name.into(),
+                           namespace: Some(Version::V1_0.namespace().into()),
+                           prefix: Some("gpx10".into()) };
+    xml::Node::Element(name, xml::Element { attributes: Vec::new(),
+                                            nodes: vec![xml::Node::Text(value)],
+                                            namespace: Namespace::empty() })
+}
+
+/// Returns extensions of the point, together with its GPX 1.0 data which GPX 1.1 has no place for
+fn extensions_v1_1<'a>(data: &'a Waypoint) -> Result<Option<Cow<'a, xml::Element>>, ser::Error> {
+    let mut nodes = Vec::new();
+    if let Some(ref course) = data.course {
+        nodes.push(v1_0_node("course", conv::Degrees::to_characters(course)?));
     }
+    if let Some(ref speed) = data.speed {
+        nodes.push(v1_0_node("speed", xsd::conv::Decimal::to_characters(speed)?));
+    }
+    if nodes.is_empty() {
+        return Ok(data.extensions.as_ref().map(Cow::Borrowed));
+    }
+    let mut extensions = data.extensions.clone().unwrap_or_default();
+    nodes.extend(extensions.nodes);
+    extensions.nodes = nodes;
+    Ok(Some(Cow::Owned(extensions)))
 }
 
 /// Custom serialization beeded because of the location field
 impl SerializeVia<Waypoint> for conv::Wpt {
-    fn serialize_via<W: io::Write>(data: &Waypoint, sink: &mut EventWriter<W>, name: &OwnedName)
+    fn serialize_via<W: io::Write>(data: &Waypoint, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
             -> Result<(), ser::Error> {
         let elemname = name.borrow();
-        let lat = try!(Latitude::to_characters(&data.location.latitude));
-        let lon = try!(Longitude::to_characters(&data.location.longitude));
-        try!(sink.write(XmlEvent::StartElement {
-            name: elemname.clone(),
+        let lat = Latitude::to_characters(&data.location.latitude)?;
+        let lon = Longitude::to_characters(&data.location.longitude)?;
+        sink.write(XmlEvent::StartElement {
+            name: elemname,
             attributes: Cow::Owned(
                     vec![Attribute { name: Name::local("lat"),
                                      value: &lat },
                          Attribute { name: Name::local("lon"),
                                      value: &lon }]),
             namespace: Cow::Owned(Namespace::empty()),
-        }));
-        set_optional!(sink, data.location.elevation, "ele", xsd::conv::Decimal);
-        set_optional!(sink, data.time, "time", xsd::conv::DateTime);
-        set_optional!(sink, data.mag_variation, "magvar", xsd::conv::Decimal); // FIXME: Degrees
-        set_optional!(sink, data.geoid_height, "geoidheight", xsd::conv::Decimal);
-        set_optional!(sink, data.name, "name", xsd::conv::String);
-        set_optional!(sink, data.comment, "cmt", xsd::conv::String);
-        set_optional!(sink, data.description, "desc", xsd::conv::String);
-        set_optional!(sink, data.source, "src", xsd::conv::String);
+        })?;
+        set_optional!(sink, options, data.location.elevation, "ele", xsd::conv::Decimal);
+        set_optional!(sink, options, data.time, "time", xsd::conv::DateTime);
+        set_optional!(sink, options, data.mag_variation, "magvar", conv::Degrees);
+        set_optional!(sink, options, data.geoid_height, "geoidheight", xsd::conv::Decimal);
+        set_optional!(sink, options, data.name, "name", ser::Text);
+        set_optional!(sink, options, data.comment, "cmt", ser::Text);
+        set_optional!(sink, options, data.description, "desc", ser::Text);
+        set_optional!(sink, options, data.source, "src", ser::Text);
         for item in &data.links {
-            try!(conv::Link::serialize_via(item, sink, &OwnedName::local("link")));
+            conv::Link::serialize_via(item, sink, options, &OwnedName::local("link"))?;
+        }
+        set_optional!(sink, options, data.symbol, "sym", ser::Text);
+        set_optional!(sink, options, data.type_, "type", ser::Text);
+        set_optional!(sink, options, data.fix, "fix", conv::Fix);
+        set_optional!(sink, options, data.satellites, "sat", xsd::conv::NonNegativeInteger);
+        set_optional!(sink, options, data.hdop, "hdop", xsd::conv::Decimal);
+        set_optional!(sink, options, data.vdop, "vdop", xsd::conv::Decimal);
+        set_optional!(sink, options, data.pdop, "pdop", xsd::conv::Decimal);
+        set_optional!(sink, options, data.dgps_age, "ageofdgpsdata", xsd::conv::Decimal);
+        set_optional!(sink, options, data.dgps_id, "dgpsid", conv::DgpsStation);
+        if let Some(extensions) = extensions_v1_1(data)? {
+            conv::Extensions::serialize_via(&*extensions, sink, options,
+                                            &OwnedName::local("extensions"))?;
         }
-        set_optional!(sink, data.symbol, "sym", xsd::conv::String);
-        set_optional!(sink, data.type_, "type", xsd::conv::String);
-        set_optional!(sink, data.fix, "fix", conv::Fix);
-        set_optional!(sink, data.satellites, "sat", xsd::conv::NonNegativeInteger);
-        set_optional!(sink, data.hdop, "hdop", xsd::conv::Decimal);
-        set_optional!(sink, data.vdop, "vdop", xsd::conv::Decimal);
-        set_optional!(sink, data.pdop, "pdop", xsd::conv::Decimal);
-        set_optional!(sink, data.dgps_age, "ageofdgpsdata", xsd::conv::Decimal);
-        set_optional!(sink, data.dgps_id, "dgpsid", conv::DgpsStation);
-        set_optional!(sink, data.extensions, "extensions", conv::Extensions);
-        try!(sink.write(XmlEvent::EndElement { name: Some(elemname) }));
+        sink.write(XmlEvent::EndElement { name: Some(elemname) })?;
         Ok(())
     }
 }
@@ -265,12 +376,12 @@ impl SerializeVia<Waypoint> for conv::Wpt {
 impl ToCharsVia<Fix> for conv::Fix {
     type Error = Error;
     fn to_characters(data: &Fix) -> Result<String, Self::Error> {
-        Ok(match data {
-            &Fix::None => "none",
-            &Fix::_2D => "2d",
-            &Fix::_3D => "3d",
-            &Fix::DGPS => "dgps",
-            &Fix::PPS => "pps"
+        Ok(match *data {
+            Fix::None => "none",
+            Fix::_2D => "2d",
+            Fix::_3D => "3d",
+            Fix::DGPS => "dgps",
+            Fix::PPS => "pps"
         }.into())
     }
 }
@@ -278,18 +389,18 @@ impl ToCharsVia<Fix> for conv::Fix {
 
 impl ToCharsVia<u16> for conv::DgpsStation {
     type Error = Error;
-    #[allow(unused_comparisons)]
+    #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
     fn to_characters(data: &u16) -> Result<String, Self::Error> {
         if 0 > *data {
             Err(Error::OutOfBounds(BoundCondition::EqualGreater,
                                    Box::new(OutOfBoundsValue { limit: 0,
                                                                value: *data })
-                                        as Box<OutOfBoundsTrait>))
-        } else if *data > 1024 {
+                                        as Box<dyn OutOfBoundsTrait>))
+        } else if *data > 1023 {
             Err(Error::OutOfBounds(BoundCondition::EqualLesser,
-                                   Box::new(OutOfBoundsValue { limit: 1024,
+                                   Box::new(OutOfBoundsValue { limit: 1023,
                                                                value: *data })
-                                        as Box<OutOfBoundsTrait>))
+                                        as Box<dyn OutOfBoundsTrait>))
 
         } else {
             <::xsd::conv::Integer as ToCharsVia<u16>>::to_characters(data).map_err(Error::from)
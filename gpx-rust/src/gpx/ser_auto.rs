@@ -17,10 +17,9 @@ use std::borrow::Cow;
 
 use self::_xml::attribute::{ Attribute, OwnedAttribute };
 use self::_xml::name::OwnedName;
-use self::_xml::namespace::Namespace;
 use self::_xml::writer::{ XmlEvent, EventWriter };
 
-use ser::{ Error, SerializeVia, ToCharsVia };
+use ser::{ Error, SerializeVia, SerializerOptions, ToCharsVia };
 use gpx::*;
 
 include!(concat!(env!("OUT_DIR"), "/gpx_ser_auto.rs"));
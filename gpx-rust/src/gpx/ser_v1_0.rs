@@ -0,0 +1,282 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Serialization impls writing GPX 1.0
+//!
+//! Data which GPX 1.0 has no place for is written as GPX 1.1 elements
+//! where GPX 1.0 allows extensions. See the `gpx` module for the details.
+
+extern crate xml as _xml;
+
+use std::io;
+use std::borrow::Cow;
+use self::_xml::name::{ Name, OwnedName };
+use self::_xml::namespace::{ Namespace, NS_NO_PREFIX };
+use self::_xml::attribute::Attribute;
+use self::_xml::writer::{ XmlEvent, EventWriter };
+
+use xml;
+use xsd;
+use gpx::{ Gpx, Version, Metadata, Waypoint, Route, Track, TrackSegment, Link, Email };
+use gpx::conv;
+use gpx::conv::{ Latitude, Longitude };
+use ser;
+use ser::{ SerializeVia, SerializerOptions, ToCharsVia };
+
+
+/// Name of a GPX 1.1 element placed inside a GPX 1.0 document
+fn v1_1_name(local_name: &str) -> OwnedName {
+    OwnedName { local_name: local_name.into(),
+                namespace: Some(Version::V1_1.namespace().into()),
+                prefix: None }
+}
+
+/// Splits links into the one which fits `<url>` and `<urlname>`, and the remaining ones
+fn split_links(links: &[Link]) -> (Option<&Link>, &[Link]) {
+    match links.first() {
+        Some(link) if link.type_.is_none() => (Some(link), &links[1..]),
+        _ => (None, links),
+    }
+}
+
+fn write_url<W: io::Write>(sink: &mut EventWriter<W>, options: &SerializerOptions,
+                           link: Option<&Link>)
+        -> Result<(), ser::Error> {
+    if let Some(link) = link {
+        xsd::conv::Uri::serialize_via(&link.href, sink, options, &OwnedName::local("url"))?;
+        set_optional!(sink, options, link.text, "urlname", ser::Text);
+    }
+    Ok(())
+}
+
+/// Writes links which didn't fit `<url>` as GPX 1.1 `<link>` elements
+fn write_links<W: io::Write>(sink: &mut EventWriter<W>, options: &SerializerOptions,
+                             links: &[Link])
+        -> Result<(), ser::Error> {
+    for link in links {
+        conv::Link::serialize_via(link, sink, options, &v1_1_name("link"))?;
+    }
+    Ok(())
+}
+
+/// Writes elements and markup from `<extensions>` directly inside the parent element, the way of GPX 1.0.
+///
+/// Attributes and text of `<extensions>` itself have no place there and are dropped.
+fn write_flat_extensions<W: io::Write>(sink: &mut EventWriter<W>, options: &SerializerOptions,
+                                       extensions: &Option<xml::Element>)
+        -> Result<(), ser::Error> {
+    if let Some(ref extensions) = *extensions {
+        for node in &extensions.nodes {
+            match *node {
+                xml::Node::Text(_) | xml::Node::CData(_) => {}
+                ref node => node.serialize(sink, options)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Tells if metadata holds anything GPX 1.0 can't express
+fn needs_v1_1(metadata: &Metadata) -> bool {
+    let (_, other_links) = split_links(&metadata.links);
+    metadata.copyright.is_some()
+        || metadata.extensions.is_some()
+        || !other_links.is_empty()
+        || metadata.author.as_ref().map(|author| author.link.is_some()).unwrap_or(false)
+}
+
+/// GPX 1.0 writes the address in one piece
+impl ToCharsVia<Email> for conv::v1_0::Email {
+    type Error = xsd::ser::Error;
+    fn to_characters(data: &Email) -> Result<String, xsd::ser::Error> {
+        Ok(data.to_string())
+    }
+}
+
+/// Writes the GPX 1.0 `<gpx>` element, with `namespace` declared besides the GPX one
+pub fn write_gpx<W: io::Write>(data: &Gpx, sink: &mut EventWriter<W>,
+                               options: &SerializerOptions, name: &OwnedName,
+                               namespace: &Namespace)
+        -> Result<(), ser::Error> {
+    let elemname = name.borrow();
+    let mut ns = namespace.clone();
+    ns.put(NS_NO_PREFIX, Version::V1_0.namespace());
+    sink.write(
+        XmlEvent::StartElement {
+            name: elemname,
+            attributes: Cow::Owned(
+                vec![Attribute { name: Name::local("version"),
+                                 value: Version::V1_0.to_attribute() },
+                     Attribute { name: Name::local("creator"),
+                                 value: &data.creator }]
+            ),
+            namespace: Cow::Owned(ns)
+        }
+    )?;
+    if let Some(ref meta) = data.metadata {
+        set_optional!(sink, options, meta.name, "name", ser::Text);
+        set_optional!(sink, options, meta.description, "desc", ser::Text);
+        if let Some(ref author) = meta.author {
+            set_optional!(sink, options, author.name, "author", ser::Text);
+            set_optional!(sink, options, author.email, "email", conv::v1_0::Email);
+        }
+        write_url(sink, options, split_links(&meta.links).0)?;
+        set_optional!(sink, options, meta.time, "time", xsd::conv::DateTime);
+        set_optional!(sink, options, meta.keywords, "keywords", ser::Text);
+        set_optional!(sink, options, meta.bounds, "bounds", conv::Bounds);
+    }
+    for item in &data.waypoints {
+        conv::v1_0::Wpt::serialize_via(item, sink, options, &OwnedName::local("wpt"))?;
+    }
+    for item in &data.routes {
+        conv::v1_0::Rte::serialize_via(item, sink, options, &OwnedName::local("rte"))?;
+    }
+    for item in &data.tracks {
+        conv::v1_0::Trk::serialize_via(item, sink, options, &OwnedName::local("trk"))?;
+    }
+    if let Some(ref meta) = data.metadata {
+        // the parser prefers it over the GPX 1.0 elements
+        if needs_v1_1(meta) {
+            conv::Metadata::serialize_via(meta, sink, options, &v1_1_name("metadata"))?;
+        }
+    }
+    write_flat_extensions(sink, options, &data.extensions)?;
+    sink.write(XmlEvent::EndElement { name: Some(elemname) })?;
+    Ok(())
+}
+
+/// GPX 1.0 points have `<course>`, `<speed>` and `<url>`, but no `<link>`
+impl SerializeVia<Waypoint> for conv::v1_0::Wpt {
+    fn serialize_via<W: io::Write>(data: &Waypoint, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
+            -> Result<(), ser::Error> {
+        let elemname = name.borrow();
+        let lat = Latitude::to_characters(&data.location.latitude)?;
+        let lon = Longitude::to_characters(&data.location.longitude)?;
+        sink.write(XmlEvent::StartElement {
+            name: elemname,
+            attributes: Cow::Owned(
+                    vec![Attribute { name: Name::local("lat"),
+                                     value: &lat },
+                         Attribute { name: Name::local("lon"),
+                                     value: &lon }]),
+            namespace: Cow::Owned(Namespace::empty()),
+        })?;
+        let (url, other_links) = split_links(&data.links);
+        set_optional!(sink, options, data.location.elevation, "ele", xsd::conv::Decimal);
+        set_optional!(sink, options, data.time, "time", xsd::conv::DateTime);
+        set_optional!(sink, options, data.course, "course", conv::Degrees);
+        set_optional!(sink, options, data.speed, "speed", xsd::conv::Decimal);
+        set_optional!(sink, options, data.mag_variation, "magvar", conv::Degrees);
+        set_optional!(sink, options, data.geoid_height, "geoidheight", xsd::conv::Decimal);
+        set_optional!(sink, options, data.name, "name", ser::Text);
+        set_optional!(sink, options, data.comment, "cmt", ser::Text);
+        set_optional!(sink, options, data.description, "desc", ser::Text);
+        set_optional!(sink, options, data.source, "src", ser::Text);
+        write_url(sink, options, url)?;
+        set_optional!(sink, options, data.symbol, "sym", ser::Text);
+        set_optional!(sink, options, data.type_, "type", ser::Text);
+        set_optional!(sink, options, data.fix, "fix", conv::Fix);
+        set_optional!(sink, options, data.satellites, "sat", xsd::conv::NonNegativeInteger);
+        set_optional!(sink, options, data.hdop, "hdop", xsd::conv::Decimal);
+        set_optional!(sink, options, data.vdop, "vdop", xsd::conv::Decimal);
+        set_optional!(sink, options, data.pdop, "pdop", xsd::conv::Decimal);
+        set_optional!(sink, options, data.dgps_age, "ageofdgpsdata", xsd::conv::Decimal);
+        set_optional!(sink, options, data.dgps_id, "dgpsid", conv::DgpsStation);
+        write_flat_extensions(sink, options, &data.extensions)?;
+        write_links(sink, options, other_links)?;
+        sink.write(XmlEvent::EndElement { name: Some(elemname) })?;
+        Ok(())
+    }
+}
+
+/// GPX 1.0 routes have `<url>`, but no `<link>` and `<type>`
+impl SerializeVia<Route> for conv::v1_0::Rte {
+    fn serialize_via<W: io::Write>(data: &Route, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
+            -> Result<(), ser::Error> {
+        let elemname = name.borrow();
+        sink.write(XmlEvent::StartElement {
+            name: elemname,
+            attributes: Cow::Owned(Vec::new()),
+            namespace: Cow::Owned(Namespace::empty()),
+        })?;
+        let (url, other_links) = split_links(&data.links);
+        set_optional!(sink, options, data.name, "name", ser::Text);
+        set_optional!(sink, options, data.comment, "cmt", ser::Text);
+        set_optional!(sink, options, data.description, "desc", ser::Text);
+        set_optional!(sink, options, data.source, "src", ser::Text);
+        write_url(sink, options, url)?;
+        set_optional!(sink, options, data.number, "number", xsd::conv::NonNegativeInteger);
+        write_flat_extensions(sink, options, &data.extensions)?;
+        write_links(sink, options, other_links)?;
+        if let Some(ref type_) = data.type_ {
+            ser::Text::serialize_via(type_, sink, options, &v1_1_name("type"))?;
+        }
+        for item in &data.waypoints {
+            conv::v1_0::Wpt::serialize_via(item, sink, options, &OwnedName::local("rtept"))?;
+        }
+        sink.write(XmlEvent::EndElement { name: Some(elemname) })?;
+        Ok(())
+    }
+}
+
+/// GPX 1.0 tracks have `<url>`, but no `<link>` and `<type>`
+impl SerializeVia<Track> for conv::v1_0::Trk {
+    fn serialize_via<W: io::Write>(data: &Track, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
+            -> Result<(), ser::Error> {
+        let elemname = name.borrow();
+        sink.write(XmlEvent::StartElement {
+            name: elemname,
+            attributes: Cow::Owned(Vec::new()),
+            namespace: Cow::Owned(Namespace::empty()),
+        })?;
+        let (url, other_links) = split_links(&data.links);
+        set_optional!(sink, options, data.name, "name", ser::Text);
+        set_optional!(sink, options, data.comment, "cmt", ser::Text);
+        set_optional!(sink, options, data.description, "desc", ser::Text);
+        set_optional!(sink, options, data.source, "src", ser::Text);
+        write_url(sink, options, url)?;
+        set_optional!(sink, options, data.number, "number", xsd::conv::NonNegativeInteger);
+        write_flat_extensions(sink, options, &data.extensions)?;
+        write_links(sink, options, other_links)?;
+        if let Some(ref type_) = data.type_ {
+            ser::Text::serialize_via(type_, sink, options, &v1_1_name("type"))?;
+        }
+        for item in &data.segments {
+            conv::v1_0::Trkseg::serialize_via(item, sink, options, &OwnedName::local("trkseg"))?;
+        }
+        sink.write(XmlEvent::EndElement { name: Some(elemname) })?;
+        Ok(())
+    }
+}
+
+/// GPX 1.0 segments hold nothing but points, so extensions are dropped
+impl SerializeVia<TrackSegment> for conv::v1_0::Trkseg {
+    fn serialize_via<W: io::Write>(data: &TrackSegment, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
+            -> Result<(), ser::Error> {
+        let elemname = name.borrow();
+        sink.write(XmlEvent::StartElement {
+            name: elemname,
+            attributes: Cow::Owned(Vec::new()),
+            namespace: Cow::Owned(Namespace::empty()),
+        })?;
+        for item in &data.waypoints {
+            conv::v1_0::Wpt::serialize_via(item, sink, options, &OwnedName::local("trkpt"))?;
+        }
+        sink.write(XmlEvent::EndElement { name: Some(elemname) })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Checks of GPX data against the constraints of the schema
+//!
+//! ```ignore
+//! for violation in gpx.validate() {
+//!     // tracks[2].segments[0].waypoints[15].mag_variation: ...
+//!     println!("{}", violation);
+//! }
+//! ```
+//!
+//! Values are checked with the same conversions which serialize them,
+//! so valid data is also written without errors. Extensions are not checked,
+//! nor are emails, URIs and years, whose types refuse invalid values on construction.
+
+use std::fmt;
+use std::error::Error as ErrorTrait;
+
+use xsd;
+use gpx::{ Gpx, Metadata, Bounds, Waypoint, Route, Track };
+use gpx::conv;
+use gpx::conv::{ Latitude, Longitude };
+use gpx::ser::Error;
+use ser::{ FormatError, ToCharsVia };
+
+
+/// Value breaking a constraint of the schema
+#[derive(Debug)]
+pub struct Violation {
+    /// Place of the value in the data, like `tracks[2].segments[0].waypoints[15].mag_variation`
+    pub path: String,
+    pub error: Error,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}: {}", self.path, self.error)
+    }
+}
+
+impl ErrorTrait for Violation {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Data refused by `SerializeDocument::serialize_valid`
+#[derive(Debug)]
+pub struct Invalid(pub Vec<Violation>);
+
+impl FormatError for Invalid {}
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{} invalid values", self.0.len())?;
+        for violation in &self.0 {
+            write!(fmt, "\n{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl ErrorTrait for Invalid {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        self.0.first().map(|violation| violation as &(dyn ErrorTrait + 'static))
+    }
+}
+
+impl Gpx {
+    /// Returns all values breaking the constraints of the schema
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut checker = Checker { violations: Vec::new() };
+        checker.gpx(self);
+        checker.violations
+    }
+}
+
+impl Waypoint {
+    /// Returns all values breaking the constraints of the schema, with paths starting at the point
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut checker = Checker { violations: Vec::new() };
+        checker.waypoint("", self);
+        checker.violations
+    }
+}
+
+impl Route {
+    /// Returns all values breaking the constraints of the schema, with paths starting at the route
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut checker = Checker { violations: Vec::new() };
+        checker.route("", self);
+        checker.violations
+    }
+}
+
+impl Track {
+    /// Returns all values breaking the constraints of the schema, with paths starting at the track
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut checker = Checker { violations: Vec::new() };
+        checker.track("", self);
+        checker.violations
+    }
+}
+
+/// Collects violations while walking the data
+struct Checker {
+    violations: Vec<Violation>,
+}
+
+/// Path of `field` inside the value at `path`, which is empty for the value being validated
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        return field.into();
+    }
+    format!("{}.{}", path, field)
+}
+
+impl Checker {
+    fn report(&mut self, path: String, error: Error) {
+        self.violations.push(Violation { path, error });
+    }
+
+    /// Checks `value` by writing it through the converter `C`
+    fn value<T, C: ToCharsVia<T>>(&mut self, path: String, value: &T) where C::Error: Into<Error> {
+        if let Err(e) = C::to_characters(value) {
+            self.report(path, e.into());
+        }
+    }
+
+    fn gpx(&mut self, data: &Gpx) {
+        if let Some(ref metadata) = data.metadata {
+            self.metadata("metadata", metadata);
+        }
+        for (i, waypoint) in data.waypoints.iter().enumerate() {
+            self.waypoint(&format!("waypoints[{}]", i), waypoint);
+        }
+        for (i, route) in data.routes.iter().enumerate() {
+            self.route(&format!("routes[{}]", i), route);
+        }
+        for (i, track) in data.tracks.iter().enumerate() {
+            self.track(&format!("tracks[{}]", i), track);
+        }
+    }
+
+    fn metadata(&mut self, path: &str, data: &Metadata) {
+        if let Some(ref bounds) = data.bounds {
+            self.bounds(&join(path, "bounds"), bounds);
+        }
+    }
+
+    fn bounds(&mut self, path: &str, data: &Bounds) {
+        self.value::<_, Latitude>(join(path, "xmin"), &data.xmin);
+        self.value::<_, Longitude>(join(path, "ymin"), &data.ymin);
+        self.value::<_, Latitude>(join(path, "xmax"), &data.xmax);
+        self.value::<_, Longitude>(join(path, "ymax"), &data.ymax);
+    }
+
+    fn waypoint(&mut self, path: &str, data: &Waypoint) {
+        self.value::<_, Latitude>(join(path, "location.latitude"), &data.location.latitude);
+        self.value::<_, Longitude>(join(path, "location.longitude"), &data.location.longitude);
+        let decimals = [("location.elevation", data.location.elevation),
+                        ("speed", data.speed),
+                        ("geoid_height", data.geoid_height),
+                        ("hdop", data.hdop),
+                        ("vdop", data.vdop),
+                        ("pdop", data.pdop),
+                        ("dgps_age", data.dgps_age)];
+        for &(field, value) in &decimals {
+            if let Some(ref value) = value {
+                self.value::<_, xsd::conv::Decimal>(join(path, field), value);
+            }
+        }
+        if let Some(ref course) = data.course {
+            self.value::<_, conv::Degrees>(join(path, "course"), course);
+        }
+        if let Some(ref mag_variation) = data.mag_variation {
+            self.value::<_, conv::Degrees>(join(path, "mag_variation"), mag_variation);
+        }
+        if let Some(ref dgps_id) = data.dgps_id {
+            self.value::<_, conv::DgpsStation>(join(path, "dgps_id"), dgps_id);
+        }
+    }
+
+    fn route(&mut self, path: &str, data: &Route) {
+        for (i, waypoint) in data.waypoints.iter().enumerate() {
+            self.waypoint(&join(path, &format!("waypoints[{}]", i)), waypoint);
+        }
+    }
+
+    fn track(&mut self, path: &str, data: &Track) {
+        for (i, segment) in data.segments.iter().enumerate() {
+            for (j, waypoint) in segment.waypoints.iter().enumerate() {
+                self.waypoint(&join(path, &format!("segments[{}].waypoints[{}]", i, j)), waypoint);
+            }
+        }
+    }
+}
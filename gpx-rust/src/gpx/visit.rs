@@ -0,0 +1,236 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Traversal of all points in GPX data.
+//!
+//! Points come with their `Place`: whether they are waypoints, route points or track points,
+//! and the indices leading to them. Iterators cover the simple cases:
+//!
+//! ```ignore
+//! for (place, point) in gpx.all_points() {
+//!     println!("{}: {}", place, point.location.latitude);
+//! }
+//! for (_, point) in gpx.all_points_mut() {
+//!     point.time = None;
+//! }
+//! ```
+//!
+//! `Visit` and `VisitMut` also announce routes, tracks and segments before their points:
+//!
+//! ```ignore
+//! struct TrackSizes(Vec<usize>);
+//!
+//! impl Visit for TrackSizes {
+//!     fn visit_track(&mut self, _track: &Track, _index: usize) {
+//!         self.0.push(0);
+//!     }
+//!     fn visit_waypoint(&mut self, _point: &Waypoint, place: Place) {
+//!         if let Place::TrackPoint { .. } = place {
+//!             *self.0.last_mut().unwrap() += 1;
+//!         }
+//!     }
+//! }
+//!
+//! let mut sizes = TrackSizes(Vec::new());
+//! gpx.visit(&mut sizes);
+//! ```
+//!
+//! Points are visited in document order: waypoints, then routes, then tracks.
+
+use std::fmt;
+use std::iter::Enumerate;
+use std::slice;
+
+use gpx::{ Gpx, Waypoint, Route, Track, TrackSegment };
+
+
+/// Where a point is in `Gpx`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Place {
+    /// `<wpt>`, `Gpx::waypoints[index]`
+    Waypoint { index: usize },
+    /// `<rtept>`, `Gpx::routes[route].waypoints[index]`
+    RoutePoint { route: usize, index: usize },
+    /// `<trkpt>`, `Gpx::tracks[track].segments[segment].waypoints[index]`
+    TrackPoint { track: usize, segment: usize, index: usize },
+}
+
+impl fmt::Display for Place {
+    /// Writes the path to the point, as in `validate::Violation`
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Place::Waypoint { index } => write!(fmt, "waypoints[{}]", index),
+            Place::RoutePoint { route, index } => {
+                write!(fmt, "routes[{}].waypoints[{}]", route, index)
+            }
+            Place::TrackPoint { track, segment, index } => {
+                write!(fmt, "tracks[{}].segments[{}].waypoints[{}]", track, segment, index)
+            }
+        }
+    }
+}
+
+/// Receives the contents of `Gpx` from `Gpx::visit`. All methods do nothing by default.
+pub trait Visit {
+    fn visit_waypoint(&mut self, _point: &Waypoint, _place: Place) {}
+    /// Called before the points of the route
+    fn visit_route(&mut self, _route: &Route, _index: usize) {}
+    /// Called before the segments of the track
+    fn visit_track(&mut self, _track: &Track, _index: usize) {}
+    /// Called before the points of the segment
+    fn visit_segment(&mut self, _segment: &TrackSegment, _track: usize, _index: usize) {}
+}
+
+/// Like `Visit`, receiving the contents for modification from `Gpx::visit_mut`.
+///
+/// Points added to routes and segments in their callbacks get visited too.
+pub trait VisitMut {
+    fn visit_waypoint(&mut self, _point: &mut Waypoint, _place: Place) {}
+    fn visit_route(&mut self, _route: &mut Route, _index: usize) {}
+    fn visit_track(&mut self, _track: &mut Track, _index: usize) {}
+    fn visit_segment(&mut self, _segment: &mut TrackSegment, _track: usize, _index: usize) {}
+}
+
+impl Gpx {
+    /// Passes all waypoints, routes, tracks and segments to `visitor`
+    pub fn visit<V: Visit>(&self, visitor: &mut V) {
+        for (index, point) in self.waypoints.iter().enumerate() {
+            visitor.visit_waypoint(point, Place::Waypoint { index });
+        }
+        for (route, data) in self.routes.iter().enumerate() {
+            visitor.visit_route(data, route);
+            for (index, point) in data.waypoints.iter().enumerate() {
+                visitor.visit_waypoint(point, Place::RoutePoint { route, index });
+            }
+        }
+        for (track, data) in self.tracks.iter().enumerate() {
+            visitor.visit_track(data, track);
+            for (segment, data) in data.segments.iter().enumerate() {
+                visitor.visit_segment(data, track, segment);
+                for (index, point) in data.waypoints.iter().enumerate() {
+                    visitor.visit_waypoint(point, Place::TrackPoint { track,
+                                                                      segment,
+                                                                      index });
+                }
+            }
+        }
+    }
+
+    /// Passes all waypoints, routes, tracks and segments to `visitor` for modification
+    pub fn visit_mut<V: VisitMut>(&mut self, visitor: &mut V) {
+        for (index, point) in self.waypoints.iter_mut().enumerate() {
+            visitor.visit_waypoint(point, Place::Waypoint { index });
+        }
+        for (route, data) in self.routes.iter_mut().enumerate() {
+            visitor.visit_route(data, route);
+            for (index, point) in data.waypoints.iter_mut().enumerate() {
+                visitor.visit_waypoint(point, Place::RoutePoint { route, index });
+            }
+        }
+        for (track, data) in self.tracks.iter_mut().enumerate() {
+            visitor.visit_track(data, track);
+            for (segment, data) in data.segments.iter_mut().enumerate() {
+                visitor.visit_segment(data, track, segment);
+                for (index, point) in data.waypoints.iter_mut().enumerate() {
+                    visitor.visit_waypoint(point, Place::TrackPoint { track,
+                                                                      segment,
+                                                                      index });
+                }
+            }
+        }
+    }
+
+    /// Iterates over waypoints, route points and track points together with their places
+    pub fn all_points(&self) -> AllPoints<'_> {
+        AllPoints { waypoints: self.waypoints.iter().enumerate(),
+                    routes: self.routes.iter().enumerate(),
+                    route: None,
+                    tracks: self.tracks.iter().enumerate(),
+                    track: None,
+                    segment: None }
+    }
+
+    /// Like `all_points`, giving the points for modification
+    pub fn all_points_mut(&mut self) -> AllPointsMut<'_> {
+        AllPointsMut { waypoints: self.waypoints.iter_mut().enumerate(),
+                       routes: self.routes.iter_mut().enumerate(),
+                       route: None,
+                       tracks: self.tracks.iter_mut().enumerate(),
+                       track: None,
+                       segment: None }
+    }
+}
+
+/// Defines an iterator over all points, borrowing them with `$iter` from slices
+macro_rules! points_iterator(
+    ($(#[$attr:meta])* $name:ident, $iter:ident, $method:ident, $($ref_:tt)*) => {
+        $(#[$attr])*
+        pub struct $name<'a> {
+            waypoints: Enumerate<slice::$iter<'a, Waypoint>>,
+            routes: Enumerate<slice::$iter<'a, Route>>,
+            /// Points of the current route
+            route: Option<(usize, Enumerate<slice::$iter<'a, Waypoint>>)>,
+            tracks: Enumerate<slice::$iter<'a, Track>>,
+            /// Segments of the current track
+            track: Option<(usize, Enumerate<slice::$iter<'a, TrackSegment>>)>,
+            /// Points of the current segment
+            segment: Option<(usize, usize, Enumerate<slice::$iter<'a, Waypoint>>)>,
+        }
+
+        impl<'a> Iterator for $name<'a> {
+            type Item = (Place, $($ref_)* Waypoint);
+
+            fn next(&mut self) -> Option<(Place, $($ref_)* Waypoint)> {
+                if let Some((index, point)) = self.waypoints.next() {
+                    return Some((Place::Waypoint { index }, point));
+                }
+                loop {
+                    if let Some((route, ref mut points)) = self.route {
+                        if let Some((index, point)) = points.next() {
+                            return Some((Place::RoutePoint { route, index }, point));
+                        }
+                    }
+                    match self.routes.next() {
+                        Some((route, data)) => self.route = Some((route, data.waypoints.$method().enumerate())),
+                        None => break,
+                    }
+                }
+                loop {
+                    if let Some((track, segment, ref mut points)) = self.segment {
+                        if let Some((index, point)) = points.next() {
+                            return Some((Place::TrackPoint { track, segment, index },
+                                         point));
+                        }
+                    }
+                    if let Some((track, ref mut segments)) = self.track {
+                        if let Some((segment, data)) = segments.next() {
+                            self.segment = Some((track, segment, data.waypoints.$method().enumerate()));
+                            continue;
+                        }
+                    }
+                    match self.tracks.next() {
+                        Some((track, data)) => self.track = Some((track, data.segments.$method().enumerate())),
+                        None => return None,
+                    }
+                }
+            }
+        }
+    }
+);
+
+points_iterator!(
+    /// Iterator over all points of `Gpx`, see `Gpx::all_points`
+    AllPoints, Iter, iter, &'a);
+points_iterator!(
+    /// Iterator over all points of `Gpx` for modification, see `Gpx::all_points_mut`
+    AllPointsMut, IterMut, iter_mut, &'a mut);
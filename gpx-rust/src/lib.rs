@@ -11,8 +11,9 @@
  * You may elect to redistribute this code under either of these licenses.     
  */
 
-#[macro_use]
-extern crate macro_attr;
+// Errors carry their position, element path and failed text, and are made once per failed parse
+#![allow(clippy::result_large_err)]
+
 #[macro_use]
 extern crate gpx_debug;
 
@@ -21,3 +22,5 @@ pub mod xsd;
 pub mod gpx;
 pub mod par;
 pub mod ser;
+#[cfg(feature = "async")]
+pub mod async_io;
@@ -14,18 +14,21 @@
 //! Contains functionaity useful for implementing XML parsers
 extern crate xml as _xml;
 
-use std::io;
-use std::io::Read;
 use std::fmt;
+use std::mem;
+use std::cmp;
 use std::error::Error as ErrorTrait;
 
-use self::_xml::common::{ Position, TextPosition };
-use self::_xml::reader::{ EventReader, XmlEvent };
+use self::_xml::common::TextPosition;
+use self::_xml::reader::XmlEvent;
 use self::_xml::name::OwnedName;
 use self::_xml::attribute::OwnedAttribute;
+use self::_xml::namespace::{ Namespace, NS_XML_PREFIX, NS_XMLNS_PREFIX, NS_EMPTY_URI };
 
 use xml;
+use xml::EventSource;
 use gpx::par::Error;
+use gpx::ext::Registry;
 
 /// Describes the position in the input stream for some data.
 ///
@@ -34,25 +37,84 @@ use gpx::par::Error;
 pub struct Positioned<Data> {
     pub data: Data,
     pub position: TextPosition,
+    /// Element where the data was found, like `/gpx/trk[1]/trkseg[3]/trkpt[1207]/time`
+    pub path: Option<String>,
+    /// Character data or attribute value which could not be parsed
+    pub text: Option<String>,
 }
 
 impl<Data> Positioned<Data> {
     pub fn with_position(data: Data, position: TextPosition) -> Self {
-        Positioned { data: data, position: position }
+        Positioned { data, position, path: None, text: None }
+    }
+
+    /// Converts the data, keeping the rest
+    pub fn map<T, F: FnOnce(Data) -> T>(self, f: F) -> Positioned<T> {
+        Positioned { data: f(self.data),
+                     position: self.position,
+                     path: self.path,
+                     text: self.text }
     }
 }
 
-impl<Data: fmt::Debug + fmt::Display> fmt::Display for Positioned<Data> {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(fmt, "Position {}: {}", self.position, self.data)
+impl<Data: fmt::Display> Positioned<Data> {
+    /// Describes the data for people, together with the line of `source` where it was found.
+    ///
+    /// `source` is the whole parsed document.
+    ///
+    /// ```ignore
+    /// Position 7:49 in /gpx/trk[1]/trkseg[3]/trkpt[1207]/time: invalid date and time
+    ///   text: "yesterday"
+    ///   7 | <trkpt lat="1" lon="2"><time>yesterday</time></trkpt>
+    ///     |                                         ^
+    /// ```
+    pub fn report(&self, source: &str) -> String {
+        let mut report = format!("{}\n", self);
+        if let Some(ref text) = self.text {
+            report.push_str(&format!("  text: {:?}\n", text));
+        }
+        report.push_str(&excerpt(source, self.position));
+        report
     }
 }
 
-impl<Data: ErrorTrait> ErrorTrait for Positioned<Data> {
-    fn description(&self) -> &str {
-        ""
+impl<Data: fmt::Display> fmt::Display for Positioned<Data> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.path {
+            Some(ref path) => write!(fmt, "Position {} in {}: {}", self.position, path, self.data),
+            None => write!(fmt, "Position {}: {}", self.position, self.data),
+        }
     }
-    fn cause(&self) -> Option<&ErrorTrait> {
+}
+
+/// Characters shown on either side of the column in an excerpt
+const EXCERPT_WIDTH: usize = 40;
+
+/// Renders the line of `source` at `position` with a caret under its column.
+///
+/// Long lines, common in GPX files, are cut around the column.
+pub fn excerpt(source: &str, position: TextPosition) -> String {
+    let line = match source.lines().nth(position.row as usize) {
+        Some(line) => line.chars().collect::<Vec<_>>(),
+        None => return String::new(),
+    };
+    let column = cmp::min(position.column as usize, line.len());
+    let start = column.saturating_sub(EXCERPT_WIDTH);
+    let end = cmp::min(line.len(), column + EXCERPT_WIDTH);
+    let (before, after) = (if start > 0 { "..." } else { "" },
+                           if end < line.len() { "..." } else { "" });
+    // tabs are kept so that the caret lines up
+    let padding = line[start..column].iter()
+                                     .map(|&c| if c == '\t' { '\t' } else { ' ' })
+                                     .collect::<String>();
+    let number = (position.row + 1).to_string();
+    format!("  {} | {}{}{}\n  {} | {}{}^\n",
+            number, before, line[start..end].iter().collect::<String>(), after,
+            " ".repeat(number.len()), " ".repeat(before.len()), padding)
+}
+
+impl<Data: ErrorTrait + 'static> ErrorTrait for Positioned<Data> {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
         Some(&self.data)
     }
 }
@@ -60,12 +122,55 @@ impl<Data: ErrorTrait> ErrorTrait for Positioned<Data> {
 /// Problem with attributes serialization
 #[derive(Debug)]
 pub enum AttributeError<T: FormatError> {
-    InvalidValue(T), // TODO: include attribute name
+    InvalidValue(T),
+    /// Value of `attribute` could not be parsed
+    BadValue { attribute: OwnedAttribute, error: T },
     /// This name is not allowed here
     Unexpected(OwnedName),
     // missing should be in build error, to give flexibility for fancy constraints
 }
 
+impl<T: FormatError> AttributeError<T> {
+    /// Turns the failure to parse the value of `attribute` into an error naming it
+    pub fn bad_value<S>(error: S, attribute: &OwnedAttribute) -> Self where Self: From<S> {
+        match Self::from(error) {
+            AttributeError::InvalidValue(e) => {
+                AttributeError::BadValue { attribute: attribute.clone(), error: e }
+            }
+            other => other,
+        }
+    }
+
+    /// Returns the text which could not be parsed, if known
+    pub fn text(&self) -> Option<String> {
+        match *self {
+            AttributeError::BadValue { ref attribute, .. } => Some(attribute.value.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FormatError> fmt::Display for AttributeError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            AttributeError::InvalidValue(ref e) => write!(fmt, "Invalid attribute value: {}", e),
+            AttributeError::BadValue { ref attribute, ref error } => {
+                write!(fmt, "Bad value {:?} of attribute {}: {}", attribute.value, attribute.name, error)
+            }
+            AttributeError::Unexpected(ref name) => write!(fmt, "Unexpected attribute {}", name),
+        }
+    }
+}
+
+impl<T: FormatError> ErrorTrait for AttributeError<T> {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        match *self {
+            AttributeError::InvalidValue(ref e) | AttributeError::BadValue { error: ref e, .. } => Some(e),
+            AttributeError::Unexpected(_) => None,
+        }
+    }
+}
+
 impl<T: FormatError> From<T> for AttributeError<T> {
     fn from(err: T) -> AttributeError<T> {
         AttributeError::InvalidValue(err)
@@ -74,8 +179,521 @@ impl<T: FormatError> From<T> for AttributeError<T> {
 
 /// A string value cannot be parsed
 ///
-/// Marks that this error can be used in AttributeError
-pub trait FormatError where Self: fmt::Debug {} // TODO: enforce Error
+/// Marks that this error can be used in AttributeError.
+///
+/// Errors are thread-safe, so that parsing can happen on another thread.
+pub trait FormatError: ErrorTrait + Send + Sync + 'static {}
+
+
+/// Kind of a non-fatal problem found in the parsed data
+#[derive(Debug)]
+pub enum WarningKind {
+    /// Attribute not described by the schema was skipped
+    IgnoredAttribute(OwnedName),
+    /// Element not described by the schema was skipped
+    IgnoredElement,
+    /// Document uses a namespace of no known GPX version
+    UnsupportedVersion,
+}
+
+/// Non-fatal problem found in the parsed data
+#[derive(Debug)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// Element where the problem was found
+    pub element: OwnedName,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.kind {
+            WarningKind::IgnoredAttribute(ref attr) => {
+                write!(fmt, "attribute {} ignored on element {}", attr, self.element)
+            }
+            WarningKind::IgnoredElement => {
+                write!(fmt, "element {} ignored", self.element)
+            }
+            WarningKind::UnsupportedVersion => {
+                write!(fmt, "version of {} not fully supported, errors may appear", self.element)
+            }
+        }
+    }
+}
+
+/// Receives warnings emitted while parsing
+pub trait Diagnostics {
+    fn warn(&mut self, warning: Positioned<Warning>);
+}
+
+/// Collects all warnings
+impl Diagnostics for Vec<Positioned<Warning>> {
+    fn warn(&mut self, warning: Positioned<Warning>) {
+        self.push(warning);
+    }
+}
+
+/// Treatment of data not described by the schema
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// Stop parsing with an error
+    Error,
+    /// Drop the data and emit a warning
+    Skip,
+    /// Keep the data in the nearest `extensions` element.
+    ///
+    /// Attributes would lose their element there, so they are skipped with a warning instead.
+    Preserve,
+}
+
+/// Configures how the parser treats data not described by the schema
+#[derive(Debug, Clone)]
+pub struct ParserOptions {
+    /// Elements in the document namespace which the schema does not allow in their place.
+    /// Also GPX 1.0 `<email>` whose address doesn't fit `emailType`.
+    pub unknown_elements: Policy,
+    /// Attributes without a namespace which the schema does not allow on their element.
+    /// They are never preserved, see `Policy::Preserve`.
+    pub unknown_attributes: Policy,
+    /// Elements and attributes in namespaces other than the document's
+    pub foreign_namespaces: Policy,
+    /// Keep comments, processing instructions, CDATA sections and namespace declarations,
+    /// so that writing the document back changes less. This does not make it lossless:
+    ///
+    /// - markup around the root element and the root's namespace declarations
+    ///   go to `xml::DocInfo`, and come back in place,
+    /// - inside extensions, everything is kept in place in `xml::Element`,
+    /// - comments and processing instructions among GPX elements are moved
+    ///   to the end of the nearest `extensions` element, losing their place among siblings,
+    /// - namespace declarations on GPX elements other than the root are dropped.
+    pub keep_markup: bool,
+    /// Types of extension elements, by namespace. Others are kept as `xml::Element`.
+    /// Empty by default, see `gpx::ext`.
+    pub extensions: Registry,
+    /// Reject elements out of the schema order, repeated more times than the schema allows,
+    /// and missing required elements. Otherwise a repeated element replaces the earlier one.
+    pub strict: bool,
+    /// Bounds on the size of the document
+    pub limits: ParseLimits,
+    /// Parts of the document to leave out
+    pub skip: Skip,
+}
+
+impl Default for ParserOptions {
+    /// Rejects unknown data, skips foreign namespaces, drops comments,
+    /// leaves extensions untyped, doesn't check element order
+    fn default() -> Self {
+        ParserOptions { unknown_elements: Policy::Error,
+                        unknown_attributes: Policy::Error,
+                        foreign_namespaces: Policy::Skip,
+                        keep_markup: false,
+                        extensions: Registry::new(),
+                        strict: false,
+                        limits: ParseLimits::default(),
+                        skip: Skip::default() }
+    }
+}
+
+/// Nesting allowed by default, well above what GPX files need.
+///
+/// Parsers recurse into nested elements, so a limit much higher than this
+/// overflows a 2 MiB thread stack in unoptimized builds.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Bounds on the size of parsed documents, for input which can't be trusted.
+///
+/// `None` means unbounded. Going over a limit stops parsing with `Error::LimitExceeded`.
+/// Text is measured once the XML parser has read it, so a single run of character data
+/// is held in memory before it's refused.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseLimits {
+    /// Elements nested in each other, counting the root
+    pub max_depth: Option<usize>,
+    /// Elements in the whole document
+    pub max_elements: Option<u64>,
+    /// Bytes of character data directly inside one element
+    pub max_text_length: Option<usize>,
+    /// Attributes on one element
+    pub max_attributes: Option<usize>,
+    /// Waypoints, route points and track points in the whole document
+    pub max_waypoints: Option<u64>,
+}
+
+impl Default for ParseLimits {
+    /// Bounds only nesting, to `DEFAULT_MAX_DEPTH`
+    fn default() -> Self {
+        ParseLimits { max_depth: Some(DEFAULT_MAX_DEPTH),
+                      max_elements: None,
+                      max_text_length: None,
+                      max_attributes: None,
+                      max_waypoints: None }
+    }
+}
+
+/// One of `ParseLimits`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Depth,
+    Elements,
+    TextLength,
+    Attributes,
+    Waypoints,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.write_str(match *self {
+            Limit::Depth => "nesting depth",
+            Limit::Elements => "number of elements",
+            Limit::TextLength => "text length",
+            Limit::Attributes => "number of attributes",
+            Limit::Waypoints => "number of waypoints",
+        })
+    }
+}
+
+/// Checks `value` against `max`, which bounds `limit`
+fn check_limit(limit: Limit, max: Option<u64>, value: u64) -> Result<(), Error> {
+    match max {
+        Some(max) if value > max => Err(Error::LimitExceeded { limit, max }),
+        _ => Ok(()),
+    }
+}
+
+/// Parts of GPX documents which the parser passes over without building their values.
+///
+/// Skipped elements are not checked against the schema, only against `ParseLimits`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Skip {
+    /// `<wpt>` elements
+    pub waypoints: bool,
+    /// `<rte>` elements
+    pub routes: bool,
+    /// `<trk>` elements
+    pub tracks: bool,
+    /// `<extensions>`, and in GPX 1.0 elements from other namespaces
+    pub extensions: bool,
+    /// `<link>`, and `<url>` with `<urlname>` in GPX 1.0
+    pub links: bool,
+    /// Stop at the first element after the metadata, without reading the rest of the document.
+    ///
+    /// Metadata which GPX 1.0 documents carry at their end is missed.
+    pub after_metadata: bool,
+}
+
+impl Skip {
+    /// Skips everything but metadata
+    pub fn all() -> Self {
+        Skip { waypoints: true,
+               routes: true,
+               tracks: true,
+               extensions: true,
+               links: true,
+               after_metadata: true }
+    }
+
+    /// Tells if the element called `name` is left out.
+    ///
+    /// `flat_extensions` is set when foreign elements are extensions, see `Context::flat_extensions`.
+    pub fn covers(&self, name: &OwnedName, flat_extensions: bool) -> bool {
+        let is_gpx = match name.namespace {
+            None => true,
+            Some(ref ns) => ns == ::gpx::Version::V1_0.namespace() || ns == ::gpx::Version::V1_1.namespace(),
+        };
+        if !is_gpx {
+            return self.extensions && flat_extensions;
+        }
+        match &name.local_name as &str {
+            "wpt" => self.waypoints,
+            "rte" => self.routes,
+            "trk" => self.tracks,
+            "extensions" => self.extensions,
+            "link" | "url" | "urlname" => self.links,
+            _ => false,
+        }
+    }
+
+    /// Tells if parsing stops at the element called `name`, found `depth` elements deep
+    pub fn stops_at(&self, name: &OwnedName, depth: usize) -> bool {
+        self.after_metadata && depth == 2 && !::gpx::par::stream::is_metadata(name)
+    }
+}
+
+/// Amounts of data parsed so far, checked against `ParseLimits`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Counts {
+    pub elements: u64,
+    pub waypoints: u64,
+}
+
+/// Place in an `xsd:sequence` reached by the elements parsed so far
+#[derive(Debug, Default)]
+pub struct SequencePosition {
+    index: usize,
+    count: u64,
+}
+
+impl SequencePosition {
+    /// Moves to the element called `name`, which is at `index` in the sequence
+    /// and may occur up to `max_occurs` times, unbounded if `None`.
+    pub fn advance(&mut self, index: usize, max_occurs: Option<u64>, name: &OwnedName)
+            -> Result<(), ::gpx::par::Error> {
+        if index < self.index {
+            return Err(::gpx::par::Error::OutOfOrder(name.clone()));
+        }
+        if index > self.index {
+            self.index = index;
+            self.count = 0;
+        }
+        self.count += 1;
+        match max_occurs {
+            Some(max) if self.count > max => Err(::gpx::par::Error::TooMany(name.clone())),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Element or markup kept because of `Policy::Preserve` or `ParserOptions::keep_markup`
+#[derive(Debug)]
+pub struct Preserved(pub xml::Node);
+
+impl Preserved {
+    /// Places the data inside `element`
+    pub fn store(self, element: &mut xml::Element) {
+        element.nodes.push(self.0);
+    }
+}
+
+/// Element in an `ElementPath`
+#[derive(Debug, Clone)]
+struct PathStep {
+    name: String,
+    /// Counted from 1 among the preceding siblings of the same name
+    index: usize,
+    /// Shown in the path only for elements which may repeat
+    max_occurs: Option<u64>,
+    /// Numbers of children seen so far, by name
+    children: Vec<(String, usize)>,
+}
+
+/// Location of the element being parsed, like `/gpx/trk[1]/trkseg[3]/trkpt[1207]/time`
+///
+/// Element names keep their prefixes. Indices are shown unless the schema allows one element only.
+#[derive(Debug, Clone, Default)]
+pub struct ElementPath {
+    steps: Vec<PathStep>,
+}
+
+impl ElementPath {
+    /// Moves into the child element `name` of the current one
+    pub fn enter(&mut self, name: &OwnedName) {
+        let name = match name.prefix {
+            Some(ref prefix) => format!("{}:{}", prefix, name.local_name),
+            None => name.local_name.clone(),
+        };
+        self.enter_qualified(name);
+    }
+
+    /// Moves into the child element called `name`, written with its prefix like `gpxtpx:hr`
+    pub fn enter_qualified<S: Into<String>>(&mut self, name: S) {
+        let name = name.into();
+        let index = match self.steps.last_mut() {
+            Some(parent) => {
+                let position = parent.children.iter().position(|(child, _)| child == &name);
+                match position {
+                    Some(i) => {
+                        parent.children[i].1 += 1;
+                        parent.children[i].1
+                    }
+                    None => {
+                        parent.children.push((name.clone(), 1));
+                        1
+                    }
+                }
+            }
+            None => 1,
+        };
+        // the root element is unique
+        let max_occurs = if self.steps.is_empty() { Some(1) } else { None };
+        self.steps.push(PathStep { name,
+                                   index,
+                                   max_occurs,
+                                   children: Vec::new() });
+    }
+
+    /// Moves back to the parent element
+    pub fn leave(&mut self) {
+        self.steps.pop();
+    }
+
+    /// Forgets the children of the current element, so that they can be visited again
+    pub fn revisit(&mut self) {
+        if let Some(step) = self.steps.last_mut() {
+            step.children.clear();
+        }
+    }
+
+    /// Returns the number of elements on the path
+    pub fn depth(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Records how many times the schema allows the current element, `None` if unbounded
+    pub fn set_max_occurs(&mut self, max_occurs: Option<u64>) {
+        if let Some(step) = self.steps.last_mut() {
+            step.max_occurs = max_occurs;
+        }
+    }
+}
+
+impl fmt::Display for ElementPath {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for step in &self.steps {
+            write!(fmt, "/{}", step.name)?;
+            if step.max_occurs != Some(1) {
+                write!(fmt, "[{}]", step.index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// State shared by parsers of all elements in a document
+pub struct Context<'a> {
+    pub options: &'a ParserOptions,
+    pub diagnostics: &'a mut dyn Diagnostics,
+    /// Preserved data which the element where it was found had no place for.
+    ///
+    /// Taken over by the closest ancestor able to store it.
+    pub preserved: Vec<Preserved>,
+    /// Foreign elements are extensions placed directly among the known ones, like in GPX 1.0.
+    ///
+    /// They are preserved regardless of `ParserOptions::foreign_namespaces`.
+    pub flat_extensions: bool,
+    /// Element being parsed
+    pub path: ElementPath,
+    /// Data parsed so far in the document
+    pub counts: Counts,
+    /// Parsing ended before the end of the document, see `Skip::after_metadata`
+    pub stopped: bool,
+    /// Namespaces in scope of the element being parsed, tracked with `keep_markup`
+    scope: Namespace,
+    /// Namespaces declared on the element being parsed, tracked with `keep_markup`
+    declared: Namespace,
+}
+
+/// Returns the mappings of `scope` which are not inherited from `outer`
+fn declarations(scope: &Namespace, outer: &Namespace) -> Namespace {
+    let mut declared = Namespace::empty();
+    for (prefix, uri) in scope {
+        if prefix == NS_XML_PREFIX || prefix == NS_XMLNS_PREFIX {
+            continue;
+        }
+        if outer.get(prefix).unwrap_or(NS_EMPTY_URI) != uri {
+            declared.put(prefix, uri);
+        }
+    }
+    declared
+}
+
+impl<'a> Context<'a> {
+    pub fn new(options: &'a ParserOptions, diagnostics: &'a mut dyn Diagnostics) -> Self {
+        Context { options,
+                  diagnostics,
+                  preserved: Vec::new(),
+                  flat_extensions: false,
+                  path: ElementPath::default(),
+                  counts: Counts::default(),
+                  stopped: false,
+                  scope: Namespace::empty(),
+                  declared: Namespace::empty() }
+    }
+
+    /// Tells if the place of element `name` in its parent is checked against the schema.
+    ///
+    /// GPX 1.1 elements in a GPX 1.0 document stand where extensions go, so they are not.
+    pub fn checks_order(&self, name: &OwnedName) -> bool {
+        let v1_1 = ::gpx::Version::V1_1.namespace();
+        self.options.strict
+            && !(self.flat_extensions && name.namespace.as_ref().map(|ns| ns as &str) == Some(v1_1))
+    }
+
+    /// Counts the element just entered in `path`, which has `attributes` attributes.
+    ///
+    /// Fails if that breaks the limits on depth, elements or attributes.
+    pub fn count_element(&mut self, attributes: usize) -> Result<(), Error> {
+        let limits = &self.options.limits;
+        self.counts.elements += 1;
+        check_limit(Limit::Depth, limits.max_depth.map(|max| max as u64), self.path.depth() as u64)?;
+        check_limit(Limit::Elements, limits.max_elements, self.counts.elements)?;
+        check_limit(Limit::Attributes, limits.max_attributes.map(|max| max as u64), attributes as u64)
+    }
+
+    /// Tells if the element called `name` is left out according to `ParserOptions::skip`
+    pub fn skips(&self, name: &OwnedName) -> bool {
+        self.options.skip.covers(name, self.flat_extensions)
+    }
+
+    /// Counts a waypoint, failing if there are too many
+    pub fn count_waypoint(&mut self) -> Result<(), Error> {
+        self.counts.waypoints += 1;
+        check_limit(Limit::Waypoints, self.options.limits.max_waypoints, self.counts.waypoints)
+    }
+
+    /// Fails if `length` bytes of text are too many for one element
+    pub fn check_text(&self, length: usize) -> Result<(), Error> {
+        check_limit(Limit::TextLength, self.options.limits.max_text_length.map(|max| max as u64),
+                    length as u64)
+    }
+
+    /// Makes `namespace` the scope of the element about to be parsed.
+    ///
+    /// Returns the enclosing scope, to be given back to `leave_scope` after the element.
+    pub fn enter_scope(&mut self, namespace: Namespace) -> Namespace {
+        if !self.options.keep_markup {
+            return Namespace::empty();
+        }
+        self.declared = declarations(&namespace, &self.scope);
+        mem::replace(&mut self.scope, namespace)
+    }
+
+    pub fn leave_scope(&mut self, outer: Namespace) {
+        if self.options.keep_markup {
+            self.scope = outer;
+        }
+    }
+
+    /// Namespaces declared on the element being parsed. Empty unless keeping markup.
+    pub fn declared(&self) -> &Namespace {
+        &self.declared
+    }
+
+    /// Keeps a comment or processing instruction for the closest element able to store it.
+    ///
+    /// Dropped unless `ParserOptions::keep_markup` is set.
+    pub fn keep_markup(&mut self, node: xml::Node) {
+        if self.options.keep_markup {
+            self.preserved.push(Preserved(node));
+        }
+    }
+
+    /// Reports a warning about `element` found at `position`
+    pub fn warn(&mut self, kind: WarningKind, element: &OwnedName, position: TextPosition) {
+        let warning = self.locate(Positioned::with_position(Warning { kind,
+                                                                      element: element.clone() },
+                                                            position));
+        self.diagnostics.warn(warning);
+    }
+
+    /// Gives `data` the path of the element being parsed, unless it has one already.
+    ///
+    /// Errors are located where they happen, before the path is left.
+    pub fn locate<Data>(&self, mut data: Positioned<Data>) -> Positioned<Data> {
+        if data.path.is_none() {
+            data.path = Some(self.path.to_string());
+        }
+        data
+    }
+}
 
 
 /// Can parse complex element in XML stream into `Data` type.
@@ -83,14 +701,15 @@ pub trait FormatError where Self: fmt::Debug {} // TODO: enforce Error
 /// The element may take any form.
 /// Implement on converter types.
 pub trait ParseVia<Data> {
-    fn parse_via<R: io::Read>(parser: &mut EventReader<R>,
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
                               name: &OwnedName, attributes: &[OwnedAttribute])
         -> Result<Data, Positioned<Error>>;
 }
 
 /// Can parse simple element in XML stream into `Data` type.
 ///
-/// The element must contain only character data.
+/// The element must contain only character data. Its text, CDATA sections and whitespace
+/// are joined before conversion.
 /// `ParseVia` trait is automatically defined.
 pub trait ParseViaChar<Data> {
     fn from_char(s: &str) -> Result<Data, ::gpx::par::Error>;
@@ -98,28 +717,40 @@ pub trait ParseViaChar<Data> {
 
 /// Implements basic event loop reading character data from inside
 impl<T, Data> ParseVia<Data> for T where T: ParseViaChar<Data> {
-    fn parse_via<R: io::Read>(parser: &mut EventReader<R>, 
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
                               end_name: &OwnedName, attributes: &[OwnedAttribute])
             -> Result<Data, Positioned<Error>> {
         let _ = attributes; // FIXME: error on present attributes
         let mut ret = String::new();
+        let mut start = None;
         loop {
             match parser.next() {
-                Ok(XmlEvent::Characters(data)) => {
-                    ret = data;
+                // text is split around entities, CDATA sections and markup
+                Ok(XmlEvent::Characters(data)) | Ok(XmlEvent::CData(data))
+                        | Ok(XmlEvent::Whitespace(data)) => {
+                    start = start.or_else(|| Some(parser.position()));
+                    ret.push_str(&data);
+                    if let Err(e) = ctx.check_text(ret.len()) {
+                        return Err(ctx.locate(Positioned::with_position(e, parser.position())));
+                    }
                 }
                 Ok(XmlEvent::EndElement { name }) => {
                     return if &name == end_name {
                         Self::from_char(&ret).map_err(|e| {
-                            Positioned::with_position(e.into(), parser.position())
+                            // points to the text rather than to the end of the element
+                            let position = start.unwrap_or_else(|| parser.position());
+                            let mut error = ctx.locate(Positioned::with_position(e, position));
+                            error.text = Some(ret);
+                            error
                         })
                     } else {
                         Err(Positioned::with_position(xml::ElementError::UnexpectedEnd.into(),
                                                       parser.position()))
                     }
                 }
-                Ok(XmlEvent::Whitespace(s)) => {
-                    println!("{:?}", s);
+                Ok(XmlEvent::Comment(data)) => ctx.keep_markup(xml::Node::Comment(data)),
+                Ok(XmlEvent::ProcessingInstruction { name, data }) => {
+                    ctx.keep_markup(xml::Node::ProcessingInstruction(name, data));
                 }
                 Ok(ev) => {
                     return Err(Positioned::with_position(xml::ElementError::UnexpectedEvent(ev).into(),
@@ -134,64 +765,202 @@ impl<T, Data> ParseVia<Data> for T where T: ParseViaChar<Data> {
 }
 
 /// Helper, converts any parse error to the positioned error type. Not a closure to hopefully save performance
-fn _with_pos<R: Read, Dst, Src: Into<Dst>>(reader: &EventReader<R>, src: Src) -> Positioned<Dst> {
+fn _with_pos<R: EventSource, Dst, Src: Into<Dst>>(reader: &R, src: Src) -> Positioned<Dst> {
     Positioned::with_position(src.into(), reader.position())
 }
 
+/// Reads past the contents of the element just entered in `ctx.path`,
+/// checking them only against `ParseLimits`
+pub fn skip_element<R: EventSource>(reader: &mut R, ctx: &mut Context)
+        -> Result<(), Positioned<Error>> {
+    let depth = ctx.path.depth();
+    // text read so far in each open element, as it may come in many pieces
+    let mut text_lengths = vec![0];
+    loop {
+        match reader.next().map_err(|e| ctx.locate(_with_pos(reader, e)))? {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                ctx.path.enter(&name);
+                ctx.count_element(attributes.len()).map_err(|e| ctx.locate(_with_pos(reader, e)))?;
+                text_lengths.push(0);
+            }
+            XmlEvent::EndElement { .. } => {
+                if ctx.path.depth() == depth {
+                    return Ok(());
+                }
+                ctx.path.leave();
+                text_lengths.pop();
+            }
+            XmlEvent::Characters(data) | XmlEvent::Whitespace(data) | XmlEvent::CData(data) => {
+                let length = text_lengths.last_mut().expect("the skipped element is open");
+                *length += data.len();
+                ctx.check_text(*length).map_err(|e| ctx.locate(_with_pos(reader, e)))?;
+            }
+            _ => {}
+        }
+    }
+}
+
 /// Can parse attribute into `Data` type.
 ///
 /// Implement for `conv` types.
 pub trait FromAttributeVia<Data> {
     type Error: FormatError;
-    fn from_attribute(&str) -> Result<Data, Self::Error>;
+    fn from_attribute(value: &str) -> Result<Data, Self::Error>;
 }
 
 /// Accepts an event stream and parses it into an Element
 pub trait ElementParse<E>
     where Self: Sized + ElementBuild + Default,
           E: From<xml::ElementError> + From<Self::BuildError> + From<::par::AttributeError<E>>
-             + From<_xml::reader::Error> + ::par::FormatError {
+             + From<_xml::reader::Error> + From<::gpx::par::Error> + ::par::FormatError {
     fn new() -> Self {
         Self::default()
     }
     
     /// Parses the element and its subelements, returning ElementBuild::Element instance.
-    fn parse<'a, R: Read>(mut self, elem_name: &OwnedName, attributes: &[OwnedAttribute],
-                          mut reader: &'a mut EventReader<R>)
+    fn parse<R: EventSource>(mut self, elem_name: &OwnedName, attributes: &[OwnedAttribute],
+                          reader: &mut R, ctx: &mut Context)
             -> Result<Self::Element, Positioned<E>> {
-        try!(self.parse_start(attributes).map_err(|e| _with_pos(reader, e)));
+        let position = reader.position();
+        let preserved_mark = ctx.preserved.len();
+        let mut text_length = 0;
+        self.parse_start(ctx, elem_name, attributes, position).map_err(|e| {
+            let text = e.text();
+            let mut error = ctx.locate(_with_pos(reader, e));
+            error.text = text;
+            error
+        })?;
         loop {
-            match try!(reader.next().map_err(|e| _with_pos(reader, e))) {
-                XmlEvent::StartElement { name, attributes, namespace: _ } => {
-                    try!(self.parse_element(&mut reader, &name, attributes.as_slice()));
+            match reader.next().map_err(|e| ctx.locate(_with_pos(reader, e)))? {
+                XmlEvent::StartElement { name, attributes, namespace } => {
+                    let outer = ctx.enter_scope(namespace);
+                    ctx.path.enter(&name);
+                    ctx.count_element(attributes.len()).map_err(|e| ctx.locate(_with_pos(reader, e)))?;
+                    if ctx.options.skip.stops_at(&name, ctx.path.depth()) {
+                        ctx.stopped = true;
+                        ctx.path.leave();
+                        ctx.leave_scope(outer);
+                        break;
+                    }
+                    if ctx.skips(&name) {
+                        skip_element(reader, ctx).map_err(|e| e.map(E::from))?;
+                    } else {
+                        self.parse_element(reader, ctx, &name, attributes.as_slice())
+                            .map_err(|e| ctx.locate(e))?;
+                        // right away, to keep the document order
+                        self.adopt_preserved(ctx, preserved_mark);
+                    }
+                    ctx.path.leave();
+                    ctx.leave_scope(outer);
                 }
                 XmlEvent::EndElement { name } => {
                     if &name == elem_name {
                         break;
                     }
-                    return Err(_with_pos(reader, xml::ElementError::UnexpectedEnd));
+                    return Err(ctx.locate(_with_pos(reader, xml::ElementError::UnexpectedEnd)));
                 }
                 XmlEvent::Characters(data) => {
-                    try!(self.parse_characters(data).map_err(|e| _with_pos(reader, e)));
+                    text_length += data.len();
+                    ctx.check_text(text_length).map_err(|e| ctx.locate(_with_pos(reader, e)))?;
+                    self.parse_characters(data).map_err(|e| ctx.locate(_with_pos(reader, e)))?;
                 }
                 XmlEvent::Whitespace(s) => {
-                    try!(self.parse_whitespace(s).map_err(|e| _with_pos(reader, e)));
+                    text_length += s.len();
+                    ctx.check_text(text_length).map_err(|e| ctx.locate(_with_pos(reader, e)))?;
+                    self.parse_whitespace(s).map_err(|e| ctx.locate(_with_pos(reader, e)))?;
+                }
+                XmlEvent::CData(data) => {
+                    text_length += data.len();
+                    ctx.check_text(text_length).map_err(|e| ctx.locate(_with_pos(reader, e)))?;
+                    let result = self.parse_cdata(ctx, data);
+                    result.map_err(|e| ctx.locate(_with_pos(reader, e)))?;
+                }
+                XmlEvent::Comment(data) => self.parse_markup(ctx, xml::Node::Comment(data)),
+                XmlEvent::ProcessingInstruction { name, data } => {
+                    self.parse_markup(ctx, xml::Node::ProcessingInstruction(name, data));
+                }
+                e => {
+                    return Err(ctx.locate(_with_pos(reader, xml::ElementError::UnexpectedEvent(e))));
                 }
-                e => return Err(_with_pos(reader, xml::ElementError::UnexpectedEvent(e)))
             }
         }
-        self.build().map_err(|e| _with_pos(reader, e))
+        let result = self.parse_end(ctx, elem_name);
+        result.map_err(|e| ctx.locate(_with_pos(reader, e)))?;
+        self.build(elem_name).map_err(|e| ctx.locate(_with_pos(reader, e)))
     }
     /// Parses the start event and attributes within it. Should be implemented, bu default ignores attributes.
-    fn parse_start(&mut self, attributes: &[OwnedAttribute])
+    ///
+    /// `position` points to the start event of the element called `name`.
+    fn parse_start(&mut self, ctx: &mut Context, name: &OwnedName, attributes: &[OwnedAttribute],
+                   position: TextPosition)
             -> Result<(), ::par::AttributeError<E>> {
-        let _ = attributes;
+        let _ = (ctx, name, attributes, position);
+        Ok(())
+    }
+    /// Checks the element once its end is reached. By default accepts anything.
+    fn parse_end(&mut self, ctx: &mut Context, name: &OwnedName) -> Result<(), E> {
+        let _ = (ctx, name);
         Ok(())
     }
     /// Parses sub-element.
-    fn parse_element<'a, R: Read>(&mut self, reader: &'a mut EventReader<R>,
+    fn parse_element<R: EventSource>(&mut self, reader: &mut R, ctx: &mut Context,
                                   name: &OwnedName, attributes: &[OwnedAttribute])
         -> Result<(), Positioned<E>>;
+    /// Stores data not described by the schema. By default there is no place for it and it's given back.
+    fn preserve(&mut self, data: Preserved) -> Result<(), Preserved> {
+        Err(data)
+    }
+    /// Takes over data left in `ctx` since `mark` by subelements which could not store it.
+    ///
+    /// Whatever doesn't fit stays in `ctx` for the parent.
+    fn adopt_preserved(&mut self, ctx: &mut Context, mark: usize) {
+        for data in ctx.preserved.split_off(mark) {
+            if let Err(data) = self.preserve(data) {
+                ctx.preserved.push(data);
+            }
+        }
+    }
+    /// Deals with an attribute not described by the schema according to `policy`.
+    fn unexpected_attribute(&mut self, ctx: &mut Context, policy: Policy,
+                            name: &OwnedName, attribute: &OwnedAttribute, position: TextPosition)
+            -> Result<(), ::par::AttributeError<E>> {
+        match policy {
+            Policy::Error => Err(::par::AttributeError::Unexpected(attribute.name.clone())),
+            Policy::Skip | Policy::Preserve => {
+                ctx.warn(WarningKind::IgnoredAttribute(attribute.name.clone()), name, position);
+                Ok(())
+            }
+        }
+    }
+    /// Deals with a sub-element not described by the schema according to `policy`.
+    ///
+    /// Consumes the whole sub-element unless `policy` is `Error`.
+    fn unexpected_element<R: EventSource>(&mut self, reader: &mut R, ctx: &mut Context,
+                                   policy: Policy,
+                                   name: &OwnedName, attributes: &[OwnedAttribute])
+            -> Result<(), Positioned<E>> {
+        match policy {
+            // unexpected names are reported the same way for elements and attributes
+            Policy::Error => {
+                Err(ctx.locate(_with_pos(reader, ::par::AttributeError::Unexpected(name.clone()))))
+            }
+            Policy::Skip => {
+                ctx.warn(WarningKind::IgnoredElement, name, reader.position());
+                xml::ElementParser::new().parse(name, attributes, reader, ctx)
+                    .map_err(|e| e.map(E::from))?;
+                Ok(())
+            }
+            Policy::Preserve => {
+                let options = ctx.options;
+                let node = options.extensions.parse(reader, ctx, name, attributes)
+                                  .map_err(|e| e.map(E::from))?;
+                if let Err(data) = self.preserve(Preserved(node)) {
+                    ctx.preserved.push(data);
+                }
+                Ok(())
+            }
+        }
+    }
     /// Parses characters. By default ignores.
     fn parse_characters(&mut self, data: String) -> Result<(), E> {
         let _ = data;
@@ -203,11 +972,27 @@ pub trait ElementParse<E>
         let _ = space;
         Ok(())
     }
+    /// Parses a CDATA section. By default not allowed.
+    fn parse_cdata(&mut self, ctx: &mut Context, data: String) -> Result<(), E> {
+        let _ = ctx;
+        Err(xml::ElementError::UnexpectedEvent(XmlEvent::CData(data)).into())
+    }
+    /// Stores a comment or processing instruction.
+    ///
+    /// By default it's kept with preserved data if keeping markup, and dropped otherwise.
+    fn parse_markup(&mut self, ctx: &mut Context, node: xml::Node) {
+        if ctx.options.keep_markup {
+            if let Err(data) = self.preserve(Preserved(node)) {
+                ctx.preserved.push(data);
+            }
+        }
+    }
 }
 
 /// Turns into a finished element
 pub trait ElementBuild {
     type Element;
     type BuildError;
-    fn build(self) -> Result<Self::Element, Self::BuildError>;
+    /// `name` is the name of the element being built, used for reporting errors.
+    fn build(self, name: &OwnedName) -> Result<Self::Element, Self::BuildError>;
 }
@@ -17,23 +17,56 @@ extern crate xml as _xml;
 use std::fmt;
 use std::io;
 use std::borrow::Cow;
+use std::error::Error as ErrorTrait;
 
 use self::_xml::common::XmlVersion;
 use self::_xml::name::OwnedName;
-use self::_xml::namespace::Namespace;
+use self::_xml::namespace::{ Namespace, NS_NO_PREFIX };
 use self::_xml::writer;
 use self::_xml::writer::{ EmitterConfig, EventWriter, XmlEvent };
 
+#[cfg(feature = "async")]
+use async_io::{ AsyncWrite, SerializeFuture };
+
     
+/// Choices about how to write data, see `SerializeDocument::serialize_with_options`.
+///
+/// Handed down to every converter writing the document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SerializerOptions {
+    /// Write free text elements, like names, comments and descriptions, as CDATA sections
+    /// instead of escaping the markup inside
+    pub text_as_cdata: bool,
+}
+
 /// Value cannot be formatted to a valid string
-pub trait FormatError where Self: fmt::Debug + 'static {}
+pub trait FormatError: ErrorTrait + Send + Sync + 'static {}
 
 /// Problems encountered while serializing
 #[derive(Debug)]
 pub enum Error {
     /// I/O and programming problems
     Writer(writer::Error),
-    Value(Box<FormatError>), // TODO: save location and generalize beyond string
+    /// Holds a `FormatError`, which can be recovered with `downcast_ref`
+    Value(Box<dyn ErrorTrait + Send + Sync>), // TODO: save location and generalize beyond string
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::Writer(ref e) => write!(fmt, "XML writer error: {}", e),
+            Error::Value(ref e) => write!(fmt, "Value can't be written: {}", e),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        match *self {
+            Error::Writer(ref e) => Some(e),
+            Error::Value(ref e) => Some(&**e),
+        }
+    }
 }
 
 impl From<writer::Error> for Error {
@@ -44,7 +77,7 @@ impl From<writer::Error> for Error {
 
 impl<E: FormatError + 'static> From<E> for Error {
     fn from(err: E) -> Self {
-        Error::Value(Box::new(err) as Box<FormatError>)
+        Error::Value(Box::new(err))
     }
 }
 
@@ -52,9 +85,7 @@ impl<E: FormatError + 'static> From<E> for Error {
 pub trait SerializeDocument {
     /// Default serialization, pretty prints the XML file
     fn serialize<W: io::Write>(&self, sink: W) -> Result<(), Error> {
-        self.serialize_with_config(EmitterConfig::new().line_separator("\n")
-                                                .perform_indent(true),
-                                   sink)
+        self.serialize_with_options(sink, &SerializerOptions::default())
     }
     /// Convenience method to create a custom EventWriter based on passed config
     fn serialize_with_config<W: io::Write>(&self, config: EmitterConfig, sink: W)
@@ -64,14 +95,88 @@ pub trait SerializeDocument {
     /// Serialize the data into XML file
     fn serialize_with<W: io::Write>(&self, sink: &mut EventWriter<W>)
             -> Result<(), Error> {
-        try!(sink.write(XmlEvent::StartDocument { version: XmlVersion::Version11,
+        self.serialize_document(sink, &SerializerOptions::default())
+    }
+    /// Like `serialize`, following `options`
+    fn serialize_with_options<W: io::Write>(&self, sink: W, options: &SerializerOptions)
+            -> Result<(), Error> {
+        let config = EmitterConfig::new().line_separator("\n")
+                                         .perform_indent(true);
+        self.serialize_document(&mut config.create_writer(sink), options)
+    }
+    /// Write the whole document inside the EventWriter, following `options`
+    fn serialize_document<W: io::Write>(&self, sink: &mut EventWriter<W>,
+                                        options: &SerializerOptions)
+            -> Result<(), Error> {
+        sink.write(XmlEvent::StartDocument { version: XmlVersion::Version10,
                                                   encoding: None,
-                                                  standalone: None }));
-        self.serialize_root(sink)
+                                                  standalone: None })?;
+        self.serialize_root(sink, options)
+    }
+    /// Checks that the data can be written validly. By default accepts anything.
+    fn check_valid(&self) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Like `serialize`, but writes nothing if `check_valid` fails
+    fn serialize_valid<W: io::Write>(&self, sink: W) -> Result<(), Error> {
+        self.check_valid()?;
+        self.serialize(sink)
+    }
+    /// Like `serialize`, writing to an asynchronous sink.
+    ///
+    /// Not streamed: the whole document is serialized to memory first, then written.
+    #[cfg(feature = "async")]
+    fn serialize_async<'a, W: AsyncWrite + Unpin>(&self, sink: &'a mut W) -> SerializeFuture<'a, W> {
+        let mut data = Vec::new();
+        let result = self.serialize(&mut data);
+        SerializeFuture::new(sink, result.map(|()| data))
     }
     /// Write root element inside the EventWriter
-    fn serialize_root<W: io::Write>(&self, sink: &mut EventWriter<W>)
+    fn serialize_root<W: io::Write>(&self, sink: &mut EventWriter<W>,
+                                    options: &SerializerOptions)
             -> Result<(), Error>;
+    /// Write root element, declaring also `namespace` on it. By default the declarations are dropped.
+    fn serialize_root_declaring<W: io::Write>(&self, sink: &mut EventWriter<W>,
+                                              options: &SerializerOptions, namespace: &Namespace)
+            -> Result<(), Error> {
+        let _ = namespace;
+        self.serialize_root(sink, options)
+    }
+}
+
+/// Returns namespace declarations needed to write an element called `name`.
+///
+/// A namespace without a prefix becomes the default one, so it applies to the children as well.
+pub fn element_namespace(name: &OwnedName) -> Namespace {
+    let mut ns = Namespace::empty();
+    if let Some(ref uri) = name.namespace {
+        let prefix = match name.prefix {
+            Some(ref prefix) => prefix as &str,
+            None => NS_NO_PREFIX,
+        };
+        ns.put(prefix, uri as &str);
+    }
+    ns
+}
+
+/// Returns the name of the child element `local_name` in the namespace of `parent`
+pub fn child_name(parent: &OwnedName, local_name: &str) -> OwnedName {
+    OwnedName { local_name: local_name.into(),
+                namespace: parent.namespace.clone(),
+                prefix: parent.prefix.clone() }
+}
+
+/// Writes `text` as CDATA sections.
+///
+/// A section can't contain `]]>`, so the text is split there.
+pub fn write_cdata<W: io::Write>(sink: &mut EventWriter<W>, text: &str) -> Result<(), Error> {
+    let mut rest = text;
+    while let Some(end) = rest.find("]]>") {
+        sink.write(XmlEvent::CData(&rest[..end + 2]))?;
+        rest = &rest[end + 2..];
+    }
+    sink.write(XmlEvent::CData(rest))?;
+    Ok(())
 }
 
 /// Can be serialized as a simple string
@@ -86,23 +191,78 @@ pub trait ToCharsVia<Data: ?Sized> {
 
 /// Can be serialized as a regular XML element
 pub trait SerializeVia<Data: ?Sized> {
-    fn serialize_via<W: io::Write>(data: &Data, sink: &mut EventWriter<W>, name: &OwnedName)
+    fn serialize_via<W: io::Write>(data: &Data, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
         -> Result<(), Error>;
 }
 
 /// Leverage char conversion capabilities
 impl<T, Data: ?Sized> SerializeVia<Data> for T where T: ToCharsVia<Data>,
         T::Error: Into<Error> {
-    fn serialize_via<W: io::Write>(data: &Data, sink: &mut EventWriter<W>, name: &OwnedName)
+    fn serialize_via<W: io::Write>(data: &Data, sink: &mut EventWriter<W>,
+                                   _options: &SerializerOptions, name: &OwnedName)
+            -> Result<(), Error> {
+        let elemname = name.borrow();
+        sink.write(
+            XmlEvent::StartElement { name: elemname,
+                                     attributes: Cow::Owned(Vec::new()),
+                                     namespace: Cow::Owned(element_namespace(name)) }
+        )?;
+        sink.write(XmlEvent::Characters(&T::to_characters(data)?))?;
+        sink.write(XmlEvent::EndElement { name: Some(elemname) })?;
+        Ok(())
+    }
+}
+
+/// Writes strings as CDATA sections instead of escaped character data.
+///
+/// Meant for text containing markup, like HTML snippets in descriptions.
+pub struct CData {}
+
+impl SerializeVia<str> for CData {
+    fn serialize_via<W: io::Write>(data: &str, sink: &mut EventWriter<W>,
+                                   _options: &SerializerOptions, name: &OwnedName)
             -> Result<(), Error> {
         let elemname = name.borrow();
-        try!(sink.write(
-            XmlEvent::StartElement { name: elemname.clone(),
+        sink.write(
+            XmlEvent::StartElement { name: elemname,
                                      attributes: Cow::Owned(Vec::new()),
-                                     namespace: Cow::Owned(Namespace::empty()) }
-        ));
-        try!(sink.write(XmlEvent::Characters(&try!(T::to_characters(data)))));
-        try!(sink.write(XmlEvent::EndElement { name: Some(elemname) }));
+                                     namespace: Cow::Owned(element_namespace(name)) }
+        )?;
+        write_cdata(sink, data)?;
+        sink.write(XmlEvent::EndElement { name: Some(elemname) })?;
         Ok(())
     }
 }
+
+impl SerializeVia<String> for CData {
+    fn serialize_via<W: io::Write>(data: &String, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
+            -> Result<(), Error> {
+        <CData as SerializeVia<str>>::serialize_via(data, sink, options, name)
+    }
+}
+
+/// Writes free text as character data,
+/// or as CDATA sections when `SerializerOptions::text_as_cdata` is set
+pub struct Text {}
+
+impl SerializeVia<str> for Text {
+    fn serialize_via<W: io::Write>(data: &str, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
+            -> Result<(), Error> {
+        if options.text_as_cdata {
+            <CData as SerializeVia<str>>::serialize_via(data, sink, options, name)
+        } else {
+            <::xsd::conv::String as SerializeVia<str>>::serialize_via(data, sink, options, name)
+        }
+    }
+}
+
+impl SerializeVia<String> for Text {
+    fn serialize_via<W: io::Write>(data: &String, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
+            -> Result<(), Error> {
+        <Text as SerializeVia<str>>::serialize_via(data, sink, options, name)
+    }
+}
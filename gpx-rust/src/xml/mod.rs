@@ -15,20 +15,27 @@
 
 extern crate xml;
 
+use std::borrow::Cow;
+use std::fmt;
 use std::io;
 use std::io::Read;
+use std::error::Error as ErrorTrait;
 
 use self::xml::name::OwnedName;
 use self::xml::attribute::OwnedAttribute;
-use self::xml::namespace::Namespace;
-use self::xml::reader::{ EventReader, XmlEvent };
-use self::xml::common::{ XmlVersion };
+use self::xml::namespace::{ Namespace, NS_NO_PREFIX };
+use self::xml::reader::{ ParserConfig, EventReader, XmlEvent };
+use self::xml::common::{ Position, TextPosition, XmlVersion };
 
-use ::par::{ Positioned, ElementParse };
+use ::par::{ Positioned, ElementParse, Context, ParserOptions };
+use gpx::ext::Extension;
 
 pub mod conv;
 pub mod par;
 mod ser;
+pub mod slice;
+#[cfg(feature = "quick-xml")]
+pub mod quick;
 
 type DataError = Positioned<::gpx::par::Error>;
 
@@ -83,38 +90,160 @@ impl From<&'static str> for Error {
     fn from(msg: &'static str) -> Error { Error::Str(msg) }
 }
 
+impl fmt::Display for DocumentError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            DocumentError::ParserError(ref e) => write!(fmt, "XML parser error: {}", e),
+            DocumentError::DocumentParserError(ref e) => write!(fmt, "{}", e),
+            DocumentError::BadData(ref e) => write!(fmt, "{}", e),
+        }
+    }
+}
+
+impl ErrorTrait for DocumentError {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        match *self {
+            DocumentError::ParserError(ref e) => Some(e),
+            DocumentError::DocumentParserError(ref e) => e.source(),
+            DocumentError::BadData(ref e) => e.source(),
+        }
+    }
+}
+
+impl fmt::Display for DocumentParserError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            DocumentParserError::UnexpectedEventPreStart(ref ev) => {
+                write!(fmt, "Unexpected event before the document: {:?}", ev)
+            }
+            DocumentParserError::UnexpectedEventInside(ref ev) => {
+                write!(fmt, "Unexpected event outside the root element: {:?}", ev)
+            }
+        }
+    }
+}
+
+impl ErrorTrait for DocumentParserError {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::Str(msg) => write!(fmt, "{}", msg),
+            Error::Io(ref e) => write!(fmt, "I/O error: {}", e),
+            Error::Xml(ref e) => write!(fmt, "XML parser error: {}", e),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        match *self {
+            Error::Str(_) => None,
+            Error::Io(ref e) => Some(e),
+            Error::Xml(ref e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for ElementError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ElementError::UnexpectedEnd => write!(fmt, "Unexpected end of the document"),
+            ElementError::UnexpectedEvent(ref ev) => write!(fmt, "Unexpected event: {:?}", ev),
+        }
+    }
+}
+
+impl ErrorTrait for ElementError {}
+
+/// Name has a prefix but no namespace, so it can't be written
 #[derive(Debug)]
+pub struct UnboundPrefix(pub OwnedName);
+
+impl ::ser::FormatError for UnboundPrefix {}
+
+impl fmt::Display for UnboundPrefix {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "Prefix of {} has no namespace", self.0)
+    }
+}
+
+impl ErrorTrait for UnboundPrefix {}
+
+#[derive(Debug, Clone)]
 pub enum Node {
     Text(String),
     Element(OwnedName, Element),
+    /// Kept only with `ParserOptions::keep_markup`, like the following ones
+    Comment(String),
+    /// Target and data
+    ProcessingInstruction(String, Option<String>),
+    CData(String),
+    /// Extension element parsed into a value of a registered type, see `gpx::ext`
+    Typed(OwnedName, Box<dyn Extension>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Element {
     pub attributes: Vec<OwnedAttribute>,
     pub nodes: Vec<Node>,
+    /// Namespaces declared on this element. Recorded only with `keep_markup`.
+    ///
+    /// Namespaces of the names inside are declared when writing even if missing here.
+    pub namespace: Namespace,
 }
 
-fn add_ns_name<'a>(namespaces: &mut Namespace, name: &'a OwnedName) -> () {
-    if let &Some(ref prefix) = &name.prefix {
-        match name.namespace {
-            None => panic!("Prefix with no namespace! {} on {:?}", prefix, name),
-            Some(ref ns_uri) => {
-                namespaces.put(prefix.clone(), ns_uri.clone());
-            },
+impl Default for Element {
+    fn default() -> Self {
+        Element { attributes: Vec::new(),
+                  nodes: Vec::new(),
+                  namespace: Namespace::empty() }
+    }
+}
+
+fn add_ns_name(namespaces: &mut Namespace, name: &OwnedName) -> Result<(), UnboundPrefix> {
+    match (&name.prefix, &name.namespace) {
+        (&Some(_), &None) => return Err(UnboundPrefix(name.clone())),
+        (Some(prefix), Some(ns_uri)) => {
+            namespaces.put(prefix.clone(), ns_uri.clone());
         }
+        (&None, Some(ns_uri)) => {
+            namespaces.put(NS_NO_PREFIX, ns_uri.clone());
+        }
+        (&None, &None) => {}
     }
+    Ok(())
 }
 
 impl Element {
-    /// Returns namespaces used on this node
-    fn get_namespaces(&self, own_name: &OwnedName) -> Namespace {
-        let mut namespaces = Namespace::empty();
-        add_ns_name(&mut namespaces, own_name);
+    /// Appends attributes and nodes of `other` after own ones
+    pub fn merge(&mut self, other: Element) {
+        self.attributes.extend(other.attributes);
+        self.nodes.extend(other.nodes);
+    }
+
+    /// Returns namespaces used or declared on this node
+    fn get_namespaces(&self, own_name: &OwnedName) -> Result<Namespace, UnboundPrefix> {
+        let mut namespaces = self.namespace.clone();
+        add_ns_name(&mut namespaces, own_name)?;
         for attribute in &self.attributes {
-            add_ns_name(&mut namespaces, &attribute.name);
+            add_ns_name(&mut namespaces, &attribute.name)?;
+        }
+        Ok(namespaces)
+    }
+}
+
+/// Stores an element in a place which may already hold data preserved by the parser
+pub trait OptionalElement {
+    fn merge(&mut self, element: Element);
+}
+
+impl OptionalElement for Option<Element> {
+    fn merge(&mut self, element: Element) {
+        match *self {
+            Some(ref mut existing) => existing.merge(element),
+            None => *self = Some(element),
         }
-        namespaces
     }
 }
 
@@ -137,32 +266,278 @@ pub struct DocInfo {
     pub version: XmlVersion,
     pub encoding: String,
     pub standalone: Option<bool>,
+    /// Comments and processing instructions before the root element. Kept only with `keep_markup`.
+    pub prolog: Vec<Node>,
+    /// Comments and processing instructions after the root element. Kept only with `keep_markup`.
+    pub epilog: Vec<Node>,
+    /// Namespaces declared on the root element. Recorded only with `keep_markup`.
+    pub namespace: Namespace,
+}
+
+/// Source of XML events read by the parsers.
+///
+/// Events follow the conventions of an `xml-rs` `EventReader` with the default configuration,
+/// whichever parser produces them, so that the results don't depend on the source.
+/// Line ends come normalized as XML 1.0 requires, see `normalize_line_ends`.
+/// Sources reading the document as written also normalize attribute values,
+/// see `normalize_attribute`, which `xml-rs` doesn't do.
+pub trait EventSource {
+    /// Reads the next event
+    fn next(&mut self) -> Result<XmlEvent, xml::reader::Error>;
+    /// Returns the position where the last event read starts
+    fn position(&self) -> TextPosition;
+}
+
+/// Replaces the line ends `\r\n` and `\r` with `\n`
+pub fn normalize_line_ends(text: &str) -> Cow<'_, str> {
+    if !text.contains('\r') {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+}
+
+/// Replaces line ends and tabs written in an attribute value with spaces.
+///
+/// Applies to the value as written, before references are replaced, so `&#10;` stays a line end.
+pub fn normalize_attribute(value: &str) -> Cow<'_, str> {
+    let whitespace = &['\t', '\n', '\r'][..];
+    if !value.contains(whitespace) {
+        return Cow::Borrowed(value);
+    }
+    Cow::Owned(value.replace("\r\n", " ").replace(whitespace, " "))
+}
+
+/// Applies `normalize` to `text`, keeping it when nothing changes
+fn normalized(text: String, normalize: fn(&str) -> Cow<'_, str>) -> String {
+    let changed = match normalize(&text) {
+        Cow::Owned(changed) => Some(changed),
+        Cow::Borrowed(_) => None,
+    };
+    changed.unwrap_or(text)
+}
+
+/// Offset of the first thing XML doesn't allow in some markup, with the message `xml-rs` gives
+pub type Malformed = (usize, String);
+
+/// Characters allowed in documents of `version`, written as references too
+fn is_xml_char(c: char, version: XmlVersion) -> bool {
+    match version {
+        XmlVersion::Version10 => matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}'
+                                             | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..),
+        XmlVersion::Version11 => matches!(c, '\u{1}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}'
+                                             | '\u{10000}'..),
+    }
 }
 
-pub fn parse_document<R: Read, D: DocumentParserData>(source: R)
+/// Characters allowed as they are in text and tags. XML 1.1 restricts control characters there.
+fn is_literal_char(c: char, version: XmlVersion) -> bool {
+    is_xml_char(c, version) && match version {
+        XmlVersion::Version10 => true,
+        XmlVersion::Version11 => !matches!(c, '\u{1}'..='\u{8}' | '\u{B}'..='\u{C}' | '\u{E}'..='\u{1F}'
+                                              | '\u{7F}'..='\u{84}' | '\u{86}'..='\u{9F}'),
+    }
+}
+
+fn invalid_character(value: u32) -> String {
+    format!("Invalid character U+{:04X}", value)
+}
+
+/// Checks the reference starting with the `&` at `index` of `raw`.
+///
+/// Errors are placed at the `;` ending it. References missing it are left to unescaping.
+fn check_reference(raw: &str, index: usize, version: XmlVersion) -> Result<(), Malformed> {
+    let rest = &raw[index + 1..];
+    let end = match rest.find(&[';', '&', '<'][..]) {
+        Some(end) if rest[end..].starts_with(';') => end,
+        _ => return Ok(()),
+    };
+    let semicolon = index + 1 + end;
+    let name = &rest[..end];
+    let value = match name {
+        "" => return Err((semicolon, "Encountered empty entity".into())),
+        "lt" | "gt" | "amp" | "apos" | "quot" => return Ok(()),
+        _ if name.starts_with("#x") => u32::from_str_radix(&name[2..], 16),
+        _ if name.starts_with('#') => name[1..].parse::<u32>(),
+        _ => return Err((semicolon, format!("Unexpected entity: {}", name))),
+    };
+    let value = value.map_err(|_| (semicolon, format!("Invalid numeric entity: {}", &name[1..])))?;
+    match char::from_u32(value) {
+        Some(c) if is_xml_char(c, version) => Ok(()),
+        _ => Err((semicolon, invalid_character(value))),
+    }
+}
+
+/// Checks the characters and references in text written between markup
+pub fn check_text(raw: &str, version: XmlVersion) -> Result<(), Malformed> {
+    for (index, c) in raw.char_indices() {
+        match c {
+            '&' => check_reference(raw, index, version)?,
+            ']' if raw[index..].starts_with("]]>") => {
+                return Err((index, "Unexpected token: ]]>".into()));
+            }
+            c if !is_literal_char(c, version) => return Err((index, invalid_character(c as u32))),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Checks the characters in CDATA, comments and processing instructions, where nothing is replaced
+pub fn check_literal(raw: &str, version: XmlVersion) -> Result<(), Malformed> {
+    match raw.char_indices().find(|&(_, c)| !is_xml_char(c, version)) {
+        Some((index, c)) => Err((index, invalid_character(c as u32))),
+        None => Ok(()),
+    }
+}
+
+/// Checks a start tag between `<` and `>` or `/>`, which is otherwise well-formed:
+/// the characters and references in it, attribute values holding `<` and attributes given twice.
+///
+/// Namespace declarations given twice are let through, like `xml-rs` does, the last one counting.
+pub fn check_start_tag(raw: &str, version: XmlVersion) -> Result<(), Malformed> {
+    let mut quote = None;
+    // where the name being read starts
+    let mut name_start = None;
+    // the element name comes first
+    let mut names: Option<Vec<&str>> = None;
+    for (index, c) in raw.char_indices() {
+        match quote {
+            Some(quote_char) => match c {
+                c if c == quote_char => quote = None,
+                '&' => check_reference(raw, index, version)?,
+                '<' => return Err((index, "'<' is not allowed in attributes".into())),
+                c if !is_literal_char(c, version) => {
+                    return Err((index, invalid_character(c as u32)));
+                }
+                _ => {}
+            },
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                ' ' | '\t' | '\r' | '\n' | '=' | '/' => {
+                    if let Some(start) = name_start.take() {
+                        let name = &raw[start..index];
+                        match names {
+                            Some(_) if name == "xmlns" || name.starts_with("xmlns:") => {}
+                            Some(ref mut names) => {
+                                if names.contains(&name) {
+                                    return Err((index, format!("Attribute '{}' is redefined", name)));
+                                }
+                                names.push(name);
+                            }
+                            None => names = Some(Vec::new()),
+                        }
+                    }
+                }
+                c if !is_literal_char(c, version) => {
+                    return Err((index, format!("Unexpected token inside opening tag: {}", c)));
+                }
+                _ => {
+                    name_start.get_or_insert(index);
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+/// `xml-rs` leaves line ends as written, so they are normalized here.
+///
+/// Line ends written as references can't be told apart at this point, and get normalized too.
+/// Attribute values are left alone, because their references are already replaced,
+/// so whitespace written in them literally stays as written.
+impl<R: Read> EventSource for EventReader<R> {
+    fn next(&mut self) -> Result<XmlEvent, xml::reader::Error> {
+        Ok(match EventReader::next(self)? {
+            XmlEvent::Characters(text) => {
+                XmlEvent::Characters(normalized(text, normalize_line_ends))
+            }
+            XmlEvent::Whitespace(text) => {
+                XmlEvent::Whitespace(normalized(text, normalize_line_ends))
+            }
+            XmlEvent::CData(text) => XmlEvent::CData(normalized(text, normalize_line_ends)),
+            XmlEvent::Comment(text) => XmlEvent::Comment(normalized(text, normalize_line_ends)),
+            XmlEvent::ProcessingInstruction { name, data } => {
+                XmlEvent::ProcessingInstruction {
+                    name,
+                    data: data.map(|data| normalized(data, normalize_line_ends)),
+                }
+            }
+            event => event,
+        })
+    }
+    fn position(&self) -> TextPosition {
+        Position::position(self)
+    }
+}
+
+impl<S: EventSource + ?Sized> EventSource for &mut S {
+    fn next(&mut self) -> Result<XmlEvent, xml::reader::Error> {
+        (**self).next()
+    }
+    fn position(&self) -> TextPosition {
+        (**self).position()
+    }
+}
+
+/// Parses the document in `source` with `xml-rs`
+pub fn parse_document<R: Read, D: DocumentParserData>(source: R, ctx: &mut Context)
+        -> Result<Document<D::Contents>, D::Error> {
+    let reader = ParserConfig::new().ignore_comments(!ctx.options.keep_markup)
+                                    .create_reader(source);
+    parse_events::<_, D>(reader, ctx)
+}
+
+/// Parses the document read from any source of events
+pub fn parse_events<S: EventSource, D: DocumentParserData>(mut reader: S, ctx: &mut Context)
         -> Result<Document<D::Contents>, D::Error> {
-    let mut reader = EventReader::new(source);
     let mut info = None;
     let mut contents = D::default();
     let mut state = ParserState::PreStart;
+    let mut after_root = false;
     loop {
-        let next = try!(reader.next());
+        let next = reader.next()?;
         state = match state {
             ParserState::PreStart => match next {
                 XmlEvent::StartDocument { version, encoding, standalone } => {
-                    info = Some(DocInfo { version: version,
-                                          encoding: encoding,
-                                          standalone: standalone });
+                    info = Some(DocInfo { version,
+                                          encoding,
+                                          standalone,
+                                          prolog: Vec::new(),
+                                          epilog: Vec::new(),
+                                          namespace: Namespace::empty() });
                     ParserState::Inside
                 }
                 ev => return Err(DocumentParserError::UnexpectedEventPreStart(ev).into())
             },
             ParserState::Inside => match next {
-                XmlEvent::StartElement { name, attributes, namespace: _ } => {
-                    try!(contents.parse_element(&mut reader, &name, &attributes));
+                XmlEvent::StartElement { name, attributes, namespace } => {
+                    let outer = ctx.enter_scope(namespace);
+                    if let Some(ref mut info) = info {
+                        info.namespace = ctx.declared().clone();
+                    }
+                    ctx.path.enter(&name);
+                    ctx.count_element(attributes.len()).map_err(|e| {
+                        ctx.locate(Positioned::with_position(e, reader.position()))
+                    })?;
+                    contents.parse_element(&mut reader, ctx, &name, &attributes)
+                            .map_err(|e| ctx.locate(e))?;
+                    ctx.path.leave();
+                    ctx.leave_scope(outer);
+                    if ctx.stopped {
+                        break;
+                    }
+                    after_root = true;
+                    ParserState::Inside
+                }
+                XmlEvent::Comment(data) => {
+                    keep_outside_root(ctx, &mut info, after_root, Node::Comment(data));
+                    ParserState::Inside
+                }
+                XmlEvent::ProcessingInstruction { name, data } => {
+                    keep_outside_root(ctx, &mut info, after_root,
+                                      Node::ProcessingInstruction(name, data));
                     ParserState::Inside
                 }
-                // TODO: more events
                 XmlEvent::EndDocument => ParserState::PostEnd,
                 ev => return Err(DocumentParserError::UnexpectedEventInside(ev).into())
             },
@@ -171,15 +546,29 @@ pub fn parse_document<R: Read, D: DocumentParserData>(source: R)
     }
     Ok(Document {
         info: info.unwrap(),
-        data: try!(contents.build())
+        data: contents.build()?
     })
 }
 
+/// Stores markup found around the root element if keeping it
+fn keep_outside_root(ctx: &Context, info: &mut Option<DocInfo>, after_root: bool, node: Node) {
+    if !ctx.options.keep_markup {
+        return;
+    }
+    if let Some(ref mut info) = *info {
+        if after_root {
+            info.epilog.push(node);
+        } else {
+            info.prolog.push(node);
+        }
+    }
+}
+
 pub trait DocumentParserData where Self: Sized + Default {
     type Contents;
     type Error: From<xml::reader::Error> + From<DocumentParserError> + From<DataError>;
     // public iface
-    fn parse_element<R: Read>(&mut self, reader: &mut EventReader<R>,
+    fn parse_element<R: EventSource>(&mut self, reader: &mut R, ctx: &mut Context,
                               name: &OwnedName, attributes: &[OwnedAttribute])
             -> Result<(), DataError>;
     fn build(self) -> Result<Self::Contents, Self::Error>;
@@ -191,10 +580,10 @@ struct ParserData(Vec<Node>);
 impl DocumentParserData for ParserData {
     type Contents = Vec<Node>;
     type Error = DocumentError;
-    fn parse_element<R: Read>(&mut self, mut reader: &mut EventReader<R>,
+    fn parse_element<R: EventSource>(&mut self, reader: &mut R, ctx: &mut Context,
                               name: &OwnedName, attributes: &[OwnedAttribute])
             -> Result<(), DataError> {
-        let elem = try!(ElementParser::new().parse(name, attributes, &mut reader));
+        let elem = ElementParser::new().parse(name, attributes, reader, ctx)?;
         self.0.push(Node::Element(name.clone(), elem));
         Ok(())
     }
@@ -204,5 +593,7 @@ impl DocumentParserData for ParserData {
 }
 
 pub fn parse<R: Read>(source: R) -> Result<Document<Vec<Node>>, DocumentError> {
-    parse_document::<R, ParserData>(source)
+    let options = ParserOptions::default();
+    let mut warnings = Vec::new();
+    parse_document::<R, ParserData>(source, &mut Context::new(&options, &mut warnings))
 }
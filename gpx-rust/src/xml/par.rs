@@ -13,68 +13,144 @@
 
 extern crate xml as _xml;
 
-use std::io::Read;
+use std::fmt;
 use std::error::Error as ErrorTrait;
 
-use self::_xml::reader::EventReader;
+use self::_xml::common::TextPosition;
 use self::_xml::name::OwnedName;
 use self::_xml::attribute::OwnedAttribute;
+use self::_xml::namespace::Namespace;
 
-use par::{ ParseVia, Positioned, ElementParse, ElementBuild };
+use par::{ ParseVia, Positioned, ElementParse, ElementBuild, Context };
 
 use xml;
 use xml::conv;
-use xml::{ Element, Node };
+use xml::{ Element, Node, EventSource };
 
 use gpx::par::Error;
 
 #[derive(Debug)]
 pub enum BuildError {
-    Custom(Box<ErrorTrait>)
+    Custom(Box<dyn ErrorTrait + Send + Sync>),
+    /// Required attribute not present
+    MissingAttribute { element: OwnedName, attribute: OwnedName },
+    /// Required sub-element not present
+    MissingElement { element: OwnedName, child: OwnedName },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            BuildError::Custom(ref e) => write!(fmt, "{}", e),
+            BuildError::MissingAttribute { ref element, ref attribute } => {
+                write!(fmt, "Missing required attribute {} on {}", attribute, element)
+            }
+            BuildError::MissingElement { ref element, ref child } => {
+                write!(fmt, "Missing required element {} in {}", child, element)
+            }
+        }
+    }
+}
+
+impl ErrorTrait for BuildError {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        match *self {
+            BuildError::Custom(ref e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the value of a required attribute, or an error if it was not present.
+///
+/// `attribute` is the local name of the attribute on `element`.
+pub fn required_attribute<T>(value: Option<T>, element: &OwnedName, attribute: &str)
+        -> Result<T, BuildError> {
+    value.ok_or_else(|| BuildError::MissingAttribute { element: element.clone(),
+                                                       attribute: OwnedName::local(attribute) })
 }
 
-#[derive(Default)]
 pub struct ElementParser {
     //name: Option<OwnedName>, // Using reference intentionally - this code does not need to interact with Name
     attributes: Vec<OwnedAttribute>,
     nodes: Vec<Node>,
+    namespace: Namespace,
+    /// Children are typed according to `ParserOptions::extensions`
+    typed: bool,
+}
+
+impl Default for ElementParser {
+    fn default() -> Self {
+        ElementParser { attributes: Vec::new(),
+                        nodes: Vec::new(),
+                        namespace: Namespace::empty(),
+                        typed: false }
+    }
+}
+
+impl ElementParser {
+    /// Parses an `extensions` element, reading registered children into typed nodes
+    pub fn extensions() -> Self {
+        ElementParser { typed: true, ..ElementParser::default() }
+    }
 }
 
 impl ElementBuild for ElementParser {
     type Element = Element;
     type BuildError = BuildError;
-    fn build(self) -> Result<Self::Element, Self::BuildError> {
+    fn build(self, name: &OwnedName) -> Result<Self::Element, Self::BuildError> {
+        let _ = name;
         Ok(Element {
             //name: self.name.unwrap().to_owned(),
             attributes: self.attributes,
-            nodes: self.nodes
+            nodes: self.nodes,
+            namespace: self.namespace,
         })
     }
 }
 
 impl ElementParse<::gpx::par::Error> for ElementParser {
-    fn parse_start(&mut self, attributes: &[OwnedAttribute])
+    fn parse_start(&mut self, ctx: &mut Context, name: &OwnedName, attributes: &[OwnedAttribute],
+                   position: TextPosition)
             -> Result<(), ::par::AttributeError<::gpx::par::Error>> {
-        let _ = attributes; // FIXME: break if attributes present
+        let _ = (name, position);
+        self.attributes.extend_from_slice(attributes);
+        self.namespace = ctx.declared().clone();
         Ok(())
     }
-    fn parse_element<'a, R: Read>(&mut self, reader: &'a mut EventReader<R>,
+    fn parse_element<R: EventSource>(&mut self, reader: &mut R, ctx: &mut Context,
                                   name: &OwnedName, attributes: &[OwnedAttribute])
             -> Result<(), Positioned<::gpx::par::Error>> {
-        let elem = try!(ElementParser::new().parse(name, attributes, reader));
-        self.nodes.push(Node::Element(name.clone(), elem));
+        let node = if self.typed {
+            let options = ctx.options;
+            options.extensions.parse(reader, ctx, name, attributes)?
+        } else {
+            Node::Element(name.clone(), ElementParser::new().parse(name, attributes, reader, ctx)?)
+        };
+        self.nodes.push(node);
         Ok(())
     }
     fn parse_characters(&mut self, data: String) -> Result<(), ::gpx::par::Error> {
         self.nodes.push(Node::Text(data));
         Ok(())
     }
+    /// CDATA sections become text unless keeping markup
+    fn parse_cdata(&mut self, ctx: &mut Context, data: String) -> Result<(), ::gpx::par::Error> {
+        self.nodes.push(if ctx.options.keep_markup { Node::CData(data) } else { Node::Text(data) });
+        Ok(())
+    }
+    /// Markup stays in place if kept
+    fn parse_markup(&mut self, ctx: &mut Context, node: Node) {
+        if ctx.options.keep_markup {
+            self.nodes.push(node);
+        }
+    }
 }
 
 impl ParseVia<xml::Element> for conv::Element {
-    fn parse_via<R: Read>(parser: &mut EventReader<R>,
-                              name: &OwnedName, attributes: &[OwnedAttribute])
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut Context,
+                          name: &OwnedName, attributes: &[OwnedAttribute])
             -> Result<xml::Element, Positioned<Error>> {
-        ElementParser::new().parse(name, attributes, parser)
+        ElementParser::new().parse(name, attributes, parser, ctx)
     }
 }
@@ -0,0 +1,510 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Event source backed by `quick-xml`, enabled by the `quick-xml` feature.
+//!
+//! About twice as fast as `xml-rs` on big files (`cargo bench --features quick-xml`).
+//! Well-formed documents give the same events at the same positions,
+//! and malformed ones are refused like `xml-rs` refuses them.
+//! Characters XML doesn't allow, wrong references, `<` in attribute values,
+//! attributes given twice, unbound prefixes and mismatched closing tags
+//! are reported with the message and position `xml-rs` gives.
+//! Other syntax errors are found by `quick-xml` and worded by it.
+//!
+//! ```ignore
+//! let f = BufReader::new(File::open("foo").unwrap());
+//! let options = ParserOptions::default();
+//! let (document, warnings) = gpx::par::parse_from(QuickReader::new(f, &options), &options).unwrap();
+//! ```
+//!
+//! Only UTF-8 documents are supported. Others are refused with an `Unsupported encoding` error.
+
+extern crate xml as _xml;
+extern crate quick_xml;
+extern crate memchr;
+
+use std::borrow::Cow;
+use std::cmp;
+use std::fmt;
+use std::io;
+use std::io::{ BufRead, Read };
+use std::mem;
+use std::str;
+use std::rc::Rc;
+use std::collections::VecDeque;
+
+use self::_xml::common::{ TextPosition, XmlVersion };
+use self::_xml::name::OwnedName;
+use self::_xml::attribute::OwnedAttribute;
+use self::_xml::namespace::{ Namespace, NS_NO_PREFIX, NS_EMPTY_URI, NS_XML_PREFIX, NS_XML_URI,
+                             NS_XMLNS_PREFIX, NS_XMLNS_URI };
+use self::_xml::reader::{ Error, XmlEvent };
+use self::quick_xml::Reader;
+use self::quick_xml::escape;
+use self::quick_xml::events::{ Event, BytesStart };
+
+use par::ParserOptions;
+use xml::{ EventSource, Malformed, check_literal, check_start_tag, check_text, normalize_line_ends,
+           normalize_attribute };
+
+
+/// Keeps the text position of the bytes consumed by the parser
+struct Tracked<B> {
+    inner: B,
+    position: TextPosition,
+    /// Position of the first `<` consumed since last taken, where the markup being read starts
+    markup_start: Option<TextPosition>,
+}
+
+impl<B: BufRead> Read for Tracked<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = {
+            let available = self.fill_buf()?;
+            let count = cmp::min(available.len(), buf.len());
+            buf[..count].copy_from_slice(&available[..count]);
+            count
+        };
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl<B: BufRead> BufRead for Tracked<B> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+    fn consume(&mut self, amount: usize) {
+        if let Ok(available) = self.inner.fill_buf() {
+            let consumed = &available[..cmp::min(amount, available.len())];
+            if self.markup_start.is_none() {
+                if let Some(index) = memchr::memchr(b'<', consumed) {
+                    let mut start = self.position;
+                    advance(&mut start, &consumed[..index]);
+                    self.markup_start = Some(start);
+                }
+            }
+            advance(&mut self.position, consumed);
+        }
+        self.inner.consume(amount);
+    }
+}
+
+/// Moves `position` past `bytes`, counting lines and characters a chunk at a time
+fn advance(position: &mut TextPosition, bytes: &[u8]) {
+    let line = match memchr::memrchr(b'\n', bytes) {
+        Some(index) => {
+            position.row += memchr::memchr_iter(b'\n', bytes).count() as u64;
+            position.column = 0;
+            &bytes[index + 1..]
+        }
+        None => bytes,
+    };
+    // columns count characters, not bytes
+    let continuations = line.iter().filter(|&&byte| byte & 0xC0 == 0x80).count();
+    position.column += (line.len() - continuations) as u64;
+}
+
+/// Namespaces in scope outside of the root element
+fn default_scope() -> Namespace {
+    let mut scope = Namespace::empty();
+    scope.force_put(NS_XML_PREFIX, NS_XML_URI);
+    scope.force_put(NS_XMLNS_PREFIX, NS_XMLNS_URI);
+    scope.force_put(NS_NO_PREFIX, NS_EMPTY_URI);
+    scope
+}
+
+/// Returns the position `columns` characters before `position` on the same line
+fn before(mut position: TextPosition, columns: u64) -> TextPosition {
+    position.column -= columns;
+    position
+}
+
+/// Returns the position `columns` characters after `position` on the same line
+fn after(mut position: TextPosition, columns: u64) -> TextPosition {
+    position.column += columns;
+    position
+}
+
+fn error_at<E: fmt::Display>(position: TextPosition, error: E) -> Error {
+    Error::from((&position, error.to_string()))
+}
+
+fn is_whitespace(byte: &u8) -> bool {
+    matches!(*byte, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+/// Reads XML events with `quick-xml`, producing them like `xml-rs` does
+pub struct QuickReader<B: BufRead> {
+    reader: Reader<Tracked<B>>,
+    buffer: Vec<u8>,
+    ignore_comments: bool,
+    /// Namespaces in scope of each open element, after the ones outside the root.
+    ///
+    /// Elements declaring no namespaces share the scope of their parent.
+    scopes: Vec<Rc<Namespace>>,
+    /// Names of open elements, as written
+    open: Vec<String>,
+    version: XmlVersion,
+    /// Events read ahead
+    pending: VecDeque<(XmlEvent, TextPosition)>,
+    /// Start of the last event returned
+    position: TextPosition,
+    started: bool,
+    seen_root: bool,
+    finished: bool,
+}
+
+impl<B: BufRead> QuickReader<B> {
+    /// Prepares to read `source` for parsing with `options`
+    pub fn new(source: B, options: &ParserOptions) -> Self {
+        let mut reader = Reader::from_reader(Tracked { inner: source,
+                                                       position: TextPosition::new(),
+                                                       markup_start: None });
+        reader.trim_text(false);
+        // closing tags are checked here, like `xml-rs` does
+        reader.check_end_names(false);
+        QuickReader { reader,
+                      buffer: Vec::new(),
+                      ignore_comments: !options.keep_markup,
+                      scopes: vec![Rc::new(default_scope())],
+                      open: Vec::new(),
+                      version: XmlVersion::Version10,
+                      pending: VecDeque::new(),
+                      position: TextPosition::new(),
+                      started: false,
+                      seen_root: false,
+                      finished: false }
+    }
+
+    fn error<E: fmt::Display>(&self, error: E) -> Error {
+        error_at(self.reader.get_ref().position, error)
+    }
+
+    /// Places the error found in `raw`, which starts at `start`
+    fn malformed(&self, start: TextPosition, raw: &str, malformed: Malformed) -> Error {
+        let (offset, message) = malformed;
+        let mut position = start;
+        advance(&mut position, &raw.as_bytes()[..offset]);
+        error_at(position, message)
+    }
+
+    fn str<'b>(&self, bytes: &'b [u8]) -> Result<&'b str, Error> {
+        str::from_utf8(bytes).map_err(|e| self.error(e))
+    }
+
+    /// Checks markup where nothing is replaced, starting at `start`
+    fn check_literal(&self, raw: &[u8], start: TextPosition) -> Result<(), Error> {
+        let raw = self.str(raw)?;
+        check_literal(raw, self.version).map_err(|malformed| self.malformed(start, raw, malformed))
+    }
+
+    /// Decodes markup other than text and attribute values, with its line ends normalized
+    fn utf8(&self, bytes: &[u8]) -> Result<String, Error> {
+        str::from_utf8(bytes).map(|text| normalize_line_ends(text).into_owned())
+                             .map_err(|e| self.error(e))
+    }
+
+    /// Splits `qname` into prefix and local name, and finds its namespace in `scope`.
+    ///
+    /// Unprefixed names are in the default namespace only if they belong to elements.
+    /// Unbound prefixes are reported at `end`, where the tag ends.
+    fn resolve(&self, scope: &Namespace, qname: String, element: bool, end: TextPosition)
+            -> Result<OwnedName, Error> {
+        let (prefix, local_name) = match qname.find(':') {
+            Some(index) => (Some(qname[..index].to_owned()), qname[index + 1..].to_owned()),
+            None => (None, qname.clone()),
+        };
+        let namespace = match prefix {
+            Some(ref prefix) => match scope.get(prefix) {
+                Some(uri) => Some(uri),
+                None => {
+                    let kind = if element { "Element" } else { "Attribute" };
+                    return Err(error_at(end, format!("{} {} prefix is unbound", kind, qname)));
+                }
+            },
+            None if element => scope.get(NS_NO_PREFIX),
+            None => None,
+        };
+        Ok(OwnedName { local_name,
+                       namespace: match namespace {
+                           Some("") | None => None,
+                           Some(uri) => Some(uri.to_owned()),
+                       },
+                       prefix })
+    }
+
+    /// Unescapes `raw` text after normalizing it with `normalize`
+    fn unescape(&self, raw: &[u8], normalize: fn(&str) -> Cow<'_, str>) -> Result<String, Error> {
+        let raw = self.str(raw)?;
+        let normalized = normalize(raw);
+        let text = escape::unescape(&normalized).map_err(|e| self.error(e))?;
+        Ok(text.into_owned())
+    }
+
+    /// Opens the scope of the element and returns its start event.
+    ///
+    /// The tag is at `position` and ends at `end`.
+    fn start_element(&mut self, start: &BytesStart, position: TextPosition, end: TextPosition)
+            -> Result<XmlEvent, Error> {
+        let raw = self.str(start)?;
+        check_start_tag(raw, self.version)
+            .map_err(|malformed| self.malformed(after(position, 1), raw, malformed))?;
+        let parent = match self.scopes.last() {
+            Some(scope) => scope.clone(),
+            None => Rc::new(default_scope()),
+        };
+        let mut declared: Option<Namespace> = None;
+        let mut attributes = Vec::new();
+        for attribute in start.attributes().with_checks(false) {
+            let attribute = attribute.map_err(|e| self.error(e))?;
+            let key = self.utf8(attribute.key.as_ref())?;
+            let value = self.unescape(&attribute.value, normalize_attribute)?;
+            if key == "xmlns" {
+                declared.get_or_insert_with(|| (*parent).clone()).force_put(NS_NO_PREFIX, value);
+            } else if let Some(prefix) = key.strip_prefix("xmlns:") {
+                declared.get_or_insert_with(|| (*parent).clone()).force_put(prefix, value);
+            } else {
+                attributes.push((key, value));
+            }
+        }
+        let scope = match declared {
+            Some(scope) => Rc::new(scope),
+            None => parent,
+        };
+        let qname = self.utf8(start.name().as_ref())?;
+        let name = self.resolve(&scope, qname.clone(), true, end)?;
+        let mut resolved = Vec::with_capacity(attributes.len());
+        for (key, value) in attributes {
+            resolved.push(OwnedAttribute { name: self.resolve(&scope, key, false, end)?,
+                                           value });
+        }
+        let namespace = (*scope).clone();
+        self.scopes.push(scope);
+        self.open.push(qname);
+        self.seen_root = true;
+        Ok(XmlEvent::StartElement { name, attributes: resolved, namespace })
+    }
+
+    /// Closes the scope of the element called `qname` and returns its end event.
+    ///
+    /// The tag is at `position` and ends at `end`.
+    fn end_element(&mut self, qname: &[u8], position: TextPosition, end: TextPosition)
+            -> Result<XmlEvent, Error> {
+        let qname = self.utf8(qname)?;
+        let scope = match (self.open.last(), self.scopes.last()) {
+            (Some(_), Some(scope)) => scope,
+            _ => return Err(error_at(position, "Unexpected token: </")),
+        };
+        let name = self.resolve(scope, qname.clone(), true, end)?;
+        match self.open.last() {
+            Some(open) if *open == qname => {}
+            Some(open) => {
+                let expected = self.resolve(scope, open.clone(), true, end)?;
+                return Err(error_at(end, format!("Unexpected closing tag: {} != {}",
+                                                 name, expected)));
+            }
+            None => {}
+        }
+        self.open.pop();
+        self.scopes.pop();
+        Ok(XmlEvent::EndElement { name })
+    }
+
+    /// Reads one event from `quick-xml` into `buffer`.
+    ///
+    /// Returns `None` for events which `xml-rs` doesn't report.
+    fn convert(&mut self, buffer: &mut Vec<u8>) -> Result<Option<(XmlEvent, TextPosition)>, Error> {
+        let text_start = self.reader.get_ref().position;
+        let event = self.reader.read_event_into(buffer).map_err(|e| self.error(e))?;
+        let end = self.reader.get_ref().position;
+        let position = match event {
+            // the text may have consumed the `<` of the markup after it
+            Event::Text(_) | Event::Eof => text_start,
+            _ => self.reader.get_mut().markup_start.take().unwrap_or(self.position),
+        };
+        let event = match event {
+            Event::Decl(decl) => {
+                let version = decl.version().map_err(|e| self.error(e))?;
+                let version = match &version as &[u8] {
+                    b"1.0" => XmlVersion::Version10,
+                    b"1.1" => XmlVersion::Version11,
+                    other => {
+                        return Err(self.error(format!("Invalid XML version: {}",
+                                                      String::from_utf8_lossy(other))));
+                    }
+                };
+                let encoding = match decl.encoding() {
+                    Some(encoding) => self.utf8(&encoding.map_err(|e| self.error(e))?)?,
+                    None => "UTF-8".into(),
+                };
+                if !encoding.eq_ignore_ascii_case("UTF-8") {
+                    return Err(error_at(before(end, 2), format!("Unsupported encoding: {}", encoding)));
+                }
+                let standalone = match decl.standalone() {
+                    Some(standalone) => match &standalone.map_err(|e| self.error(e))? as &[u8] {
+                        b"yes" => Some(true),
+                        b"no" => Some(false),
+                        _ => return Err(self.error("Invalid standalone declaration")),
+                    },
+                    None => None,
+                };
+                self.version = version;
+                XmlEvent::StartDocument { version, encoding, standalone }
+            }
+            Event::Start(start) => self.start_element(&start, position, before(end, 1))?,
+            Event::Empty(start) => {
+                let event = self.start_element(&start, position, before(end, 2))?;
+                let end_event = self.end_element(start.name().as_ref(), position, before(end, 2))?;
+                self.pending.push_back((end_event, position));
+                event
+            }
+            Event::End(tag) => self.end_element(tag.name().as_ref(), position, before(end, 1))?,
+            Event::Text(text) => {
+                if text.is_empty() {
+                    return Ok(None);
+                }
+                let whitespace = text.iter().all(is_whitespace);
+                if self.scopes.len() == 1 {
+                    if whitespace {
+                        return Ok(None);
+                    }
+                    return Err(self.error("Unexpected characters outside the root element"));
+                }
+                let raw = self.str(&text)?;
+                check_text(raw, self.version)
+                    .map_err(|malformed| self.malformed(text_start, raw, malformed))?;
+                let text = self.unescape(&text, normalize_line_ends)?;
+                if whitespace { XmlEvent::Whitespace(text) } else { XmlEvent::Characters(text) }
+            }
+            Event::CData(data) => {
+                self.check_literal(&data, after(position, "<![CDATA[".len() as u64))?;
+                XmlEvent::CData(self.utf8(&data)?)
+            }
+            Event::Comment(comment) => {
+                self.check_literal(&comment, after(position, "<!--".len() as u64))?;
+                if self.ignore_comments {
+                    return Ok(None);
+                }
+                XmlEvent::Comment(self.utf8(&comment)?)
+            }
+            Event::PI(instruction) => {
+                self.check_literal(&instruction, after(position, "<?".len() as u64))?;
+                let instruction = self.utf8(&instruction)?;
+                let (name, data) = match instruction.find(is_whitespace_char) {
+                    Some(index) => (instruction[..index].to_owned(),
+                                    Some(instruction[index..].trim_start().to_owned())),
+                    None => (instruction, None),
+                };
+                XmlEvent::ProcessingInstruction { name, data }
+            }
+            Event::DocType(_) => return Ok(None),
+            Event::Eof => {
+                if self.scopes.len() > 1 {
+                    return Err(self.error("Unexpected end of stream: still inside the root element"));
+                }
+                if !self.seen_root {
+                    return Err(self.error("Unexpected end of stream: no root element found"));
+                }
+                XmlEvent::EndDocument
+            }
+        };
+        Ok(Some((event, position)))
+    }
+
+    /// Tells if the document starts like one in UTF-16, which `quick-xml` can't read
+    fn is_utf16(&mut self) -> bool {
+        match self.reader.get_mut().fill_buf() {
+            Ok(start) => [[0xFE, 0xFF], [0xFF, 0xFE], [0, b'<'], [b'<', 0]].iter()
+                                                                         .any(|mark| start.starts_with(mark)),
+            // the error comes again when reading
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the next event reported by `xml-rs`, without joining text
+    fn read(&mut self) -> Result<(XmlEvent, TextPosition), Error> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(event);
+        }
+        loop {
+            let mut buffer = mem::take(&mut self.buffer);
+            buffer.clear();
+            let result = self.convert(&mut buffer);
+            self.buffer = buffer;
+            if let Some(event) = result? {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Joins `text` with the text following it, like `xml-rs` does around skipped comments
+    fn join_text(&mut self, mut text: String, mut whitespace: bool) -> Result<XmlEvent, Error> {
+        loop {
+            match self.read()? {
+                (XmlEvent::Characters(more), _) => {
+                    text.push_str(&more);
+                    whitespace = false;
+                }
+                (XmlEvent::Whitespace(more), _) => text.push_str(&more),
+                event => {
+                    self.pending.push_front(event);
+                    break;
+                }
+            }
+        }
+        Ok(if whitespace { XmlEvent::Whitespace(text) } else { XmlEvent::Characters(text) })
+    }
+}
+
+fn is_whitespace_char(c: char) -> bool {
+    c.is_ascii() && is_whitespace(&(c as u8))
+}
+
+impl<B: BufRead> EventSource for QuickReader<B> {
+    fn next(&mut self) -> Result<XmlEvent, Error> {
+        if self.finished {
+            return Ok(XmlEvent::EndDocument);
+        }
+        if !self.started && self.is_utf16() {
+            return Err(error_at(TextPosition::new(), "Unsupported encoding: UTF-16"));
+        }
+        let (event, position) = self.read()?;
+        if !self.started {
+            self.started = true;
+            match event {
+                XmlEvent::StartDocument { .. } => {}
+                // there's no declaration
+                event => {
+                    self.pending.push_front((event, position));
+                    self.position = TextPosition::new();
+                    return Ok(XmlEvent::StartDocument { version: XmlVersion::Version10,
+                                                        encoding: "UTF-8".into(),
+                                                        standalone: None });
+                }
+            }
+        }
+        self.position = position;
+        match event {
+            XmlEvent::Characters(text) => self.join_text(text, false),
+            XmlEvent::Whitespace(text) => self.join_text(text, true),
+            XmlEvent::EndDocument => {
+                self.finished = true;
+                Ok(XmlEvent::EndDocument)
+            }
+            event => Ok(event),
+        }
+    }
+    fn position(&self) -> TextPosition {
+        self.position
+    }
+}
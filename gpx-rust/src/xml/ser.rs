@@ -19,16 +19,19 @@ use std::borrow::Cow;
 use self::_xml::name::OwnedName;
 use self::_xml::writer::{ EventWriter, XmlEvent };
 
-use ser::{ SerializeVia, Error };
+use ser;
+use ser::{ SerializeDocument, SerializeVia, SerializerOptions, Error };
 
 use xml;
 use xml::conv;
 
 /// Special handling of namespaces
 impl SerializeVia<xml::Element> for conv::Element {
-    fn serialize_via<W: Write>(data: &xml::Element, sink: &mut EventWriter<W>, name: &OwnedName) 
+    fn serialize_via<W: Write>(data: &xml::Element, sink: &mut EventWriter<W>,
+                               options: &SerializerOptions, name: &OwnedName)
             -> Result<(), Error> {
-        try!(sink.write(
+        let namespace = data.get_namespaces(name)?;
+        sink.write(
             XmlEvent::StartElement { name: name.borrow(),
                                      attributes: Cow::Borrowed(
                                          data.attributes
@@ -36,17 +39,62 @@ impl SerializeVia<xml::Element> for conv::Element {
                                              .map(|a| { a.borrow() })
                                              .collect::<Vec<_>>()
                                              .as_slice()),
-                                     namespace: Cow::Borrowed(&data.get_namespaces(name)) }
-        ));
+                                     namespace: Cow::Borrowed(&namespace) }
+        )?;
         for node in &data.nodes {
-            try!(match node {
-                &xml::Node::Text(ref s) => {
-                    sink.write(XmlEvent::Characters(s)).map_err(Error::from)
-                },
-                &xml::Node::Element(ref name, ref e) => conv::Element::serialize_via(e, sink, name),
-            });
+            node.serialize(sink, options)?;
         }
-        try!(sink.write(XmlEvent::EndElement { name: Some(name.borrow()) }));
+        sink.write(XmlEvent::EndElement { name: Some(name.borrow()) })?;
         Ok(())
     }
 }
+
+impl xml::Node {
+    /// Writes the node in its place in `sink`
+    pub fn serialize<W: Write>(&self, sink: &mut EventWriter<W>, options: &SerializerOptions)
+            -> Result<(), Error> {
+        match *self {
+            xml::Node::Text(ref s) => sink.write(XmlEvent::Characters(s)).map_err(Error::from),
+            xml::Node::Element(ref name, ref e) => conv::Element::serialize_via(e, sink, options, name),
+            xml::Node::Comment(ref s) => sink.write(XmlEvent::Comment(s)).map_err(Error::from),
+            xml::Node::ProcessingInstruction(ref name, ref data) => {
+                sink.write(XmlEvent::ProcessingInstruction {
+                    name,
+                    data: data.as_ref().map(|data| data as &str)
+                }).map_err(Error::from)
+            }
+            xml::Node::CData(ref s) => ser::write_cdata(sink, s),
+            xml::Node::Typed(ref name, ref value) => {
+                conv::Element::serialize_via(&value.to_element(name, options)?, sink, options, name)
+            }
+        }
+    }
+}
+
+/// Writes the document the way it was parsed: with its XML declaration, root namespaces,
+/// and markup around the root element.
+///
+/// The declaration names UTF-8 whatever the source encoding, because that's what gets written.
+impl<T: SerializeDocument> SerializeDocument for xml::Document<T> {
+    fn serialize_document<W: Write>(&self, sink: &mut EventWriter<W>, options: &SerializerOptions)
+            -> Result<(), Error> {
+        sink.write(XmlEvent::StartDocument { version: self.info.version,
+                                                  encoding: Some("UTF-8"),
+                                                  standalone: self.info.standalone })?;
+        for node in &self.info.prolog {
+            node.serialize(sink, options)?;
+        }
+        self.serialize_root(sink, options)?;
+        for node in &self.info.epilog {
+            node.serialize(sink, options)?;
+        }
+        Ok(())
+    }
+    fn check_valid(&self) -> Result<(), Error> {
+        self.data.check_valid()
+    }
+    fn serialize_root<W: Write>(&self, sink: &mut EventWriter<W>, options: &SerializerOptions)
+            -> Result<(), Error> {
+        self.data.serialize_root_declaring(sink, options, &self.info.namespace)
+    }
+}
@@ -0,0 +1,688 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Reader of XML documents held in memory, such as memory mapped files.
+//!
+//! Names, attribute values and text are borrowed from the document,
+//! unless entity references or normalized line ends make them differ from it.
+//!
+//! Besides its own tokens, the reader produces events like `xml-rs` does,
+//! so that parts of the document can be handed to the parsers reading `EventSource`s.
+//! Characters XML doesn't allow, wrong references, `<` in attribute values,
+//! attributes given twice, unbound prefixes and mismatched closing tags
+//! are refused with the message and position `xml-rs` gives.
+//!
+//! ```ignore
+//! let options = ParserOptions::default();
+//! let (document, warnings) = gpx::par::parse_from(SliceReader::new(&data, &options), &options).unwrap();
+//! ```
+
+extern crate xml as _xml;
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::char;
+
+use self::_xml::common::{ TextPosition, XmlVersion };
+use self::_xml::name::OwnedName;
+use self::_xml::attribute::OwnedAttribute;
+use self::_xml::namespace::{ Namespace, NS_NO_PREFIX, NS_EMPTY_URI, NS_XML_PREFIX, NS_XML_URI,
+                             NS_XMLNS_PREFIX, NS_XMLNS_URI };
+use self::_xml::reader::{ Error, XmlEvent };
+
+use par::ParserOptions;
+use xml::{ EventSource, Malformed, check_literal, check_start_tag, check_text, normalize_line_ends,
+           normalize_attribute };
+
+
+/// Name of an element or attribute, with its namespace resolved
+#[derive(Debug, Clone)]
+pub struct Name<'a> {
+    /// Name as written, including the prefix
+    pub qualified: &'a str,
+    pub prefix: Option<&'a str>,
+    pub local_name: &'a str,
+    pub namespace: Option<Cow<'a, str>>,
+}
+
+impl<'a> Name<'a> {
+    /// Copies the name into an `xml-rs` one
+    pub fn owned(&self) -> OwnedName {
+        OwnedName { local_name: self.local_name.into(),
+                    namespace: self.namespace.as_ref().map(|ns| ns.clone().into_owned()),
+                    prefix: self.prefix.map(String::from) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Attribute<'a> {
+    pub name: Name<'a>,
+    pub value: Cow<'a, str>,
+}
+
+impl<'a> Attribute<'a> {
+    /// Copies the attribute into an `xml-rs` one
+    pub fn owned(&self) -> OwnedAttribute {
+        OwnedAttribute { name: self.name.owned(), value: self.value.clone().into_owned() }
+    }
+}
+
+/// Start of an element. Namespace declarations are not among the attributes.
+#[derive(Debug, Clone)]
+pub struct Start<'a> {
+    pub name: Name<'a>,
+    pub attributes: Vec<Attribute<'a>>,
+}
+
+impl<'a> Start<'a> {
+    /// Copies the attributes into `xml-rs` ones
+    pub fn owned_attributes(&self) -> Vec<OwnedAttribute> {
+        self.attributes.iter().map(Attribute::owned).collect()
+    }
+}
+
+/// Piece of the document.
+///
+/// Empty elements, like `<a/>`, give a `Start` followed by an `End`.
+/// Text outside the root element is skipped if it's whitespace.
+#[derive(Debug)]
+pub enum Token<'a> {
+    Declaration { version: XmlVersion, encoding: Option<&'a str>, standalone: Option<bool> },
+    Start(Start<'a>),
+    End(Name<'a>),
+    /// Character data between markup, with entity references replaced
+    Text(Cow<'a, str>),
+    CData(Cow<'a, str>),
+    Comment(Cow<'a, str>),
+    ProcessingInstruction { name: &'a str, data: Option<Cow<'a, str>> },
+    EndDocument,
+}
+
+/// Namespaces in scope outside of the root element
+fn default_scope() -> Namespace {
+    let mut scope = Namespace::empty();
+    scope.force_put(NS_XML_PREFIX, NS_XML_URI);
+    scope.force_put(NS_XMLNS_PREFIX, NS_XMLNS_URI);
+    scope.force_put(NS_NO_PREFIX, NS_EMPTY_URI);
+    scope
+}
+
+fn is_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\r' || c == '\n'
+}
+
+/// Ends names and unquoted parts of tags
+fn is_delimiter(c: char) -> bool {
+    is_whitespace(c) || c == '/' || c == '>' || c == '='
+}
+
+/// Replaces entity and character references in `raw`. Borrows it if there are none.
+fn unescape(raw: &str) -> Result<Cow<'_, str>, String> {
+    if !raw.contains('&') {
+        return Ok(Cow::Borrowed(raw));
+    }
+    let mut text = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => return Err("Unterminated entity reference".into()),
+        };
+        let entity = &rest[..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "apos" => Some('\''),
+            "quot" => Some('"'),
+            _ if entity.starts_with("#x") => {
+                u32::from_str_radix(&entity[2..], 16).ok().and_then(char::from_u32)
+            }
+            _ if entity.starts_with('#') => {
+                entity[1..].parse::<u32>().ok().and_then(char::from_u32)
+            }
+            _ => return Err(format!("Unexpected entity: {}", entity)),
+        };
+        match c {
+            Some(c) => text.push(c),
+            None => return Err(format!("Invalid character reference: {}", entity)),
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    Ok(Cow::Owned(text))
+}
+
+/// Normalizes whitespace in `raw` as written with `normalize`, then replaces references.
+/// Borrows `raw` if neither changes it.
+fn unescape_normalized(raw: &str, normalize: fn(&str) -> Cow<'_, str>) -> Result<Cow<'_, str>, String> {
+    match normalize(raw) {
+        Cow::Borrowed(raw) => unescape(raw),
+        Cow::Owned(normalized) => unescape(&normalized).map(|text| Cow::Owned(text.into_owned())),
+    }
+}
+
+/// Reads tokens from a document in memory
+pub struct SliceReader<'a> {
+    source: &'a str,
+    /// Where reading continues
+    offset: usize,
+    /// Start of the last token returned
+    start: usize,
+    /// Names of open elements, with the number of `declarations` made outside of each
+    open: Vec<(&'a str, usize)>,
+    /// Namespace declarations in scope, by prefix, the default namespace having an empty one
+    declarations: Vec<(&'a str, Cow<'a, str>)>,
+    /// End of the empty element started last, returned next
+    pending_end: Option<Name<'a>>,
+    /// Token read ahead while joining text into an event, with its start
+    peeked: Option<(Token<'a>, usize)>,
+    ignore_comments: bool,
+    version: XmlVersion,
+    /// Something was read already, so a document start event would be out of place
+    started: bool,
+    seen_root: bool,
+    /// Last position computed, where counting lines continues
+    cursor: Cell<(usize, TextPosition)>,
+}
+
+impl<'a> SliceReader<'a> {
+    /// Prepares to read `source` for parsing with `options`
+    pub fn new(source: &'a str, options: &ParserOptions) -> Self {
+        // a byte order mark is not part of the document
+        let offset = if source.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+        SliceReader { source,
+                      offset,
+                      start: offset,
+                      open: Vec::new(),
+                      declarations: Vec::new(),
+                      pending_end: None,
+                      peeked: None,
+                      ignore_comments: !options.keep_markup,
+                      version: XmlVersion::Version10,
+                      started: false,
+                      seen_root: false,
+                      cursor: Cell::new((0, TextPosition::new())) }
+    }
+
+    /// Returns the position of the byte at `offset`
+    fn position_of(&self, offset: usize) -> TextPosition {
+        let (mut counted, mut position) = self.cursor.get();
+        if offset < counted {
+            counted = 0;
+            position = TextPosition::new();
+        }
+        for &byte in &self.source.as_bytes()[counted..offset] {
+            match byte {
+                b'\n' => {
+                    position.row += 1;
+                    position.column = 0;
+                }
+                // columns count characters, not bytes
+                byte if byte & 0xC0 == 0x80 => {}
+                _ => position.column += 1,
+            }
+        }
+        self.cursor.set((offset, position));
+        position
+    }
+
+    /// Returns the position where the last token starts
+    pub fn token_position(&self) -> TextPosition {
+        self.position_of(self.start)
+    }
+
+    fn error<S: Into<String>>(&self, offset: usize, message: S) -> Error {
+        Error::from((&self.position_of(offset), message.into()))
+    }
+
+    /// Places the error found in the part of the document starting at `offset`
+    fn malformed(&self, offset: usize, malformed: Malformed) -> Error {
+        self.error(offset + malformed.0, malformed.1)
+    }
+
+    /// Checks markup where nothing is replaced, starting at `offset`
+    fn check_literal(&self, offset: usize, raw: &str) -> Result<(), Error> {
+        check_literal(raw, self.version).map_err(|malformed| self.malformed(offset, malformed))
+    }
+
+    fn unexpected_end(&self) -> Error {
+        let message = if self.open.is_empty() {
+            "Unexpected end of stream"
+        } else {
+            "Unexpected end of stream: still inside the root element"
+        };
+        self.error(self.source.len(), message)
+    }
+
+    /// Returns the index of `needle` after the current offset, failing if it's missing
+    fn find(&self, needle: &str) -> Result<usize, Error> {
+        match self.source[self.offset..].find(needle) {
+            Some(index) => Ok(self.offset + index),
+            None => Err(self.unexpected_end()),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.source[self.offset..];
+        self.offset += rest.len() - rest.trim_start_matches(is_whitespace).len();
+    }
+
+    /// Reads a name at the current offset
+    fn read_name(&mut self) -> Result<&'a str, Error> {
+        let source = self.source;
+        let rest = &source[self.offset..];
+        let length = rest.find(is_delimiter).unwrap_or(rest.len());
+        if length == 0 {
+            return Err(self.error(self.offset, "Name expected"));
+        }
+        self.offset += length;
+        Ok(&rest[..length])
+    }
+
+    /// Reads `name="value"` at the current offset, the value not unescaped yet
+    fn read_attribute(&mut self) -> Result<(&'a str, &'a str), Error> {
+        let source = self.source;
+        let name = self.read_name()?;
+        self.skip_whitespace();
+        if !source[self.offset..].starts_with('=') {
+            return Err(self.error(self.offset, format!("Attribute {} has no value", name)));
+        }
+        self.offset += 1;
+        self.skip_whitespace();
+        let quote = match source[self.offset..].chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => quote,
+            Some(_) => return Err(self.error(self.offset, "Quote expected")),
+            None => return Err(self.unexpected_end()),
+        };
+        self.offset += 1;
+        let end = match source[self.offset..].find(quote) {
+            Some(end) => self.offset + end,
+            None => return Err(self.unexpected_end()),
+        };
+        let value = &source[self.offset..end];
+        self.offset = end + 1;
+        Ok((name, value))
+    }
+
+    /// Splits `qualified` into the prefix and the local name, and finds its namespace.
+    ///
+    /// Unprefixed names are in the default namespace only if they belong to elements.
+    /// Unbound prefixes are reported at `end`, where the tag ends.
+    fn resolve(&self, qualified: &'a str, element: bool, end: usize) -> Result<Name<'a>, Error> {
+        let (prefix, local_name) = match qualified.find(':') {
+            Some(index) => (Some(&qualified[..index]), &qualified[index + 1..]),
+            None => (None, qualified),
+        };
+        let namespace = match prefix {
+            Some(NS_XML_PREFIX) => Some(Cow::Borrowed(NS_XML_URI)),
+            Some(prefix) => match self.lookup(prefix) {
+                Some(uri) => Some(uri),
+                None => {
+                    let kind = if element { "Element" } else { "Attribute" };
+                    return Err(self.error(end, format!("{} {} prefix is unbound", kind, qualified)));
+                }
+            },
+            None if element => self.lookup(NS_NO_PREFIX),
+            None => None,
+        };
+        Ok(Name { qualified,
+                  prefix,
+                  local_name,
+                  namespace: match namespace {
+                      Some(ref uri) if uri.is_empty() => None,
+                      namespace => namespace,
+                  } })
+    }
+
+    fn lookup(&self, prefix: &str) -> Option<Cow<'a, str>> {
+        self.declarations.iter().rev()
+                         .find(|declaration| declaration.0 == prefix)
+                         .map(|declaration| declaration.1.clone())
+    }
+
+    /// Reads the tag starting an element, the `<` being consumed
+    fn read_start(&mut self) -> Result<Token<'a>, Error> {
+        let source = self.source;
+        let qualified = self.read_name()?;
+        let outer = self.declarations.len();
+        let mut raw_attributes = Vec::new();
+        let (end, empty) = loop {
+            self.skip_whitespace();
+            let rest = &source[self.offset..];
+            if rest.starts_with("/>") {
+                self.offset += 2;
+                break (self.offset - 2, true);
+            }
+            if rest.starts_with('>') {
+                self.offset += 1;
+                break (self.offset - 1, false);
+            }
+            if rest.is_empty() {
+                return Err(self.unexpected_end());
+            }
+            raw_attributes.push(self.read_attribute()?);
+        };
+        let tag = self.start + 1;
+        check_start_tag(&source[tag..end], self.version)
+            .map_err(|malformed| self.malformed(tag, malformed))?;
+        let mut values = Vec::with_capacity(raw_attributes.len());
+        for (name, value) in raw_attributes {
+            let value = unescape_normalized(value, normalize_attribute)
+                            .map_err(|e| self.error(self.start, e))?;
+            if name == "xmlns" {
+                self.declarations.push((NS_NO_PREFIX, value));
+            } else if let Some(prefix) = name.strip_prefix("xmlns:") {
+                self.declarations.push((prefix, value));
+            } else {
+                values.push((name, value));
+            }
+        }
+        let name = self.resolve(qualified, true, end)?;
+        let mut attributes = Vec::with_capacity(values.len());
+        for (name, value) in values {
+            attributes.push(Attribute { name: self.resolve(name, false, end)?, value });
+        }
+        self.open.push((qualified, outer));
+        self.seen_root = true;
+        if empty {
+            self.pending_end = Some(name.clone());
+        }
+        Ok(Token::Start(Start { name, attributes }))
+    }
+
+    /// Closes the element started last
+    fn close(&mut self) {
+        if let Some((_, outer)) = self.open.pop() {
+            self.declarations.truncate(outer);
+        }
+    }
+
+    /// Reads the tag ending an element, the `</` being consumed
+    fn read_end(&mut self) -> Result<Token<'a>, Error> {
+        let qualified = self.read_name()?;
+        self.skip_whitespace();
+        if !self.source[self.offset..].starts_with('>') {
+            return Err(self.error(self.offset, "Closing tag expected"));
+        }
+        let end = self.offset;
+        self.offset += 1;
+        let name = match self.open.last() {
+            Some(_) => self.resolve(qualified, true, end)?,
+            None => return Err(self.error(self.start, "Unexpected token: </")),
+        };
+        match self.open.last() {
+            Some(&(open, _)) if open == qualified => {}
+            Some(&(open, _)) => {
+                let expected = self.resolve(open, true, end)?.owned();
+                return Err(self.error(end, format!("Unexpected closing tag: {} != {}",
+                                                   name.owned(), expected)));
+            }
+            None => {}
+        }
+        self.close();
+        Ok(Token::End(name))
+    }
+
+    /// Reads `<?...?>`, the `<?` being consumed
+    fn read_instruction(&mut self) -> Result<Token<'a>, Error> {
+        let source = self.source;
+        let end = self.find("?>")?;
+        let contents = &source[self.offset..end];
+        self.check_literal(self.offset, contents)?;
+        let (name, data) = match contents.find(is_whitespace) {
+            Some(index) => (&contents[..index], Some(contents[index..].trim_start_matches(is_whitespace))),
+            None => (contents, None),
+        };
+        // only the first thing in the document can be the XML declaration
+        if name == "xml" && source[..self.start].trim_start_matches('\u{feff}').is_empty() {
+            self.offset += name.len();
+            let declaration = self.read_declaration(end);
+            self.offset = end + 2;
+            return declaration;
+        }
+        self.offset = end + 2;
+        Ok(Token::ProcessingInstruction { name, data: data.map(normalize_line_ends) })
+    }
+
+    /// Reads the pseudo-attributes of the XML declaration, which end at `end`
+    fn read_declaration(&mut self, end: usize) -> Result<Token<'a>, Error> {
+        let mut version = None;
+        let mut encoding = None;
+        let mut standalone = None;
+        loop {
+            self.skip_whitespace();
+            if self.offset >= end {
+                break;
+            }
+            let (name, value) = self.read_attribute()?;
+            match name {
+                "version" => version = Some(match value {
+                    "1.0" => XmlVersion::Version10,
+                    "1.1" => XmlVersion::Version11,
+                    other => return Err(self.error(self.start, format!("Invalid XML version: {}", other))),
+                }),
+                "encoding" => encoding = Some(value),
+                "standalone" => standalone = Some(match value {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err(self.error(self.start, "Invalid standalone declaration")),
+                }),
+                _ => return Err(self.error(self.start, "Invalid XML declaration")),
+            }
+        }
+        match version {
+            Some(version) => {
+                self.version = version;
+                Ok(Token::Declaration { version,
+                                        encoding,
+                                        standalone })
+            }
+            None => Err(self.error(self.start, "XML declaration without version")),
+        }
+    }
+
+    /// Skips `<!DOCTYPE ...>`, including the internal subset in brackets
+    fn skip_doctype(&mut self) -> Result<(), Error> {
+        let mut brackets = 0;
+        for (index, c) in self.source[self.offset..].char_indices() {
+            match c {
+                '[' => brackets += 1,
+                ']' => brackets -= 1,
+                '>' if brackets == 0 => {
+                    self.offset += index + 1;
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        Err(self.unexpected_end())
+    }
+
+    /// Reads the next token from the document
+    fn scan(&mut self) -> Result<Token<'a>, Error> {
+        let source = self.source;
+        loop {
+            self.start = self.offset;
+            let rest = &source[self.offset..];
+            if rest.is_empty() {
+                if !self.open.is_empty() {
+                    // xml-rs counts trailing text as part of the unfinished token
+                    self.start = source.rfind('>').map_or(0, |end| end + 1);
+                    return Err(self.error(self.offset,
+                                          "Unexpected end of stream: still inside the root element"));
+                }
+                if !self.seen_root {
+                    return Err(self.error(self.offset,
+                                          "Unexpected end of stream: no root element found"));
+                }
+                return Ok(Token::EndDocument);
+            }
+            if !rest.starts_with('<') {
+                let length = rest.find('<').unwrap_or(rest.len());
+                let raw = &rest[..length];
+                self.offset += length;
+                if self.open.is_empty() {
+                    if raw.chars().all(is_whitespace) {
+                        continue;
+                    }
+                    return Err(self.error(self.start, "Unexpected characters outside the root element"));
+                }
+                check_text(raw, self.version).map_err(|malformed| self.malformed(self.start, malformed))?;
+                return unescape_normalized(raw, normalize_line_ends).map(Token::Text)
+                                                                    .map_err(|e| self.error(self.start, e));
+            }
+            if rest.starts_with("<?") {
+                self.offset += 2;
+                return self.read_instruction();
+            }
+            if rest.starts_with("<!--") {
+                self.offset += 4;
+                let end = self.find("-->")?;
+                let comment = &source[self.offset..end];
+                self.check_literal(self.offset, comment)?;
+                self.offset = end + 3;
+                return Ok(Token::Comment(normalize_line_ends(comment)));
+            }
+            if rest.starts_with("<![CDATA[") {
+                if self.open.is_empty() {
+                    return Err(self.error(self.start, "Unexpected CDATA outside the root element"));
+                }
+                self.offset += "<![CDATA[".len();
+                let end = self.find("]]>")?;
+                let data = &source[self.offset..end];
+                self.check_literal(self.offset, data)?;
+                self.offset = end + 3;
+                return Ok(Token::CData(normalize_line_ends(data)));
+            }
+            if rest.starts_with("<!DOCTYPE") {
+                self.offset += "<!DOCTYPE".len();
+                self.skip_doctype()?;
+                continue;
+            }
+            if rest.starts_with("</") {
+                self.offset += 2;
+                return self.read_end();
+            }
+            self.offset += 1;
+            return self.read_start();
+        }
+    }
+
+    /// Reads the next token
+    fn read(&mut self) -> Result<(Token<'a>, usize), Error> {
+        self.started = true;
+        if let Some(peeked) = self.peeked.take() {
+            return Ok(peeked);
+        }
+        if let Some(name) = self.pending_end.take() {
+            self.close();
+            return Ok((Token::End(name), self.start));
+        }
+        let token = self.scan()?;
+        Ok((token, self.start))
+    }
+
+    /// Returns the next token of the document
+    pub fn next_token(&mut self) -> Result<Token<'a>, Error> {
+        let (token, start) = self.read()?;
+        self.start = start;
+        Ok(token)
+    }
+
+    /// Returns the namespaces in scope of the element started last
+    pub fn scope(&self) -> Namespace {
+        let mut scope = default_scope();
+        for &(prefix, ref uri) in &self.declarations {
+            scope.force_put(prefix, uri.clone().into_owned());
+        }
+        scope
+    }
+
+    /// Turns `start`, the last token read, into an `xml-rs` event
+    pub fn start_event(&self, start: &Start) -> XmlEvent {
+        XmlEvent::StartElement { name: start.name.owned(),
+                                 attributes: start.owned_attributes(),
+                                 namespace: self.scope() }
+    }
+
+    /// Joins `text` with the text following it, like `xml-rs` does around skipped comments
+    fn join_text(&mut self, text: Cow<'a, str>) -> Result<XmlEvent, Error> {
+        let start = self.start;
+        let mut text = text.into_owned();
+        loop {
+            match self.read()? {
+                (Token::Text(more), _) => text.push_str(&more),
+                (Token::Comment(_), _) if self.ignore_comments => {}
+                token => {
+                    self.peeked = Some(token);
+                    break;
+                }
+            }
+        }
+        self.start = start;
+        Ok(if text.chars().all(is_whitespace) {
+            XmlEvent::Whitespace(text)
+        } else {
+            XmlEvent::Characters(text)
+        })
+    }
+}
+
+impl<'a> EventSource for SliceReader<'a> {
+    fn next(&mut self) -> Result<XmlEvent, Error> {
+        if !self.started {
+            self.started = true;
+            let (token, start) = self.read()?;
+            match token {
+                Token::Declaration { version, encoding, standalone } => {
+                    self.start = start;
+                    return Ok(XmlEvent::StartDocument { version,
+                                                        encoding: encoding.unwrap_or("UTF-8").into(),
+                                                        standalone });
+                }
+                // there's no declaration
+                token => {
+                    self.peeked = Some((token, start));
+                    return Ok(XmlEvent::StartDocument { version: XmlVersion::Version10,
+                                                        encoding: "UTF-8".into(),
+                                                        standalone: None });
+                }
+            }
+        }
+        loop {
+            let (token, start) = self.read()?;
+            self.start = start;
+            return Ok(match token {
+                Token::Start(start) => self.start_event(&start),
+                Token::End(name) => XmlEvent::EndElement { name: name.owned() },
+                Token::Text(text) => self.join_text(text)?,
+                Token::CData(data) => XmlEvent::CData(data.into_owned()),
+                Token::Comment(_) if self.ignore_comments => continue,
+                Token::Comment(comment) => XmlEvent::Comment(comment.into_owned()),
+                Token::ProcessingInstruction { name, data } => {
+                    XmlEvent::ProcessingInstruction { name: name.into(), data: data.map(Cow::into_owned) }
+                }
+                Token::Declaration { .. } => {
+                    return Err(self.error(start, "Unexpected XML declaration"));
+                }
+                Token::EndDocument => XmlEvent::EndDocument,
+            });
+        }
+    }
+    fn position(&self) -> TextPosition {
+        self.token_position()
+    }
+}
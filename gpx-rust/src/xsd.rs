@@ -18,21 +18,216 @@
 extern crate chrono;
 extern crate std;
 
+use std::fmt;
+use std::str::FromStr;
+
+use self::chrono::Offset;
+
 pub type Time = chrono::DateTime<chrono::FixedOffset>;
 pub type DateTime = chrono::DateTime<chrono::FixedOffset>;
 
 pub type NonNegativeInteger = u64;
 pub type Integer = i64;
-pub type GYear = i16;
 pub type Decimal = f64;
-pub type Uri = String;
+pub type Double = f64;
+pub type UnsignedByte = u8;
+pub type HexBinary = Vec<u8>;
+
+
+/// `xsd:anyURI`, checked to be a URI reference.
+///
+/// A scheme, if present, must be well-formed, percent signs must start escapes,
+/// and there must be no control characters or surrounding spaces.
+/// Relative references like `photos/1.jpg` are accepted.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Uri(String);
+
+impl Uri {
+    pub fn new<S: Into<String>>(value: S) -> Result<Uri, par::Error> {
+        let value = value.into();
+        if Uri::is_valid(&value) {
+            Ok(Uri(value))
+        } else {
+            Err(par::Error::BadUri(value))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Scheme like `http`, absent from relative references
+    pub fn scheme(&self) -> Option<&str> {
+        Uri::scheme_end(&self.0).map(|end| &self.0[..end])
+    }
+
+    /// The colon ending the scheme comes before any `/`, `?` or `#`
+    fn scheme_end(value: &str) -> Option<usize> {
+        match value.find(&[':', '/', '?', '#'][..]) {
+            Some(end) if value[end..].starts_with(':') => Some(end),
+            _ => None,
+        }
+    }
+
+    fn is_valid(value: &str) -> bool {
+        if value.trim() != value || value.chars().any(char::is_control) {
+            return false;
+        }
+        let bytes = value.as_bytes();
+        let escapes_valid = value.match_indices('%').all(|(i, _)| {
+            bytes.len() > i + 2 && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit()
+        });
+        if !escapes_valid {
+            return false;
+        }
+        match Uri::scheme_end(value) {
+            Some(end) => {
+                let mut scheme = value[..end].chars();
+                match scheme.next() {
+                    Some(first) if first.is_ascii_alphabetic() => {
+                        scheme.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+                    }
+                    _ => false,
+                }
+            }
+            None => true,
+        }
+    }
+}
+
+impl FromStr for Uri {
+    type Err = par::Error;
+    fn from_str(s: &str) -> Result<Uri, par::Error> {
+        Uri::new(s)
+    }
+}
+
+impl AsRef<str> for Uri {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Uri {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for Uri {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, fmt)
+    }
+}
+
+/// `xsd:gYear`, a year with an optional timezone.
+///
+/// Years range over `i16`, without the year 0 which XSD 1.0 leaves out.
+/// Timezones are whole minutes, at most 14 hours away from UTC.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct GYear {
+    year: i16,
+    timezone: Option<chrono::FixedOffset>,
+}
+
+impl GYear {
+    pub fn new(year: i16) -> Result<GYear, par::Error> {
+        if year == 0 {
+            return Err(par::Error::BadGYear(year.to_string()));
+        }
+        Ok(GYear { year, timezone: None })
+    }
+
+    pub fn with_timezone(self, timezone: chrono::FixedOffset) -> Result<GYear, par::Error> {
+        let seconds = timezone.local_minus_utc().num_seconds();
+        if seconds % 60 != 0 || seconds.abs() > 14 * 3600 {
+            return Err(par::Error::BadGYear(format!("{}{}", self.year, timezone)));
+        }
+        Ok(GYear { year: self.year, timezone: Some(timezone) })
+    }
+
+    pub fn year(&self) -> i16 {
+        self.year
+    }
+
+    pub fn timezone(&self) -> Option<chrono::FixedOffset> {
+        self.timezone
+    }
+}
+
+/// Parses the lexical form, like `2011`, `-0044` or `2011+02:00`
+impl FromStr for GYear {
+    type Err = par::Error;
+    fn from_str(s: &str) -> Result<GYear, par::Error> {
+        let bad = || par::Error::BadGYear(s.into());
+        let digits_start = if s.starts_with('-') { 1 } else { 0 };
+        let digits_end = s[digits_start..].find(|c: char| !c.is_ascii_digit())
+                                          .map_or(s.len(), |end| digits_start + end);
+        let digits = &s[digits_start..digits_end];
+        if digits.len() < 4 || (digits.len() > 4 && digits.starts_with('0')) {
+            return Err(bad());
+        }
+        let year = i16::from_str(&s[..digits_end]).map_err(|_| bad())?;
+        let year = GYear::new(year).map_err(|_| bad())?;
+        let timezone = &s[digits_end..];
+        if timezone.is_empty() {
+            return Ok(year);
+        }
+        let seconds = if timezone == "Z" {
+            0
+        } else {
+            let bytes = timezone.as_bytes();
+            let sign = match bytes[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return Err(bad()),
+            };
+            if bytes.len() != 6 || bytes[3] != b':'
+                    || !timezone[1..3].chars().chain(timezone[4..].chars()).all(|c| c.is_ascii_digit()) {
+                return Err(bad());
+            }
+            let hours = i32::from_str(&timezone[1..3]).map_err(|_| bad())?;
+            let minutes = i32::from_str(&timezone[4..]).map_err(|_| bad())?;
+            if minutes >= 60 {
+                return Err(bad());
+            }
+            sign * (hours * 3600 + minutes * 60)
+        };
+        let offset = chrono::FixedOffset::east_opt(seconds).ok_or_else(&bad)?;
+        year.with_timezone(offset).map_err(|_| bad())
+    }
+}
+
+/// Writes the canonical form, with `Z` for UTC
+impl fmt::Display for GYear {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if self.year < 0 {
+            write!(fmt, "-{:04}", -(self.year as i32))?;
+        } else {
+            write!(fmt, "{:04}", self.year)?;
+        }
+        match self.timezone {
+            None => Ok(()),
+            Some(ref timezone) if timezone.local_minus_utc().num_seconds() == 0 => fmt.write_str("Z"),
+            Some(ref timezone) => write!(fmt, "{}", timezone),
+        }
+    }
+}
+
+impl fmt::Debug for GYear {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, fmt)
+    }
+}
 
 
 pub mod par {
     //! Parsing impls
     extern crate chrono;
     
+    use std::fmt;
     use std::str::FromStr;
+    use std::error::Error as ErrorTrait;
     use std::num::ParseIntError;
     use std::num::ParseFloatError;
     
@@ -46,10 +241,40 @@ pub mod par {
         BadInt(ParseIntError),
         BadFloat(ParseFloatError),
         BadTime(chrono::ParseError),
+        BadBoolean(String),
+        BadHexBinary(String),
+        BadUri(String),
+        BadGYear(String),
     }
     
     impl FormatError for Error {}
 
+    impl fmt::Display for Error {
+        fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+            match *self {
+                Error::BadInt(ref e) => write!(fmt, "Bad integer: {}", e),
+                Error::BadFloat(ref e) => write!(fmt, "Bad decimal: {}", e),
+                Error::BadTime(ref e) => write!(fmt, "Bad date and time: {}", e),
+                Error::BadBoolean(ref s) => write!(fmt, "Bad boolean {:?}", s),
+                Error::BadHexBinary(ref s) => write!(fmt, "Bad hex binary {:?}", s),
+                Error::BadUri(ref s) => write!(fmt, "Bad URI {:?}", s),
+                Error::BadGYear(ref s) => write!(fmt, "Bad year {:?}", s),
+            }
+        }
+    }
+
+    impl ErrorTrait for Error {
+        fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+            match *self {
+                Error::BadInt(ref e) => Some(e),
+                Error::BadFloat(ref e) => Some(e),
+                Error::BadTime(ref e) => Some(e),
+                Error::BadBoolean(_) | Error::BadHexBinary(_)
+                    | Error::BadUri(_) | Error::BadGYear(_) => None,
+            }
+        }
+    }
+
     impl From<ParseIntError> for Error {
         fn from(err: ParseIntError) -> Error {
             Error::BadInt(err)
@@ -68,6 +293,12 @@ pub mod par {
         }
     }
     
+    impl ParseViaChar<u8> for conv::Integer {
+        fn from_char(s: &str) -> Result<u8, ::gpx::par::Error> {
+            u8::from_str(s).map_err(|e| Error::from(e).into())
+        }
+    }
+    
     impl ParseViaChar<u16> for conv::Integer {
         fn from_char(s: &str) -> Result<u16, ::gpx::par::Error> {
             u16::from_str(s).map_err(|e| Error::from(e).into())
@@ -98,55 +329,157 @@ pub mod par {
         }
     }
     
+    impl ParseViaChar<f64> for conv::Double {
+        fn from_char(s: &str) -> Result<f64, ::gpx::par::Error> {
+            match s {
+                "INF" => Ok(f64::INFINITY),
+                "-INF" => Ok(f64::NEG_INFINITY),
+                "NaN" => Ok(f64::NAN),
+                _ => conv::Decimal::from_char(s),
+            }
+        }
+    }
+    
     impl ParseViaChar<xsd::DateTime> for conv::DateTime {
         fn from_char(chars: &str) -> Result<xsd::DateTime, ::gpx::par::Error> {
             xsd::DateTime::parse_from_rfc3339(chars).map_err(|e| Error::BadTime(e).into())
         }
     }
     
+    impl ParseViaChar<bool> for conv::Boolean {
+        fn from_char(s: &str) -> Result<bool, ::gpx::par::Error> {
+            match s.trim() {
+                "true" | "1" => Ok(true),
+                "false" | "0" => Ok(false),
+                _ => Err(Error::BadBoolean(s.into()).into()),
+            }
+        }
+    }
+    
+    impl ParseViaChar<xsd::HexBinary> for conv::HexBinary {
+        fn from_char(s: &str) -> Result<xsd::HexBinary, ::gpx::par::Error> {
+            let s = s.trim();
+            if s.len() % 2 == 1 || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(Error::BadHexBinary(s.into()).into());
+            }
+            (0..s.len() / 2).map(|i| {
+                u8::from_str_radix(&s[2 * i..2 * i + 2], 16).map_err(|e| Error::from(e).into())
+            }).collect()
+        }
+    }
+    
+    /// Surrounding spaces are collapsed as `xsd:anyURI` prescribes
+    impl ParseViaChar<xsd::Uri> for conv::Uri {
+        fn from_char(s: &str) -> Result<xsd::Uri, ::gpx::par::Error> {
+            xsd::Uri::new(s.trim()).map_err(|e| e.into())
+        }
+    }
+    
+    impl ParseViaChar<xsd::GYear> for conv::GYear {
+        fn from_char(s: &str) -> Result<xsd::GYear, ::gpx::par::Error> {
+            xsd::GYear::from_str(s.trim()).map_err(|e| e.into())
+        }
+    }
+    
     impl FromAttributeVia<String> for conv::String {
         type Error = Error;
         fn from_attribute(attr: &str) -> Result<String, Self::Error> {
             Ok(String::from(attr))
         }
     }
+    
+    impl FromAttributeVia<xsd::Uri> for conv::Uri {
+        type Error = Error;
+        fn from_attribute(attr: &str) -> Result<xsd::Uri, Self::Error> {
+            xsd::Uri::new(attr.trim())
+        }
+    }
 }
 
 pub mod conv {
     //! conversion markers
     pub struct String {}
     pub struct Decimal {}
-    pub type Uri = String;
+    pub struct Uri {}
     pub struct Integer {}
     pub type NonNegativeInteger = Integer; // FIXME
-    pub type GYear = Integer;
+    pub struct GYear {}
+    pub type UnsignedByte = Integer;
+    /// Like `Decimal`, also holding `INF`, `-INF` and `NaN`
+    pub struct Double {}
     pub struct DateTime {}
+    pub struct Boolean {}
+    pub struct HexBinary {}
 }
 
 pub mod ser {
     //! Serialization impls
     use std;
+    use std::fmt;
+    use std::error::Error as ErrorTrait;
 
     use xsd;
     use xsd::conv;
     use ser::ToCharsVia;
     use ser::FormatError;
 
+    /// Value which has no representation in its XSD type
     #[derive(Debug)]
-    pub enum Error {}
+    pub enum Error {
+        /// `xsd:decimal` holds neither infinities nor NaN
+        NotFinite(f64),
+    }
 
     impl FormatError for Error {}
+
+    impl fmt::Display for Error {
+        fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                Error::NotFinite(value) => {
+                    write!(fmt, "Decimal value is not a finite number: {}", value)
+                }
+            }
+        }
+    }
+
+    impl ErrorTrait for Error {
+    }
     
     type Result = std::result::Result<String, Error>;
 
     impl ToCharsVia<f64> for xsd::conv::Decimal {
         type Error = Error;
-        fn to_characters(data: &f64) -> Result { Ok(data.to_string()) }
+        fn to_characters(data: &f64) -> Result {
+            if !data.is_finite() {
+                return Err(Error::NotFinite(*data));
+            }
+            Ok(data.to_string())
+        }
     }
     
     impl ToCharsVia<f32> for xsd::conv::Decimal {
         type Error = Error;
-        fn to_characters(data: &f32) -> Result { Ok(data.to_string()) }
+        fn to_characters(data: &f32) -> Result {
+            if !data.is_finite() {
+                return Err(Error::NotFinite(*data as f64));
+            }
+            Ok(data.to_string())
+        }
+    }
+
+    impl ToCharsVia<f64> for xsd::conv::Double {
+        type Error = Error;
+        fn to_characters(data: &f64) -> Result {
+            Ok(if data.is_nan() {
+                "NaN".into()
+            } else if *data == f64::INFINITY {
+                "INF".into()
+            } else if *data == f64::NEG_INFINITY {
+                "-INF".into()
+            } else {
+                data.to_string()
+            })
+        }
     }
 
     impl ToCharsVia<u64> for xsd::conv::Integer {
@@ -154,6 +487,11 @@ pub mod ser {
         fn to_characters(data: &u64) -> Result { Ok(data.to_string()) }
     }
     
+    impl ToCharsVia<u8> for xsd::conv::Integer {
+        type Error = Error;
+        fn to_characters(data: &u8) -> Result { Ok(data.to_string()) }
+    }
+    
     impl ToCharsVia<u16> for xsd::conv::Integer {
         type Error = Error;
         fn to_characters(data: &u16) -> Result { Ok(data.to_string()) }
@@ -169,6 +507,18 @@ pub mod ser {
         fn to_characters(data: &xsd::DateTime) -> Result { Ok(data.to_rfc3339()) }
     }
 
+    impl ToCharsVia<bool> for conv::Boolean {
+        type Error = Error;
+        fn to_characters(data: &bool) -> Result { Ok(data.to_string()) }
+    }
+
+    impl ToCharsVia<xsd::HexBinary> for conv::HexBinary {
+        type Error = Error;
+        fn to_characters(data: &xsd::HexBinary) -> Result {
+            Ok(data.iter().map(|byte| format!("{:02X}", byte)).collect())
+        }
+    }
+
     impl ToCharsVia<String> for conv::String {
         type Error = Error;
         fn to_characters(data: &String) -> Result { Ok(data.clone()) }
@@ -178,4 +528,14 @@ pub mod ser {
         type Error = Error;
         fn to_characters(data: &str) -> Result { Ok(data.into()) }
     }
+
+    impl ToCharsVia<xsd::Uri> for conv::Uri {
+        type Error = Error;
+        fn to_characters(data: &xsd::Uri) -> Result { Ok(data.as_str().into()) }
+    }
+
+    impl ToCharsVia<xsd::GYear> for conv::GYear {
+        type Error = Error;
+        fn to_characters(data: &xsd::GYear) -> Result { Ok(data.to_string()) }
+    }
 }
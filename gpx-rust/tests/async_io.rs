@@ -0,0 +1,77 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Parsing and writing through `futures`
+#![cfg(feature = "async")]
+
+extern crate futures;
+extern crate gpx_rust;
+
+use std::io;
+use std::io::Read;
+use std::panic;
+use std::pin::Pin;
+use std::task::{ Context, Poll };
+
+use futures::executor::block_on;
+use futures::io::{ AsyncRead, Cursor };
+
+use gpx_rust::async_io;
+use gpx_rust::gpx;
+use gpx_rust::ser::SerializeDocument;
+
+
+const RICH: &[u8] = include_bytes!("fixtures/rich.gpx");
+
+#[test]
+fn same_as_sync() {
+    let (expected, _) = gpx::parse(RICH).unwrap();
+    let (found, _) = block_on(gpx::par::parse_async(Cursor::new(RICH))).unwrap();
+    assert_eq!(format!("{:?}", found.data), format!("{:?}", expected.data));
+    let mut written = Cursor::new(Vec::new());
+    block_on(found.serialize_async(&mut written)).unwrap();
+    let mut expected_output = Vec::new();
+    expected.serialize(&mut expected_output).unwrap();
+    assert_eq!(written.into_inner(), expected_output);
+}
+
+#[test]
+fn panic_resumed() {
+    let future = async_io::parse(Cursor::new(RICH), |mut input| -> Result<(), io::Error> {
+        let mut start = [0; 5];
+        input.read_exact(&mut start).unwrap();
+        panic!("parser failed after {:?}", String::from_utf8_lossy(&start));
+    });
+    let payload = panic::catch_unwind(panic::AssertUnwindSafe(|| block_on(future))).unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+    assert_eq!(message, "parser failed after \"<?xml\"");
+}
+
+/// Source losing its connection
+struct Broken;
+
+impl AsyncRead for Broken {
+    fn poll_read(self: Pin<&mut Self>, _: &mut Context, _: &mut [u8]) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::Error::new(io::ErrorKind::ConnectionReset, "connection lost")))
+    }
+}
+
+#[test]
+fn read_error_returned() {
+    match block_on(gpx::par::parse_async(Broken)) {
+        Err(gpx::par::DocumentError::ParserError(e)) => {
+            assert!(e.to_string().contains("connection lost"), "{}", e);
+        }
+        other => panic!("Unexpected result {:?}", other.map(|_| ())),
+    }
+}
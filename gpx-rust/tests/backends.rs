@@ -0,0 +1,190 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! `xml-rs` and `quick-xml` produce the same events and documents
+#![cfg(feature = "quick-xml")]
+
+extern crate xml;
+extern crate gpx_rust;
+
+use xml::common::TextPosition;
+use xml::reader::{ ParserConfig, XmlEvent };
+
+use gpx_rust::gpx;
+use gpx_rust::par::ParserOptions;
+use gpx_rust::xml::EventSource;
+use gpx_rust::xml::quick::QuickReader;
+
+
+const FIXTURES: [&str; 4] = [
+    include_str!("fixtures/rich.gpx"),
+    include_str!("fixtures/v1_0.gpx"),
+    include_str!("fixtures/markup.gpx"),
+    include_str!("fixtures/garmin.gpx"),
+];
+
+/// Reads all events with their positions.
+///
+/// `xml-rs` misplaces closing tags after text containing `/` or `]` by a column,
+/// so their positions are left out.
+fn events<S: EventSource>(mut source: S) -> Vec<(XmlEvent, Option<TextPosition>)> {
+    let mut events = Vec::new();
+    loop {
+        let event = source.next().expect("Failed to read");
+        let position = match event {
+            XmlEvent::EndElement { .. } => None,
+            _ => Some(source.position()),
+        };
+        let end = event == XmlEvent::EndDocument;
+        events.push((event, position));
+        if end {
+            return events;
+        }
+    }
+}
+
+/// Reads events until the error, which must come
+fn error<S: EventSource>(mut source: S) -> String {
+    loop {
+        match source.next() {
+            Ok(XmlEvent::EndDocument) => panic!("Malformed document accepted"),
+            Ok(_) => {}
+            Err(e) => return e.to_string(),
+        }
+    }
+}
+
+fn assert_same_events(document: &str, options: &ParserOptions) {
+    let reader = ParserConfig::new().ignore_comments(!options.keep_markup)
+                                    .create_reader(document.as_bytes());
+    let expected = events(reader);
+    let found = events(QuickReader::new(document.as_bytes(), options));
+    assert_eq!(format!("{:?}", found), format!("{:?}", expected));
+}
+
+fn assert_same_document(document: &str, options: &ParserOptions) {
+    let (expected, expected_warnings) = gpx::par::parse_with(document.as_bytes(), options).unwrap();
+    let (found, found_warnings) = gpx::par::parse_from(QuickReader::new(document.as_bytes(),
+                                                                        options),
+                                                       options).unwrap();
+    assert_eq!(format!("{:?}", found.data), format!("{:?}", expected.data));
+    assert_eq!(format!("{:?}", found.info.prolog), format!("{:?}", expected.info.prolog));
+    assert_eq!(format!("{:?}", found.info.epilog), format!("{:?}", expected.info.epilog));
+    let messages = |warnings: Vec<_>| warnings.iter().map(|w| format!("{}", w))
+                                              .collect::<Vec<_>>();
+    assert_eq!(messages(found_warnings), messages(expected_warnings));
+}
+
+#[test]
+fn events_match() {
+    let markup = ParserOptions { keep_markup: true, ..ParserOptions::default() };
+    for document in FIXTURES.iter() {
+        assert_same_events(document, &ParserOptions::default());
+        assert_same_events(document, &markup);
+    }
+}
+
+#[test]
+fn documents_match() {
+    let markup = ParserOptions { keep_markup: true, ..ParserOptions::default() };
+    for document in FIXTURES.iter() {
+        assert_same_document(document, &ParserOptions::default());
+        assert_same_document(document, &markup);
+    }
+}
+
+#[test]
+fn line_ends_normalized() {
+    for document in FIXTURES.iter() {
+        let crlf = document.replace('\n', "\r\n");
+        assert_same_events(&crlf, &ParserOptions::default());
+        assert_same_document(&crlf, &ParserOptions::default());
+        let unix = ParserOptions::default();
+        let (expected, _) = gpx::par::parse_with(document.as_bytes(), &unix).unwrap();
+        let (found, _) = gpx::par::parse_from(QuickReader::new(crlf.as_bytes(), &unix),
+                                              &unix).unwrap();
+        assert_eq!(format!("{:?}", found.data), format!("{:?}", expected.data));
+    }
+}
+
+#[test]
+fn attributes_normalized() {
+    let document = "<?xml version=\"1.0\"?>\r\n\
+                    <gpx version=\"1.1\" creator=\"a\tb\r\nc&#10;d\" \
+                    xmlns=\"http://www.topografix.com/GPX/1/1\"/>";
+    let source = QuickReader::new(document.as_bytes(), &ParserOptions::default());
+    match events(source)[1].0 {
+        XmlEvent::StartElement { ref attributes, .. } => {
+            assert_eq!(attributes[1].value, "a b c\nd");
+        }
+        ref other => panic!("Unexpected event {:?}", other),
+    }
+}
+
+#[test]
+fn attribute_references_kept() {
+    let document = "<?xml version=\"1.0\"?>\n\
+                    <gpx version=\"1.1\" creator=\"a&#10;b&#9;c&#13;d\" \
+                    xmlns=\"http://www.topografix.com/GPX/1/1\"/>";
+    assert_same_events(document, &ParserOptions::default());
+    assert_same_document(document, &ParserOptions::default());
+    let reader = ParserConfig::new().create_reader(document.as_bytes());
+    match events(reader)[1].0 {
+        XmlEvent::StartElement { ref attributes, .. } => {
+            assert_eq!(attributes[1].value, "a\nb\tc\rd");
+        }
+        ref other => panic!("Unexpected event {:?}", other),
+    }
+}
+
+#[test]
+fn malformed_refused_alike() {
+    let documents = [
+        "<gpx creator=\"a<b\"/>",
+        "<gpx creator=\"a\u{1}b\"/>",
+        "<gpx creator=\"a&#1;b\"/>",
+        "<gpx>\n  <name>a\u{1}b</name>\n</gpx>",
+        "<gpx><name>a&#0;b</name></gpx>",
+        "<gpx><name>a&bogus;b</name></gpx>",
+        "<gpx><!-- \u{1} --></gpx>",
+        "<gpx><![CDATA[\u{fffe}]]></gpx>",
+        "<gpx>a ]]> b</gpx>",
+        "<gpx xmlns=\"http://www.topografix.com/GPX/1/1\" version=\"1.1\" creator=\"a\">\
+         <wpt lat=\"1\" lon=\"2\"><p:name>x</p:name></wpt></gpx>",
+        "<gpx xmlns=\"http://www.topografix.com/GPX/1/1\" creator=\"a\" version=\"1.1\" \
+         creator=\"b\"><wpt lat=\"1\" lon=\"2\"/></gpx>",
+        "<gpx><wpt lat=\"1\" lon=\"2\" p:lat=\"3\"/></gpx>",
+        "<gpx><wpt></trk></gpx>",
+        "<gpx/></gpx>",
+    ];
+    for document in documents.iter() {
+        let document = format!("<?xml version=\"1.0\"?>\n{}", document);
+        let reader = ParserConfig::new().create_reader(document.as_bytes());
+        let quick = QuickReader::new(document.as_bytes(), &ParserOptions::default());
+        assert_eq!(error(quick), error(reader), "{}", document);
+    }
+}
+
+#[test]
+fn other_encodings_refused() {
+    let latin1 = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n<gpx creator=\"\xe9\"/>";
+    let quick = QuickReader::new(&latin1[..], &ParserOptions::default());
+    assert_eq!(error(quick), "1:42 Unsupported encoding: ISO-8859-1");
+    let mut utf16 = vec![0xFF, 0xFE];
+    for unit in "<?xml version=\"1.0\"?>\n<gpx creator=\"\u{e9}\"/>".encode_utf16() {
+        utf16.push(unit as u8);
+        utf16.push((unit >> 8) as u8);
+    }
+    let quick = QuickReader::new(&utf16[..], &ParserOptions::default());
+    assert_eq!(error(quick), "1:1 Unsupported encoding: UTF-16");
+}
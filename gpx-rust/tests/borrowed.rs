@@ -0,0 +1,129 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! The borrowing parser gives the same data as the parser reading events
+
+extern crate gpx_rust;
+
+use std::borrow::Cow;
+use std::error::Error;
+
+use gpx_rust::gpx;
+use gpx_rust::par::ParserOptions;
+
+
+const FIXTURES: [&str; 4] = [
+    include_str!("fixtures/rich.gpx"),
+    include_str!("fixtures/v1_0.gpx"),
+    include_str!("fixtures/markup.gpx"),
+    include_str!("fixtures/garmin.gpx"),
+];
+
+fn assert_same(document: &str, options: &ParserOptions) {
+    let (expected, expected_warnings) = gpx::par::parse_with(document.as_bytes(), options).unwrap();
+    let (found, found_warnings) = gpx::borrowed::parse_with(document, options).unwrap();
+    assert_eq!(format!("{:?}", found.into_owned()), format!("{:?}", expected.data));
+    let messages = |warnings: Vec<_>| warnings.iter().map(|w| format!("{}", w))
+                                              .collect::<Vec<_>>();
+    assert_eq!(messages(found_warnings), messages(expected_warnings));
+}
+
+/// Returns the message of the error at the bottom of `error`, where it was found
+fn cause(error: &dyn Error) -> String {
+    match error.source() {
+        Some(source) => cause(source),
+        None => error.to_string(),
+    }
+}
+
+fn all_options() -> Vec<ParserOptions> {
+    vec![ParserOptions::default(),
+         ParserOptions { keep_markup: true, ..ParserOptions::default() },
+         ParserOptions { strict: true, ..ParserOptions::default() }]
+}
+
+#[test]
+fn fixtures() {
+    for options in &all_options() {
+        for document in FIXTURES.iter() {
+            assert_same(document, options);
+        }
+    }
+}
+
+#[test]
+fn line_ends_normalized() {
+    for options in &all_options() {
+        for document in FIXTURES.iter() {
+            assert_same(&document.replace('\n', "\r\n"), options);
+            assert_same(&document.replace('\n', "\r"), options);
+        }
+    }
+}
+
+#[test]
+fn attributes_normalized() {
+    let document = |creator| format!("<?xml version=\"1.0\"?>\r\n\
+                                      <gpx version=\"1.1\" creator=\"{}\" \
+                                      xmlns=\"http://www.topografix.com/GPX/1/1\">\r\n\
+                                      <wpt lat=\"1\" lon=\"2\"><name>x\r\ny</name>\
+                                      <desc><![CDATA[p\r\nq]]></desc></wpt></gpx>", creator);
+    assert_same(&document("a&#9;b&#13;&#10;c"), &ParserOptions::default());
+    // `xml-rs` leaves whitespace written in attributes alone
+    let document = document("a\tb\r\nc&#10;d");
+    let (data, _) = gpx::borrowed::parse(&document).unwrap();
+    assert_eq!(data.creator, "a b c\nd");
+    assert_eq!(data.waypoints[0].name.as_deref(), Some("x\ny"));
+    assert_eq!(data.waypoints[0].description.as_deref(), Some("p\nq"));
+}
+
+#[test]
+fn text_borrowed() {
+    let document = FIXTURES[0];
+    let (data, _) = gpx::borrowed::parse(document).unwrap();
+    match data.waypoints[0].name {
+        Some(Cow::Borrowed(name)) => assert_eq!(name, "W1"),
+        ref other => panic!("Unexpected name {:?}", other),
+    }
+}
+
+#[test]
+fn malformed_refused_alike() {
+    let documents = [
+        "<wpt lat=\"1\" lon=\"2\" lat=\"3\"/>",
+        "<wpt lat=\"1\" lon=\"2\"><name>a</name><desc>b</desc><name>c</name></wpt>",
+        "<wpt lat=\"1\" lon=\"2\"><link href=\"a<b\"/></wpt>",
+        "<wpt lat=\"1\" lon=\"2\"><name>a\u{1}b</name></wpt>",
+        "<wpt lat=\"1\" lon=\"2\"><name>a&#1;b</name></wpt>",
+        "<wpt lat=\"1\" lon=\"2\"><name>a&bogus;b</name></wpt>",
+        "<wpt lat=\"1\" lon=\"2\" src=\"a&#0;b\"/>",
+        "<wpt lat=\"1\" lon=\"2\"><!-- \u{1} --></wpt>",
+        "<wpt lat=\"1\" lon=\"2\"><desc><![CDATA[\u{fffe}]]></desc></wpt>",
+        "<wpt lat=\"1\" lon=\"2\"><name>a ]]> b</name></wpt>",
+        "<wpt lat=\"1\" lon=\"2\"><p:name>a</p:name></wpt>",
+        "<wpt lat=\"1\" lon=\"2\"></trk>",
+    ];
+    for document in documents.iter() {
+        let document = format!("<?xml version=\"1.0\"?>\n\
+                                <gpx xmlns=\"http://www.topografix.com/GPX/1/1\" version=\"1.1\" \
+                                creator=\"a\">\n{}\n</gpx>", document);
+        for options in &all_options() {
+            let expected = gpx::par::parse_with(document.as_bytes(), options).map(|_| ());
+            let found = gpx::borrowed::parse_with(&document, options).map(|_| ());
+            // `xml-rs` stays at the failed token or at the error, so only the errors are compared
+            assert_eq!(format!("{:?}", found.map_err(|e| cause(&e))),
+                       format!("{:?}", expected.map_err(|e| cause(&e))),
+                       "{}", document);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Free text is written as CDATA sections with `SerializerOptions::text_as_cdata`
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::{ Gpx, Version, Waypoint };
+use gpx_rust::par::ParserOptions;
+use gpx_rust::ser::{ SerializeDocument, SerializerOptions };
+
+
+const CDATA: SerializerOptions = SerializerOptions { text_as_cdata: true };
+
+fn parse(document: &[u8]) -> Gpx {
+    gpx::parse(document).unwrap().0.data
+}
+
+fn write(data: &Gpx, options: &SerializerOptions) -> String {
+    let mut output = Vec::new();
+    data.serialize_with_options(&mut output, options).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn text_as_cdata() {
+    for &version in &[Version::V1_1, Version::V1_0] {
+        let mut data = parse(include_bytes!("fixtures/rich.gpx"));
+        data.version = version;
+        // GPX 1.0 segments have no place for extensions
+        for segment in &mut data.tracks[0].segments {
+            segment.extensions = None;
+        }
+        let output = write(&data, &CDATA);
+        assert!(output.contains("<name><![CDATA[Test & more]]></name>"), "{}", output);
+        assert!(output.contains("<desc><![CDATA[<b>x</b>]]></desc>"), "{}", output);
+        assert!(output.contains("<name><![CDATA[W1]]></name>"), "{}", output);
+        assert_eq!(format!("{:?}", parse(output.as_bytes())), format!("{:?}", data));
+    }
+}
+
+#[test]
+fn escaped_by_default() {
+    let data = parse(include_bytes!("fixtures/rich.gpx"));
+    assert!(write(&data, &CDATA).contains("CDATA"));
+    let output = write(&data, &SerializerOptions::default());
+    assert!(!output.contains("CDATA"));
+    assert!(output.contains("<desc>&lt;b&gt;x&lt;/b&gt;</desc>"), "{}", output);
+}
+
+#[test]
+fn section_end_split() {
+    let mut data = Gpx::new("test");
+    data.waypoints.push(Waypoint::at(1.0, 2.0).comment("a]]>b").build().unwrap());
+    let output = write(&data, &CDATA);
+    assert!(output.contains("<cmt><![CDATA[a]]]]><![CDATA[>b]]></cmt>"), "{}", output);
+    assert_eq!(parse(output.as_bytes()).waypoints[0].comment, Some("a]]>b".into()));
+}
+
+#[test]
+fn kept_markup() {
+    let options = ParserOptions { keep_markup: true, ..ParserOptions::default() };
+    let mut document = gpx::par::parse_with(&include_bytes!("fixtures/markup.gpx")[..], &options)
+        .unwrap().0;
+    document.data.waypoints[0].name = Some("a & b".into());
+    let mut output = Vec::new();
+    document.serialize_with_options(&mut output, &CDATA).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("<!-- exported by hand -->"), "{}", output);
+    assert!(output.contains("<name><![CDATA[a & b]]></name>"), "{}", output);
+}
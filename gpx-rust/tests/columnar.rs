@@ -0,0 +1,110 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Track segments stored in columns keep all the data of their points
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::TrackSegment;
+use gpx_rust::gpx::columnar::ColumnarSegment;
+
+
+const DOCUMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="me" xmlns="http://www.topografix.com/GPX/1/1">
+  <trk><trkseg>
+    <trkpt lat="1" lon="2">
+      <ele>3</ele><time>2017-01-01T00:00:00Z</time><magvar>4</magvar><geoidheight>5</geoidheight>
+      <name>N</name><cmt>C</cmt><desc>D</desc><src>S</src>
+      <link href="http://one/"/><link href="http://two/"><text>two</text></link>
+      <sym>Y</sym><type>T</type><fix>3d</fix><sat>6</sat>
+      <hdop>7</hdop><vdop>8</vdop><pdop>9</pdop><ageofdgpsdata>10</ageofdgpsdata><dgpsid>11</dgpsid>
+      <extensions><e xmlns="http://example.com/">x</e></extensions>
+    </trkpt>
+    <trkpt lat="1.1" lon="2.1"/>
+    <trkpt lat="1.2" lon="2.2"><sym>Flag</sym></trkpt>
+    <trkpt lat="1.3" lon="2.3"><sat>4</sat><extensions><e xmlns="http://example.com/"/></extensions></trkpt>
+    <trkpt lat="1.4" lon="2.4"><ele>-1</ele><link href="http://three/"/></trkpt>
+    <extensions><s xmlns="http://example.com/"/></extensions>
+  </trkseg></trk>
+</gpx>"#;
+
+fn segments(document: &str) -> Vec<TrackSegment> {
+    let (document, _) = gpx::par::parse(document.as_bytes()).unwrap();
+    document.data.tracks.into_iter().flat_map(|track| track.segments).collect()
+}
+
+#[test]
+fn lossless() {
+    let fixtures = [DOCUMENT,
+                    include_str!("fixtures/rich.gpx"),
+                    include_str!("fixtures/v1_0.gpx"),
+                    include_str!("fixtures/garmin.gpx")];
+    for document in fixtures.iter() {
+        for segment in segments(document) {
+            let expected = format!("{:?}", segment);
+            let columnar = ColumnarSegment::from(segment);
+            assert_eq!(format!("{:?}", TrackSegment::from(columnar)), expected);
+        }
+    }
+}
+
+#[test]
+fn pushed_like_converted() {
+    let segment = segments(DOCUMENT).remove(0);
+    let mut pushed = ColumnarSegment::new();
+    pushed.extensions = segments(DOCUMENT).remove(0).extensions;
+    for point in segments(DOCUMENT).remove(0).waypoints {
+        pushed.push(point);
+    }
+    assert_eq!(format!("{:?}", pushed), format!("{:?}", ColumnarSegment::from(segment)));
+}
+
+#[test]
+fn views() {
+    let columnar = ColumnarSegment::from(segments(DOCUMENT).remove(0));
+    assert_eq!(columnar.len(), 5);
+    assert_eq!(columnar.iter().map(|point| point.index()).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+    assert_eq!(columnar.iter().len(), 5);
+
+    let first = columnar.get(0).unwrap();
+    assert_eq!((first.latitude(), first.longitude(), first.elevation()), (1.0, 2.0, Some(3.0)));
+    assert!(first.time().is_some());
+    assert_eq!(first.mag_variation(), Some(4.0));
+    assert_eq!(first.geoid_height(), Some(5.0));
+    assert_eq!((first.name(), first.comment(), first.description(), first.source()),
+               (Some("N"), Some("C"), Some("D"), Some("S")));
+    assert_eq!(first.links().len(), 2);
+    assert_eq!(first.links()[1].text, Some("two".into()));
+    assert_eq!((first.symbol(), first.type_()), (Some("Y"), Some("T")));
+    assert!(first.fix().is_some());
+    assert_eq!(first.satellites(), Some(6));
+    assert_eq!((first.hdop(), first.vdop(), first.pdop()), (Some(7.0), Some(8.0), Some(9.0)));
+    assert_eq!((first.dgps_age(), first.dgps_id()), (Some(10.0), Some(11)));
+    assert!(first.extensions().is_some());
+
+    let empty = columnar.get(1).unwrap();
+    assert_eq!(empty.elevation(), None);
+    assert_eq!((empty.name(), empty.symbol(), empty.satellites()), (None, None, None));
+    assert!(empty.links().is_empty());
+    assert!(empty.extensions().is_none());
+
+    let points = columnar.iter().collect::<Vec<_>>();
+    assert_eq!(points[2].symbol(), Some("Flag"));
+    assert_eq!(points[2].satellites(), None);
+    assert_eq!(points[3].symbol(), None);
+    assert_eq!(points[3].satellites(), Some(4));
+    assert!(points[3].extensions().is_some());
+    assert_eq!(points[4].links()[0].href.as_str(), "http://three/");
+    assert!(columnar.get(5).is_none());
+}
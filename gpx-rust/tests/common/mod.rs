@@ -0,0 +1,126 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Parsing shared by the tests, checking that every parser agrees
+
+// each test uses a part
+#![allow(dead_code)]
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::{ Gpx, TrackSegment };
+use gpx_rust::gpx::par::stream::Event;
+use gpx_rust::par::ParserOptions;
+
+
+/// Puts streamed events back together, returning the names of the events too
+pub fn collect<I: Iterator<Item=Event>>(events: I) -> (Gpx, Vec<&'static str>) {
+    let mut gpx = Gpx::default();
+    let mut kinds = Vec::new();
+    for event in events {
+        kinds.push(match event {
+            Event::Start { version, creator } => {
+                gpx.version = version;
+                gpx.creator = creator;
+                "Start"
+            }
+            Event::Metadata(metadata) => {
+                gpx.metadata = Some(metadata);
+                "Metadata"
+            }
+            Event::Waypoint(waypoint) => {
+                gpx.waypoints.push(waypoint);
+                "Waypoint"
+            }
+            Event::RouteStart(route) => {
+                gpx.routes.push(route);
+                "RouteStart"
+            }
+            Event::RoutePoint(point) => {
+                gpx.routes.last_mut().unwrap().waypoints.push(point);
+                "RoutePoint"
+            }
+            Event::RouteEnd => "RouteEnd",
+            Event::TrackStart(track) => {
+                gpx.tracks.push(track);
+                "TrackStart"
+            }
+            Event::SegmentStart => {
+                gpx.tracks.last_mut().unwrap().segments.push(TrackSegment::default());
+                "SegmentStart"
+            }
+            Event::TrackPoint(point) => {
+                let track = gpx.tracks.last_mut().unwrap();
+                track.segments.last_mut().unwrap().waypoints.push(point);
+                "TrackPoint"
+            }
+            Event::SegmentEnd(extensions) => {
+                let track = gpx.tracks.last_mut().unwrap();
+                track.segments.last_mut().unwrap().extensions = extensions;
+                "SegmentEnd"
+            }
+            Event::TrackEnd => "TrackEnd",
+            Event::Extensions(extensions) => {
+                match gpx.extensions {
+                    Some(ref mut existing) => existing.nodes.extend(extensions.nodes),
+                    None => gpx.extensions = Some(extensions),
+                }
+                "Extensions"
+            }
+            Event::End => "End",
+        });
+    }
+    (gpx, kinds)
+}
+
+/// Document parsed by every parser
+#[derive(Debug)]
+pub struct Parsed {
+    pub data: Gpx,
+    pub warnings: Vec<String>,
+    /// Names of the streamed events
+    pub events: Vec<&'static str>,
+}
+
+/// Parses `document` whole, borrowed and streamed, checking that the data and warnings agree.
+///
+/// Returns the error message if they all refuse the document.
+pub fn parse(document: &str, options: &ParserOptions) -> Result<Parsed, String> {
+    let whole = gpx::par::parse_with(document.as_bytes(), options);
+    let borrowed = gpx::borrowed::parse_with(document, options);
+    let mut stream = gpx::par::stream_with(document.as_bytes(), options.clone());
+    let streamed = stream.by_ref().collect::<Result<Vec<_>, _>>();
+    let messages = |warnings: Vec<_>| warnings.iter().map(|w| format!("{}", w))
+                                              .collect::<Vec<_>>();
+    match (whole, borrowed, streamed) {
+        (Ok((whole, whole_warnings)), Ok((borrowed, borrowed_warnings)), Ok(events)) => {
+            let (streamed, events) = collect(events.into_iter());
+            let expected = format!("{:?}", whole.data);
+            assert_eq!(format!("{:?}", borrowed.into_owned()), expected);
+            assert_eq!(format!("{:?}", streamed), expected);
+            let warnings = messages(whole_warnings);
+            assert_eq!(messages(borrowed_warnings), warnings);
+            assert_eq!(messages(stream.take_warnings()), warnings);
+            Ok(Parsed { data: whole.data, warnings, events })
+        }
+        (Err(whole), Err(borrowed), Err(streamed)) => {
+            let expected = format!("{}", whole);
+            assert_eq!(format!("{}", borrowed), expected);
+            assert_eq!(format!("{}", streamed), expected);
+            Err(expected)
+        }
+        (whole, borrowed, streamed) => panic!("Parsers disagree: {:?} {:?} {:?}",
+                                              whole.map(|_| ()).map_err(|e| e.to_string()),
+                                              borrowed.map(|_| ()).map_err(|e| e.to_string()),
+                                              streamed.map(|_| ()).map_err(|e| e.to_string())),
+    }
+}
@@ -0,0 +1,132 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Addresses must fit `emailType`, and GPX 1.0 ones not fitting its pattern follow `unknown_elements`
+
+extern crate gpx_rust;
+
+mod common;
+
+use std::str::FromStr;
+
+use gpx_rust::gpx::Email;
+use gpx_rust::par::{ ParserOptions, Policy };
+
+use common::{ parse, Parsed };
+
+
+const RICH: &str = include_str!("fixtures/rich.gpx");
+const V1_0: &str = include_str!("fixtures/v1_0.gpx");
+
+fn with_email(address: &str) -> String {
+    V1_0.replace("jane@example.com", address)
+}
+
+fn with_email_v1_1(id: &str, domain: &str) -> String {
+    RICH.replace("<email id=\"a\" domain=\"b.c\"/>",
+                 &format!("<email id=\"{}\" domain=\"{}\"/>", id, domain))
+}
+
+fn unknown_elements(policy: Policy) -> ParserOptions {
+    ParserOptions { unknown_elements: policy, ..ParserOptions::default() }
+}
+
+#[test]
+fn accepted() {
+    for address in &["jane@example.com", "jane.doe@mail.example.com", "john+gpx@example.com",
+                     "a@localhost", "a..b@x.y", "a b@x.y"] {
+        assert_eq!(Email::from_str(address).unwrap().to_string(), *address);
+    }
+    let email = Email::new("a", "b.c").unwrap();
+    assert_eq!((email.id(), email.domain()), ("a", "b.c"));
+}
+
+#[test]
+fn refused() {
+    for address in &["@x.y", "a@", "a", "a@x@y.z"] {
+        assert!(Email::from_str(address).is_err(), "{} accepted", address);
+    }
+    assert!(Email::new("", "b.c").is_err());
+    assert!(Email::new("a", "").is_err());
+    assert!(Email::new("a@b", "c").is_err());
+}
+
+#[test]
+fn v1_1_free() {
+    for &(id, domain) in &[("john+gpx", "example.com"), ("john.doe", "example.com"),
+                           ("john", "localhost"), ("first_last", "my-host.co.uk")] {
+        let data = parse(&with_email_v1_1(id, domain), &ParserOptions::default()).unwrap().data;
+        let email = data.metadata.unwrap().author.unwrap().email.unwrap();
+        assert_eq!((email.id(), email.domain()), (id, domain));
+    }
+}
+
+#[test]
+fn v1_1_refused() {
+    for &(id, domain) in &[("", "example.com"), ("john", ""), ("john@x", "example.com")] {
+        assert!(parse(&with_email_v1_1(id, domain), &ParserOptions::default()).is_err(),
+                "{}@{} accepted", id, domain);
+    }
+}
+
+#[test]
+fn v1_0_pattern() {
+    for address in &["jane.doe@mail.example.com", "billgates2004@hotmail.com",
+                     "first_last@my-host.co.uk"] {
+        let data = parse(&with_email(address), &ParserOptions::default()).unwrap().data;
+        assert_eq!(data.metadata.unwrap().author.unwrap().email.unwrap().to_string(), *address);
+    }
+    for address in &["a@localhost", "a..b@x.y", ".a@x.y", "a.@x.y", "a@x..y", "a@.x.y", "a@x.y.",
+                     "@x.y", "a@", "a", "a b@x.y", "a@x@y.z", "a+b@x.y"] {
+        assert!(parse(&with_email(address), &ParserOptions::default()).is_err(),
+                "{} accepted", address);
+    }
+}
+
+#[test]
+fn v1_0_refused_by_default() {
+    let error = parse(&with_email("jane@localhost"), &ParserOptions::default()).unwrap_err();
+    assert!(error.contains("localhost"), "{}", error);
+}
+
+#[test]
+fn v1_0_skipped() {
+    let options = unknown_elements(Policy::Skip);
+    let Parsed { data, warnings, .. } = parse(&with_email("jane@localhost"), &options).unwrap();
+    let author = data.metadata.unwrap().author.unwrap();
+    assert_eq!(author.name, Some("Jane".into()));
+    assert!(author.email.is_none());
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("email"), "{}", warnings[0]);
+    assert!(!format!("{:?}", data.extensions).contains("localhost"));
+}
+
+#[test]
+fn v1_0_preserved() {
+    let options = unknown_elements(Policy::Preserve);
+    let Parsed { data, warnings, .. } = parse(&with_email(" a..b@x.y "), &options).unwrap();
+    assert!(data.metadata.unwrap().author.unwrap().email.is_none());
+    assert!(warnings.is_empty());
+    let extensions = format!("{:?}", data.extensions.unwrap());
+    assert!(extensions.contains("email") && extensions.contains(" a..b@x.y "), "{}", extensions);
+}
+
+#[test]
+fn v1_0_valid_kept() {
+    for policy in &[Policy::Error, Policy::Skip, Policy::Preserve] {
+        let Parsed { data, warnings, .. } = parse(V1_0, &unknown_elements(*policy)).unwrap();
+        let email = data.metadata.unwrap().author.unwrap().email.unwrap();
+        assert_eq!(email.to_string(), "jane@example.com");
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Registered extensions are typed, others stay plain elements
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::{ Gpx, Waypoint };
+use gpx_rust::gpx::garmin;
+use gpx_rust::par::{ ParserOptions, ParseLimits };
+use gpx_rust::ser::SerializeDocument;
+use gpx_rust::xml;
+
+
+const GARMIN: &[u8] = include_bytes!("fixtures/garmin.gpx");
+
+fn registered() -> ParserOptions {
+    let mut options = ParserOptions::default();
+    garmin::register(&mut options.extensions);
+    options
+}
+
+fn parse(document: &[u8], options: &ParserOptions) -> Gpx {
+    gpx::par::parse_with(document, options).unwrap().0.data
+}
+
+fn points(data: &Gpx) -> &[Waypoint] {
+    &data.tracks[0].segments[0].waypoints
+}
+
+#[test]
+fn untyped_by_default() {
+    let data = parse(GARMIN, &ParserOptions::default());
+    let point = &points(&data)[0];
+    assert!(point.track_point_extension().is_none());
+    match point.extensions.as_ref().unwrap().nodes[0] {
+        xml::Node::Element(ref name, _) => assert_eq!(name.local_name, "TrackPointExtension"),
+        ref other => panic!("Unexpected node {:?}", other),
+    }
+}
+
+#[test]
+fn typed_when_registered() {
+    let data = parse(GARMIN, &registered());
+    let first = points(&data)[0].track_point_extension().unwrap();
+    assert_eq!(first.air_temperature, Some(21.5));
+    assert_eq!(first.heart_rate, Some(140));
+    assert_eq!(first.cadence, Some(80));
+    let second = points(&data)[1].track_point_extension().unwrap();
+    assert_eq!(second.heart_rate, Some(141));
+    assert_eq!(second.speed, Some(3.5));
+    assert!(points(&data)[2].track_point_extension().is_none());
+}
+
+#[test]
+fn round_trip() {
+    let options = registered();
+    let data = parse(GARMIN, &options);
+    let mut output = Vec::new();
+    data.serialize(&mut output).unwrap();
+    assert_eq!(format!("{:?}", parse(&output, &options)), format!("{:?}", data));
+}
+
+/// Typed extensions are read once, so they count against the limits like plain ones
+#[test]
+fn counted_once() {
+    for max in 1..40 {
+        let limits = ParseLimits { max_elements: Some(max), ..ParseLimits::default() };
+        let plain = ParserOptions { limits: limits.clone(), ..ParserOptions::default() };
+        let typed = ParserOptions { limits, ..registered() };
+        assert_eq!(gpx::par::parse_with(GARMIN, &typed).is_ok(),
+                   gpx::par::parse_with(GARMIN, &plain).is_ok(),
+                   "max_elements {}", max);
+    }
+}
@@ -0,0 +1,153 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Garmin extensions keep their data through writing and reading back
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::{ Gpx, Waypoint };
+use gpx_rust::gpx::garmin;
+use gpx_rust::gpx::garmin::TrackPointExtension;
+use gpx_rust::par::ParserOptions;
+use gpx_rust::ser::SerializeDocument;
+
+
+fn options() -> ParserOptions {
+    let mut options = ParserOptions::default();
+    garmin::register(&mut options.extensions);
+    options
+}
+
+fn parse(document: &[u8]) -> Gpx {
+    gpx::par::parse_with(document, &options()).unwrap().0.data
+}
+
+fn write(data: &Gpx) -> String {
+    let mut output = Vec::new();
+    data.serialize(&mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+fn points(data: &Gpx) -> &[Waypoint] {
+    &data.tracks[0].segments[0].waypoints
+}
+
+/// Checks the points of the `garmin.gpx` fixture, mixing both versions of the extension
+fn check_track_points(data: &Gpx) {
+    let v1 = points(data)[0].track_point_extension().unwrap();
+    assert_eq!(v1.air_temperature, Some(21.5));
+    assert_eq!(v1.heart_rate, Some(140));
+    assert_eq!(v1.cadence, Some(80));
+    assert_eq!(v1.speed, None);
+    let v2 = points(data)[1].track_point_extension().unwrap();
+    assert_eq!(v2.air_temperature, None);
+    assert_eq!(v2.heart_rate, Some(141));
+    assert_eq!(v2.speed, Some(3.5));
+    assert_eq!(v2.course, Some(359.5));
+    assert_eq!(v2.extensions.as_ref().unwrap().nodes.len(), 1);
+    assert!(points(data)[2].track_point_extension().is_none());
+}
+
+#[test]
+fn track_point_extension() {
+    let data = parse(include_bytes!("fixtures/garmin.gpx"));
+    check_track_points(&data);
+    let output = write(&data);
+    // each version is written back in its own namespace
+    assert!(output.contains("<gpxtpx:TrackPointExtension \
+                              xmlns:gpxtpx=\"http://www.garmin.com/xmlschemas/TrackPointExtension/v1\">"),
+            "{}", output);
+    assert!(output.contains("<tp2:TrackPointExtension \
+                              xmlns:tp2=\"http://www.garmin.com/xmlschemas/TrackPointExtension/v2\">"),
+            "{}", output);
+    check_track_points(&parse(output.as_bytes()));
+}
+
+/// Export of a device recording, as offered by Garmin Connect
+#[test]
+fn connect_export() {
+    let data = parse(include_bytes!("fixtures/garmin_connect.gpx"));
+    let values = |data: &Gpx| {
+        points(data).iter().map(|point| {
+            let extension = point.track_point_extension().unwrap();
+            (extension.air_temperature, extension.heart_rate, extension.cadence)
+        }).collect::<Vec<_>>()
+    };
+    assert_eq!(values(&data), vec![(Some(18.0), Some(97), Some(0)), (Some(18.0), Some(99), Some(62))]);
+    let output = write(&data);
+    let again = parse(output.as_bytes());
+    assert_eq!(values(&again), values(&data));
+    assert_eq!(format!("{:?}", again), format!("{:?}", data));
+}
+
+/// Checks the route of the `garmin_route.gpx` fixture
+fn check_route(data: &Gpx) {
+    let route = &data.routes[0];
+    let extension = route.route_extension().unwrap();
+    assert_eq!(extension.is_auto_named, Some(false));
+    assert_eq!(format!("{:?}", extension.display_color), "Some(Magenta)");
+    let first = route.waypoints[0].route_point_extension().unwrap();
+    assert_eq!(first.subclass.as_ref().map(Vec::len), Some(18));
+    let shaping = first.points.iter()
+                              .map(|point| (point.latitude, point.longitude, point.subclass.clone()))
+                              .collect::<Vec<_>>();
+    assert_eq!(shaping, vec![(50.061518, 19.938521,
+                              Some(vec![0x0f, 0x01, 0x11, 0, 0, 0, 0xc0, 0x5e, 0, 0, 0, 0, 0, 0, 0, 0x0c, 0, 0])),
+                             (50.062303, 19.940102, None)]);
+    assert!(route.waypoints[1].route_point_extension().unwrap().points.is_empty());
+}
+
+/// Route calculated by BaseCamp, with the shaping points leading to each route point
+#[test]
+fn route_shaping_points() {
+    let data = parse(include_bytes!("fixtures/garmin_route.gpx"));
+    check_route(&data);
+    let output = write(&data);
+    assert!(output.contains("<gpxx:rpt lat=\"50.062303\" lon=\"19.940102\""), "{}", output);
+    check_route(&parse(output.as_bytes()));
+}
+
+/// `xsd:double` values hold infinities and NaN
+#[test]
+fn special_doubles() {
+    let mut data = Gpx::new("x");
+    let mut point = Waypoint::at(1.0, 2.0).build().unwrap();
+    point.set_track_point_extension(TrackPointExtension { air_temperature: Some(f64::INFINITY),
+                                                          water_temperature: Some(f64::NEG_INFINITY),
+                                                          depth: Some(f64::NAN),
+                                                          ..TrackPointExtension::default() });
+    data.waypoints.push(point);
+    let output = write(&data);
+    assert!(output.contains("<gpxtpx:atemp>INF</gpxtpx:atemp>"), "{}", output);
+    assert!(output.contains("<gpxtpx:wtemp>-INF</gpxtpx:wtemp>"), "{}", output);
+    assert!(output.contains("<gpxtpx:depth>NaN</gpxtpx:depth>"), "{}", output);
+    let again = parse(output.as_bytes());
+    let extension = again.waypoints[0].track_point_extension().unwrap();
+    assert_eq!(extension.air_temperature, Some(f64::INFINITY));
+    assert_eq!(extension.water_temperature, Some(f64::NEG_INFINITY));
+    assert!(extension.depth.unwrap().is_nan());
+}
+
+/// Degrees are limited to a range, which leaves no room for NaN
+#[test]
+fn course_not_a_number() {
+    let mut data = Gpx::new("x");
+    let mut point = Waypoint::at(1.0, 2.0).build().unwrap();
+    point.set_track_point_extension(TrackPointExtension { course: Some(f64::NAN),
+                                                          ..TrackPointExtension::default() });
+    data.waypoints.push(point);
+    let error = data.serialize(Vec::new()).unwrap_err();
+    assert_eq!(error.to_string(),
+               "Value can't be written: Value outside of allowed range: must be at least 0, got NaN");
+}
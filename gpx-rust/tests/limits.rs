@@ -0,0 +1,106 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Documents going over `ParseLimits` are refused with a typed error by every parser
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::par::{ DocumentError, Error };
+use gpx_rust::par::{ Limit, ParseLimits, ParserOptions, DEFAULT_MAX_DEPTH };
+
+
+fn document(content: &str) -> String {
+    format!("<?xml version=\"1.0\"?>\n\
+             <gpx xmlns=\"http://www.topografix.com/GPX/1/1\" version=\"1.1\" creator=\"x\">\n\
+             {}\n</gpx>", content)
+}
+
+/// Returns the limit broken by the document, if any
+fn broken(error: DocumentError) -> (Limit, u64) {
+    match error {
+        DocumentError::BadData(positioned) => match positioned.data {
+            Error::LimitExceeded { limit, max } => (limit, max),
+            other => panic!("Unexpected error {:?}", other),
+        },
+        other => panic!("Unexpected error {:?}", other),
+    }
+}
+
+/// Parses `document` with each parser, checking that they agree on the limit it breaks
+fn check(document: &str, limits: ParseLimits) -> Option<(Limit, u64)> {
+    let options = ParserOptions { limits, ..ParserOptions::default() };
+    let whole = gpx::par::parse_with(document.as_bytes(), &options).err().map(broken);
+    let streamed = gpx::par::stream_with(document.as_bytes(), options.clone())
+                       .filter_map(|event| event.err()).next().map(broken);
+    let borrowed = gpx::borrowed::parse_with(document, &options).err().map(broken);
+    assert_eq!(streamed, whole);
+    assert_eq!(borrowed, whole);
+    whole
+}
+
+fn nested(depth: usize) -> String {
+    format!("<extensions>{}{}</extensions>", "<a>".repeat(depth), "</a>".repeat(depth))
+}
+
+#[test]
+fn depth() {
+    let limits = ParseLimits { max_depth: Some(5), ..ParseLimits::default() };
+    // gpx, extensions and three more
+    assert_eq!(check(&document(&nested(3)), limits.clone()), None);
+    assert_eq!(check(&document(&nested(4)), limits), Some((Limit::Depth, 5)));
+}
+
+#[test]
+fn deep_nesting_refused_by_default() {
+    let document = document(&nested(100_000));
+    assert_eq!(check(&document, ParseLimits::default()),
+               Some((Limit::Depth, DEFAULT_MAX_DEPTH as u64)));
+}
+
+#[test]
+fn elements() {
+    let points = "<wpt lat=\"1\" lon=\"2\"><name>a</name></wpt>".repeat(3);
+    let limits = |max| ParseLimits { max_elements: Some(max), ..ParseLimits::default() };
+    assert_eq!(check(&document(&points), limits(7)), None);
+    assert_eq!(check(&document(&points), limits(6)), Some((Limit::Elements, 6)));
+}
+
+#[test]
+fn text_length() {
+    let limits = ParseLimits { max_text_length: Some(10), ..ParseLimits::default() };
+    let named = |name: &str| document(&format!("<wpt lat=\"1\" lon=\"2\"><name>{}</name></wpt>", name));
+    assert_eq!(check(&named("0123456789"), limits.clone()), None);
+    assert_eq!(check(&named("0123456789a"), limits.clone()), Some((Limit::TextLength, 10)));
+    // inside extensions, and split by markup
+    let extension = document("<extensions><a>01234<!-- x --><![CDATA[56789a]]></a></extensions>");
+    assert_eq!(check(&extension, limits), Some((Limit::TextLength, 10)));
+}
+
+#[test]
+fn attributes() {
+    let limits = ParseLimits { max_attributes: Some(2), ..ParseLimits::default() };
+    assert_eq!(check(&document("<wpt lat=\"1\" lon=\"2\"/>"), limits.clone()), None);
+    assert_eq!(check(&document("<extensions><a b=\"1\" c=\"2\" d=\"3\"/></extensions>"), limits),
+               Some((Limit::Attributes, 2)));
+}
+
+#[test]
+fn waypoints() {
+    let limits = ParseLimits { max_waypoints: Some(3), ..ParseLimits::default() };
+    let points = |count| document(&format!(
+        "<wpt lat=\"1\" lon=\"2\"/><rte>{}</rte>",
+        "<rtept lat=\"1\" lon=\"2\"/>".repeat(count)));
+    assert_eq!(check(&points(2), limits.clone()), None);
+    assert_eq!(check(&points(3), limits), Some((Limit::Waypoints, 3)));
+}
@@ -0,0 +1,84 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Markup kept with `ParserOptions::keep_markup` is written back
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::Document;
+use gpx_rust::par::ParserOptions;
+use gpx_rust::ser::SerializeDocument;
+
+
+const MARKUP: &str = include_str!("fixtures/markup.gpx");
+
+fn parse(document: &[u8]) -> Document {
+    let options = ParserOptions { keep_markup: true, ..ParserOptions::default() };
+    gpx::par::parse_with(document, &options).unwrap().0
+}
+
+fn write(document: &Document) -> String {
+    let mut output = Vec::new();
+    document.serialize(&mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+fn assert_same(found: &Document, expected: &Document) {
+    assert_eq!(format!("{:?}", found.data), format!("{:?}", expected.data));
+    assert_eq!(format!("{:?}", found.info.prolog), format!("{:?}", expected.info.prolog));
+    assert_eq!(format!("{:?}", found.info.epilog), format!("{:?}", expected.info.epilog));
+    assert_eq!(format!("{:?}", found.info.namespace), format!("{:?}", expected.info.namespace));
+}
+
+#[test]
+fn round_trip() {
+    let original = parse(MARKUP.as_bytes());
+    let output = write(&original);
+    assert_same(&parse(output.as_bytes()), &original);
+    // a second pass changes nothing
+    assert_eq!(write(&parse(output.as_bytes())), output);
+}
+
+#[test]
+fn kept_in_place() {
+    let output = write(&parse(MARKUP.as_bytes()));
+    let prolog = output.find("<?xml-stylesheet type=\"text/xsl\" href=\"gpx.xsl\"?>").unwrap();
+    let comment = output.find("<!-- exported by hand -->").unwrap();
+    let root = output.find("<gpx ").unwrap();
+    assert!(prolog < comment && comment < root);
+    assert!(output.contains("xmlns:topo=\"urn:topo\""));
+    assert!(output.contains("<topo:note><![CDATA[a < b]]><?pi data?>"));
+    assert!(output.contains("<plain xmlns=\"urn:plain\">"));
+    assert!(output.trim_end().ends_with("</gpx>\n<!-- trailer -->"));
+}
+
+/// Markup among GPX elements only survives inside `extensions`
+#[test]
+fn moved_into_extensions() {
+    let output = write(&parse(MARKUP.as_bytes()));
+    for comment in &["<!-- first point -->", "<!-- meters -->", "<!-- gap -->"] {
+        let at = output.find(comment).unwrap();
+        let extensions = output[..at].rfind("<extensions>").unwrap();
+        assert!(!output[extensions..at].contains("</extensions>"), "{} not in extensions", comment);
+    }
+}
+
+#[test]
+fn declares_utf8() {
+    let latin1 = MARKUP.replace("encoding=\"UTF-8\"", "encoding=\"ISO-8859-1\"");
+    let document = parse(latin1.as_bytes());
+    assert_eq!(document.info.encoding, "ISO-8859-1");
+    let output = write(&document);
+    assert!(output.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\""));
+}
@@ -0,0 +1,86 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Numbers without a representation in GPX are refused when building and writing
+
+extern crate gpx_rust;
+
+use std::f64;
+
+use gpx_rust::gpx::{ Gpx, Waypoint, Point };
+use gpx_rust::ser::SerializeDocument;
+
+
+const NOT_FINITE: [f64; 3] = [f64::NAN, f64::INFINITY, f64::NEG_INFINITY];
+
+/// Returns the message of the error writing a document holding only `point`
+fn write_error(point: Waypoint) -> String {
+    let mut data = Gpx::new("test");
+    data.waypoints.push(point);
+    let mut output = Vec::new();
+    format!("{}", data.serialize(&mut output).unwrap_err())
+}
+
+fn at(latitude: f64, longitude: f64) -> Waypoint {
+    Waypoint { location: Point { latitude, longitude, elevation: None },
+               ..Waypoint::default() }
+}
+
+#[test]
+fn coordinates() {
+    for &value in NOT_FINITE.iter() {
+        assert!(write_error(at(value, 0.)).contains("not a finite number"), "{}", value);
+        assert!(write_error(at(0., value)).contains("not a finite number"), "{}", value);
+    }
+}
+
+#[test]
+fn decimals() {
+    for &value in NOT_FINITE.iter() {
+        let mut point = at(0., 0.);
+        point.location.elevation = Some(value);
+        assert!(write_error(point).contains("not a finite number"), "{}", value);
+        let point = Waypoint { hdop: Some(value), ..at(0., 0.) };
+        assert!(write_error(point).contains("not a finite number"), "{}", value);
+    }
+}
+
+#[test]
+fn degrees() {
+    for &value in NOT_FINITE.iter() {
+        let point = Waypoint { mag_variation: Some(value as f32), ..at(0., 0.) };
+        assert!(write_error(point).contains("not a finite number"), "{}", value);
+    }
+    // the upper limit is exclusive
+    let point = Waypoint { mag_variation: Some(360.), ..at(0., 0.) };
+    assert_eq!(write_error(point),
+               "Value can't be written: Value outside of allowed range: must be less than 360, got 360");
+    let point = Waypoint { mag_variation: Some(-1.), ..at(0., 0.) };
+    assert_eq!(write_error(point),
+               "Value can't be written: Value outside of allowed range: must be at least 0, got -1");
+}
+
+#[test]
+fn refused_when_building() {
+    assert!(Waypoint::at(1., 2.).ele(3.).mag_variation(4.).build().is_ok());
+    for &value in NOT_FINITE.iter() {
+        let latitude = Waypoint::at(value, 0.).build().unwrap_err();
+        assert_eq!(latitude.0.len(), 1, "{}", value);
+        assert_eq!(latitude.0[0].path, "location.latitude");
+        let longitude = Waypoint::at(0., value).build().unwrap_err();
+        assert_eq!(longitude.0[0].path, "location.longitude");
+        assert!(Waypoint::at(0., 0.).ele(value).build().is_err(), "{}", value);
+        assert!(Waypoint::at(0., 0.).mag_variation(value as f32).build().is_err(), "{}", value);
+        assert!(Waypoint::at(0., 0.).hdop(value).build().is_err(), "{}", value);
+    }
+}
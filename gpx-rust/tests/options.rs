@@ -0,0 +1,85 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Data not described by the schema is rejected, skipped or preserved as configured
+
+extern crate gpx_rust;
+
+mod common;
+
+use gpx_rust::par::{ ParserOptions, Policy };
+use gpx_rust::xml::Node;
+
+use common::Parsed;
+
+
+const DOCUMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="me" xmlns="http://www.topografix.com/GPX/1/1"
+     xmlns:foo="http://example.com/foo">
+  <wpt lat="1" lon="2" color="red" foo:size="3">
+    <name>W</name>
+    <label>L</label>
+    <foo:note>N</foo:note>
+  </wpt>
+</gpx>"#;
+
+fn parse(options: &ParserOptions) -> Result<Parsed, String> {
+    common::parse(DOCUMENT, options)
+}
+
+fn names(nodes: &[Node]) -> Vec<String> {
+    nodes.iter().filter_map(|node| match *node {
+        Node::Element(ref name, _) => Some(name.local_name.clone()),
+        _ => None,
+    }).collect()
+}
+
+#[test]
+fn error() {
+    assert!(parse(&ParserOptions::default()).is_err());
+}
+
+#[test]
+fn skip() {
+    let options = ParserOptions { unknown_elements: Policy::Skip,
+                                  unknown_attributes: Policy::Skip,
+                                  foreign_namespaces: Policy::Skip,
+                                  ..ParserOptions::default() };
+    let parsed = parse(&options).unwrap();
+    assert!(parsed.data.waypoints[0].extensions.is_none());
+    assert_eq!(parsed.warnings.len(), 4);
+}
+
+#[test]
+fn preserve_elements() {
+    let options = ParserOptions { unknown_elements: Policy::Preserve,
+                                  unknown_attributes: Policy::Skip,
+                                  foreign_namespaces: Policy::Preserve,
+                                  ..ParserOptions::default() };
+    let data = parse(&options).unwrap().data;
+    let extensions = data.waypoints[0].extensions.as_ref().unwrap();
+    assert_eq!(names(&extensions.nodes), vec!["label", "note"]);
+}
+
+#[test]
+fn attributes_not_preserved() {
+    let options = ParserOptions { unknown_elements: Policy::Skip,
+                                  unknown_attributes: Policy::Preserve,
+                                  foreign_namespaces: Policy::Preserve,
+                                  ..ParserOptions::default() };
+    let parsed = parse(&options).unwrap();
+    let extensions = parsed.data.waypoints[0].extensions.as_ref().unwrap();
+    assert!(extensions.attributes.is_empty());
+    assert_eq!(names(&extensions.nodes), vec!["note"]);
+    assert_eq!(parsed.warnings.iter().filter(|w| w.contains("attribute")).count(), 2);
+}
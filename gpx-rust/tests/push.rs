@@ -0,0 +1,73 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Feeding a document in pieces gives the same events as streaming it
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::par::PushParser;
+use gpx_rust::par::ParserOptions;
+
+
+const FIXTURES: [&str; 4] = [
+    include_str!("fixtures/rich.gpx"),
+    include_str!("fixtures/v1_0.gpx"),
+    include_str!("fixtures/markup.gpx"),
+    include_str!("fixtures/garmin.gpx"),
+];
+
+/// Feeds `document` in pieces of `size` bytes
+fn push(document: &str, size: usize, options: &ParserOptions) -> Vec<String> {
+    let mut parser = PushParser::with_options(options.clone());
+    let mut events = Vec::new();
+    for chunk in document.as_bytes().chunks(size) {
+        parser.feed(chunk);
+        events.extend(parser.by_ref().map(|event| format!("{:?}", event.unwrap())));
+    }
+    parser.finish();
+    events.extend(parser.map(|event| format!("{:?}", event.unwrap())));
+    events
+}
+
+fn assert_same(document: &str, options: &ParserOptions) {
+    let expected = gpx::par::stream_with(document.as_bytes(), options.clone())
+                       .map(|event| format!("{:?}", event.unwrap()))
+                       .collect::<Vec<_>>();
+    for &size in &[1, 2, 7, 64, document.len()] {
+        assert_eq!(push(document, size, options), expected, "pieces of {}", size);
+    }
+}
+
+#[test]
+fn fixtures() {
+    let markup = ParserOptions { keep_markup: true, ..ParserOptions::default() };
+    for document in FIXTURES.iter() {
+        assert_same(document, &ParserOptions::default());
+        assert_same(document, &markup);
+    }
+}
+
+#[test]
+fn markup_across_pieces() {
+    let long = "x]]-->?>".repeat(1000);
+    let document = format!(
+        "<?xml version=\"1.0\"?>\n\
+         <gpx version=\"1.1\" creator=\"a > b\" xmlns=\"http://www.topografix.com/GPX/1/1\">\
+         <wpt lat=\"1\" lon=\"2\"><!--{}--><name><![CDATA[{}]]></name>\
+         <cmt>c<!-- -->d</cmt><extensions><?pi {}?></extensions></wpt></gpx>",
+        long.replace("-->", "- >"), long.replace("]]>", "]>"), long.replace("?>", "? >"));
+    let markup = ParserOptions { keep_markup: true, ..ParserOptions::default() };
+    assert_same(&document, &ParserOptions::default());
+    assert_same(&document, &markup);
+}
@@ -0,0 +1,104 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Data read before an error is recovered, with the section where parsing stopped
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::par::recover::Section;
+
+
+const DOCUMENT: &str = include_str!("fixtures/rich.gpx");
+
+/// Returns the document up to the end of `marker`
+fn cut_after(marker: &str) -> &'static str {
+    let end = DOCUMENT.find(marker).expect("Marker missing") + marker.len();
+    &DOCUMENT[..end]
+}
+
+#[test]
+fn whole() {
+    let recovered = gpx::par::recover(DOCUMENT.as_bytes());
+    assert!(recovered.failure.is_none());
+    let (document, _) = gpx::parse(DOCUMENT.as_bytes()).unwrap();
+    assert_eq!(format!("{:?}", recovered.data.unwrap()), format!("{:?}", document.data));
+}
+
+#[test]
+fn prolog() {
+    let recovered = gpx::par::recover(&b"<?xml version=\"1.0\"?><gpx"[..]);
+    assert!(recovered.data.is_none());
+    assert_eq!(recovered.failure.unwrap().section, Section::Prolog);
+}
+
+#[test]
+fn inside_gpx() {
+    let recovered = gpx::par::recover(cut_after("<wpt lat=\"-1.5\" lon=\"-2.5\">").as_bytes());
+    let data = recovered.data.unwrap();
+    assert!(data.metadata.is_some());
+    assert_eq!(data.waypoints.len(), 1);
+    assert_eq!(recovered.failure.unwrap().section, Section::Gpx);
+}
+
+#[test]
+fn inside_route() {
+    let recovered = gpx::par::recover(cut_after("<rtept lat=\"1\" lon=\"2\"/>").as_bytes());
+    let data = recovered.data.unwrap();
+    assert_eq!(data.waypoints.len(), 2);
+    assert_eq!(data.routes.len(), 1);
+    assert_eq!(data.routes[0].name, Some("R".into()));
+    assert_eq!(data.routes[0].waypoints.len(), 1);
+    assert_eq!(recovered.failure.unwrap().section, Section::Route);
+}
+
+#[test]
+fn inside_segment() {
+    let recovered = gpx::par::recover(cut_after("</trkpt>").as_bytes());
+    let data = recovered.data.unwrap();
+    assert_eq!(data.routes[0].waypoints.len(), 2);
+    assert_eq!(data.tracks.len(), 1);
+    assert_eq!(data.tracks[0].segments.len(), 1);
+    assert_eq!(data.tracks[0].segments[0].waypoints.len(), 1);
+    assert_eq!(recovered.failure.unwrap().section, Section::Segment);
+}
+
+#[test]
+fn between_segments() {
+    let recovered = gpx::par::recover(cut_after("</trkseg>").as_bytes());
+    let data = recovered.data.unwrap();
+    assert_eq!(data.tracks[0].segments.len(), 1);
+    assert_eq!(data.tracks[0].segments[0].waypoints.len(), 2);
+    assert!(data.tracks[0].segments[0].extensions.is_some());
+    assert_eq!(recovered.failure.unwrap().section, Section::Track);
+}
+
+#[test]
+fn bad_data() {
+    let broken = DOCUMENT.replace("<sat>4</sat>", "<sat>four</sat>");
+    let recovered = gpx::par::recover(broken.as_bytes());
+    let data = recovered.data.unwrap();
+    assert_eq!(data.tracks[0].segments[0].waypoints.len(), 1);
+    let failure = recovered.failure.unwrap();
+    assert_eq!(failure.section, Section::Segment);
+    assert_eq!(failure.position.row + 1, 25);
+}
+
+#[test]
+fn epilog() {
+    let trailing = format!("{}<gpx/>", DOCUMENT);
+    let recovered = gpx::par::recover(trailing.as_bytes());
+    let (document, _) = gpx::parse(DOCUMENT.as_bytes()).unwrap();
+    assert_eq!(format!("{:?}", recovered.data.unwrap()), format!("{:?}", document.data));
+    assert_eq!(recovered.failure.unwrap().section, Section::Epilog);
+}
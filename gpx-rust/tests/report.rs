@@ -0,0 +1,61 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Errors are reported with the path to the element and an excerpt of the source
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::par::DocumentError;
+
+
+const DOCUMENT: &str = "<?xml version=\"1.0\"?>
+<gpx xmlns=\"http://www.topografix.com/GPX/1/1\" version=\"1.1\" creator=\"x\">
+  <trk>
+    <trkseg><trkpt lat=\"1\" lon=\"2\"/></trkseg>
+    <trkseg>
+      <trkpt lat=\"1\" lon=\"2\"><name>Łódź</name></trkpt><trkpt lat=\"1\" lon=\"3\"><time>yesterday</time></trkpt>
+    </trkseg>
+  </trk>
+</gpx>";
+
+fn report(document: &str) -> String {
+    match gpx::parse(document.as_bytes()) {
+        Err(DocumentError::BadData(positioned)) => positioned.report(document),
+        Err(other) => panic!("Unexpected error {:?}", other),
+        Ok(_) => panic!("Document accepted"),
+    }
+}
+
+#[test]
+fn deep_in_track() {
+    assert_eq!(report(DOCUMENT),
+               "Position 6:84 in /gpx/trk[1]/trkseg[2]/trkpt[2]/time: \
+                Bad date and time: input contains invalid characters\n\
+                \x20 text: \"yesterday\"\n\
+                \x20 6 | ...me></trkpt><trkpt lat=\"1\" lon=\"3\"><time>yesterday</time></trkpt>\n\
+                \x20   |                                            ^\n");
+}
+
+#[test]
+fn column_in_characters() {
+    let document = "<gpx xmlns=\"http://www.topografix.com/GPX/1/1\" version=\"1.1\" creator=\"x\">\n\
+                    <metadata><name>Łódź</name><time>now</time></metadata>\n\
+                    </gpx>";
+    assert_eq!(report(document),
+               "Position 2:34 in /gpx/metadata/time: \
+                Bad date and time: input contains invalid characters\n\
+                \x20 text: \"now\"\n\
+                \x20 2 | <metadata><name>Łódź</name><time>now</time></metadata>\n\
+                \x20   |                                  ^\n");
+}
@@ -0,0 +1,73 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Missing required attributes are refused with a typed, positioned error by every parser
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::par::{ DocumentError, Error };
+use gpx_rust::par::ParserOptions;
+
+
+fn document(content: &str) -> String {
+    format!("<?xml version=\"1.0\"?>\n\
+             <gpx xmlns=\"http://www.topografix.com/GPX/1/1\" version=\"1.1\" creator=\"x\">\n\
+             {}\n</gpx>", content)
+}
+
+/// Returns the element and attribute found missing, with the position
+fn missing(error: DocumentError) -> (String, String, String) {
+    match error {
+        DocumentError::BadData(positioned) => match positioned.data {
+            Error::MissingAttribute { element, attribute } => {
+                (element.local_name, attribute.local_name, positioned.position.to_string())
+            }
+            other => panic!("Unexpected error {:?}", other),
+        },
+        other => panic!("Unexpected error {:?}", other),
+    }
+}
+
+/// Parses `document` with each parser, checking that they agree on the error
+fn check(document: &str) -> (String, String, String) {
+    let options = ParserOptions::default();
+    let whole = gpx::par::parse_with(document.as_bytes(), &options).err().map(missing);
+    let streamed = gpx::par::stream_with(document.as_bytes(), options.clone())
+                       .filter_map(|event| event.err()).next().map(missing);
+    let borrowed = gpx::borrowed::parse_with(document, &options).err().map(missing);
+    assert_eq!(streamed, whole);
+    assert_eq!(borrowed, whole);
+    whole.expect("Document accepted")
+}
+
+fn expected(element: &str, attribute: &str, position: &str) -> (String, String, String) {
+    (element.into(), attribute.into(), position.into())
+}
+
+#[test]
+fn waypoint() {
+    assert_eq!(check(&document("<wpt lon=\"1\"/>")), expected("wpt", "lat", "3:1"));
+}
+
+#[test]
+fn bounds() {
+    let content = "<metadata>\n<bounds minlat=\"1\" minlon=\"2\" maxlon=\"4\"/>\n</metadata>";
+    assert_eq!(check(&document(content)), expected("bounds", "maxlat", "4:1"));
+}
+
+#[test]
+fn email() {
+    let content = "<metadata><author><email id=\"x\"/></author></metadata>";
+    assert_eq!(check(&document(content)), expected("email", "domain", "3:19"));
+}
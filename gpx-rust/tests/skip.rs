@@ -0,0 +1,162 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Parts of documents chosen in `Skip` are left out the same way by every parser
+
+extern crate gpx_rust;
+
+mod common;
+
+use gpx_rust::par::{ ParseLimits, ParserOptions, Skip };
+
+use common::{ parse, Parsed };
+
+
+const RICH: &str = include_str!("fixtures/rich.gpx");
+const V1_0: &str = include_str!("fixtures/v1_0.gpx");
+
+/// Returns the beginning of `document` up to the first waypoint's start tag, followed by garbage
+fn cut(document: &str) -> String {
+    let start = document.find("<wpt").unwrap();
+    let end = start + document[start..].find('>').unwrap() + 1;
+    format!("{}<<&broken", &document[..end])
+}
+
+fn skipping(skip: Skip) -> ParserOptions {
+    ParserOptions { skip, ..ParserOptions::default() }
+}
+
+#[test]
+fn nothing_by_default() {
+    let data = parse(RICH, &ParserOptions::default()).unwrap().data;
+    assert_eq!(data.waypoints.len(), 2);
+    assert_eq!(data.routes.len(), 1);
+    assert_eq!(data.tracks.len(), 1);
+}
+
+#[test]
+fn subtrees() {
+    let options = skipping(Skip { waypoints: true, ..Skip::default() });
+    let Parsed { data, events, .. } = parse(RICH, &options).unwrap();
+    assert!(data.waypoints.is_empty());
+    assert_eq!(data.routes[0].waypoints.len(), 2);
+    assert_eq!(data.tracks.len(), 1);
+    assert!(!events.contains(&"Waypoint"));
+
+    let options = skipping(Skip { routes: true, tracks: true, ..Skip::default() });
+    let Parsed { data, events, .. } = parse(RICH, &options).unwrap();
+    assert_eq!(data.waypoints.len(), 2);
+    assert!(data.routes.is_empty());
+    assert!(data.tracks.is_empty());
+    assert_eq!(events, vec!["Start", "Metadata", "Waypoint", "Waypoint", "End"]);
+}
+
+#[test]
+fn extensions() {
+    let options = skipping(Skip { extensions: true, ..Skip::default() });
+    let data = parse(RICH, &options).unwrap().data;
+    assert!(data.tracks[0].segments[0].extensions.is_none());
+    assert_eq!(data.tracks[0].segments[0].waypoints.len(), 2);
+
+    // GPX 1.0 extensions are foreign elements among the GPX ones
+    let data = parse(V1_0, &ParserOptions::default()).unwrap().data;
+    assert!(data.waypoints[0].extensions.is_some());
+    let data = parse(V1_0, &options).unwrap().data;
+    assert!(data.waypoints[0].extensions.is_none());
+    assert!(data.routes[0].extensions.is_none());
+    assert!(data.extensions.is_none());
+    assert_eq!(data.waypoints[0].symbol, Some("Flag".into()));
+}
+
+#[test]
+fn links() {
+    let options = skipping(Skip { links: true, ..Skip::default() });
+    let data = parse(RICH, &options).unwrap().data;
+    let metadata = data.metadata.unwrap();
+    assert!(metadata.links.is_empty());
+    assert!(metadata.author.unwrap().link.is_none());
+    assert!(data.waypoints[0].links.is_empty());
+    assert_eq!(data.waypoints[0].name, Some("W1".into()));
+
+    let data = parse(V1_0, &options).unwrap().data;
+    assert!(data.metadata.unwrap().links.is_empty());
+    assert!(data.waypoints[0].links.is_empty());
+    assert!(data.routes[0].links.is_empty());
+}
+
+#[test]
+fn not_checked_against_schema() {
+    let broken = RICH.replace("<name>W2</name>", "<name>W2</name><bogus/>")
+                     .replace("lat=\"-1.5\"", "lat=\"south\"");
+    assert!(parse(&broken, &ParserOptions::default()).is_err());
+    let data = parse(&broken, &skipping(Skip { waypoints: true, ..Skip::default() }))
+                   .unwrap().data;
+    assert!(data.waypoints.is_empty());
+}
+
+#[test]
+fn checked_against_limits() {
+    let long = RICH.replace("<name>W2</name>", &format!("<name>{}</name>", "x".repeat(100)));
+    let options = ParserOptions {
+        skip: Skip { waypoints: true, ..Skip::default() },
+        limits: ParseLimits { max_text_length: Some(50), ..ParseLimits::default() },
+        ..ParserOptions::default()
+    };
+    let error = parse(&long, &options).unwrap_err();
+    assert!(error.contains("50"), "{}", error);
+
+    // the whole text counts, even in pieces shorter than the limit
+    let pieces = format!("<name>{0}&amp;{0}<![CDATA[{0}]]></name>", "x".repeat(20));
+    let split = RICH.replace("<name>W2</name>", &pieces);
+    let error = parse(&split, &options).unwrap_err();
+    assert!(error.contains("50"), "{}", error);
+    assert!(parse(&split, &ParserOptions { limits: ParseLimits::default(), ..options }).is_ok());
+}
+
+#[test]
+fn after_metadata() {
+    // nothing after the metadata is read
+    let document = cut(RICH);
+    let options = skipping(Skip { after_metadata: true, ..Skip::default() });
+    let Parsed { data, events, .. } = parse(&document, &options).unwrap();
+    assert_eq!(data.metadata.unwrap().name, Some("Test & more".into()));
+    assert!(data.waypoints.is_empty());
+    assert_eq!(events, vec!["Start", "Metadata", "End"]);
+}
+
+#[test]
+fn after_metadata_v1_0() {
+    let document = cut(V1_0);
+    let options = skipping(Skip { after_metadata: true, ..Skip::default() });
+    let Parsed { data, events, .. } = parse(&document, &options).unwrap();
+    let metadata = data.metadata.unwrap();
+    assert_eq!(metadata.name, Some("Trip".into()));
+    assert_eq!(metadata.links.len(), 1);
+    assert!(data.waypoints.is_empty());
+    assert_eq!(events, vec!["Start", "Metadata", "End"]);
+}
+
+#[test]
+fn after_skipped_metadata() {
+    // GPX 1.0 links are metadata, skipped before the stop
+    let document = cut(V1_0);
+    let Parsed { data, events, .. } = parse(&document, &skipping(Skip::all())).unwrap();
+    let metadata = data.metadata.unwrap();
+    assert_eq!(metadata.name, Some("Trip".into()));
+    assert!(metadata.links.is_empty());
+    assert_eq!(events, vec!["Start", "Metadata", "End"]);
+
+    let document = cut(RICH);
+    let data = parse(&document, &skipping(Skip::all())).unwrap().data;
+    assert!(data.metadata.unwrap().links.is_empty());
+}
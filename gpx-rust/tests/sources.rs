@@ -0,0 +1,74 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Errors can be passed between threads, and lead to their underlying causes
+
+extern crate gpx_rust;
+extern crate xml;
+
+use std::error::Error;
+use std::num::ParseFloatError;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::{ Gpx, Waypoint };
+use gpx_rust::gpx::par::DocumentError;
+use gpx_rust::ser;
+use gpx_rust::ser::SerializeDocument;
+use gpx_rust::xsd;
+
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn send_sync() {
+    assert_send_sync::<DocumentError>();
+    assert_send_sync::<ser::Error>();
+}
+
+/// Returns the last error of the `source` chain
+fn cause<'a>(error: &'a (dyn Error + 'static)) -> &'a (dyn Error + 'static) {
+    match error.source() {
+        Some(source) => cause(source),
+        None => error,
+    }
+}
+
+fn parse_error(document: &str) -> DocumentError {
+    match gpx::parse(document.as_bytes()) {
+        Err(error) => error,
+        Ok(_) => panic!("Document accepted"),
+    }
+}
+
+#[test]
+fn bad_number() {
+    let error = parse_error("<gpx xmlns=\"http://www.topografix.com/GPX/1/1\" version=\"1.1\" creator=\"x\">\
+                             <wpt lat=\"north\" lon=\"1\"/></gpx>");
+    assert!(cause(&error).downcast_ref::<ParseFloatError>().is_some(), "{:?}", error);
+}
+
+#[test]
+fn malformed() {
+    let error = parse_error("<gpx xmlns=\"http://www.topografix.com/GPX/1/1\" version=\"1.1\" creator=\"x\">\
+                             <wpt></gpx>");
+    assert!(cause(&error).downcast_ref::<xml::reader::Error>().is_some(), "{:?}", error);
+}
+
+#[test]
+fn bad_value_written() {
+    let mut data = Gpx::new("x");
+    data.waypoints.push(Waypoint::at(1.0, 2.0).build().unwrap());
+    data.waypoints[0].location.latitude = f64::NAN;
+    let error = data.serialize(Vec::new()).unwrap_err();
+    assert!(cause(&error).downcast_ref::<xsd::ser::Error>().is_some(), "{:?}", error);
+}
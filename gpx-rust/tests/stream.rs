@@ -0,0 +1,132 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Streaming a document gives the same data as parsing it whole
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::{ Gpx, TrackSegment };
+use gpx_rust::gpx::par::stream::Event;
+use gpx_rust::par::ParserOptions;
+
+
+const FIXTURES: [&str; 4] = [
+    include_str!("fixtures/rich.gpx"),
+    include_str!("fixtures/v1_0.gpx"),
+    include_str!("fixtures/markup.gpx"),
+    include_str!("fixtures/garmin.gpx"),
+];
+
+/// Puts streamed events back together
+fn collect<I: Iterator<Item=Event>>(events: I) -> Gpx {
+    let mut gpx = Gpx::default();
+    for event in events {
+        match event {
+            Event::Start { version, creator } => {
+                gpx.version = version;
+                gpx.creator = creator;
+            }
+            Event::Metadata(metadata) => gpx.metadata = Some(metadata),
+            Event::Waypoint(waypoint) => gpx.waypoints.push(waypoint),
+            Event::RouteStart(route) => gpx.routes.push(route),
+            Event::RoutePoint(point) => gpx.routes.last_mut().unwrap().waypoints.push(point),
+            Event::RouteEnd => {}
+            Event::TrackStart(track) => gpx.tracks.push(track),
+            Event::SegmentStart => {
+                gpx.tracks.last_mut().unwrap().segments.push(TrackSegment::default());
+            }
+            Event::TrackPoint(point) => {
+                let track = gpx.tracks.last_mut().unwrap();
+                track.segments.last_mut().unwrap().waypoints.push(point);
+            }
+            Event::SegmentEnd(extensions) => {
+                let track = gpx.tracks.last_mut().unwrap();
+                track.segments.last_mut().unwrap().extensions = extensions;
+            }
+            Event::TrackEnd => {}
+            Event::Extensions(extensions) => match gpx.extensions {
+                Some(ref mut existing) => existing.nodes.extend(extensions.nodes),
+                None => gpx.extensions = Some(extensions),
+            },
+            Event::End => {}
+        }
+    }
+    gpx
+}
+
+fn assert_same(document: &str, options: &ParserOptions) {
+    let (expected, expected_warnings) = gpx::par::parse_with(document.as_bytes(), options).unwrap();
+    let mut stream = gpx::par::stream_with(document.as_bytes(), options.clone());
+    let found = collect(stream.by_ref().map(|event| event.unwrap()));
+    assert_eq!(format!("{:?}", found), format!("{:?}", expected.data));
+    let messages = |warnings: Vec<_>| warnings.iter().map(|w| format!("{}", w))
+                                              .collect::<Vec<_>>();
+    assert_eq!(messages(stream.take_warnings()), messages(expected_warnings));
+}
+
+#[test]
+fn fixtures() {
+    for document in FIXTURES.iter() {
+        assert_same(document, &ParserOptions::default());
+    }
+}
+
+#[test]
+fn fixtures_strict() {
+    let strict = ParserOptions { strict: true, ..ParserOptions::default() };
+    for document in FIXTURES.iter() {
+        assert_same(document, &strict);
+    }
+}
+
+#[test]
+fn errors_agree() {
+    let broken = include_str!("fixtures/rich.gpx").replace("<name>W2</name>", "<name>W2</name><bogus/>");
+    let whole = gpx::par::parse(broken.as_bytes()).map(|_| ()).unwrap_err();
+    let streamed = gpx::par::stream(broken.as_bytes()).filter_map(|event| event.err()).next()
+                                                      .unwrap();
+    assert_eq!(format!("{}", streamed), format!("{}", whole));
+}
+
+/// Returns the first error of parsing `document` whole and of streaming it
+fn errors(document: &str, options: &ParserOptions) -> (Option<String>, Option<String>) {
+    let whole = gpx::par::parse_with(document.as_bytes(), options).err();
+    let streamed = gpx::par::stream_with(document.as_bytes(), options.clone())
+                       .filter_map(|event| event.err()).next();
+    (whole.map(|e| format!("{}", e)), streamed.map(|e| format!("{}", e)))
+}
+
+#[test]
+fn order_agrees_strict() {
+    let strict = ParserOptions { strict: true, ..ParserOptions::default() };
+    let contents = [
+        "<trk><name>T</name></trk><wpt lat=\"1\" lon=\"2\"/>",
+        "<extensions/><rte/>",
+        "<extensions/><extensions/>",
+        "<wpt lat=\"1\" lon=\"2\"/><rte/><rte/><trk/><extensions/>",
+        "<rte><name>R</name><rtept lat=\"1\" lon=\"2\"/><rtept lat=\"1\" lon=\"2\"/></rte>",
+        "<rte><extensions/><name>R</name></rte>",
+        "<trk><name>T</name><trkseg/><trkseg/></trk>",
+        "<trk><extensions/><extensions/><trkseg/></trk>",
+    ];
+    for content in contents.iter() {
+        let document = format!("<gpx xmlns=\"http://www.topografix.com/GPX/1/1\" version=\"1.1\" \
+                                creator=\"x\">{}</gpx>", content);
+        let (whole, streamed) = errors(&document, &strict);
+        assert_eq!(streamed, whole, "{}", content);
+        if content.contains("extensions/><rte") || content.starts_with("<trk><name>T</name></trk>") {
+            assert!(whole.is_some(), "{}", content);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! `ParserOptions::strict` refuses documents breaking the element order and counts of the schema
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::Gpx;
+use gpx_rust::gpx::garmin;
+use gpx_rust::gpx::par::{ DocumentError, Error };
+use gpx_rust::par::ParserOptions;
+
+
+fn document(content: &str) -> String {
+    format!("<gpx xmlns=\"http://www.topografix.com/GPX/1/1\" \
+                  xmlns:gpxx=\"http://www.garmin.com/xmlschemas/GpxExtensions/v3\" \
+                  version=\"1.1\" creator=\"x\">\n{}\n</gpx>", content)
+}
+
+fn options(strict: bool) -> ParserOptions {
+    let mut options = ParserOptions { strict, ..ParserOptions::default() };
+    garmin::register(&mut options.extensions);
+    options
+}
+
+/// Parses the document without `strict`
+fn accepted(content: &str) -> Gpx {
+    gpx::par::parse_with(document(content).as_bytes(), &options(false)).unwrap().0.data
+}
+
+/// Returns the error refusing the document in strict mode, with its position
+fn refused(content: &str) -> (Error, String) {
+    match gpx::par::parse_with(document(content).as_bytes(), &options(true)) {
+        Err(DocumentError::BadData(positioned)) => (positioned.data, positioned.position.to_string()),
+        Err(other) => panic!("Unexpected error {:?}", other),
+        Ok(_) => panic!("Document accepted"),
+    }
+}
+
+#[test]
+fn out_of_order() {
+    let content = "<wpt lat=\"1\" lon=\"2\"><time>2017-01-01T00:00:00Z</time><ele>5</ele></wpt>";
+    match refused(content) {
+        (Error::OutOfOrder(ref name), ref position) => {
+            assert_eq!(name.local_name, "ele");
+            assert_eq!(position, "2:55");
+        }
+        other => panic!("Unexpected error {:?}", other),
+    }
+    let data = accepted(content);
+    assert_eq!(data.waypoints[0].location.elevation, Some(5.0));
+}
+
+#[test]
+fn too_many() {
+    let content = "<wpt lat=\"1\" lon=\"2\"><name>a</name><name>b</name></wpt>";
+    match refused(content) {
+        (Error::TooMany(ref name), ref position) => {
+            assert_eq!(name.local_name, "name");
+            assert_eq!(position, "2:36");
+        }
+        other => panic!("Unexpected error {:?}", other),
+    }
+    // the later one replaces the earlier
+    let data = accepted(content);
+    assert_eq!(data.waypoints[0].name, Some("b".into()));
+}
+
+#[test]
+fn missing_element() {
+    let content = "<rte><extensions><gpxx:RouteExtension>\
+                   <gpxx:DisplayColor>Red</gpxx:DisplayColor>\
+                   </gpxx:RouteExtension></extensions></rte>";
+    match refused(content) {
+        (Error::MissingElement { ref element, ref child }, _) => {
+            assert_eq!(element.local_name, "RouteExtension");
+            assert_eq!(child.local_name, "IsAutoNamed");
+        }
+        other => panic!("Unexpected error {:?}", other),
+    }
+    let data = accepted(content);
+    assert_eq!(data.routes[0].route_extension().unwrap().is_auto_named, None);
+}
@@ -0,0 +1,76 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Documents convert between GPX 1.0 and 1.1 without losing data
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::{ Gpx, Version };
+use gpx_rust::ser::SerializeDocument;
+
+
+fn parse(document: &[u8]) -> Gpx {
+    gpx::parse(document).unwrap().0.data
+}
+
+fn write(data: &Gpx) -> Vec<u8> {
+    let mut output = Vec::new();
+    data.serialize(&mut output).unwrap();
+    output
+}
+
+/// Writes `data` in `version` and reads it back
+fn convert(mut data: Gpx, version: Version) -> (Gpx, String) {
+    data.version = version;
+    let output = write(&data);
+    (parse(&output), String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn from_1_0() {
+    let original = parse(include_bytes!("fixtures/v1_0.gpx"));
+    assert_eq!(original.version, Version::V1_0);
+    let (converted, output) = convert(parse(include_bytes!("fixtures/v1_0.gpx")), Version::V1_1);
+    assert!(output.contains("http://www.topografix.com/GPX/1/1"));
+    assert!(output.contains("<metadata>"));
+    let (back, output) = convert(converted, Version::V1_0);
+    assert!(output.contains("http://www.topografix.com/GPX/1/0"));
+    assert!(!output.contains("<metadata>"));
+    assert_eq!(format!("{:?}", back), format!("{:?}", original));
+}
+
+#[test]
+fn from_1_1() {
+    let mut original = parse(include_bytes!("fixtures/rich.gpx"));
+    // GPX 1.0 segments have no place for extensions
+    for segment in &mut original.tracks[0].segments {
+        segment.extensions = None;
+    }
+    let (converted, output) = convert(parse(include_bytes!("fixtures/rich.gpx")), Version::V1_0);
+    assert!(output.contains("http://www.topografix.com/GPX/1/0"));
+    let (back, _) = convert(converted, Version::V1_1);
+    assert_eq!(format!("{:?}", back), format!("{:?}", original));
+}
+
+#[test]
+fn point_fields_1_0() {
+    let original = parse(include_bytes!("fixtures/v1_0.gpx"));
+    let (converted, _) = convert(parse(include_bytes!("fixtures/v1_0.gpx")), Version::V1_1);
+    let point = &converted.tracks[0].segments[0].waypoints[0];
+    let expected = &original.tracks[0].segments[0].waypoints[0];
+    assert_eq!(format!("{:?}", point), format!("{:?}", expected));
+    let metadata = converted.metadata.unwrap();
+    assert_eq!(metadata.name, Some("Trip".into()));
+    assert_eq!(metadata.author.unwrap().email.unwrap().to_string(), "jane@example.com");
+}
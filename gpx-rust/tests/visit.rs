@@ -0,0 +1,178 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Points are visited and iterated in document order, with the indices leading to them
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::{ Gpx, Waypoint, Route, Track, TrackSegment };
+use gpx_rust::gpx::visit::{ Place, Visit, VisitMut };
+
+
+/// Points are named after their order in the document.
+/// Empty routes, tracks and segments are there to be skipped.
+const DOCUMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="me" xmlns="http://www.topografix.com/GPX/1/1">
+  <wpt lat="0" lon="0"><name>0</name></wpt>
+  <wpt lat="0" lon="0"><name>1</name></wpt>
+  <rte/>
+  <rte><rtept lat="0" lon="0"><name>2</name></rtept><rtept lat="0" lon="0"><name>3</name></rtept></rte>
+  <trk/>
+  <trk>
+    <trkseg/>
+    <trkseg><trkpt lat="0" lon="0"><name>4</name></trkpt></trkseg>
+  </trk>
+  <trk>
+    <trkseg><trkpt lat="0" lon="0"><name>5</name></trkpt><trkpt lat="0" lon="0"><name>6</name></trkpt></trkseg>
+    <trkseg><trkpt lat="0" lon="0"><name>7</name></trkpt></trkseg>
+  </trk>
+</gpx>"#;
+
+fn expected() -> Vec<(Place, String)> {
+    vec![(Place::Waypoint { index: 0 }, "0".into()),
+         (Place::Waypoint { index: 1 }, "1".into()),
+         (Place::RoutePoint { route: 1, index: 0 }, "2".into()),
+         (Place::RoutePoint { route: 1, index: 1 }, "3".into()),
+         (Place::TrackPoint { track: 1, segment: 1, index: 0 }, "4".into()),
+         (Place::TrackPoint { track: 2, segment: 0, index: 0 }, "5".into()),
+         (Place::TrackPoint { track: 2, segment: 0, index: 1 }, "6".into()),
+         (Place::TrackPoint { track: 2, segment: 1, index: 0 }, "7".into())]
+}
+
+fn document() -> Gpx {
+    gpx::par::parse(DOCUMENT.as_bytes()).unwrap().0.data
+}
+
+fn name(point: &Waypoint) -> String {
+    point.name.clone().unwrap()
+}
+
+#[test]
+fn all_points() {
+    let data = document();
+    let found = data.all_points().map(|(place, point)| (place, name(point))).collect::<Vec<_>>();
+    assert_eq!(found, expected());
+}
+
+#[test]
+fn all_points_mut() {
+    let mut data = document();
+    let mut found = Vec::new();
+    for (place, point) in data.all_points_mut() {
+        found.push((place, name(point)));
+        point.comment = Some(format!("{}", place));
+    }
+    assert_eq!(found, expected());
+    assert_eq!(data.routes[1].waypoints[1].comment, Some("routes[1].waypoints[1]".into()));
+    assert_eq!(data.tracks[2].segments[1].waypoints[0].comment,
+               Some("tracks[2].segments[1].waypoints[0]".into()));
+    // every point was reached once
+    assert!(data.all_points().all(|(place, point)| point.comment == Some(format!("{}", place))));
+}
+
+#[test]
+fn empty() {
+    let mut data = Gpx::default();
+    assert_eq!(data.all_points().count(), 0);
+    assert_eq!(data.all_points_mut().count(), 0);
+}
+
+/// Records the calls it receives
+#[derive(Default)]
+struct Log(Vec<String>);
+
+impl Visit for Log {
+    fn visit_waypoint(&mut self, point: &Waypoint, place: Place) {
+        self.0.push(format!("{} {}", place, name(point)));
+    }
+    fn visit_route(&mut self, _route: &Route, index: usize) {
+        self.0.push(format!("route {}", index));
+    }
+    fn visit_track(&mut self, _track: &Track, index: usize) {
+        self.0.push(format!("track {}", index));
+    }
+    fn visit_segment(&mut self, _segment: &TrackSegment, track: usize, index: usize) {
+        self.0.push(format!("segment {} {}", track, index));
+    }
+}
+
+impl VisitMut for Log {
+    fn visit_waypoint(&mut self, point: &mut Waypoint, place: Place) {
+        Visit::visit_waypoint(self, point, place);
+    }
+    fn visit_route(&mut self, route: &mut Route, index: usize) {
+        Visit::visit_route(self, route, index);
+    }
+    fn visit_track(&mut self, track: &mut Track, index: usize) {
+        Visit::visit_track(self, track, index);
+    }
+    fn visit_segment(&mut self, segment: &mut TrackSegment, track: usize, index: usize) {
+        Visit::visit_segment(self, segment, track, index);
+    }
+}
+
+const CALLS: [&str; 17] = [
+    "waypoints[0] 0",
+    "waypoints[1] 1",
+    "route 0",
+    "route 1",
+    "routes[1].waypoints[0] 2",
+    "routes[1].waypoints[1] 3",
+    "track 0",
+    "track 1",
+    "segment 1 0",
+    "segment 1 1",
+    "tracks[1].segments[1].waypoints[0] 4",
+    "track 2",
+    "segment 2 0",
+    "tracks[2].segments[0].waypoints[0] 5",
+    "tracks[2].segments[0].waypoints[1] 6",
+    "segment 2 1",
+    "tracks[2].segments[1].waypoints[0] 7",
+];
+
+#[test]
+fn visit() {
+    let mut log = Log::default();
+    document().visit(&mut log);
+    assert_eq!(log.0, CALLS);
+}
+
+#[test]
+fn visit_mut() {
+    let mut log = Log::default();
+    document().visit_mut(&mut log);
+    assert_eq!(log.0, CALLS);
+}
+
+/// Adds a point to each segment it's given
+struct Extend(usize);
+
+impl VisitMut for Extend {
+    fn visit_segment(&mut self, segment: &mut TrackSegment, _track: usize, _index: usize) {
+        segment.waypoints.push(Waypoint { name: Some("added".into()), ..Waypoint::default() });
+    }
+    fn visit_waypoint(&mut self, point: &mut Waypoint, _place: Place) {
+        if point.name == Some("added".into()) {
+            self.0 += 1;
+        }
+    }
+}
+
+#[test]
+fn added_points_visited() {
+    let mut extend = Extend(0);
+    document().visit_mut(&mut extend);
+    assert_eq!(extend.0, 4);
+}
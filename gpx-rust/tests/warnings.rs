@@ -0,0 +1,90 @@
+/* This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License v1.0 and the GNU General Public License
+ * v3.0 or later which accompanies this distribution.
+ * 
+ *      The Eclipse Public License (EPL) v1.0 is available at
+ *      http://www.eclipse.org/legal/epl-v10.html
+ * 
+ *      You should have received a copy of the GNU General Public License
+ *      along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ * 
+ * You may elect to redistribute this code under either of these licenses.     
+ */
+
+//! Skipped data is reported as positioned warnings by every way of parsing
+
+extern crate gpx_rust;
+
+use gpx_rust::gpx;
+use gpx_rust::gpx::par::PushParser;
+use gpx_rust::par::{ ParserOptions, Policy, Positioned, Warning };
+
+
+const DOCUMENT: &str = r#"<?xml version="1.0"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" xmlns:o="urn:other" version="1.1" creator="x">
+  <wpt lat="1" lon="2" color="red">
+    <name>W</name>
+    <color>red</color>
+  </wpt>
+  <o:note>skipped</o:note>
+  <trk><trkseg><trkpt lat="1" lon="2"><o:hr>80</o:hr></trkpt></trkseg></trk>
+</gpx>"#;
+
+fn options() -> ParserOptions {
+    ParserOptions { unknown_elements: Policy::Skip,
+                    unknown_attributes: Policy::Skip,
+                    ..ParserOptions::default() }
+}
+
+fn describe(warnings: Vec<Positioned<Warning>>) -> Vec<String> {
+    warnings.into_iter()
+            .map(|warning| format!("{} {} at {}", warning.position, warning.data,
+                                   warning.path.unwrap()))
+            .collect()
+}
+
+fn expected() -> Vec<String> {
+    vec![
+        "3:3 attribute color ignored on element {http://www.topografix.com/GPX/1/1}wpt \
+         at /gpx/wpt[1]".into(),
+        "5:5 element {http://www.topografix.com/GPX/1/1}color ignored at /gpx/wpt[1]/color[1]".into(),
+        "7:3 element {urn:other}o:note ignored at /gpx/o:note[1]".into(),
+        "8:39 element {urn:other}o:hr ignored at /gpx/trk[1]/trkseg[1]/trkpt[1]/o:hr[1]".into(),
+    ]
+}
+
+#[test]
+fn whole() {
+    let mut warnings = Vec::new();
+    gpx::par::parse_with_diagnostics(DOCUMENT.as_bytes(), &options(), &mut warnings).unwrap();
+    assert_eq!(describe(warnings), expected());
+}
+
+#[test]
+fn stream() {
+    let mut stream = gpx::par::stream_with(DOCUMENT.as_bytes(), options());
+    for event in stream.by_ref() {
+        event.unwrap();
+    }
+    assert_eq!(describe(stream.take_warnings()), expected());
+    assert!(stream.take_warnings().is_empty());
+}
+
+#[test]
+fn push() {
+    let mut parser = PushParser::with_options(options());
+    let mut warnings = Vec::new();
+    for chunk in DOCUMENT.as_bytes().chunks(16) {
+        parser.feed(chunk);
+        for event in parser.by_ref() {
+            event.unwrap();
+        }
+        warnings.extend(parser.take_warnings());
+    }
+    parser.finish();
+    for event in parser.by_ref() {
+        event.unwrap();
+    }
+    warnings.extend(parser.take_warnings());
+    assert_eq!(describe(warnings), expected());
+}
@@ -32,8 +32,8 @@ fn impl_xmldebug(ast: &syn::MacroInput) -> quote::Tokens {
         &syn::Body::Struct(syn::VariantData::Struct(ref v)) => {
             v.iter().map(|field| {
                 let name = &field.ident.clone().expect("Structure has unnamed fields");
-                let ref path = match &field.ty {
-                    &syn::Ty::Path(_, ref path) => path,
+                let path = match field.ty {
+                    syn::Ty::Path(_, ref path) => path,
                     _ => panic!("Wrong object type")
                 };
                 let simple = quote! {
@@ -64,11 +64,8 @@ fn impl_xmldebug(ast: &syn::MacroInput) -> quote::Tokens {
         }
     };
     
-    let const_name = syn::Ident::new(format!("_IMPL_XMLDEBUG_FOR_{}", name));
-    
     quote! {
-        #[allow(non_upper_case_globals)]
-        const #const_name : () = {
+        const _: () = {
             use std::fmt;
             impl fmt::Debug for #name {
                 fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -21,7 +21,9 @@ use std::fs::File;
 use std::path::Path;
 use clap::{ App, Arg };
 
-use xml_parsergen::{ ParserGen, StructInfo, ParserInfo, gpx, prettify, TypeMap };
+use xml_parsergen::{ ParserGen, ParserInfo, ConvMap, TagMap, gpx, prettify };
+use xml_parsergen::gpx::DEFAULT_GENERATOR;
+use xml_parsergen::xsd_types::{ Type, ComplexType };
 
 
 macro_rules! map(
@@ -36,27 +38,18 @@ macro_rules! map(
      };
 );
 
-/// type_convs - type->converter class mapping. So far elements only, user-provided, may be missing
-fn save(filename: &str, attr_type_convs: TypeMap, type_convs: HashMap<String, String>,
-                        serializers: Vec<StructInfo>,
-                        types: Vec<ParserInfo>) -> Result<(), io::Error> {
-    let f = try!(File::create(filename));
+/// Writes parsers of `parsers` and serializers of `serializers`, (struct name, tags, type name)
+fn save(filename: &str, convs: &ConvMap, serializers: &[(&str, TagMap, &str, &ComplexType)],
+        parsers: &[ParserInfo]) -> Result<(), io::Error> {
+    let f = File::create(filename)?;
     let mut f = BufWriter::new(f);
 
-    try!(f.write(gpx::Generator::header().as_bytes()));
-    
-    for item in types {
-        try!(f.write(
-            gpx::Generator::parser_cls(&item.name, item.type_, &attr_type_convs).as_bytes()
-        ));
-        try!(f.write(
-            gpx::Generator::parser_impl(&item.name, item.type_, &attr_type_convs).as_bytes()
-        ));
+    for item in parsers {
+        f.write_all(DEFAULT_GENERATOR.parser_type(&item.name, item.type_, convs).as_bytes())?;
+        f.write_all(DEFAULT_GENERATOR.parser_impl(&item.name, item.type_, convs).as_bytes())?;
     }
-    for item in serializers {
-        try!(f.write(
-            gpx::Generator::serializer_impl(&item.name, &item.tags, item.type_, &type_convs).as_bytes()
-        ));
+    for &(name, ref tags, type_name, type_) in serializers {
+        f.write_all(gpx::Generator::serializer_impl(name, tags, type_name, type_, convs).as_bytes())?;
     }
     Ok(())
 }
@@ -68,44 +61,22 @@ fn main() {
                       .get_matches();
 
     let types = gpx::get_types();
-    let structs = vec![
-        StructInfo { name: "Metadata".into(), type_: types.get("metadataType").unwrap(),
-                     tags: HashMap::new() },
-        StructInfo { name: "Track".into(), type_: types.get("trkType").unwrap(),
-                     tags: map! {
-                          "cmt" => "comment",
-                          "desc" => "description",
-                          "src" => "source",
-                          "link" => "links",
-                          "type" => "type_",
-                          "trkseg" => "segments"} },
-        StructInfo { name: "TrackSegment".into(), type_: types.get("trksegType").unwrap(),
-                     tags: map! { "trkpt" => "waypoints" } },
-    ];
-    let attr_convs: TypeMap = map!{
-        "latitudeType".into() => ("f64".into(), "Latitude::from_attr".into()),
-        "longitudeType".into() => ("f64".into(), "Longitude::from_attr".into()),
-        "linkType".into() => ("XmlElement".into(), "parse_elem".into()),
-        "fixType".into() => ("Fix".into(), "parse_fix".into()),
-        "dgpsStationType".into() => ("String".into(), "parse_string".into()), // FIXME
-        "extensionsType".into() => ("XmlElement".into(), "parse_elem".into()), // FIXME: dedicated type?
-        "wptType".into() => ("Waypoint".into(), "FIXME".into()),
-        "xsd:decimal".into() => ("xsd::Decimal".into(), "parse_decimal".into()),
-        "xsd:dateTime".into() => ("xsd::DateTime".into(), "parse_time".into()),
-        "xsd:string".into() => ("String".into(), "parse_string".into()),
-        "xsd:nonNegativeInteger".into() => ("xsd::NonNegativeInteger".into(), "parse_u16".into()),
-        "xsd:degreesType".into() => ("xsd::Degrees".into(), "parse_string".into()),
+    let get_complex = |name| match types.get(name) {
+        Some(Type::Complex(type_)) => type_,
+        _ => panic!("{} is not a complex type", name),
     };
-    let mut elem_convs = map!{ "boundsType".into() => "gpx::conv::Bounds".into() };
-    for (name, &(ref type_, _)) in &attr_convs {
-        elem_convs.insert(name.clone(), type_.clone());
-    }
+    let convs: ConvMap = map!{
+        "boundsType".into() => ("Bounds".into(), "::gpx::conv::Bounds".into()),
+        "latitudeType".into() => ("f64".into(), "::gpx::conv::Latitude".into()),
+        "longitudeType".into() => ("f64".into(), "::gpx::conv::Longitude".into()),
+    };
+    let serializers = vec![
+        ("Bounds", HashMap::new(), "boundsType", get_complex("boundsType")),
+    ];
     let parsers = vec![
-        ParserInfo { name: "BoundsParser".into(), type_: types.get("boundsType").unwrap() },
-        ParserInfo { name: "WaypointParser".into(), type_: types.get("wptType").unwrap() },
-        ParserInfo { name: "TrackSegmentParser".into(), type_: types.get("trksegType").unwrap() },
+        ParserInfo { name: "BoundsParser".into(), type_: get_complex("boundsType") },
     ];
     let dest = matches.value_of("destination").unwrap();
-    save(dest, attr_convs, elem_convs, structs, parsers).expect("Failed to save");
-    prettify(Path::new(dest.into())).expect("Failed to prettify");
+    save(dest, &convs, &serializers, &parsers).expect("Failed to save");
+    prettify(Path::new(dest)).expect("Failed to prettify");
 }
@@ -13,7 +13,6 @@
 
 extern crate rustache;
 
-use std;
 use std::io;
 use std::collections::HashMap;
 use quote;
@@ -66,9 +65,14 @@ fn get_elem_field_name(elem: &Element, tags: &TagMap) -> String {
     }).into()
 }
 
+/// Parsers store elements in fields named after the tag. Some schemas capitalize tags.
+fn get_parser_field_name(elem: &Element) -> String {
+    ident_safe(&elem.name.to_lowercase()).into()
+}
+
 fn get_attr_field_name(attr: &Attribute, tags: &TagMap) -> String {
-    ident_safe(&match tags.get(attr.name.as_str()) {
-        Some(i) => *i,
+    ident_safe(match tags.get(attr.name.as_str()) {
+        Some(i) => i,
         None => &attr.name,
     }).into()
 }
@@ -89,6 +93,16 @@ macro_rules! ElementSingle (
     ( $name:expr, $type_:expr ) => {
         Element { name: String::from($name),
                   type_: $type_.into(),
+                  min_occurs: 0,
+                  max_occurs: ElementMaxOccurs::Some(1) }
+    }
+);
+
+macro_rules! ElementRequired (
+    ( $name:expr, $type_:expr ) => {
+        Element { name: String::from($name),
+                  type_: $type_.into(),
+                  min_occurs: 1,
                   max_occurs: ElementMaxOccurs::Some(1) }
     }
 );
@@ -96,17 +110,20 @@ macro_rules! ElementSingle (
 
 pub fn get_types<'a>() -> HashMap<&'a str, Type> {
     map!{
-        "gpxType".into() => Type::Complex(ComplexType {
+        "gpxType" => Type::Complex(ComplexType {
             sequence: vec![
                 ElementSingle!("metadata", "metadataType"),
                 Element { name: "wpt".into(),
                           type_: "wptType".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Unbounded },
                 Element { name: "rte".into(),
                           type_: "rteType".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Unbounded },
                 Element { name: "trk".into(),
                           type_: "trkType".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Unbounded },
                 ElementSingle!("extensions", "extensionsType"),
             ],
@@ -115,18 +132,21 @@ pub fn get_types<'a>() -> HashMap<&'a str, Type> {
                 Attribute { name: "creator".into(), type_: "xsd:string".into(), required: true },
             ],
         }),
-        "metadataType".into() => Type::Complex(ComplexType {
+        "metadataType" => Type::Complex(ComplexType {
             sequence: vec![
                 Element { name: String::from("name"),
                           type_: "xsd:string".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Some(1) },
                 Element { name: String::from("desc"),
                           type_: "xsd:string".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Some(1) },
                 ElementSingle!("author", "personType"),
                 ElementSingle!("copyright", "copyrightType"),
                 Element { name: String::from("link"),
                           type_: "linkType".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Unbounded },
                 ElementSingle!("time", "xsd:dateTime"),
                 ElementSingle!("keywords", "xsd:string"),
@@ -135,7 +155,7 @@ pub fn get_types<'a>() -> HashMap<&'a str, Type> {
             ],
             attributes: vec![],
         }),
-        "trkType".into() => Type::Complex(ComplexType {
+        "trkType" => Type::Complex(ComplexType {
             sequence: vec![
                 ElementSingle!("name", "xsd:string"),
                 ElementSingle!("cmt", "xsd:string"),
@@ -143,17 +163,19 @@ pub fn get_types<'a>() -> HashMap<&'a str, Type> {
                 ElementSingle!("src", "xsd:string"),
                 Element { name: String::from("link"),
                           type_: "linkType".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Unbounded },
                 ElementSingle!("number", "xsd:nonNegativeInteger"),
                 ElementSingle!("type", "xsd:string"),
                 ElementSingle!("extensions", "extensionsType"),
                 Element { name: String::from("trkseg"),
                           type_: "trksegType".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Unbounded },
             ],
             attributes: vec![],
         }),
-        "rteType".into() => Type::Complex(ComplexType {
+        "rteType" => Type::Complex(ComplexType {
             sequence: vec![
                 ElementSingle!("name", "xsd:string"),
                 ElementSingle!("cmt", "xsd:string"),
@@ -161,26 +183,29 @@ pub fn get_types<'a>() -> HashMap<&'a str, Type> {
                 ElementSingle!("src", "xsd:string"),
                 Element { name: String::from("link"),
                           type_: "linkType".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Unbounded },
                 ElementSingle!("number", "xsd:nonNegativeInteger"),
                 ElementSingle!("type", "xsd:string"),
                 ElementSingle!("extensions", "extensionsType"),
                 Element { name: String::from("rtept"),
                           type_: "wptType".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Unbounded },
             ],
             attributes: vec![],
         }),
-        "trksegType".into() => Type::Complex(ComplexType {
+        "trksegType" => Type::Complex(ComplexType {
             sequence: vec![
                 Element { name: "trkpt".into(),
                           type_: "wptType".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Unbounded },
                 ElementSingle!("extensions", "extensionsType"),
             ],
             attributes: vec![],
         }),
-        "boundsType".into() => Type::Complex(ComplexType {
+        "boundsType" => Type::Complex(ComplexType {
             sequence: vec![],
             attributes: vec![
                 Attribute { name: "minlat".into(), type_: "latitudeType".into(), required: true },
@@ -189,7 +214,7 @@ pub fn get_types<'a>() -> HashMap<&'a str, Type> {
                 Attribute { name: "maxlon".into(), type_: "longitudeType".into(), required: true },
             ],
         }),
-        "wptType".into() => Type::Complex(ComplexType {
+        "wptType" => Type::Complex(ComplexType {
             sequence: vec![
                 ElementSingle!("ele", "xsd:decimal"),
                 ElementSingle!("time", "xsd:dateTime"),
@@ -201,6 +226,7 @@ pub fn get_types<'a>() -> HashMap<&'a str, Type> {
                 ElementSingle!("src", "xsd:string"),
                 Element { name: String::from("link"),
                           type_: "linkType".into(),
+                          min_occurs: 0,
                           max_occurs: ElementMaxOccurs::Unbounded },
                 ElementSingle!("sym", "xsd:string"),
                 ElementSingle!("type", "xsd:string"),
@@ -218,7 +244,7 @@ pub fn get_types<'a>() -> HashMap<&'a str, Type> {
                 Attribute { name: "lon".into(), type_: "longitudeType".into(), required: true }
             ],
         }),
-        "linkType".into() => Type::Complex(ComplexType {
+        "linkType" => Type::Complex(ComplexType {
             sequence: vec![
                 ElementSingle!("text", "xsd:string"),
                 ElementSingle!("type", "xsd:string"),
@@ -227,15 +253,15 @@ pub fn get_types<'a>() -> HashMap<&'a str, Type> {
                 Attribute { name: "href".into(), type_: "xsd:anyURI".into(), required: true },
             ],
         }),
-        "degreesType".into() => Type::Simple(SimpleType {
+        "degreesType" => Type::Simple(SimpleType {
             base: "xsd:decimal".into(),
             min_inclusive: 0., max_inclusive: None, max_exclusive: Some(360.),
         }),
-        "dgpsStationType".into() => Type::Simple(SimpleType {
+        "dgpsStationType" => Type::Simple(SimpleType {
             base: "xsd:integer".into(),
-            min_inclusive: 0., max_inclusive: Some(1024.), max_exclusive: None,
+            min_inclusive: 0., max_inclusive: Some(1023.), max_exclusive: None,
         }),
-        "copyrightType".into() => Type::Complex(ComplexType {
+        "copyrightType" => Type::Complex(ComplexType {
             sequence: vec![
                 ElementSingle!("year", "xsd:gYear"),
                 ElementSingle!("license", "xsd:anyURI"),
@@ -244,7 +270,7 @@ pub fn get_types<'a>() -> HashMap<&'a str, Type> {
                 Attribute { name: "author".into(), type_: "xsd:string".into(), required: true },
             ],
         }),
-        "personType".into() => Type::Complex(ComplexType {
+        "personType" => Type::Complex(ComplexType {
             sequence: vec![
                 ElementSingle!("name", "xsd:string"),
                 ElementSingle!("email", "emailType"),
@@ -252,7 +278,7 @@ pub fn get_types<'a>() -> HashMap<&'a str, Type> {
             ],
             attributes: vec![],
         }),
-        "emailType".into() => Type::Complex(ComplexType {
+        "emailType" => Type::Complex(ComplexType {
             sequence: vec![],
             attributes: vec![
                 Attribute { name: "id".into(), type_: "xsd:string".into(), required: true },
@@ -262,6 +288,178 @@ pub fn get_types<'a>() -> HashMap<&'a str, Type> {
     }
 }
 
+/// Garmin TrackPointExtension and GpxExtensions v3 types.
+///
+/// TrackPointExtension version 2 only appends elements to version 1, so both fit in one data type.
+/// Restrictions of `xsd:double` without facets are replaced by their base,
+/// `xsd:token` is read as `xsd:string` and the length of `Subclass_t` is not checked.
+pub fn get_garmin_types<'a>() -> HashMap<&'a str, Type> {
+    map!{
+        "WaypointExtension_t" => Type::Complex(ComplexType {
+            sequence: vec![
+                ElementSingle!("Proximity", "xsd:double"),
+                ElementSingle!("Temperature", "xsd:double"),
+                ElementSingle!("Depth", "xsd:double"),
+                ElementSingle!("DisplayMode", "DisplayMode_t"),
+                ElementSingle!("Categories", "Categories_t"),
+                ElementSingle!("Address", "Address_t"),
+                Element { name: "PhoneNumber".into(),
+                          type_: "PhoneNumber_t".into(),
+                          min_occurs: 0,
+                          max_occurs: ElementMaxOccurs::Unbounded },
+                ElementSingle!("Extensions", "extensionsType"),
+            ],
+            attributes: vec![],
+        }),
+        "Categories_t" => Type::Complex(ComplexType {
+            sequence: vec![
+                Element { name: "Category".into(),
+                          type_: "xsd:string".into(),
+                          min_occurs: 1,
+                          max_occurs: ElementMaxOccurs::Unbounded },
+            ],
+            attributes: vec![],
+        }),
+        "Address_t" => Type::Complex(ComplexType {
+            sequence: vec![
+                Element { name: "StreetAddress".into(),
+                          type_: "xsd:string".into(),
+                          min_occurs: 0,
+                          max_occurs: ElementMaxOccurs::Some(2) },
+                ElementSingle!("City", "xsd:string"),
+                ElementSingle!("State", "xsd:string"),
+                ElementSingle!("Country", "xsd:string"),
+                ElementSingle!("PostalCode", "xsd:string"),
+                ElementSingle!("Extensions", "extensionsType"),
+            ],
+            attributes: vec![],
+        }),
+        "RouteExtension_t" => Type::Complex(ComplexType {
+            sequence: vec![
+                ElementRequired!("IsAutoNamed", "xsd:boolean"),
+                ElementSingle!("DisplayColor", "DisplayColor_t"),
+                ElementSingle!("Extensions", "extensionsType"),
+            ],
+            attributes: vec![],
+        }),
+        "RoutePointExtension_t" => Type::Complex(ComplexType {
+            sequence: vec![
+                ElementSingle!("Subclass", "xsd:hexBinary"),
+                Element { name: "rpt".into(),
+                          type_: "AutoroutePoint_t".into(),
+                          min_occurs: 0,
+                          max_occurs: ElementMaxOccurs::Unbounded },
+                ElementSingle!("Extensions", "extensionsType"),
+            ],
+            attributes: vec![],
+        }),
+        "AutoroutePoint_t" => Type::Complex(ComplexType {
+            sequence: vec![
+                ElementSingle!("Subclass", "xsd:hexBinary"),
+            ],
+            attributes: vec![
+                Attribute { name: "lat".into(), type_: "latitudeType".into(), required: true },
+                Attribute { name: "lon".into(), type_: "longitudeType".into(), required: true },
+            ],
+        }),
+        "TrackExtension_t" => Type::Complex(ComplexType {
+            sequence: vec![
+                ElementSingle!("DisplayColor", "DisplayColor_t"),
+                ElementSingle!("Extensions", "extensionsType"),
+            ],
+            attributes: vec![],
+        }),
+        "TrackPointExtensionV1_t" => Type::Complex(ComplexType {
+            sequence: vec![
+                ElementSingle!("atemp", "xsd:double"),
+                ElementSingle!("wtemp", "xsd:double"),
+                ElementSingle!("depth", "xsd:double"),
+                ElementSingle!("hr", "BeatsPerMinute_t"),
+                ElementSingle!("cad", "RevolutionsPerMinute_t"),
+                ElementSingle!("Extensions", "extensionsType"),
+            ],
+            attributes: vec![],
+        }),
+        "TrackPointExtensionV2_t" => Type::Complex(ComplexType {
+            sequence: vec![
+                ElementSingle!("atemp", "xsd:double"),
+                ElementSingle!("wtemp", "xsd:double"),
+                ElementSingle!("depth", "xsd:double"),
+                ElementSingle!("hr", "BeatsPerMinute_t"),
+                ElementSingle!("cad", "RevolutionsPerMinute_t"),
+                ElementSingle!("speed", "xsd:double"),
+                ElementSingle!("course", "DegreesTrue_t"),
+                ElementSingle!("bearing", "DegreesTrue_t"),
+                ElementSingle!("Extensions", "extensionsType"),
+            ],
+            attributes: vec![],
+        }),
+        "BeatsPerMinute_t" => Type::Simple(SimpleType {
+            base: "xsd:unsignedByte".into(),
+            min_inclusive: 1., max_inclusive: None, max_exclusive: None,
+        }),
+        "RevolutionsPerMinute_t" => Type::Simple(SimpleType {
+            base: "xsd:unsignedByte".into(),
+            min_inclusive: 0., max_inclusive: Some(254.), max_exclusive: None,
+        }),
+        "DegreesTrue_t" => Type::Simple(SimpleType {
+            base: "xsd:double".into(),
+            min_inclusive: 0., max_inclusive: None, max_exclusive: Some(360.),
+        }),
+    }
+}
+
+/// GPX 1.1 types extended with the elements which exist only in GPX 1.0.
+///
+/// Parsers generated from these accept documents of either version.
+/// The added elements are placed so that the order of both versions is kept.
+pub fn get_types_with_v1_0<'a>() -> HashMap<&'a str, Type> {
+    let mut types = get_types();
+    let additions = vec![
+        ("gpxType", "metadata", vec![
+            ElementSingle!("name", "xsd:string"),
+            ElementSingle!("desc", "xsd:string"),
+            ElementSingle!("author", "xsd:string"),
+            ElementSingle!("email", "_gpx:email"),
+            ElementSingle!("url", "xsd:anyURI"),
+            ElementSingle!("urlname", "xsd:string"),
+            ElementSingle!("time", "xsd:dateTime"),
+            ElementSingle!("keywords", "xsd:string"),
+            ElementSingle!("bounds", "boundsType"),
+        ]),
+        ("wptType", "time", vec![
+            ElementSingle!("course", "degreesType"),
+            ElementSingle!("speed", "xsd:decimal"),
+        ]),
+        ("wptType", "src", vec![
+            ElementSingle!("url", "xsd:anyURI"),
+            ElementSingle!("urlname", "xsd:string"),
+        ]),
+        ("rteType", "src", vec![
+            ElementSingle!("url", "xsd:anyURI"),
+            ElementSingle!("urlname", "xsd:string"),
+        ]),
+        ("trkType", "src", vec![
+            ElementSingle!("url", "xsd:anyURI"),
+            ElementSingle!("urlname", "xsd:string"),
+        ]),
+    ];
+    for (type_name, after, elements) in additions {
+        match types.get_mut(type_name) {
+            Some(&mut Type::Complex(ref mut type_)) => {
+                let position = type_.sequence.iter()
+                                             .position(|elem| elem.name == after)
+                                             .unwrap_or_else(|| panic!("{} not in {}", after, type_name));
+                for (offset, elem) in elements.into_iter().enumerate() {
+                    type_.sequence.insert(position + 1 + offset, elem);
+                }
+            }
+            _ => panic!("{} is not a complex type", type_name),
+        }
+    }
+    types
+}
+
 
 pub struct Generator<'a> {
     data_struct_type: &'a str,
@@ -269,6 +467,15 @@ pub struct Generator<'a> {
     parse_via_char: &'a str,
     parse_via: &'a str,
     element_parse: &'a str,
+    /// Namespaces of elements and attributes handled by the generated parsers
+    namespaces: &'a [&'a str],
+}
+
+impl<'a> Generator<'a> {
+    /// Returns a generator whose parsers take `namespaces` as their own instead
+    pub fn with_namespaces(&self, namespaces: &'a [&'a str]) -> Generator<'a> {
+        Generator { namespaces, ..*self }
+    }
 }
 
 pub static DEFAULT_GENERATOR: Generator<'static> = Generator {
@@ -282,25 +489,26 @@ struct {{{ data_struct_type }}} {
 struct {{{ parser_type }}} {
     {{# attribute }} {{{ field }}}: Option<{{{ type }}}>, {{/ attribute }}
     {{# element }} {{{ field }}}: {{{ parser_type }}}<{{{ type }}}>, {{/ element }}
+    {{# has_element }} sequence: ::par::SequencePosition, {{/ has_element }}
 }"#,
     parse_via_char: r#"
 impl ParseViaChar<{{{ type }}}> for {{{ conv }}} {
-    #[allow(unused_comparisons)]
+    #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
     fn from_char(s: &str) -> Result<{{{ type }}}, ::gpx::par::Error> {
-        let value = try!(<{{{ base_conv }}} as ParseViaChar<{{{ type }}}>>::from_char(s));
+        let value = <{{{ base_conv }}} as ParseViaChar<{{{ type }}}>>::from_char(s)?;
 {{# lower }}
         if {{{ lower }}} > value {
-            Err(::gpx::par::Error::TooSmall { limit: {{{ lower }}}.into(), value: value.into() })
+            Err(::gpx::par::Error::TooSmall { limit: {{{ lower_limit }}}, {{{ value_field }}} })
         } else
 {{/ lower }}
 {{# max_inclusive }}
         if value > {{{ max_inclusive }}} {
-            Err(::gpx::par::Error::TooLarge { limit: {{{ max_inclusive }}}.into(), value: value.into() })
+            Err(::gpx::par::Error::TooLarge { limit: {{{ max_inclusive_limit }}}, {{{ value_field }}} })
         } else
 {{/ max_inclusive }}
 {{# max_exclusive }}
         if value >= {{{ max_exclusive }}} {
-            Err(::gpx::par::Error::TooLarge { limit: {{{ max_exclusive }}}.into(), value: value.into() })
+            Err(::gpx::par::Error::TooLarge { limit: {{{ max_exclusive_limit }}}, {{{ value_field }}} })
         } else
 {{/ max_exclusive }}
         {
@@ -310,107 +518,118 @@ impl ParseViaChar<{{{ type }}}> for {{{ conv }}} {
 }"#,
     parse_via: r#"
 impl ParseVia<{{{ data }}}> for {{{ conv }}} {
-    fn parse_via<R: io::Read>(parser: &mut EventReader<R>,
+    fn parse_via<R: EventSource>(parser: &mut R, ctx: &mut ::par::Context,
                               name: &OwnedName, attributes: &[OwnedAttribute])
             -> Result<{{{ data }}}, Positioned<Error>> {
-        {{{ parser_type }}}::new().parse(name, attributes, parser)
+        {{{ parser_type }}}::new().parse(name, attributes, parser, ctx)
     }
 }"#,
     element_parse: r#"
 impl ElementParse<::gpx::par::Error> for {{{ parser_type }}} {
-    fn parse_start(&mut self, attributes: &[OwnedAttribute])
+    // without attributes, only the catch-all arm is left
+    #[allow(clippy::match_single_binding)]
+    fn parse_start(&mut self, ctx: &mut ::par::Context, name: &OwnedName,
+                   attributes: &[OwnedAttribute], position: TextPosition)
             -> Result<(), ::par::AttributeError<::gpx::par::Error>> {
         for attr in attributes {
-            let name = &attr.name;
-
-            if let &Some(ref ns) = &name.namespace {
-                match &ns as &str {
-                    "http://www.topografix.com/GPX/1/1" |
-                    "http://www.topografix.com/GPX/1/0" => (),
-                    ns => {
-                        println!("WARNING: namespace ignored on {:?}:{}: {}",
-                             name.prefix,
-                             name.local_name,
-                             ns);
+            if let Some(ns) = &attr.name.namespace {
+                match ns.as_str() {
+                    {{# namespace }}
+                    {{{ uri }}} => (),
+                    {{/ namespace }}
+                    _ => {
+                        let policy = ctx.options.foreign_namespaces;
+                        self.unexpected_attribute(ctx, policy, name, attr, position)?;
                         continue;
                     }
                 }
             }
-            match &(name.local_name) as &str {
+            match &(attr.name.local_name) as &str {
                 {{# attribute }}
                 {{{ name }}} => {
-                    self.{{{ field }}} = Some(try!({{{ conv }}}::from_attribute(&attr.value)));
+                    self.{{{ field }}} = Some({{{ conv }}}::from_attribute(&attr.value).map_err(|e| {
+                        ::par::AttributeError::bad_value(e, attr)
+                    })?);
                 }
                 {{/ attribute }}
                 _ => {
-                    return Err(::par::AttributeError::Unexpected(name.clone()));
+                    let policy = ctx.options.unknown_attributes;
+                    self.unexpected_attribute(ctx, policy, name, attr, position)?;
                 }
             }
         }
         Ok(())
     }
-    fn parse_element<'a, R: Read>(&mut self, reader: &'a mut EventReader<R>,
-                                  name: &OwnedName, attributes: &[OwnedAttribute])
+    // without child elements, only the catch-all arm is left
+    #[allow(clippy::match_single_binding)]
+    fn parse_element<R: EventSource>(&mut self, reader: &mut R,
+                                     ctx: &mut ::par::Context,
+                                     name: &OwnedName, attributes: &[OwnedAttribute])
             -> Result<(), Positioned<::gpx::par::Error>> {
-{{# has_element }}
-        if let Some(ref ns) = name.namespace.clone() {
-            match &ns as &str {
-                "http://www.topografix.com/GPX/1/1" => (),
-                "http://www.topografix.com/GPX/1/0" => {
-                    println!("WARNING: GPX 1.0 not fully supported, errors may appear");
-                },
-                ns => {
-                    {
-                        println!("WARNING: unknown namespace ignored on {:?}:{}: {}",
-                             name.prefix,
-                             name.local_name,
-                             ns);
+        if let Some(ns) = &name.namespace {
+            match ns.as_str() {
+                {{# namespace }}
+                {{{ uri }}} => (),
+                {{/ namespace }}
+                _ => {
+{{# has_extensions }}
+                    if ctx.flat_extensions {
+                        return self.unexpected_element(reader, ctx, ::par::Policy::Preserve,
+                                                       name, attributes);
                     }
-                    try!(ElementParser::new().parse(name, attributes, reader));
-                    return Ok(());
+{{/ has_extensions }}
+                    let policy = ctx.options.foreign_namespaces;
+                    return self.unexpected_element(reader, ctx, policy, name, attributes);
                 }
             }
         }
         match &name.local_name as &str {
             {{# element }}
             {{{ name }}} => {
-                {{{ saver }}}(try!({{{ conv }}}::parse_via(reader, name, attributes)));
+                ctx.path.set_max_occurs({{{ max_occurs }}});
+                if ctx.checks_order(name) {
+                    self.sequence.advance({{{ index }}}, {{{ max_occurs }}}, name).map_err(|e| {
+                        ctx.locate(Positioned::with_position(e, reader.position()))
+                    })?;
+                }
+                {{{ saver }}}({{{ conv }}}::parse_via(reader, ctx, name, attributes)?);
             }
             {{/ element }}
             _ => {
-                // TODO: add config and handler
-                return Err(Positioned::with_position(
-                    ::gpx::par::Error::UnknownElement(name.clone()),
-                    reader.position()
-                ));
+                let policy = ctx.options.unknown_elements;
+                self.unexpected_element(reader, ctx, policy, name, attributes)?;
             }
         };
         Ok(())
-{{/ has_element }}
-{{^ has_element }}
-        let _ = attributes;
-        Err(Positioned::with_position(
-            ::gpx::par::Error::UnknownElement(name.clone()),
-            reader.position())
-        )
-{{/ has_element }}
     }
-}"#,
-};
-
-trait GetOrElse<K, V> {
-    fn get_or_else(&self, key: &K, f: &Fn(&K) -> V) -> V;
-}
-
-impl<'a, K: std::cmp::Eq + std::hash::Hash, V: Clone> GetOrElse<K, V> for HashMap<K, V> {
-    fn get_or_else(&self, key: &K, f: &Fn(&K) -> V) -> V {
-        match self.get(key) {
-            Some(i) => V::clone(i),
-            None => f(key),
+{{# has_required }}
+    #[allow(clippy::collapsible_if)]
+    fn parse_end(&mut self, ctx: &mut ::par::Context, name: &OwnedName)
+            -> Result<(), ::gpx::par::Error> {
+        if ctx.options.strict {
+            {{# required }}
+            if {{{ missing }}} {
+                return Err(::gpx::par::Error::MissingElement {
+                    element: name.clone(),
+                    child: OwnedName { local_name: {{{ name }}}.into(),
+                                       namespace: name.namespace.clone(),
+                                       prefix: name.prefix.clone() }
+                });
+            }
+            {{/ required }}
         }
+        Ok(())
     }
-}
-
+{{/ has_required }}
+{{# has_extensions }}
+    fn preserve(&mut self, data: ::par::Preserved) -> Result<(), ::par::Preserved> {
+        data.store(self.extensions.get_or_insert_with(xml::Element::default));
+        Ok(())
+    }
+{{/ has_extensions }}
+}"#,
+    namespaces: &["http://www.topografix.com/GPX/1/1", "http://www.topografix.com/GPX/1/0"],
+};
 
 impl<'a> ParserGen for Generator<'a> {
     fn data_struct_type(&self, name: &str, tags: &TagMap, data: &ComplexType, convs: &ConvMap) -> String {
@@ -427,8 +646,8 @@ impl<'a> ParserGen for Generator<'a> {
         };
         
         let attributes_owned = data.attributes.iter().map(|attr| {
-            let &(ref type_, _) = convs.get(&attr.type_)
-                                      .expect(format!("No parser for {}", &attr.type_).as_str());
+            let (type_, _) = convs.get(&attr.type_)
+                                      .unwrap_or_else(|| panic!("No parser for {}", &attr.type_));
             let wrap_type = match attr.required {
                 true => None,
                 false => Some("Option"),
@@ -446,8 +665,8 @@ impl<'a> ParserGen for Generator<'a> {
                 ElementMaxOccurs::Some(1) => "Option",
                 _ => "Vec",
             };
-            let &(ref type_, _) = convs.get(&elem.type_)
-                                       .expect(format!("Missing type for {}", &elem.type_).as_str());
+            let (type_, _) = convs.get(&elem.type_)
+                                       .unwrap_or_else(|| panic!("Missing type for {}", &elem.type_));
             (field, Some(wrap_type), type_)
         });//.collect::<Vec<_>>(); // this data must be kept until processing
         // but only references can be processed
@@ -461,7 +680,7 @@ impl<'a> ParserGen for Generator<'a> {
             };
             (field, type_)
         }).collect::<Vec<_>>();
-        let fields = fields_owned.iter().map(|&(ref f, ref t)| vec![f.as_str(), t.as_str()]);
+        let fields = fields_owned.iter().map(|(f, t)| vec![f.as_str(), t.as_str()]);
         render_string(HashBuilder::new().insert("struct_type", name)
                                         .insert_array("field", &["field", "type"],
                                         fields),
@@ -470,15 +689,15 @@ impl<'a> ParserGen for Generator<'a> {
 
     fn parser_type(&self, name: &str, data: &ComplexType, convs: &ConvMap) -> String {
         let attributes_owned = data.attributes.iter().map(|attr| {
-            let &(ref type_, _) = convs.get(&attr.type_)
-                                           .expect(format!("No type for {}", &attr.type_).as_str());
+            let (type_, _) = convs.get(&attr.type_)
+                                           .unwrap_or_else(|| panic!("No type for {}", &attr.type_));
             (String::from(ident_safe(&attr.name)), type_)
         }).collect::<Vec<_>>();
         let attributes = attributes_owned.iter().map(|&(ref field, type_)| {
             vec![field.as_str(), type_.as_user_type()]
         });
         let elements_owned = data.sequence.iter().map(|elem| {
-            let field = ident_safe(&elem.name);
+            let field = get_parser_field_name(elem);
             let stor_type = match elem.max_occurs {
                 ElementMaxOccurs::Some(0) => {
                     panic!("Element has 0 occurrences, can't derive data type")
@@ -486,9 +705,9 @@ impl<'a> ParserGen for Generator<'a> {
                 ElementMaxOccurs::Some(1) => "Option",
                 _ => "Vec",
             };
-            let &(ref type_, _) = convs.get(&elem.type_)
-                                       .expect(format!("Missing type for {}", &elem.type_).as_str());
-            (String::from(field), String::from(stor_type), type_)
+            let (type_, _) = convs.get(&elem.type_)
+                                       .unwrap_or_else(|| panic!("Missing type for {}", &elem.type_));
+            (field, String::from(stor_type), type_)
         }).collect::<Vec<_>>(); // this data must be kept until processing
         // but only references can be processed
         let elements = elements_owned.iter().map(|&(ref field, ref stor_type, type_)| {
@@ -504,48 +723,86 @@ impl<'a> ParserGen for Generator<'a> {
     
     fn parser_impl(&self, name: &str, data: &ComplexType, convs: &ConvMap) -> String {
         let attributes_owned = data.attributes.iter().map(|attr| {
-            let &(_, ref conv) = convs.get(&attr.type_)
-                                      .expect(format!("No parser for {}", &attr.type_).as_str());
+            let (_, conv) = convs.get(&attr.type_)
+                                      .unwrap_or_else(|| panic!("No parser for {}", &attr.type_));
             let name = &attr.name;
             (quote!(#name), String::from(ident_safe(&attr.name)), conv)
         }).collect::<Vec<_>>();
-        let attributes = attributes_owned.iter().map(|&(ref name, ref field, ref conv)| {
+        let attributes = attributes_owned.iter().map(|&(ref name, ref field, conv)| {
             vec![name.as_str(), field, conv.as_user_type()]
         });
         let elements_owned = data.sequence.iter().map(|elem| {
             let tag = &elem.name;
-            let field = ident_safe(&elem.name);
+            let field = get_parser_field_name(elem);
             let (type_, saver) = match elem.max_occurs {
                 ElementMaxOccurs::Some(0) => {
                     panic!("Element has 0 occurrences, can't derive data type")
                 }
+                // may already contain preserved unknown data
+                ElementMaxOccurs::Some(1) if elem.type_ == "extensionsType" => {
+                    ("Option", format!("self.{}.merge", field))
+                }
                 ElementMaxOccurs::Some(1) => ("Option", format!("self.{} = Some", field)),
                 _ => ("Vec", format!("self.{}.push", field))
             };
-            let &(_, ref conv) = convs.get(&elem.type_)
-                                      .expect(format!("Missing conversion for {}", &elem.type_).as_str());
-            (quote!(#tag), String::from(field), type_, saver, conv)
+            let (_, conv) = convs.get(&elem.type_)
+                                      .unwrap_or_else(|| panic!("Missing conversion for {}", &elem.type_));
+            let max_occurs = match elem.max_occurs {
+                ElementMaxOccurs::Some(count) => format!("Some({})", count),
+                ElementMaxOccurs::Unbounded => "None".into(),
+            };
+            (quote!(#tag), field, type_, saver, conv, max_occurs)
         }).collect::<Vec<_>>(); // this data must be kept until processing
         // but only references can be processed
-        let elements = elements_owned.iter().map(|&(ref name, ref field, ref type_, ref saver, conv)| {
-            vec![name.as_str(), field.as_str(), type_, saver.as_str(), conv.as_user_type()]
+        let indices = (0..elements_owned.len()).map(|i| i.to_string()).collect::<Vec<_>>();
+        let elements = elements_owned.iter().zip(indices.iter())
+                                     .map(|(&(ref name, ref field, type_, ref saver, conv, ref max_occurs),
+                                            index)| {
+            vec![name.as_str(), field.as_str(), type_, saver.as_str(), conv.as_user_type(),
+                 index.as_str(), max_occurs.as_str()]
+        });
+
+        // checked in strict mode only
+        let required_owned = data.sequence.iter().filter(|elem| elem.min_occurs > 0).map(|elem| {
+            let tag = &elem.name;
+            let field = get_parser_field_name(elem);
+            let missing = match elem.max_occurs {
+                ElementMaxOccurs::Some(1) => format!("self.{}.is_none()", field),
+                _ if elem.min_occurs == 1 => format!("self.{}.is_empty()", field),
+                _ => format!("self.{}.len() < {}", field, elem.min_occurs),
+            };
+            (quote!(#tag), missing)
+        }).collect::<Vec<_>>();
+        let required = required_owned.iter().map(|(name, missing)| {
+            vec![name.as_str(), missing.as_str()]
         });
 
+        // unknown data can be preserved only where there's an extensions element
+        let has_extensions = data.sequence.iter().any(|elem| elem.type_ == "extensionsType");
+
+        let namespaces_owned = self.namespaces.iter().map(|uri| quote!(#uri).to_string())
+                                                     .collect::<Vec<_>>();
+        let namespaces = namespaces_owned.iter().map(|uri| vec![uri.as_str()]);
+
         render_string(HashBuilder::new().insert("parser_type", name)
+                                        .insert("has_extensions", has_extensions)
+                                        .insert_array("namespace", &["uri"], namespaces)
                                         .insert_array("attribute",
                                                       &["name", "field", "conv"],
                                                       attributes)
                                         .insert_array("element",
-                                                      &["name", "field", "parser_type", "saver", "conv"], 
-                                                      elements),
+                                                      &["name", "field", "parser_type", "saver", "conv",
+                                                        "index", "max_occurs"],
+                                                      elements)
+                                        .insert_array("required", &["name", "missing"], required),
                       self.element_parse)
     }
     
-    fn parse_impl(&self, type_name: &str, data: &SimpleType, convs: &ConvMap, types_: &TypeMap)
+    fn parse_impl(&self, type_name: &str, data: &SimpleType, convs: &ConvMap, _types: &TypeMap)
             -> String {
         let converter = convs.get(type_name).unwrap();
-        let &(_, ref conv_name) = converter;
-        let &(_, ref base_conv) = convs.get(data.base.as_str()).expect(format!("Base {} not found", data.base).as_str());
+        let (_, conv_name) = converter;
+        let (_, base_conv) = convs.get(data.base.as_str()).unwrap_or_else(|| panic!("Base {} not found", data.base));
         let format_literal = |value| {
             match converter.0.as_user_type() {
                 "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64" => format!("{}", value),
@@ -553,17 +810,26 @@ impl<'a> ParserGen for Generator<'a> {
                 other => panic!("Value {} is not a number", other)
             }
         };
+        // errors hold f64
+        let value_field = match converter.0.as_user_type() {
+            "f64" => "value",
+            _ => "value: value.into()",
+        };
         let mut values = HashBuilder::new().insert("type", converter.0.as_user_type())
                                            .insert("conv", conv_name.as_str())
-                                           .insert("base_conv", base_conv.as_str());
+                                           .insert("base_conv", base_conv.as_str())
+                                           .insert("value_field", value_field);
         // TODO: make optional
         // TODO: format as float/int
-        values = values.insert("lower", format_literal(data.min_inclusive));
+        values = values.insert("lower", format_literal(data.min_inclusive))
+                       .insert("lower_limit", format!("{:.1}", data.min_inclusive));
         if let Some(val) = data.max_inclusive {
-            values = values.insert("max_inclusive", format_literal(val));
+            values = values.insert("max_inclusive", format_literal(val))
+                           .insert("max_inclusive_limit", format!("{:.1}", val));
         }
         if let Some(val) = data.max_exclusive {
-            values = values.insert("max_exclusive", format_literal(val));
+            values = values.insert("max_exclusive", format_literal(val))
+                           .insert("max_exclusive_limit", format!("{:.1}", val));
         }
         render_string(values, self.parse_via_char)
     }
@@ -577,13 +843,14 @@ impl<'a> ParserGen for Generator<'a> {
     }
 
     fn build_impl(parser_name: &str, data: &ComplexType, struct_info: &StructInfo,
-                  type_convs: &ConvMap)
+                  _type_convs: &ConvMap)
             -> String {
         let inits = data.attributes.iter().map(|attr| {
             let field_name = ident_safe(&attr.name);
+            let attr_name = &attr.name;
             let attr_val = match attr.required {
-                true => format!("self.{name}.expect(\"BUG: Attribute {name} is required but not present\")",
-                                name=field_name),
+                true => format!("xml::par::required_attribute(self.{field}, name, {name})?",
+                                field=field_name, name=quote!(#attr_name)),
                 false => format!("self.{name}", name=field_name)
             };
             format!("{}: {},\n",
@@ -593,18 +860,19 @@ impl<'a> ParserGen for Generator<'a> {
             data.sequence.iter().map(|elem| {
                 format!("{}: self.{},\n",
                         get_elem_field_name(elem, &struct_info.tags),
-                        ident_safe(&elem.name))
+                        get_parser_field_name(elem))
             })
         ).collect::<String>();
         render_string(HashBuilder::new().insert("parser_name", parser_name)
-                                        .insert("struct_name", (&struct_info.name).as_str())
+                                        .insert("struct_name", struct_info.name.as_str())
                                         .insert("error_name", "Error") // TODO: figure out how to handle the class
                                         .insert("inits", inits.as_str()),
                       r#"
 impl ElementBuild for {{{ parser_name }}} {
     type Element = {{{ struct_name }}};
     type BuildError = xml::BuildError;
-    fn build(self) -> Result<Self::Element, Self::BuildError> {
+    fn build(self, name: &OwnedName) -> Result<Self::Element, Self::BuildError> {
+        let _ = name;
         Ok({{{ struct_name }}} {
             {{{ inits }}}
         })
@@ -624,15 +892,15 @@ impl ElementBuild for {{{ parser_name }}} {
                     true => format!("data.{}", field_name),
                     false => "value".into()
                 };
-                let &(_, ref conv_name) = type_convs.get(&attr.type_).expect("Failed");
+                let (_, conv_name) = type_convs.get(&attr.type_).expect("Failed");
                 let push = render_string(HashBuilder::new().insert("attr_name", quote!(#attr_name).to_string())
                                                 .insert("field_value", field_value.as_str())
                                                 .insert("conv_name", conv_name.as_user_type()),
                               r#"
                 OwnedAttribute { name: OwnedName::local( {{{ attr_name }}} ),
-                                 value: try!({{{ conv_name }}}::to_characters(&{{{ field_value }}})) },
+                                 value: {{{ conv_name }}}::to_characters(&{{{ field_value }}})? },
     "#);
-                if attr.required == false {
+                if !attr.required {
                     format!("if let Some(value) = &data.{} {{
                     {}
                 }}", field_name, push)
@@ -652,22 +920,25 @@ impl ElementBuild for {{{ parser_name }}} {
         };
         let events = data.sequence.iter().map(|elem| {
             let elem_name = elem.name.clone();
-            let get_attr_name = |f: &Fn(&str) -> String| {
+            let get_attr_name = |f: &dyn Fn(&str) -> String| {
                 String::from(ident_safe(&match tags.get(elem_name.as_str()) {
                     Some(i) => String::from(*i),
                     None => f(elem_name.as_str())
                 }))
             };
-            let type_name = quote::Ident::new(type_convs.get(&elem.type_)
-                                                        .expect("No item").1.as_user_type());
-            let ser_call = quote!(#type_name::serialize_via(item, sink, &OwnedName::local(#elem_name)));
+            // free text may be written as CDATA, see `ser::SerializerOptions`
+            let type_name = quote::Ident::new(match elem.type_.as_str() {
+                "xsd:string" => "::ser::Text",
+                other => type_convs.get(other).expect("No item").1.as_user_type(),
+            });
+            let ser_call = quote!(#type_name::serialize_via(item, sink, options, &::ser::child_name(name, #elem_name)));
 
             match elem.max_occurs {
                 ElementMaxOccurs::Some(1) => {
                     let name = quote::Ident::new(get_attr_name(&|n| { String::from(n) }));
                     quote!(
                         if let Some(ref item) = data.#name {
-                            try!(#ser_call);
+                            #ser_call?;
                         }
                     )
                 }
@@ -675,15 +946,15 @@ impl ElementBuild for {{{ parser_name }}} {
                     let name = quote::Ident::new(get_attr_name(&|n| { format!("{}s", n) }));
                     quote!(
                         for item in &data.#name {
-                            try!(#ser_call);
+                            #ser_call?;
                         }
                     )
                 }
             }
         });
         
-        let &(_, ref conv_name) = type_convs.get(type_name)
-                                            .expect(format!("Refusing to create serializer for non-universal class {}", type_name).as_str());
+        let (_, conv_name) = type_convs.get(type_name)
+                                            .unwrap_or_else(|| panic!("Refusing to create serializer for non-universal class {}", type_name));
         
         render_string(HashBuilder::new().insert("cls_name", cls_name)
                                         .insert("conv_name", conv_name.as_user_type())
@@ -691,22 +962,23 @@ impl ElementBuild for {{{ parser_name }}} {
                                         .insert("events", quote!( #( #events )* ).to_string()),
                       r#"
 impl SerializeVia<{{{ cls_name }}}> for {{{ conv_name }}} {
-    fn serialize_via<W: io::Write>(data: &{{{ cls_name }}}, sink: &mut EventWriter<W>, name: &OwnedName)
+    fn serialize_via<W: io::Write>(data: &{{{ cls_name }}}, sink: &mut EventWriter<W>,
+                                   options: &SerializerOptions, name: &OwnedName)
             -> Result<(), Error> {
         let elemname = name.borrow();
         let attributes = {{{ attributes }}};
         
-        try!(sink.write(
+        sink.write(
             XmlEvent::StartElement {
-                name: elemname.clone(),
+                name: elemname,
                 attributes: Cow::Owned(attributes),
-                namespace: Cow::Owned(Namespace::empty())
+                namespace: Cow::Owned(::ser::element_namespace(name))
             }
-        ));
+        )?;
         
         {{{ events }}}
         
-        try!(sink.write(XmlEvent::EndElement { name: Some(elemname) }));
+        sink.write(XmlEvent::EndElement { name: Some(elemname) })?;
         Ok(())
     }
 }"#)
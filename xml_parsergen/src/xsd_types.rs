@@ -31,6 +31,7 @@ pub struct SimpleType {
 pub struct Element {
     pub name: String,
     pub type_: String,
+    pub min_occurs: u64,
     pub max_occurs: ElementMaxOccurs,
 }
 